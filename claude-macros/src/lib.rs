@@ -0,0 +1,212 @@
+//! Procedural macros for the `claude` crate
+//!
+//! Use them through their re-exports, e.g. `#[claude::hook_fn]` or
+//! `#[claude::tool]`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, spanned::Spanned, FnArg, ItemFn, LitStr, Pat, ReturnType, Type};
+
+/// Turn an `async fn` into a function returning a `HookCallback`
+///
+/// See `claude::hook_fn` for the supported parameters.
+#[proc_macro_attribute]
+pub fn hook_fn(args: TokenStream, item: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        return syn::Error::new(TokenStream2::from(args).span(), "#[hook_fn] takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    let function = parse_macro_input!(item as ItemFn);
+    expand_hook_fn(function)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_hook_fn(function: ItemFn) -> syn::Result<TokenStream2> {
+    let signature = &function.sig;
+    if signature.asyncness.is_none() {
+        return Err(syn::Error::new(signature.fn_token.span(), "#[hook_fn] requires an async fn"));
+    }
+    if !signature.generics.params.is_empty() {
+        return Err(syn::Error::new(signature.generics.span(), "#[hook_fn] functions cannot be generic"));
+    }
+
+    let mut arguments = Vec::new();
+    for input in &signature.inputs {
+        let FnArg::Typed(argument) = input else {
+            return Err(syn::Error::new(input.span(), "#[hook_fn] functions cannot take self"));
+        };
+        let Pat::Ident(ref pattern) = *argument.pat else {
+            return Err(syn::Error::new(argument.pat.span(), "#[hook_fn] parameters must be plain names"));
+        };
+        arguments.push(extract_argument(&pattern.ident, &argument.ty));
+    }
+
+    // Plain outputs are wrapped in `Ok` so hooks that cannot fail skip the typing
+    let call = quote!(__hook_impl(#(#arguments),*).await);
+    let body = match signature.output {
+        ReturnType::Type(_, ref ty) if last_segment(ty).as_deref() == Some("HookJSONOutput") => {
+            quote!(::std::result::Result::Ok(#call))
+        }
+        _ => call,
+    };
+
+    let vis = &function.vis;
+    let name = &signature.ident;
+    let attrs = &function.attrs;
+    let mut implementation = function.clone();
+    implementation.attrs.clear();
+    implementation.vis = syn::Visibility::Inherited;
+    implementation.sig.ident = format_ident!("__hook_impl");
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis fn #name() -> ::claude::HookCallback {
+            #implementation
+
+            ::claude::hooks::callback_from_fn(|__input, __tool_use_id, __context| async move {
+                let __output: ::claude::Result<::claude::HookJSONOutput> = async move { #body }.await;
+                __output
+            })
+        }
+    })
+}
+
+/// Turn an `async fn` into a function returning an `McpTool`
+///
+/// See `claude::tool` for the supported arguments.
+#[proc_macro_attribute]
+pub fn tool(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut name: Option<LitStr> = None;
+    let mut description: Option<LitStr> = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("description") {
+            description = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("expected `name` or `description`"))
+        }
+    });
+    parse_macro_input!(args with parser);
+
+    let function = parse_macro_input!(item as ItemFn);
+    expand_tool(function, name, description)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_tool(function: ItemFn, name: Option<LitStr>, description: Option<LitStr>) -> syn::Result<TokenStream2> {
+    let signature = &function.sig;
+    if signature.asyncness.is_none() {
+        return Err(syn::Error::new(signature.fn_token.span(), "#[tool] requires an async fn"));
+    }
+    if !signature.generics.params.is_empty() {
+        return Err(syn::Error::new(signature.generics.span(), "#[tool] functions cannot be generic"));
+    }
+
+    // Parameters become fields of the argument struct, keeping their attributes
+    // so `///` docs and `#[serde(...)]` reach the schema
+    let mut fields = Vec::new();
+    let mut field_defs = Vec::new();
+    for input in &signature.inputs {
+        let FnArg::Typed(argument) = input else {
+            return Err(syn::Error::new(input.span(), "#[tool] functions cannot take self"));
+        };
+        let Pat::Ident(ref pattern) = *argument.pat else {
+            return Err(syn::Error::new(argument.pat.span(), "#[tool] parameters must be plain names"));
+        };
+        let field = &pattern.ident;
+        let ty = &argument.ty;
+        let attrs = &argument.attrs;
+        field_defs.push(quote!(#(#attrs)* #field: #ty));
+        fields.push(field.clone());
+    }
+
+    let tool_name = name.map_or_else(|| signature.ident.to_string(), |name| name.value());
+    let description = match description {
+        Some(description) => description.value(),
+        None => doc_comment(&function.attrs).ok_or_else(|| {
+            syn::Error::new(signature.ident.span(), "#[tool] needs `description = \"...\"` or a doc comment")
+        })?,
+    };
+
+    let vis = &function.vis;
+    let name = &signature.ident;
+    let attrs = &function.attrs;
+    let mut implementation = function.clone();
+    implementation.attrs.clear();
+    implementation.vis = syn::Visibility::Inherited;
+    implementation.sig.ident = format_ident!("__tool_impl");
+    for input in implementation.sig.inputs.iter_mut() {
+        if let FnArg::Typed(argument) = input {
+            argument.attrs.clear();
+        }
+    }
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis fn #name() -> ::claude::mcp::McpTool {
+            #[derive(::claude::__private::serde::Deserialize, ::claude::__private::schemars::JsonSchema)]
+            #[serde(crate = "::claude::__private::serde")]
+            #[schemars(crate = "::claude::__private::schemars")]
+            struct __ToolArgs {
+                #(#field_defs),*
+            }
+
+            #implementation
+
+            ::claude::mcp::McpTool::from_schema(#tool_name, #description, |__args: __ToolArgs| async move {
+                let __ToolArgs { #(#fields),* } = __args;
+                ::std::result::Result::Ok(::claude::mcp::IntoToolResult::into_tool_result(
+                    __tool_impl(#(#fields),*).await,
+                ))
+            })
+        }
+    })
+}
+
+/// The `///` comment of an item, one line per doc attribute
+fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(text), .. }),
+                ..
+            }) => Some(text.value().trim().to_string()),
+            _ => None,
+        })
+        .collect();
+
+    let doc = lines.join("\n").trim().to_string();
+    (!doc.is_empty()).then_some(doc)
+}
+
+/// Expression producing the value of one parameter
+fn extract_argument(name: &syn::Ident, ty: &Type) -> TokenStream2 {
+    match last_segment(ty).as_deref() {
+        Some("HookInput") => quote!(::claude::HookInput::parse(&__input)?),
+        Some("HookContext") => quote!(__context.clone()),
+        _ if name == "tool_use_id" => quote!(__tool_use_id.clone()),
+        _ => {
+            let field = name.to_string();
+            let field = field.strip_prefix('_').unwrap_or(&field);
+            quote!(::claude::hooks::extract_field::<#ty>(&__input, #field)?)
+        }
+    }
+}
+
+/// Name of the last path segment of a type, e.g. `HookInput` for `claude::HookInput`
+fn last_segment(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
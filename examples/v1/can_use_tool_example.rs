@@ -78,6 +78,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 println!("   ✓ Success: {:?}", content);
                             }
                         }
+                        ContentBlock::Image { .. } | ContentBlock::Document { .. } | ContentBlock::Unknown(_) => {}
                     }
                 }
             }
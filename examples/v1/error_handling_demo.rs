@@ -1,10 +1,10 @@
-/// Example demonstrating error handling in the Claude SDK
-///
-/// This example shows:
-/// - Different error types and their use cases
-/// - Error creation and formatting
-/// - Result type usage
-/// - Error propagation
+//! Example demonstrating error handling in the Claude SDK
+//!
+//! This example shows:
+//! - Different error types and their use cases
+//! - Error creation and formatting
+//! - Result type usage
+//! - Error propagation
 
 use claude::errors::{ClaudeSDKError, Result};
 
@@ -1,13 +1,13 @@
-/// Hooks example demonstrating PreToolUse callbacks
-///
-/// This shows how to:
-/// - Create hook callbacks
-/// - Register them with HookManager
-/// - Use them with ClaudeSDKClient (when integrated)
-///
-/// Run with: cargo run --example hooks_example
-
-use claude::{HookCallback, HookManager, HookMatcherConfig, HookJSONOutput, HookContext};
+//! Hooks example demonstrating PreToolUse callbacks
+//!
+//! This shows how to:
+//! - Create hook callbacks
+//! - Register them with HookManager
+//! - Use them with ClaudeSDKClient (when integrated)
+//!
+//! Run with: cargo run --example hooks_example
+
+use claude::{HookCallback, HookEvent, HookManager, HookMatcherConfig, HookJSONOutput, HookContext};
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -28,6 +28,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 decision: Some("allow".to_string()),
                 system_message: Some("Hook executed successfully".to_string()),
                 hook_specific_output: None,
+                updated_input: None,
             })
         })
     });
@@ -53,6 +54,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         decision: Some("block".to_string()),
                         system_message: Some("Dangerous command blocked for safety".to_string()),
                         hook_specific_output: None,
+                        updated_input: None,
                     });
                 }
             }
@@ -62,13 +64,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 decision: Some("allow".to_string()),
                 system_message: None,
                 hook_specific_output: None,
+                updated_input: None,
             })
         })
     });
 
     let bash_callback_id = manager.register_callback(bash_callback);
     let bash_matcher = HookMatcherConfig::new("Bash".to_string(), vec![bash_callback_id]);
-    manager.add_matcher("PreToolUse".to_string(), bash_matcher);
+    manager.add_matcher(HookEvent::PreToolUse, bash_matcher);
 
     println!("Registered Bash command safety hook");
 
@@ -89,7 +92,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let logging_id = manager.register_callback(logging_callback);
     let wildcard_matcher = HookMatcherConfig::new("*".to_string(), vec![logging_id]);
-    manager.add_matcher("PreToolUse".to_string(), wildcard_matcher);
+    manager.add_matcher(HookEvent::PreToolUse, wildcard_matcher);
 
     println!("Registered wildcard logging hook");
 
@@ -105,11 +108,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         serde_json::Value::String("ls -la".to_string()),
     );
 
-    let context = HookContext { signal: None };
+    let context = HookContext::default();
 
     println!("Testing safe command:");
     let results = manager
-        .execute_hooks("PreToolUse", "Bash", test_input.clone(), Some("test-id".to_string()), context.clone())
+        .execute_hooks(HookEvent::PreToolUse, "Bash", test_input.clone(), Some("test-id".to_string()), context.clone())
         .await?;
 
     println!("Hook execution results: {} hooks executed", results.len());
@@ -122,7 +125,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
 
     let dangerous_results = manager
-        .execute_hooks("PreToolUse", "Bash", dangerous_input, Some("test-id-2".to_string()), context)
+        .execute_hooks(HookEvent::PreToolUse, "Bash", dangerous_input, Some("test-id-2".to_string()), context)
         .await?;
 
     for (i, result) in dangerous_results.iter().enumerate() {
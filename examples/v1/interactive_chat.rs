@@ -1,13 +1,13 @@
-/// Interactive chat example using ClaudeSDKClient
-///
-/// This demonstrates bidirectional conversation with Claude Code.
-/// It shows how to:
-/// - Connect to Claude
-/// - Send queries
-/// - Receive and process responses
-/// - Use receive_response() to get a complete response
-///
-/// Run with: cargo run --example interactive_chat
+//! Interactive chat example using ClaudeSDKClient
+//!
+//! This demonstrates bidirectional conversation with Claude Code.
+//! It shows how to:
+//! - Connect to Claude
+//! - Send queries
+//! - Receive and process responses
+//! - Use receive_response() to get a complete response
+//!
+//! Run with: cargo run --example interactive_chat
 
 use claude::{ClaudeSDKClient, ClaudeAgentOptions, Message, ContentBlock};
 use futures::StreamExt;
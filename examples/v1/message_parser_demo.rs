@@ -1,10 +1,10 @@
-/// Example demonstrating message parsing functionality
-///
-/// This example shows how to:
-/// - Parse different message types from JSON
-/// - Handle various content blocks
-/// - Work with streaming events
-/// - Error handling during parsing
+//! Example demonstrating message parsing functionality
+//!
+//! This example shows how to:
+//! - Parse different message types from JSON
+//! - Handle various content blocks
+//! - Work with streaming events
+//! - Error handling during parsing
 
 use claude::message_parser::parse_message;
 use claude::types::*;
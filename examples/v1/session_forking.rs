@@ -38,7 +38,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         ).await?;
 
         let mut response = client.receive_response();
-        let mut session_id: Option<String> = None;
+        let mut session_id: Option<claude::SessionId> = None;
 
         while let Some(result) = response.next().await {
             match result {
@@ -78,7 +78,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("--- Forking Session ({}) ---\n", approach);
 
         let options = ClaudeAgentOptions {
-            resume: Some(session_id.clone()),
+            resume: Some(session_id.as_str().into()),
             fork_session: true,  // Enable forking
             ..Default::default()
         };
@@ -102,7 +102,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         client.query(query, None).await?;
 
         let mut response = client.receive_response();
-        let mut forked_session_id: Option<String> = None;
+        let mut forked_session_id: Option<claude::SessionId> = None;
 
         while let Some(result) = response.next().await {
             match result {
@@ -36,7 +36,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         // Receive response and extract session ID
         let mut response = client.receive_response();
-        let mut session_id: Option<String> = None;
+        let mut session_id: Option<claude::SessionId> = None;
 
         while let Some(result) = response.next().await {
             match result {
@@ -79,7 +79,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("--- Resuming Session: {} ---\n", session_id);
 
         let options = ClaudeAgentOptions {
-            resume: Some(session_id.clone()),
+            resume: Some(session_id.as_str().into()),
             ..Default::default()
         };
 
@@ -1,13 +1,13 @@
-/// Simplified Real Claude Code connection example
-///
-/// This example actually connects to Claude Code CLI and gets real responses.
-/// It uses the simple_query function that collects all messages into a vector.
-///
-/// Prerequisites:
-/// - Claude Code must be installed: npm install -g @anthropic-ai/claude-code
-/// - ANTHROPIC_API_KEY must be set in environment
-///
-/// Run with: cargo run --example simple_real_query
+//! Simplified Real Claude Code connection example
+//!
+//! This example actually connects to Claude Code CLI and gets real responses.
+//! It uses the simple_query function that collects all messages into a vector.
+//!
+//! Prerequisites:
+//! - Claude Code must be installed: npm install -g @anthropic-ai/claude-code
+//! - ANTHROPIC_API_KEY must be set in environment
+//!
+//! Run with: cargo run --example simple_real_query
 
 use claude::{simple_query, ClaudeAgentOptions, ContentBlock, Message};
 
@@ -66,10 +66,8 @@ async fn basic_query() -> Result<(), Box<dyn std::error::Error>> {
                     println!("  Cost: ${:.6}", cost);
                 }
             }
-            Message::System(sys) => {
-                if sys.subtype == "status" {
-                    println!("  📋 Status update");
-                }
+            Message::System(sys) if sys.subtype == "status" => {
+                println!("  📋 Status update");
             }
             _ => {}
         }
@@ -83,11 +81,13 @@ async fn query_with_options() -> Result<(), Box<dyn std::error::Error>> {
     println!("--- Example 2: Query with Options ---");
     println!("Asking with custom system prompt\n");
 
-    let mut options = ClaudeAgentOptions::default();
-    options.system_prompt = Some(claude::SystemPromptConfig::Text(
-        "You are a helpful assistant that explains things simply in one sentence.".to_string(),
-    ));
-    options.max_turns = Some(1);
+    let options = ClaudeAgentOptions {
+        system_prompt: Some(claude::SystemPromptConfig::Text(
+            "You are a helpful assistant that explains things simply in one sentence.".to_string(),
+        )),
+        max_turns: Some(1),
+        ..Default::default()
+    };
 
     let messages = simple_query("What is Rust programming language?", Some(options)).await?;
 
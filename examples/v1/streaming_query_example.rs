@@ -1,12 +1,12 @@
-/// Streaming query example - demonstrates true async iteration
-///
-/// Unlike simple_query which collects all messages into a Vec,
-/// this example shows streaming where messages are processed as they arrive.
-///
-/// Prerequisites:
-/// - Claude Code must be installed: npm install -g @anthropic-ai/claude-code
-///
-/// Run with: cargo run --example streaming_query_example
+//! Streaming query example - demonstrates true async iteration
+//!
+//! Unlike simple_query which collects all messages into a Vec,
+//! this example shows streaming where messages are processed as they arrive.
+//!
+//! Prerequisites:
+//! - Claude Code must be installed: npm install -g @anthropic-ai/claude-code
+//!
+//! Run with: cargo run --example streaming_query_example
 
 use claude::{streaming_query, ContentBlock, Message};
 use futures::stream::StreamExt;
@@ -61,10 +61,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             println!("  Cost: ${:.6}", cost);
                         }
                     }
-                    Message::System(sys) => {
-                        if sys.subtype == "status" {
-                            println!("  📋 Status update");
-                        }
+                    Message::System(sys) if sys.subtype == "status" => {
+                        println!("  📋 Status update");
                     }
                     _ => {}
                 }
@@ -1,14 +1,13 @@
-/// Example demonstrating Claude SDK types
-///
-/// This example shows how to work with:
-/// - Message types (User, Assistant, System, Result)
-/// - Content blocks (Text, ToolUse, ToolResult, Thinking)
-/// - ClaudeAgentOptions configuration
-/// - Permission types
-/// - Serialization/deserialization
+//! Example demonstrating Claude SDK types
+//!
+//! This example shows how to work with:
+//! - Message types (User, Assistant, System, Result)
+//! - Content blocks (Text, ToolUse, ToolResult, Thinking)
+//! - ClaudeAgentOptions configuration
+//! - Permission types
+//! - Serialization/deserialization
 
 use claude::types::*;
-use serde_json;
 use std::collections::HashMap;
 
 fn main() {
@@ -36,11 +35,13 @@ fn main() {
 fn example_agent_options() {
     println!("--- Example 1: ClaudeAgentOptions ---");
 
-    let mut options = ClaudeAgentOptions::default();
-    options.allowed_tools = vec!["Read".to_string(), "Write".to_string(), "Bash".to_string()];
-    options.max_turns = Some(5);
-    options.permission_mode = Some(PermissionMode::AcceptEdits);
-    options.model = Some("claude-3-sonnet".to_string());
+    let options = ClaudeAgentOptions {
+        allowed_tools: vec!["Read".to_string(), "Write".to_string(), "Bash".to_string()],
+        max_turns: Some(5),
+        permission_mode: Some(PermissionMode::AcceptEdits),
+        model: Some("claude-3-sonnet".to_string()),
+        ..Default::default()
+    };
 
     println!("Created options with:");
     println!("  - Allowed tools: {:?}", options.allowed_tools);
@@ -57,6 +58,8 @@ fn example_messages() {
     let user_msg = UserMessage {
         content: UserMessageContent::Text("Hello, Claude!".to_string()),
         parent_tool_use_id: None,
+        uuid: None,
+        timestamp: None,
     };
     println!("User message: {:?}", user_msg);
 
@@ -69,6 +72,8 @@ fn example_messages() {
         ],
         model: "claude-3-sonnet".to_string(),
         parent_tool_use_id: None,
+        uuid: None,
+        timestamp: None,
     };
     println!("Assistant message: {:?}", assistant_msg);
 
@@ -79,10 +84,13 @@ fn example_messages() {
         duration_api_ms: 1200,
         is_error: false,
         num_turns: 3,
-        session_id: "session-123".to_string(),
+        session_id: "session-123".into(),
         total_cost_usd: Some(0.05),
         usage: None,
         result: Some("Success".to_string()),
+        model_usage: None,
+        uuid: None,
+        timestamp: None,
     };
     println!("Result message: {:?}", result_msg);
     println!();
@@ -160,17 +168,11 @@ fn example_permissions() {
     println!("Deny result: {:?}", deny_result);
 
     // Permission update
-    let permission_update = PermissionUpdate {
-        r#type: "addRules".to_string(),
-        rules: Some(vec![PermissionRuleValue {
-            tool_name: "Bash".to_string(),
-            rule_content: Some("allow all".to_string()),
-        }]),
-        behavior: Some(PermissionBehavior::Allow),
-        mode: None,
-        directories: None,
-        destination: Some(PermissionUpdateDestination::Session),
-    };
+    let permission_update = PermissionUpdate::add_rules(
+        vec![PermissionRuleValue::new("Bash", "allow all")],
+        PermissionBehavior::Allow,
+    )
+    .with_destination(PermissionUpdateDestination::Session);
     println!("Permission update: {:?}", permission_update);
     println!();
 }
@@ -230,6 +232,8 @@ fn example_serialization() {
         ],
         model: "claude-3-sonnet".to_string(),
         parent_tool_use_id: None,
+        uuid: None,
+        timestamp: None,
     };
 
     // Serialize to JSON
@@ -247,9 +251,11 @@ fn example_serialization() {
     println!();
 
     // Serialize ClaudeAgentOptions
-    let mut options = ClaudeAgentOptions::default();
-    options.allowed_tools = vec!["Read".to_string(), "Write".to_string()];
-    options.max_turns = Some(3);
+    let options = ClaudeAgentOptions {
+        allowed_tools: vec!["Read".to_string(), "Write".to_string()],
+        max_turns: Some(3),
+        ..Default::default()
+    };
 
     println!("ClaudeAgentOptions example:");
     println!("  Allowed tools: {:?}", options.allowed_tools);
@@ -1,7 +1,7 @@
-/// Quick verification that the library loads and basic types work
-/// This doesn't require Claude Code to be installed or API key
-///
-/// Run with: cargo run --example verify_library
+//! Quick verification that the library loads and basic types work
+//! This doesn't require Claude Code to be installed or API key
+//!
+//! Run with: cargo run --example verify_library
 
 use claude::{
     ClaudeAgentOptions, ContentBlock, Message, PermissionMode,
@@ -14,13 +14,15 @@ fn main() {
 
     // Test 1: Create options
     println!("✓ Creating ClaudeAgentOptions...");
-    let mut options = ClaudeAgentOptions::default();
-    options.allowed_tools = vec!["Read".to_string(), "Write".to_string()];
-    options.max_turns = Some(5);
-    options.permission_mode = Some(PermissionMode::AcceptEdits);
-    options.system_prompt = Some(SystemPromptConfig::Text(
-        "You are a helpful assistant".to_string(),
-    ));
+    let options = ClaudeAgentOptions {
+        allowed_tools: vec!["Read".to_string(), "Write".to_string()],
+        max_turns: Some(5),
+        permission_mode: Some(PermissionMode::AcceptEdits),
+        system_prompt: Some(SystemPromptConfig::Text(
+            "You are a helpful assistant".to_string(),
+        )),
+        ..Default::default()
+    };
     println!("  Options created: {:?} tools, max_turns={:?}",
              options.allowed_tools.len(), options.max_turns);
 
@@ -29,6 +31,8 @@ fn main() {
     let user_msg = UserMessage {
         content: UserMessageContent::Text("Hello Claude!".to_string()),
         parent_tool_use_id: None,
+        uuid: None,
+        timestamp: None,
     };
     println!("  User message created");
 
@@ -38,6 +42,8 @@ fn main() {
         }],
         model: "claude-sonnet-4".to_string(),
         parent_tool_use_id: None,
+        uuid: None,
+        timestamp: None,
     };
     println!("  Assistant message created with model: {}", assistant_msg.model);
 
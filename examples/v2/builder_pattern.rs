@@ -1,7 +1,7 @@
-/// Example: ClaudeOptionsBuilder - Fluent Configuration API
-///
-/// Demonstrates the builder pattern for constructing ClaudeAgentOptions
-/// with a chainable, discoverable API.
+//! Example: ClaudeOptionsBuilder - Fluent Configuration API
+//!
+//! Demonstrates the builder pattern for constructing ClaudeAgentOptions
+//! with a chainable, discoverable API.
 
 use claude::prelude::*;
 
@@ -11,7 +11,7 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
 
     // Example 1: Basic builder usage
     println!("1. Basic Configuration:");
-    let options = ClaudeOptionsBuilder::new()
+    let _options = ClaudeOptionsBuilder::new()
         .system_prompt("You are a helpful math tutor")
         .max_turns(5)
         .model("claude-sonnet-4-5")
@@ -21,7 +21,7 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
 
     // Example 2: Tool configuration
     println!("2. Tool Configuration:");
-    let options = ClaudeOptionsBuilder::new()
+    let _options = ClaudeOptionsBuilder::new()
         .allow_tools(["Read", "Write", "Edit"])
         .deny_tool("Bash")
         .permission_mode(PermissionMode::AcceptEdits)
@@ -33,7 +33,7 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
 
     // Example 3: Session management
     println!("3. Session Configuration:");
-    let options = ClaudeOptionsBuilder::new()
+    let _options = ClaudeOptionsBuilder::new()
         .resume_session("session-12345")
         .continue_conversation(true)
         .build();
@@ -43,7 +43,7 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
 
     // Example 4: Fork session
     println!("4. Fork Session:");
-    let options = ClaudeOptionsBuilder::new()
+    let _options = ClaudeOptionsBuilder::new()
         .fork_session("session-12345")
         .build();
 
@@ -51,7 +51,7 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
 
     // Example 5: Environment and paths
     println!("5. Environment Configuration:");
-    let options = ClaudeOptionsBuilder::new()
+    let _options = ClaudeOptionsBuilder::new()
         .cwd("/path/to/project")
         .add_directory("/path/to/context")
         .env("DEBUG", "true")
@@ -64,7 +64,7 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
 
     // Example 6: Complete configuration
     println!("6. Complete Configuration:");
-    let options = ClaudeOptionsBuilder::new()
+    let _options = ClaudeOptionsBuilder::new()
         .system_prompt("You are a code review assistant")
         .model("claude-sonnet-4-5")
         .max_turns(10)
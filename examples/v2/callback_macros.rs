@@ -1,7 +1,7 @@
-/// Example: Callback Macros - Simplified hook! and permission_callback!
-///
-/// Demonstrates how the macros hide complex type signatures
-/// and make callback creation much simpler.
+//! Example: Callback Macros - Simplified hook! and permission_callback!
+//!
+//! Demonstrates how the macros hide complex type signatures
+//! and make callback creation much simpler.
 
 use claude::{hook, permission_callback, HookJSONOutput, PermissionResult};
 
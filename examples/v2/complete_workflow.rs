@@ -1,7 +1,7 @@
-/// Example: Complete V2 Workflow
-///
-/// Demonstrates a complete workflow using all V2 features together
-/// in a realistic scenario.
+//! Example: Complete V2 Workflow
+//!
+//! Demonstrates a complete workflow using all V2 features together
+//! in a realistic scenario.
 
 use claude::prelude::*;
 
@@ -20,7 +20,7 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     // Step 2: Configure with builder
     println!("Step 2: Configure Review Assistant");
     println!("-------");
-    let options = ClaudeOptionsBuilder::new()
+    let _options = ClaudeOptionsBuilder::new()
         .system_prompt("You are an expert code reviewer focusing on Rust best practices")
         .model("claude-sonnet-4-5")
         .max_turns(5)
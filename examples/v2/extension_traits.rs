@@ -1,7 +1,7 @@
-/// Example: MessageVecExt - Extension Trait Methods
-///
-/// Demonstrates all the helper methods provided by the MessageVecExt trait
-/// for working with message vectors without verbose pattern matching.
+//! Example: MessageVecExt - Extension Trait Methods
+//!
+//! Demonstrates all the helper methods provided by the MessageVecExt trait
+//! for working with message vectors without verbose pattern matching.
 
 use claude::prelude::*;
 
@@ -135,6 +135,8 @@ fn create_mock_messages() -> Vec<Message> {
             ],
             model: "claude-sonnet-4-5".to_string(),
             parent_tool_use_id: None,
+            uuid: None,
+            timestamp: None,
         }),
         Message::Assistant(AssistantMessage {
             content: vec![ContentBlock::Text {
@@ -142,6 +144,8 @@ fn create_mock_messages() -> Vec<Message> {
             }],
             model: "claude-sonnet-4-5".to_string(),
             parent_tool_use_id: None,
+            uuid: None,
+            timestamp: None,
         }),
     ]
 }
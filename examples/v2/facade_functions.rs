@@ -1,7 +1,7 @@
-/// Example: Facade Functions - Simplest API
-///
-/// Demonstrates ask(), ask_with_options(), and QuickQuery
-/// for the most common use cases with minimal code.
+//! Example: Facade Functions - Simplest API
+//!
+//! Demonstrates ask(), ask_with_options(), and QuickQuery
+//! for the most common use cases with minimal code.
 
 use claude::prelude::*;
 
@@ -20,7 +20,7 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
 
     // Example 2: ask_with_options()
     println!("2. ask_with_options() - With Configuration:");
-    let options = ClaudeAgentOptions::with_system_prompt("You are a Rust expert");
+    let _options = ClaudeAgentOptions::with_system_prompt("You are a Rust expert");
     println!("   Code:");
     println!("   let options = ClaudeAgentOptions::with_system_prompt(\"You are a Rust expert\");");
     println!("   let answer = ask_with_options(\"What is ownership?\", options).await?;");
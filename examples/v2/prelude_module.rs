@@ -1,9 +1,10 @@
-/// Example: Prelude Module - One Import for Everything
-///
-/// Demonstrates the prelude module that provides all commonly used
-/// types and functions in a single import.
+//! Example: Prelude Module - One Import for Everything
+//!
+//! Demonstrates the prelude module that provides all commonly used
+//! types and functions in a single import.
 
 // The magic line - one import for everything!
+#[allow(unused_imports)]
 use claude::prelude::*;
 
 #[tokio::main]
@@ -1,7 +1,7 @@
-/// Example: Using MessageVecExt trait for easier message handling
-///
-/// This demonstrates the new extension methods that make it easier
-/// to work with message vectors without verbose pattern matching.
+//! Example: Using MessageVecExt trait for easier message handling
+//!
+//! This demonstrates the new extension methods that make it easier
+//! to work with message vectors without verbose pattern matching.
 
 use claude::prelude::*;
 
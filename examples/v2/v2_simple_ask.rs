@@ -1,7 +1,7 @@
-/// Example: Simplest way to use the SDK with v2 API
-///
-/// This demonstrates the new `ask()` facade function which is the
-/// most straightforward way to interact with Claude.
+//! Example: Simplest way to use the SDK with v2 API
+//!
+//! This demonstrates the new `ask()` facade function which is the
+//! most straightforward way to interact with Claude.
 
 use claude::prelude::*;
 
@@ -1,7 +1,7 @@
-/// Example: Simplified callback creation with macros
-///
-/// This demonstrates the new hook! and permission_callback! macros
-/// that hide the complex type signatures.
+//! Example: Simplified callback creation with macros
+//!
+//! This demonstrates the new hook! and permission_callback! macros
+//! that hide the complex type signatures.
 
 use claude::{hook, permission_callback, HookJSONOutput, PermissionResult};
 
@@ -0,0 +1,609 @@
+//! Transport that talks to the Anthropic Messages API directly
+//!
+//! [`ApiTransport`] implements [`Transport`] without the Claude Code CLI. Each
+//! user turn is sent to `POST /v1/messages` and the response is translated
+//! into the same stream-json messages the CLI would print (`system` init,
+//! `assistant`, `result`), so `simple_query`, `ask`, and `ClaudeSDKClient`
+//! work unchanged.
+//!
+//! Claude Code's built-in tools, hooks, and permission callbacks are not
+//! available: the model only sees the conversation, the system prompt, and
+//! the model options.
+//!
+//! Requires the `api-transport` cargo feature.
+//!
+//! # Example
+//! ```no_run
+//! use claude::api_transport::ApiTransport;
+//! use claude::simple_query_with_transport;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     // Reads ANTHROPIC_API_KEY from the environment
+//!     let transport = ApiTransport::new("What is 2 + 2?".to_string(), Default::default());
+//!     let messages = simple_query_with_transport(Box::new(transport)).await?;
+//!     println!("{:?}", messages);
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::{ClaudeSDKError, Result};
+use crate::transport::{Transport, TransportStream};
+use crate::types::{ClaudeAgentOptions, SystemPromptConfig};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Default API endpoint
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+
+/// API version sent in the `anthropic-version` header
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Model used when `ClaudeAgentOptions::model` is unset
+const DEFAULT_MODEL: &str = "claude-sonnet-4-5";
+
+/// Default `max_tokens` for each request
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+type Outbox = Arc<Mutex<Option<mpsc::UnboundedSender<Result<Value>>>>>;
+
+/// State shared between the transport and its in-flight request tasks
+struct Conversation {
+    client: reqwest::Client,
+    url: String,
+    api_key: String,
+    model: String,
+    max_tokens: u32,
+    system: Option<String>,
+    session_id: String,
+    history: Mutex<Vec<Value>>,
+    num_turns: Mutex<i32>,
+}
+
+/// Transport backed by the Anthropic Messages API instead of the CLI
+pub struct ApiTransport {
+    prompt: String,
+    options: ClaudeAgentOptions,
+    api_key: Option<String>,
+    base_url: String,
+    max_tokens: u32,
+    outbox: Outbox,
+    inbox: Option<mpsc::UnboundedReceiver<Result<Value>>>,
+    conversation: Option<Arc<Conversation>>,
+    turn_task: Option<JoinHandle<()>>,
+}
+
+impl ApiTransport {
+    /// Create a transport for a prompt, mirroring `SubprocessTransport::new`
+    ///
+    /// A non-empty prompt is sent as a one-shot query and the message stream
+    /// ends after its result. An empty prompt starts an interactive session
+    /// driven by the user messages written to the transport.
+    pub fn new(prompt: String, options: ClaudeAgentOptions) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        Self {
+            prompt,
+            options,
+            api_key: None,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            max_tokens: DEFAULT_MAX_TOKENS,
+            outbox: Arc::new(Mutex::new(Some(tx))),
+            inbox: Some(rx),
+            conversation: None,
+            turn_task: None,
+        }
+    }
+
+    /// Use an explicit API key instead of `ANTHROPIC_API_KEY`
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Send requests to a different endpoint (e.g. a proxy or gateway)
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set `max_tokens` for each request
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Resolve the API key from the builder, `options.env`, or the process environment
+    fn resolve_api_key(&self) -> Option<String> {
+        self.api_key
+            .clone()
+            .or_else(|| self.options.env.get("ANTHROPIC_API_KEY").cloned())
+            .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok())
+            .filter(|key| !key.is_empty())
+    }
+
+    fn system_prompt(&self) -> Option<String> {
+        match self.options.system_prompt.as_ref()? {
+            SystemPromptConfig::Text(text) => Some(text.clone()),
+            SystemPromptConfig::Preset(preset) => preset.append.clone(),
+        }
+    }
+
+    fn push(outbox: &Outbox, item: Result<Value>) {
+        if let Some(tx) = outbox.lock().unwrap().as_ref() {
+            let _ = tx.send(item);
+        }
+    }
+
+    /// Queue a user turn and run the request in the background
+    fn start_turn(&mut self, content: Value, finish_after: bool) -> Result<()> {
+        let conversation = self.conversation.clone().ok_or_else(|| {
+            ClaudeSDKError::cli_connection_error("Transport is not ready for writing".to_string())
+        })?;
+        let outbox = Arc::clone(&self.outbox);
+
+        // Turns run one at a time so the history stays in order
+        let previous = self.turn_task.take();
+        self.turn_task = Some(tokio::spawn(async move {
+            if let Some(previous) = previous {
+                let _ = previous.await;
+            }
+
+            conversation
+                .history
+                .lock()
+                .unwrap()
+                .push(json!({"role": "user", "content": content}));
+
+            for item in conversation.run_turn().await {
+                Self::push(&outbox, item);
+            }
+
+            if finish_after {
+                outbox.lock().unwrap().take();
+            }
+        }));
+
+        Ok(())
+    }
+
+    fn respond_to_control_request(&mut self, message: &Value) {
+        let Some(request_id) = message.get("request_id").and_then(|v| v.as_str()) else {
+            return;
+        };
+
+        if message.pointer("/request/subtype").and_then(|v| v.as_str()) == Some("interrupt") {
+            if let Some(task) = self.turn_task.take() {
+                task.abort();
+            }
+        }
+
+        Self::push(
+            &self.outbox,
+            Ok(json!({
+                "type": "control_response",
+                "response": {
+                    "subtype": "success",
+                    "request_id": request_id,
+                    "response": {}
+                }
+            })),
+        );
+    }
+}
+
+impl Conversation {
+    /// Send the conversation so far and translate the reply into CLI messages
+    async fn run_turn(&self) -> Vec<Result<Value>> {
+        let started = Instant::now();
+        let messages = self.history.lock().unwrap().clone();
+
+        let mut body = json!({
+            "model": self.model,
+            "max_tokens": self.max_tokens,
+            "messages": messages,
+        });
+        if let Some(ref system) = self.system {
+            body["system"] = json!(system);
+        }
+
+        let response = match self.send(&body).await {
+            Ok(response) => response,
+            Err(e) => return vec![Err(e)],
+        };
+        let duration_ms = started.elapsed().as_millis() as i64;
+
+        let content = response.get("content").cloned().unwrap_or_else(|| json!([]));
+        self.history
+            .lock()
+            .unwrap()
+            .push(json!({"role": "assistant", "content": content}));
+
+        let num_turns = {
+            let mut turns = self.num_turns.lock().unwrap();
+            *turns += 1;
+            *turns
+        };
+
+        let text: String = content
+            .as_array()
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("")
+            })
+            .unwrap_or_default();
+
+        vec![
+            Ok(json!({
+                "type": "assistant",
+                "message": {
+                    "model": response.get("model").cloned().unwrap_or_else(|| json!(self.model)),
+                    "content": content,
+                }
+            })),
+            Ok(json!({
+                "type": "result",
+                "subtype": "success",
+                "duration_ms": duration_ms,
+                "duration_api_ms": duration_ms,
+                "is_error": false,
+                "num_turns": num_turns,
+                "session_id": self.session_id,
+                "usage": response.get("usage").cloned().unwrap_or_else(|| json!({})),
+                "result": text,
+            })),
+        ]
+    }
+
+    async fn send(&self, body: &Value) -> Result<Value> {
+        let response = self
+            .client
+            .post(&self.url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("content-type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| ClaudeSDKError::cli_connection_error(format!("Anthropic API request failed: {}", e)))?;
+
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get("retry-after")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ClaudeSDKError::cli_connection_error(format!("Failed to read Anthropic API response: {}", e)))?;
+
+        if !status.is_success() {
+            let message = serde_json::from_str::<Value>(&text)
+                .ok()
+                .and_then(|v| v.pointer("/error/message").and_then(|m| m.as_str()).map(String::from))
+                .unwrap_or(text);
+            // 529 is the API's "overloaded" status
+            if matches!(status.as_u16(), 429 | 529) {
+                return Err(ClaudeSDKError::rate_limited(
+                    format!("Anthropic API error ({}): {}", status.as_u16(), message),
+                    retry_after,
+                ));
+            }
+            return Err(ClaudeSDKError::cli_connection_error(format!(
+                "Anthropic API error ({}): {}",
+                status.as_u16(),
+                message
+            )));
+        }
+
+        serde_json::from_str(&text).map_err(|e| ClaudeSDKError::json_decode_error_from(text, e))
+    }
+}
+
+#[async_trait]
+impl Transport for ApiTransport {
+    async fn connect(&mut self) -> Result<()> {
+        if self.conversation.is_some() {
+            return Ok(());
+        }
+
+        let api_key = self
+            .resolve_api_key()
+            .ok_or_else(|| ClaudeSDKError::cli_connection_error("ANTHROPIC_API_KEY is not set".to_string()))?;
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let model = self.options.model.clone().unwrap_or_else(|| DEFAULT_MODEL.to_string());
+
+        let conversation = Conversation {
+            client: reqwest::Client::new(),
+            url: format!("{}/v1/messages", self.base_url),
+            api_key,
+            model: model.clone(),
+            max_tokens: self.max_tokens,
+            system: self.system_prompt(),
+            session_id: format!("api-{:x}", nanos),
+            history: Mutex::new(Vec::new()),
+            num_turns: Mutex::new(0),
+        };
+
+        Self::push(
+            &self.outbox,
+            Ok(json!({
+                "type": "system",
+                "subtype": "init",
+                "session_id": conversation.session_id,
+                "model": model,
+                "tools": [],
+            })),
+        );
+
+        self.conversation = Some(Arc::new(conversation));
+
+        if !self.prompt.is_empty() {
+            let prompt = std::mem::take(&mut self.prompt);
+            self.start_turn(json!(prompt), true)?;
+        }
+
+        Ok(())
+    }
+
+    async fn write(&mut self, data: &str) -> Result<()> {
+        if self.conversation.is_none() {
+            return Err(ClaudeSDKError::cli_connection_error(
+                "Transport is not ready for writing".to_string(),
+            ));
+        }
+
+        for line in data.lines().filter(|line| !line.trim().is_empty()) {
+            let message: Value = serde_json::from_str(line)
+                .map_err(|e| ClaudeSDKError::json_decode_error_from(line, e))?;
+
+            match message.get("type").and_then(|v| v.as_str()) {
+                Some("user") => {
+                    let content = message
+                        .pointer("/message/content")
+                        .cloned()
+                        .ok_or_else(|| ClaudeSDKError::message_parse_error("User message missing content", Some(message.clone())))?;
+                    self.start_turn(content, false)?;
+                }
+                Some("control_request") => self.respond_to_control_request(&message),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn end_input(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_messages(&mut self) -> TransportStream {
+        match self.inbox.take() {
+            Some(mut rx) => Box::pin(async_stream::stream! {
+                while let Some(item) = rx.recv().await {
+                    yield item;
+                }
+            }),
+            None => Box::pin(futures::stream::empty()),
+        }
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        if let Some(task) = self.turn_task.take() {
+            task.abort();
+        }
+        self.conversation = None;
+        self.outbox.lock().unwrap().take();
+        Ok(())
+    }
+
+    fn is_ready(&self) -> bool {
+        self.conversation.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve canned HTTP responses, one per connection, returning the request bodies
+    async fn serve(responses: Vec<(u16, Value)>) -> (String, JoinHandle<Vec<Value>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let mut bodies = Vec::new();
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                let body_start = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break pos + 4;
+                    }
+                };
+                let headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+                let length: usize = headers
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map(|v| v.trim().parse().unwrap())
+                    .unwrap_or(0);
+                while request.len() < body_start + length {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                assert!(headers.contains("x-api-key: test-key"));
+                bodies.push(serde_json::from_slice(&request[body_start..]).unwrap());
+
+                let body = body.to_string();
+                let response = format!(
+                    "HTTP/1.1 {} OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            bodies
+        });
+
+        (url, server)
+    }
+
+    fn reply(text: &str) -> Value {
+        json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-5",
+            "content": [{"type": "text", "text": text}],
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 10, "output_tokens": 2}
+        })
+    }
+
+    #[test]
+    fn test_api_key_resolution_order() {
+        let mut env = std::collections::HashMap::new();
+        env.insert("ANTHROPIC_API_KEY".to_string(), "from-options".to_string());
+        let options = ClaudeAgentOptions {
+            env,
+            ..Default::default()
+        };
+
+        let transport = ApiTransport::new(String::new(), options.clone());
+        assert_eq!(transport.resolve_api_key().as_deref(), Some("from-options"));
+
+        let transport = ApiTransport::new(String::new(), options).with_api_key("explicit");
+        assert_eq!(transport.resolve_api_key().as_deref(), Some("explicit"));
+    }
+
+    #[tokio::test]
+    async fn test_one_shot_query_translates_response() {
+        let (url, server) = serve(vec![(200, reply("4"))]).await;
+
+        let options = ClaudeAgentOptions {
+            system_prompt: Some(SystemPromptConfig::Text("Be brief".to_string())),
+            ..Default::default()
+        };
+        let transport = ApiTransport::new("What is 2 + 2?".to_string(), options)
+            .with_api_key("test-key")
+            .with_base_url(url);
+
+        let messages = crate::simple_query::simple_query_with_transport(Box::new(transport))
+            .await
+            .unwrap();
+
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(messages[0], crate::Message::System(_)));
+        assert!(matches!(messages[1], crate::Message::Assistant(_)));
+        match &messages[2] {
+            crate::Message::Result(result) => {
+                assert_eq!(result.result.as_deref(), Some("4"));
+                assert_eq!(result.num_turns, 1);
+            }
+            other => panic!("expected result, got {:?}", other),
+        }
+
+        let bodies = server.await.unwrap();
+        assert_eq!(bodies[0]["system"], "Be brief");
+        assert_eq!(bodies[0]["messages"][0]["content"], "What is 2 + 2?");
+    }
+
+    #[tokio::test]
+    async fn test_interactive_turns_keep_history() {
+        let (url, server) = serve(vec![(200, reply("Hello!")), (200, reply("Bye!"))]).await;
+
+        let mut transport = ApiTransport::new(String::new(), ClaudeAgentOptions::default())
+            .with_api_key("test-key")
+            .with_base_url(url);
+        transport.connect().await.unwrap();
+        let mut stream = transport.read_messages();
+        assert_eq!(stream.next().await.unwrap().unwrap()["type"], "system");
+
+        for prompt in ["Hi", "Goodbye"] {
+            let message = crate::types::UserEnvelope::text(prompt).to_wire();
+            transport.write(&format!("{}\n", message)).await.unwrap();
+            assert_eq!(stream.next().await.unwrap().unwrap()["type"], "assistant");
+            assert_eq!(stream.next().await.unwrap().unwrap()["type"], "result");
+        }
+
+        let bodies = server.await.unwrap();
+        let history = bodies[1]["messages"].as_array().unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[1]["role"], "assistant");
+        assert_eq!(history[2]["content"], "Goodbye");
+    }
+
+    #[tokio::test]
+    async fn test_api_error_is_surfaced() {
+        let (url, _server) = serve(vec![(
+            401,
+            json!({"type": "error", "error": {"type": "authentication_error", "message": "invalid x-api-key"}}),
+        )])
+        .await;
+
+        let transport = ApiTransport::new("hi".to_string(), ClaudeAgentOptions::default())
+            .with_api_key("test-key")
+            .with_base_url(url);
+
+        let err = crate::simple_query::simple_query_with_transport(Box::new(transport))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid x-api-key"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_is_typed() {
+        let (url, _server) = serve(vec![(
+            429,
+            json!({"type": "error", "error": {"type": "rate_limit_error", "message": "Slow down"}}),
+        )])
+        .await;
+
+        let transport = ApiTransport::new("hi".to_string(), ClaudeAgentOptions::default())
+            .with_api_key("test-key")
+            .with_base_url(url);
+
+        let err = crate::simple_query::simple_query_with_transport(Box::new(transport))
+            .await
+            .unwrap_err();
+        assert!(matches!(&err, ClaudeSDKError::RateLimited { message, retry_after: None } if message.contains("Slow down")));
+    }
+
+    #[tokio::test]
+    async fn test_answers_control_requests() {
+        let mut transport = ApiTransport::new(String::new(), ClaudeAgentOptions::default())
+            .with_api_key("test-key");
+        transport.connect().await.unwrap();
+        let mut stream = transport.read_messages();
+        stream.next().await.unwrap().unwrap();
+
+        transport
+            .write("{\"type\":\"control_request\",\"request_id\":\"req_1\",\"request\":{\"subtype\":\"initialize\"}}\n")
+            .await
+            .unwrap();
+
+        let response = stream.next().await.unwrap().unwrap();
+        assert_eq!(response["response"]["request_id"], "req_1");
+    }
+}
@@ -0,0 +1,243 @@
+//! Rebuilding assistant messages from partial-message stream events
+//!
+//! With `include_partial_messages` the CLI sends [`StreamEvent`]s carrying
+//! raw deltas. [`MessageAssembler`] folds them back into content blocks, so
+//! a UI can render the message as it grows and still get the finished
+//! [`AssistantMessage`] at the end.
+//!
+//! # Example
+//! ```no_run
+//! use claude::{streaming_query, AssembledMessage, ClaudeOptionsBuilder};
+//! use futures::stream::StreamExt;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let options = ClaudeOptionsBuilder::new().include_partial_messages(true).build();
+//!     let mut stream = streaming_query("Write a haiku", Some(options)).await?.partial_messages();
+//!
+//!     while let Some(update) = stream.next().await {
+//!         match update? {
+//!             AssembledMessage::Partial(partial) => println!("{} blocks so far", partial.content.len()),
+//!             AssembledMessage::Complete(message) => println!("done: {:?}", message.content),
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::types::{AssistantMessage, ContentBlock, ContentDelta, Message, StreamEvent, StreamEventKind};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Snapshot of an assistant message that is still streaming
+#[derive(Debug, Clone)]
+pub struct PartialAssistantMessage {
+    /// Blocks received so far; the last one may be incomplete. A tool use's
+    /// input stays empty until its JSON is complete.
+    pub content: Vec<ContentBlock>,
+    pub model: String,
+    pub parent_tool_use_id: Option<String>,
+}
+
+/// Output of [`MessageAssembler::push`]
+#[derive(Debug, Clone)]
+pub enum AssembledMessage {
+    /// The message changed but is not finished
+    Partial(PartialAssistantMessage),
+    /// The message is finished
+    Complete(AssistantMessage),
+}
+
+/// A message being assembled
+#[derive(Default)]
+struct InProgress {
+    model: String,
+    content: Vec<ContentBlock>,
+    // Raw `input_json_delta` fragments per block index
+    tool_input_json: HashMap<usize, String>,
+}
+
+/// State machine that turns stream events into assistant messages
+///
+/// Events of subagents (with a `parent_tool_use_id`) are tracked separately
+/// from the main conversation, so interleaved streams don't mix.
+#[derive(Default)]
+pub struct MessageAssembler {
+    messages: HashMap<Option<String>, InProgress>,
+}
+
+impl MessageAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one stream event
+    ///
+    /// Returns a snapshot when the message changed, the finished message on
+    /// `message_stop`, and `None` for events that don't affect the content
+    /// (such as `ping` or `message_delta`).
+    pub fn push(&mut self, event: &StreamEvent) -> Option<AssembledMessage> {
+        let key = event.parent_tool_use_id.clone();
+        match event.kind() {
+            StreamEventKind::MessageStart { message } => {
+                let model = message.get("model").and_then(|v| v.as_str()).unwrap_or_default();
+                self.messages.insert(
+                    key.clone(),
+                    InProgress {
+                        model: model.to_string(),
+                        ..Default::default()
+                    },
+                );
+            }
+            StreamEventKind::ContentBlockStart { index, content_block } => {
+                let message = self.messages.entry(key.clone()).or_default();
+                if index < message.content.len() {
+                    message.content[index] = content_block;
+                } else {
+                    message.content.push(content_block);
+                }
+            }
+            StreamEventKind::ContentBlockDelta { index, delta } => {
+                let message = self.messages.get_mut(&key)?;
+                let block = message.content.get_mut(index)?;
+                match (block, delta) {
+                    (ContentBlock::Text { text }, ContentDelta::TextDelta { text: more }) => text.push_str(&more),
+                    (ContentBlock::Thinking { thinking, .. }, ContentDelta::ThinkingDelta { thinking: more }) => {
+                        thinking.push_str(&more)
+                    }
+                    (ContentBlock::Thinking { signature, .. }, ContentDelta::SignatureDelta { signature: more }) => {
+                        signature.push_str(&more)
+                    }
+                    (ContentBlock::ToolUse { .. }, ContentDelta::InputJsonDelta { partial_json }) => {
+                        message.tool_input_json.entry(index).or_default().push_str(&partial_json)
+                    }
+                    _ => return None,
+                }
+            }
+            StreamEventKind::ContentBlockStop { index } => {
+                let message = self.messages.get_mut(&key)?;
+                let json = message.tool_input_json.remove(&index);
+                if let (Some(ContentBlock::ToolUse { input, .. }), Some(json)) = (message.content.get_mut(index), json) {
+                    if let Ok(parsed) = serde_json::from_str::<HashMap<String, Value>>(&json) {
+                        *input = parsed;
+                    }
+                }
+            }
+            StreamEventKind::MessageStop => {
+                let message = self.messages.remove(&key)?;
+                return Some(AssembledMessage::Complete(AssistantMessage {
+                    content: message.content,
+                    model: message.model,
+                    parent_tool_use_id: key,
+                    uuid: None,
+                    timestamp: None,
+                }));
+            }
+            StreamEventKind::MessageDelta { .. } | StreamEventKind::Unknown => return None,
+        }
+
+        let message = self.messages.get(&key)?;
+        Some(AssembledMessage::Partial(PartialAssistantMessage {
+            content: message.content.clone(),
+            model: message.model.clone(),
+            parent_tool_use_id: key,
+        }))
+    }
+
+    /// Feed a message, ignoring anything but stream events
+    pub fn push_message(&mut self, message: &Message) -> Option<AssembledMessage> {
+        match message {
+            Message::Stream(event) => self.push(event),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(data: Value) -> StreamEvent {
+        StreamEvent {
+            uuid: "e".to_string(),
+            session_id: "s".into(),
+            event: serde_json::from_value(data).unwrap(),
+            parent_tool_use_id: None,
+        }
+    }
+
+    #[test]
+    fn test_assembles_text_and_tool_use() {
+        let mut assembler = MessageAssembler::new();
+        let events = [
+            json!({"type": "message_start", "message": {"model": "claude-sonnet-4-5"}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Let me "}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "check"}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "t1", "name": "Bash", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"command\": "}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "\"ls\"}"}}),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}}),
+        ];
+
+        let mut last_partial = None;
+        for data in events {
+            if let Some(AssembledMessage::Partial(partial)) = assembler.push(&event(data)) {
+                last_partial = Some(partial);
+            }
+        }
+        let partial = last_partial.unwrap();
+        assert_eq!(partial.content.len(), 2);
+        assert!(matches!(&partial.content[0], ContentBlock::Text { text } if text == "Let me check"));
+
+        match assembler.push(&event(json!({"type": "message_stop"}))) {
+            Some(AssembledMessage::Complete(message)) => {
+                assert_eq!(message.model, "claude-sonnet-4-5");
+                match &message.content[1] {
+                    ContentBlock::ToolUse { id, input, .. } => {
+                        assert_eq!(id, "t1");
+                        assert_eq!(input["command"], "ls");
+                    }
+                    other => panic!("expected tool use, got {:?}", other),
+                }
+            }
+            other => panic!("expected complete message, got {:?}", other),
+        }
+        assert!(assembler.push(&event(json!({"type": "message_stop"}))).is_none());
+    }
+
+    #[test]
+    fn test_keeps_subagent_streams_apart() {
+        let mut assembler = MessageAssembler::new();
+        let sub = |data: Value| {
+            let mut e = event(data);
+            e.parent_tool_use_id = Some("task-1".to_string());
+            e
+        };
+
+        assembler.push(&event(json!({"type": "message_start", "message": {"model": "main"}})));
+        assembler.push(&sub(json!({"type": "message_start", "message": {"model": "sub"}})));
+        assembler.push(&event(json!({"type": "content_block_start", "index": 0, "content_block": {"type": "thinking", "thinking": "", "signature": ""}})));
+        assembler.push(&sub(json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": "sub"}})));
+        assembler.push(&event(json!({"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "hmm"}})));
+        assembler.push(&event(json!({"type": "content_block_delta", "index": 0, "delta": {"type": "signature_delta", "signature": "sig"}})));
+
+        match assembler.push(&sub(json!({"type": "message_stop"}))) {
+            Some(AssembledMessage::Complete(message)) => {
+                assert_eq!(message.model, "sub");
+                assert_eq!(message.parent_tool_use_id.as_deref(), Some("task-1"));
+            }
+            other => panic!("expected complete subagent message, got {:?}", other),
+        }
+        match assembler.push(&event(json!({"type": "message_stop"}))) {
+            Some(AssembledMessage::Complete(message)) => assert!(matches!(
+                &message.content[0],
+                ContentBlock::Thinking { thinking, signature } if thinking == "hmm" && signature == "sig"
+            )),
+            other => panic!("expected complete message, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Audit trail of permission decisions
+//!
+//! [`AuditLogger`] wraps a [`CanUseToolCallback`] and records every tool
+//! request it sees, together with the decision, to a JSONL file or a sink of
+//! your own. Records are written before the decision is returned to the CLI.
+//!
+//! # Example
+//! ```no_run
+//! use claude::permissions::sandbox;
+//! use claude::{AuditLogger, ClaudeSDKClient};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let audit = AuditLogger::jsonl("permissions.jsonl")?;
+//!     let mut client = ClaudeSDKClient::builder()
+//!         .can_use_tool(audit.wrap(sandbox("/srv/project")))
+//!         .build();
+//!     client.connect().await?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::permissions::CanUseToolCallback;
+use crate::types::PermissionResult;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Outcome of an audited tool request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditDecision {
+    Allow,
+    Deny,
+    /// The callback returned an error, which is passed on unchanged
+    Error,
+}
+
+/// One audited tool request
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditRecord {
+    /// Milliseconds since the Unix epoch when the decision was made
+    pub timestamp_ms: u64,
+    /// Session the request belongs to, if the CLI has reported one
+    pub session_id: Option<crate::types::SessionId>,
+    pub tool_name: String,
+    /// Tool input as requested by Claude
+    pub input: HashMap<String, Value>,
+    pub decision: AuditDecision,
+    /// Deny message or error text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Input the callback rewrote the request to, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_input: Option<HashMap<String, Value>>,
+}
+
+/// Receives every audit record
+pub type AuditSink = Arc<dyn Fn(&AuditRecord) + Send + Sync>;
+
+/// Records the permission decisions of the callbacks it wraps
+#[derive(Clone)]
+pub struct AuditLogger {
+    sink: AuditSink,
+}
+
+impl AuditLogger {
+    /// Send records to a closure
+    pub fn new(sink: impl Fn(&AuditRecord) + Send + Sync + 'static) -> Self {
+        Self { sink: Arc::new(sink) }
+    }
+
+    /// Append records to a JSONL file, creating it if needed
+    ///
+    /// Each record is written and flushed as one line. Write errors are
+    /// ignored so a full disk cannot block tool use.
+    pub fn jsonl(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let file: Mutex<File> = Mutex::new(file);
+
+        Ok(Self::new(move |record| {
+            if let Ok(line) = serde_json::to_string(record) {
+                let mut file = file.lock().unwrap();
+                let _ = writeln!(file, "{}", line).and_then(|_| file.flush());
+            }
+        }))
+    }
+
+    /// Wrap `callback` so each of its decisions is recorded
+    pub fn wrap(&self, callback: CanUseToolCallback) -> CanUseToolCallback {
+        let sink = Arc::clone(&self.sink);
+        Arc::new(move |tool_name, input, context| {
+            let sink = Arc::clone(&sink);
+            let callback = Arc::clone(&callback);
+            Box::pin(async move {
+                let session_id = context.session_id.clone();
+                let result = callback(tool_name.clone(), input.clone(), context).await;
+
+                let (decision, reason, updated_input) = match &result {
+                    Ok(PermissionResult::Allow { updated_input, .. }) => {
+                        (AuditDecision::Allow, None, updated_input.clone())
+                    }
+                    Ok(PermissionResult::Deny { message, .. }) => {
+                        (AuditDecision::Deny, Some(message.clone()), None)
+                    }
+                    Err(e) => (AuditDecision::Error, Some(e.to_string()), None),
+                };
+                sink(&AuditRecord {
+                    timestamp_ms: now_ms(),
+                    session_id,
+                    tool_name,
+                    input,
+                    decision,
+                    reason,
+                    updated_input,
+                });
+
+                result
+            })
+        })
+    }
+}
+
+impl std::fmt::Debug for AuditLogger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditLogger").finish_non_exhaustive()
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ClaudeSDKError;
+    use crate::types::ToolPermissionContext;
+    use serde_json::json;
+
+    fn callback() -> CanUseToolCallback {
+        Arc::new(|tool_name, _input, _context| {
+            Box::pin(async move {
+                match tool_name.as_str() {
+                    "Bash" => Ok(PermissionResult::deny("no shell")),
+                    "Broken" => Err(ClaudeSDKError::CLIConnectionError("offline".to_string())),
+                    _ => Ok(PermissionResult::allow()),
+                }
+            })
+        })
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_records_every_decision() {
+        let path = std::env::temp_dir().join(format!("claude-rs-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let audited = AuditLogger::jsonl(&path).unwrap().wrap(callback());
+        let context = ToolPermissionContext {
+            session_id: Some("session-1".into()),
+            ..Default::default()
+        };
+        let input = HashMap::from([("command".to_string(), json!("ls"))]);
+
+        assert!(matches!(
+            audited("Bash".to_string(), input.clone(), context.clone()).await,
+            Ok(PermissionResult::Deny { .. })
+        ));
+        assert!(audited("Read".to_string(), HashMap::new(), context.clone()).await.is_ok());
+        assert!(audited("Broken".to_string(), HashMap::new(), context).await.is_err());
+
+        let records: Vec<Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["tool_name"], "Bash");
+        assert_eq!(records[0]["input"], json!({"command": "ls"}));
+        assert_eq!(records[0]["decision"], "deny");
+        assert_eq!(records[0]["reason"], "no shell");
+        assert_eq!(records[0]["session_id"], "session-1");
+        assert!(records[0]["timestamp_ms"].as_u64().unwrap() > 0);
+        assert_eq!(records[1]["decision"], "allow");
+        assert!(records[1].get("reason").is_none());
+        assert_eq!(records[2]["decision"], "error");
+        assert_eq!(records[2]["reason"], "CLI Connection Error: offline");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_custom_sink() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let logger = AuditLogger::new({
+            let records = Arc::clone(&records);
+            move |record: &AuditRecord| records.lock().unwrap().push(record.clone())
+        });
+
+        logger.wrap(callback())("Edit".to_string(), HashMap::new(), Default::default())
+            .await
+            .unwrap();
+
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].decision, AuditDecision::Allow);
+        assert!(records[0].session_id.is_none());
+    }
+}
@@ -0,0 +1,216 @@
+//! Running many one-shot queries with bounded concurrency
+//!
+//! [`ask_many`] runs each prompt as its own [`simple_query`], with at most
+//! [`BatchOptions::concurrency`] CLI processes alive at a time, and returns
+//! the results in prompt order together with the total cost.
+//!
+//! # Example
+//! ```no_run
+//! use claude::{ask_many, BatchOptions};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let prompts = ["Translate 'cat' to French", "Translate 'dog' to French"];
+//!     let batch = ask_many(prompts, BatchOptions { concurrency: 2, ..Default::default() }).await;
+//!
+//!     for (prompt, answer) in prompts.iter().zip(batch.texts()) {
+//!         println!("{prompt}: {answer:?}");
+//!     }
+//!     println!("Total cost: ${:.4}", batch.total_cost_usd);
+//! }
+//! ```
+
+use crate::errors::Result;
+use crate::extensions::MessageVecExt;
+use crate::retry::RetryPolicy;
+use crate::simple_query::simple_query;
+use crate::tokens::Usage;
+use crate::types::{ClaudeAgentOptions, Message};
+use futures::stream::{self, StreamExt};
+use std::future::Future;
+
+/// Settings for [`ask_many`]
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// Maximum number of queries running at once (default 4)
+    pub concurrency: usize,
+    /// Options used for every query
+    pub per_query_options: ClaudeAgentOptions,
+    /// Retries of failed queries, transient errors up to three attempts by
+    /// default
+    pub retry: RetryPolicy,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions {
+            concurrency: 4,
+            per_query_options: ClaudeAgentOptions::default(),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// Outcome of [`ask_many`]
+#[derive(Debug)]
+pub struct BatchResult {
+    /// Messages of each query, in prompt order
+    pub results: Vec<Result<Vec<Message>>>,
+    /// Sum of the reported cost of all queries
+    pub total_cost_usd: f64,
+    /// Sum of the reported token counts of all queries
+    pub usage: Usage,
+}
+
+impl BatchResult {
+    /// Text answer of each query, `None` for failed ones
+    pub fn texts(&self) -> Vec<Option<String>> {
+        self.results
+            .iter()
+            .map(|result| result.as_ref().ok().map(|messages| messages.text_content()))
+            .collect()
+    }
+
+    /// Number of queries that failed after all retries
+    pub fn failed(&self) -> usize {
+        self.results.iter().filter(|result| result.is_err()).count()
+    }
+}
+
+/// Run every prompt as a one-shot query, `options.concurrency` at a time
+///
+/// Failures don't stop the batch; they are reported per prompt in
+/// [`BatchResult::results`].
+pub async fn ask_many<I, S>(prompts: I, options: BatchOptions) -> BatchResult
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    run_batch(prompts, options, |prompt, options| async move { simple_query(&prompt, Some(options)).await }).await
+}
+
+async fn run_batch<I, S, F, Fut>(prompts: I, options: BatchOptions, run: F) -> BatchResult
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+    F: Fn(String, ClaudeAgentOptions) -> Fut,
+    Fut: Future<Output = Result<Vec<Message>>>,
+{
+    let BatchOptions {
+        concurrency,
+        per_query_options,
+        retry,
+    } = options;
+    let run = &run;
+    let per_query_options = &per_query_options;
+    let retry = &retry;
+
+    // `buffered` keeps the input order while polling up to `concurrency` queries
+    let results: Vec<Result<Vec<Message>>> = stream::iter(prompts.into_iter().map(Into::into))
+        .map(|prompt: String| async move { retry.run(|| run(prompt.clone(), per_query_options.clone())).await })
+        .buffered(concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut total_cost_usd = 0.0;
+    let mut usage = Usage::default();
+    for result in results.iter().flatten().filter_map(|messages| messages.result_message()) {
+        total_cost_usd += result.total_cost_usd.unwrap_or(0.0);
+        if let Some(counts) = result.token_usage() {
+            usage += counts;
+        }
+    }
+
+    BatchResult {
+        results,
+        total_cost_usd,
+        usage,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ClaudeSDKError;
+    use crate::message_parser::parse_message;
+    use crate::retry::Backoff;
+    use crate::testing::result_message;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    fn answer(prompt: &str) -> Vec<Message> {
+        let assistant = json!({
+            "type": "assistant",
+            "message": {"model": "m", "content": [{"type": "text", "text": prompt.to_uppercase()}]}
+        });
+        let mut result = result_message("s");
+        result["total_cost_usd"] = json!(0.25);
+        result["usage"] = json!({"input_tokens": 10, "output_tokens": 5});
+        vec![parse_message(&assistant).unwrap(), parse_message(&result).unwrap()]
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_batch_keeps_order_and_bounds_concurrency() {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let prompts: Vec<String> = (0..10).map(|i| format!("p{}", i)).collect();
+        let options = BatchOptions {
+            concurrency: 3,
+            ..Default::default()
+        };
+
+        let batch = run_batch(prompts, options, |prompt, _| {
+            let (running, peak) = (&running, &peak);
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                // Later prompts finish first
+                let index: u64 = prompt[1..].parse().unwrap();
+                tokio::time::sleep(Duration::from_millis(100 - index * 10)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(answer(&prompt))
+            }
+        })
+        .await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+        let texts: Vec<_> = batch.texts().into_iter().map(Option::unwrap).collect();
+        assert_eq!(texts, (0..10).map(|i| format!("P{}", i)).collect::<Vec<_>>());
+        assert_eq!(batch.total_cost_usd, 2.5);
+        assert_eq!(batch.usage.total_tokens(), 150);
+        assert_eq!(batch.failed(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_batch_retries_failures() {
+        let attempts = Mutex::new(Vec::new());
+        let options = BatchOptions {
+            retry: RetryPolicy::new(3).backoff(Backoff::fixed(Duration::from_secs(1))),
+            ..Default::default()
+        };
+
+        let batch = run_batch(["flaky", "broken"], options, |prompt, _| {
+            let attempts = &attempts;
+            async move {
+                let count = {
+                    let mut attempts = attempts.lock().unwrap();
+                    attempts.push(prompt.clone());
+                    attempts.iter().filter(|p| **p == prompt).count()
+                };
+                match prompt.as_str() {
+                    "flaky" if count == 2 => Ok(answer(&prompt)),
+                    _ => Err(ClaudeSDKError::cli_connection_error("CLI exited")),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(batch.texts(), [Some("FLAKY".to_string()), None]);
+        assert_eq!(batch.failed(), 1);
+        let attempts = attempts.into_inner().unwrap();
+        assert_eq!(attempts.iter().filter(|p| *p == "flaky").count(), 2);
+        assert_eq!(attempts.iter().filter(|p| *p == "broken").count(), 3);
+    }
+}
@@ -0,0 +1,141 @@
+//! Synchronous API for code that doesn't run an async runtime
+//!
+//! The functions and [`Client`] here mirror their async counterparts but
+//! block the calling thread. Each owns a current-thread tokio runtime, so
+//! they must not be called from within an async context; use the async API
+//! there instead.
+//!
+//! Requires the `blocking` cargo feature.
+//!
+//! # Example
+//! ```no_run
+//! fn main() -> claude::Result<()> {
+//!     let answer = claude::blocking::ask("What is 2 + 2?")?;
+//!     println!("{}", answer);
+//!
+//!     let mut client = claude::blocking::Client::new(None);
+//!     client.connect()?;
+//!     println!("{}", client.query_text("Name a prime number")?);
+//!     println!("{}", client.query_text("And the next one?")?);
+//!     client.close()
+//! }
+//! ```
+
+use crate::client::ClaudeSDKClient;
+use crate::errors::Result;
+use crate::extensions::MessageVecExt;
+use crate::transport::Transport;
+use crate::types::{ClaudeAgentOptions, Message, SessionId};
+use tokio::runtime::Runtime;
+
+/// Ask Claude a question and wait for the text response, see [`crate::ask`]
+pub fn ask(prompt: impl AsRef<str>) -> Result<String> {
+    Ok(simple_query(prompt.as_ref(), None)?.text_content())
+}
+
+/// Run a one-shot query and wait for all messages, see [`crate::simple_query`]
+pub fn simple_query(prompt: &str, options: Option<ClaudeAgentOptions>) -> Result<Vec<Message>> {
+    runtime().block_on(crate::simple_query(prompt, options))
+}
+
+/// An interactive session whose calls block until Claude answers
+///
+/// Wraps a [`ClaudeSDKClient`]. Background work such as hooks and permission
+/// callbacks only runs while a call is in progress.
+pub struct Client {
+    // Declared first so the session is closed before the runtime shuts down
+    inner: ClaudeSDKClient,
+    runtime: Runtime,
+}
+
+impl Client {
+    pub fn new(options: Option<ClaudeAgentOptions>) -> Self {
+        Self::from_async(ClaudeSDKClient::new(options))
+    }
+
+    /// Create a client that talks to Claude over `transport`
+    pub fn with_transport(options: Option<ClaudeAgentOptions>, transport: Box<dyn Transport>) -> Self {
+        Self::from_async(ClaudeSDKClient::with_transport(options, transport))
+    }
+
+    /// Wrap a client configured with [`ClaudeSDKClient::builder`]
+    pub fn from_async(client: ClaudeSDKClient) -> Self {
+        Client {
+            inner: client,
+            runtime: runtime(),
+        }
+    }
+
+    pub fn connect(&mut self) -> Result<()> {
+        self.runtime.block_on(self.inner.connect())
+    }
+
+    /// Send `prompt` and wait for the whole response, see
+    /// [`ClaudeSDKClient::query_and_collect`]
+    pub fn query(&mut self, prompt: &str) -> Result<Vec<Message>> {
+        self.runtime.block_on(self.inner.query_and_collect(prompt))
+    }
+
+    /// Send `prompt` and wait for the text of the response
+    pub fn query_text(&mut self, prompt: &str) -> Result<String> {
+        self.runtime.block_on(self.inner.query_for_text(prompt))
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    /// ID of the current session, once the CLI reported it
+    pub fn session_id(&self) -> Option<SessionId> {
+        self.inner.session_id()
+    }
+
+    /// End the session and stop the CLI
+    pub fn close(&mut self) -> Result<()> {
+        self.runtime.block_on(self.inner.close())
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if self.inner.is_connected() {
+            let _ = self.runtime.block_on(self.inner.close());
+        }
+    }
+}
+
+fn runtime() -> Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to start the tokio runtime for the blocking API")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockTransport;
+
+    #[test]
+    fn test_client_blocks_until_answered() {
+        let transport = MockTransport::new()
+            .with_assistant_text("4")
+            .with_result("session-1")
+            .with_assistant_text("8")
+            .with_result("session-1");
+        let handle = transport.handle();
+
+        let mut client = Client::with_transport(None, Box::new(transport));
+        client.connect().unwrap();
+        assert_eq!(client.query_text("What is 2 + 2?").unwrap(), "4");
+        assert_eq!(client.session_id().as_deref(), Some("session-1"));
+
+        let messages = client.query("And doubled?").unwrap();
+        assert_eq!(messages.text_content(), "8");
+        handle.assert_user_message("And doubled?");
+
+        client.close().unwrap();
+        assert!(!client.is_connected());
+        assert!(handle.is_closed());
+    }
+}
@@ -0,0 +1,239 @@
+//! SDK-side enforcement of `max_cost_usd` and `max_total_tokens`
+
+use crate::errors::ClaudeSDKError;
+use crate::tokens::Usage;
+use crate::types::{ClaudeAgentOptions, Message, SessionId, StreamEventKind};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Turns the running totals on result messages into what each result adds
+///
+/// The CLI reports the cost and usage of its process so far, so a result
+/// adds the difference to the previous result with the same key, usually
+/// its session. Totals lower than the previous ones mean the session went
+/// on in a new process, which counts from zero again.
+#[derive(Debug, Clone)]
+pub(crate) struct RunningTotals<K> {
+    last: HashMap<K, (f64, Usage)>,
+}
+
+impl<K> Default for RunningTotals<K> {
+    fn default() -> Self {
+        RunningTotals { last: HashMap::new() }
+    }
+}
+
+impl<K: Eq + Hash> RunningTotals<K> {
+    /// Cost and usage added since the previous totals for `key`
+    pub(crate) fn delta(&mut self, key: K, cost_usd: f64, usage: Usage) -> (f64, Usage) {
+        match self.last.insert(key, (cost_usd, usage)) {
+            Some((last_cost, last_usage)) if cost_usd >= last_cost => match usage.since(&last_usage) {
+                Some(added) => (cost_usd - last_cost, added),
+                None => (cost_usd, usage),
+            },
+            _ => (cost_usd, usage),
+        }
+    }
+}
+
+/// Adds up the cost and tokens reported in messages and checks them against
+/// the limits
+///
+/// Result messages carry the exact running totals of a session. While a
+/// turn is still streaming, the usage in partial-message events is counted
+/// so a limit can trip before the turn ends.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BudgetTracker {
+    max_cost_usd: Option<f64>,
+    max_total_tokens: Option<u64>,
+    cost_usd: f64,
+    // Exact tokens of finished turns
+    tokens: u64,
+    // Tokens of finished messages of the current turn
+    streamed_tokens: u64,
+    // Usage of messages being streamed, per `parent_tool_use_id`
+    in_flight: HashMap<Option<String>, Usage>,
+    results: RunningTotals<SessionId>,
+    exceeded: bool,
+}
+
+impl BudgetTracker {
+    /// A tracker for the limits in `options`, `None` if there are none
+    pub(crate) fn from_options(options: &ClaudeAgentOptions) -> Option<Self> {
+        if options.max_cost_usd.is_none() && options.max_total_tokens.is_none() {
+            return None;
+        }
+        Some(BudgetTracker {
+            max_cost_usd: options.max_cost_usd,
+            max_total_tokens: options.max_total_tokens,
+            ..Default::default()
+        })
+    }
+
+    /// Account for `message`
+    ///
+    /// Returns the error the first time a limit is crossed; later messages
+    /// are still counted but don't report it again.
+    pub(crate) fn record(&mut self, message: &Message) -> Option<ClaudeSDKError> {
+        match message {
+            Message::Result(result) => {
+                let (cost_usd, usage) = self.results.delta(
+                    result.session_id.clone(),
+                    result.total_cost_usd.unwrap_or(0.0),
+                    result.token_usage().unwrap_or_default(),
+                );
+                self.cost_usd += cost_usd;
+                self.tokens += usage.total_tokens();
+                self.streamed_tokens = 0;
+                self.in_flight.clear();
+            }
+            Message::Stream(event) => {
+                let key = event.parent_tool_use_id.clone();
+                match event.kind() {
+                    StreamEventKind::MessageStart { message } => {
+                        let usage = message
+                            .get("usage")
+                            .and_then(|usage| serde_json::from_value::<HashMap<_, _>>(usage.clone()).ok())
+                            .map(|usage| Usage::from_map(&usage))
+                            .unwrap_or_default();
+                        self.in_flight.insert(key, usage);
+                    }
+                    // `output_tokens` in a delta is the running total of the message
+                    StreamEventKind::MessageDelta { usage: Some(usage), .. } => {
+                        let usage = Usage::from_map(&usage);
+                        let current = self.in_flight.entry(key).or_default();
+                        current.output_tokens = current.output_tokens.max(usage.output_tokens);
+                    }
+                    StreamEventKind::MessageStop => {
+                        self.streamed_tokens += self.in_flight.remove(&key).map_or(0, |usage| usage.total_tokens());
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+
+        if self.exceeded || !self.over_limit() {
+            return None;
+        }
+        self.exceeded = true;
+        Some(ClaudeSDKError::budget_exceeded(self.cost_usd, self.total_tokens()))
+    }
+
+    /// Whether a limit has been crossed
+    pub(crate) fn is_exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    /// The error for the spending so far
+    pub(crate) fn error(&self) -> ClaudeSDKError {
+        ClaudeSDKError::budget_exceeded(self.cost_usd, self.total_tokens())
+    }
+
+    fn total_tokens(&self) -> u64 {
+        self.tokens + self.streamed_tokens + self.in_flight.values().map(Usage::total_tokens).sum::<u64>()
+    }
+
+    fn over_limit(&self) -> bool {
+        self.max_cost_usd.is_some_and(|max| self.cost_usd > max)
+            || self.max_total_tokens.is_some_and(|max| self.total_tokens() > max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message_parser::parse_message;
+    use crate::testing::result_message;
+    use serde_json::{json, Value};
+
+    fn result(cost: f64, input_tokens: u64, output_tokens: u64) -> Message {
+        session_result("s", cost, input_tokens, output_tokens)
+    }
+
+    fn session_result(session_id: &str, cost: f64, input_tokens: u64, output_tokens: u64) -> Message {
+        let mut result = result_message(session_id);
+        result["total_cost_usd"] = json!(cost);
+        result["usage"] = json!({"input_tokens": input_tokens, "output_tokens": output_tokens});
+        parse_message(&result).unwrap()
+    }
+
+    fn event(event: Value) -> Message {
+        parse_message(&json!({"type": "stream_event", "uuid": "e", "session_id": "s", "event": event})).unwrap()
+    }
+
+    #[test]
+    fn test_no_limits_no_tracker() {
+        assert!(BudgetTracker::from_options(&ClaudeAgentOptions::default()).is_none());
+    }
+
+    #[test]
+    fn test_cost_limit() {
+        let options = ClaudeAgentOptions {
+            max_cost_usd: Some(0.5),
+            ..Default::default()
+        };
+        let mut budget = BudgetTracker::from_options(&options).unwrap();
+
+        assert!(budget.record(&result(0.3, 10, 10)).is_none());
+        match budget.record(&session_result("other", 0.3, 10, 10)) {
+            Some(ClaudeSDKError::BudgetExceeded { cost_usd, total_tokens }) => {
+                assert!((cost_usd - 0.6).abs() < 1e-9);
+                assert_eq!(total_tokens, 40);
+            }
+            other => panic!("expected budget error, got {:?}", other),
+        }
+        assert!(budget.is_exceeded());
+        // Reported once
+        assert!(budget.record(&result(0.9, 30, 30)).is_none());
+    }
+
+    #[test]
+    fn test_results_count_what_they_add_to_the_session() {
+        let options = ClaudeAgentOptions {
+            max_cost_usd: Some(1.0),
+            ..Default::default()
+        };
+        let mut budget = BudgetTracker::from_options(&options).unwrap();
+
+        // Each turn reports the session's totals so far
+        assert!(budget.record(&result(0.3, 100, 10)).is_none());
+        assert!(budget.record(&result(0.5, 250, 30)).is_none());
+        assert!(budget.record(&result(0.7, 300, 40)).is_none());
+        assert!((budget.cost_usd - 0.7).abs() < 1e-9);
+        assert_eq!(budget.total_tokens(), 340);
+
+        // A resumed session in a new process counts from zero again
+        assert!(budget.record(&result(0.2, 50, 5)).is_none());
+        assert!((budget.cost_usd - 0.9).abs() < 1e-9);
+        assert_eq!(budget.total_tokens(), 395);
+
+        match budget.record(&result(0.4, 80, 10)) {
+            Some(ClaudeSDKError::BudgetExceeded { cost_usd, total_tokens }) => {
+                assert!((cost_usd - 1.1).abs() < 1e-9);
+                assert_eq!(total_tokens, 430);
+            }
+            other => panic!("expected budget error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_token_limit_trips_while_streaming() {
+        let options = ClaudeAgentOptions {
+            max_total_tokens: Some(1000),
+            ..Default::default()
+        };
+        let mut budget = BudgetTracker::from_options(&options).unwrap();
+
+        assert!(budget.record(&result(0.0, 300, 100)).is_none());
+        let start = json!({"type": "message_start", "message": {"usage": {"input_tokens": 500, "output_tokens": 1}}});
+        assert!(budget.record(&event(start)).is_none());
+        let delta = json!({"type": "message_delta", "delta": {}, "usage": {"output_tokens": 50}});
+        assert!(budget.record(&event(delta)).is_none());
+        let delta = json!({"type": "message_delta", "delta": {}, "usage": {"output_tokens": 250}});
+        assert!(matches!(
+            budget.record(&event(delta)),
+            Some(ClaudeSDKError::BudgetExceeded { total_tokens: 1150, .. })
+        ));
+    }
+}
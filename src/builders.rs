@@ -1,7 +1,11 @@
 /// Builder patterns for SDK types
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::types::{ClaudeAgentOptions, PermissionMode, SystemPromptConfig, SystemPromptPreset};
+use crate::errors::Result;
+use crate::types::{ClaudeAgentOptions, McpServerConfig, ParseMode, PermissionMode, SessionId, SystemPromptConfig, SystemPromptPreset};
+use crate::cost::CostTracker;
+use crate::metrics::Metrics;
+use crate::version::CliVersion;
 
 /// Fluent builder for ClaudeAgentOptions
 ///
@@ -149,14 +153,14 @@ impl ClaudeOptionsBuilder {
     }
 
     /// Resume a previous session
-    pub fn resume_session(mut self, session_id: impl Into<String>) -> Self {
+    pub fn resume_session(mut self, session_id: impl Into<SessionId>) -> Self {
         self.inner.resume = Some(session_id.into());
         self.inner.fork_session = false;
         self
     }
 
     /// Fork from a previous session
-    pub fn fork_session(mut self, session_id: impl Into<String>) -> Self {
+    pub fn fork_session(mut self, session_id: impl Into<SessionId>) -> Self {
         self.inner.resume = Some(session_id.into());
         self.inner.fork_session = true;
         self
@@ -174,7 +178,11 @@ impl ClaudeOptionsBuilder {
         self
     }
 
-    /// Set maximum buffer size
+    /// Set the maximum size in bytes of a single line of CLI output
+    ///
+    /// Longer lines are skipped and reported as
+    /// [`ClaudeSDKError::BufferOverflow`](crate::ClaudeSDKError::BufferOverflow).
+    /// Defaults to 1 MiB.
     pub fn max_buffer_size(mut self, size: usize) -> Self {
         self.inner.max_buffer_size = Some(size);
         self
@@ -192,6 +200,104 @@ impl ClaudeOptionsBuilder {
         self
     }
 
+    /// Set how long to wait for the CLI to exit gracefully on close
+    pub fn shutdown_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.inner.shutdown_timeout = Some(timeout);
+        self
+    }
+
+    /// Stop once the reported cost exceeds `max_cost_usd`
+    pub fn max_cost_usd(mut self, max_cost_usd: f64) -> Self {
+        self.inner.max_cost_usd = Some(max_cost_usd);
+        self
+    }
+
+    /// Stop once more than `max_total_tokens` input and output tokens were used
+    pub fn max_total_tokens(mut self, max_total_tokens: u64) -> Self {
+        self.inner.max_total_tokens = Some(max_total_tokens);
+        self
+    }
+
+    /// Report metrics about queries and sessions to `metrics`
+    pub fn metrics(mut self, metrics: Metrics) -> Self {
+        self.inner.metrics = Some(metrics);
+        self
+    }
+
+    /// Add the cost and tokens of each query to `tracker`
+    pub fn cost_tracker(mut self, tracker: CostTracker) -> Self {
+        self.inner.cost_tracker = Some(tracker);
+        self
+    }
+
+    /// Refuse to connect to a CLI older than `version`
+    pub fn required_version(mut self, version: CliVersion) -> Self {
+        self.inner.required_version = Some(version);
+        self
+    }
+
+    /// Give up on one-shot queries that take longer than `timeout`
+    pub fn query_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.inner.query_timeout = Some(timeout);
+        self
+    }
+
+    /// Set how many messages are buffered before reading from the CLI pauses
+    ///
+    /// A consumer that falls behind applies backpressure to the CLI instead of
+    /// letting messages pile up in memory. Defaults to 100.
+    pub fn message_channel_capacity(mut self, capacity: usize) -> Self {
+        self.inner.message_channel_capacity = Some(capacity);
+        self
+    }
+
+    /// Set how unknown message and content block types are handled
+    ///
+    /// `ParseMode::Lenient` passes them through as `Message::Unknown` and
+    /// `ContentBlock::Unknown`, so newer CLI releases don't end the stream.
+    pub fn parse_mode(mut self, mode: ParseMode) -> Self {
+        self.inner.parse_mode = mode;
+        self
+    }
+
+    /// Add an MCP server, replacing any server with the same name
+    ///
+    /// Accepts an [`SdkMcpServer`](crate::mcp::SdkMcpServer) as well, whose
+    /// tools a connected client then serves in-process.
+    ///
+    /// # Example
+    /// ```
+    /// use claude::mcp::SdkMcpServer;
+    /// use claude::types::McpServerConfig;
+    /// use claude::ClaudeOptionsBuilder;
+    ///
+    /// let tools = SdkMcpServer::builder("tools").build().unwrap();
+    /// let options = ClaudeOptionsBuilder::new()
+    ///     .mcp_server("docs", McpServerConfig::streamable_http("https://example.com/mcp"))
+    ///     .mcp_server("tools", tools)
+    ///     .build();
+    /// assert_eq!(options.mcp_servers.len(), 2);
+    /// ```
+    pub fn mcp_server(mut self, name: impl Into<String>, config: impl Into<McpServerConfig>) -> Self {
+        self.inner.mcp_servers.insert(name.into(), config.into());
+        self
+    }
+
+    /// Add the MCP servers listed in a `.mcp.json` or `claude_desktop_config.json` file
+    ///
+    /// Servers with the same name as one already configured replace it. See
+    /// [`McpServerConfig::load_file`] for the accepted format.
+    pub fn mcp_servers_from_file(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        self.inner.mcp_servers.extend(McpServerConfig::load_file(path)?);
+        Ok(self)
+    }
+
+    /// Pass an extra flag straight to the CLI (`--flag` or `--flag value`)
+    pub fn extra_arg(mut self, flag: impl Into<String>, value: Option<String>) -> Self {
+        self.inner.extra_args.insert(flag.into(), value);
+        self
+    }
+
     /// Build the final ClaudeAgentOptions
     pub fn build(self) -> ClaudeAgentOptions {
         self.inner
@@ -243,11 +349,22 @@ mod tests {
         assert_eq!(options.disallowed_tools, vec!["Bash"]);
     }
 
+    #[test]
+    fn test_builder_extra_args() {
+        let options = ClaudeOptionsBuilder::new()
+            .extra_arg("debug-to-stderr", None)
+            .extra_arg("betas", Some("context-1m".to_string()))
+            .build();
+
+        assert_eq!(options.extra_args.get("debug-to-stderr"), Some(&None));
+        assert_eq!(options.extra_args.get("betas"), Some(&Some("context-1m".to_string())));
+    }
+
     #[test]
     fn test_builder_session() {
         let options = ClaudeOptionsBuilder::new().resume_session("session-123").build();
 
-        assert_eq!(options.resume, Some("session-123".to_string()));
+        assert_eq!(options.resume, Some("session-123".into()));
         assert!(!options.fork_session);
     }
 
@@ -255,10 +372,37 @@ mod tests {
     fn test_builder_fork() {
         let options = ClaudeOptionsBuilder::new().fork_session("session-456").build();
 
-        assert_eq!(options.resume, Some("session-456".to_string()));
+        assert_eq!(options.resume, Some("session-456".into()));
         assert!(options.fork_session);
     }
 
+    #[test]
+    fn test_builder_message_channel_capacity() {
+        let options = ClaudeOptionsBuilder::new().message_channel_capacity(8).build();
+
+        assert_eq!(options.message_channel_capacity, Some(8));
+    }
+
+    #[test]
+    fn test_builder_parse_mode() {
+        assert_eq!(ClaudeOptionsBuilder::new().build().parse_mode, ParseMode::Strict);
+        let options = ClaudeOptionsBuilder::new().parse_mode(ParseMode::Lenient).build();
+
+        assert_eq!(options.parse_mode, ParseMode::Lenient);
+    }
+
+    #[test]
+    fn test_builder_mcp_servers_from_file() {
+        let path = std::env::temp_dir().join(format!("claude-rs-builder-{}.mcp.json", std::process::id()));
+        std::fs::write(&path, r#"{"mcpServers": {"git": {"command": "mcp-git"}}}"#).unwrap();
+
+        let options = ClaudeOptionsBuilder::new().mcp_servers_from_file(&path).unwrap().build();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(options.mcp_servers["git"], McpServerConfig::Stdio { ref command, .. } if command == "mcp-git"));
+        assert!(ClaudeOptionsBuilder::new().mcp_servers_from_file(&path).is_err());
+    }
+
     #[test]
     fn test_quick_constructors() {
         let opt1 = ClaudeAgentOptions::with_system_prompt("test");
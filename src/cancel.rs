@@ -0,0 +1,84 @@
+//! Cooperative cancellation of in-flight queries
+//!
+//! A [`CancelHandle`] is cloned into whichever task should be able to stop a
+//! query. Pass it to
+//! [`ClaudeSDKClient::receive_response_with_cancel`](crate::ClaudeSDKClient::receive_response_with_cancel)
+//! or [`QuickQuery::cancel_on`](crate::QuickQuery::cancel_on); calling
+//! [`cancel`](CancelHandle::cancel) ends the query with
+//! [`ClaudeSDKError::Interrupted`](crate::ClaudeSDKError::Interrupted).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// Shared flag that cancels the queries it was passed to
+#[derive(Clone, Default)]
+pub struct CancelHandle {
+    inner: Arc<CancelState>,
+}
+
+#[derive(Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelHandle {
+    /// Create a handle that is not cancelled yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel every query using this handle; later calls are no-ops
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Whether [`cancel`](Self::cancel) has been called
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the handle is cancelled
+    pub async fn cancelled(&self) {
+        loop {
+            // Register before checking so a concurrent `cancel` is not missed
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl std::fmt::Debug for CancelHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancelHandle")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_wakes_waiters() {
+        let handle = CancelHandle::new();
+        let waiter = tokio::spawn({
+            let handle = handle.clone();
+            async move { handle.cancelled().await }
+        });
+
+        assert!(!handle.is_cancelled());
+        handle.cancel();
+        waiter.await.unwrap();
+        assert!(handle.is_cancelled());
+
+        // Already cancelled handles resolve immediately
+        handle.cancelled().await;
+    }
+}
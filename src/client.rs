@@ -1,13 +1,25 @@
 use crate::errors::{ClaudeSDKError, Result};
+use crate::handlers::Handlers;
+use crate::hooks::{HookCallback, HookManager};
+use crate::mcp_server::SdkMcpServer;
 use crate::permissions::CanUseToolCallback;
-use crate::query::Query;
-use crate::transport::{SubprocessTransport, Transport};
-use crate::types::{ClaudeAgentOptions, Message};
-use futures::stream::Stream;
+use crate::cancel::CancelHandle;
+use crate::budget::BudgetTracker;
+use crate::query::{write_json, Interrupter, Query, SessionRoutes};
+use crate::transport::{SubprocessTransport, Transport, TransportFactory};
+use crate::types::{ClaudeAgentOptions, ContentBlock, HeartbeatConfig, HeartbeatEvent, IntoHookEvent, McpServerConfig, McpServerStatus, Message, RestartPolicy, ServerInfo, SessionId, SystemPromptConfig, UserEnvelope};
+use crate::extensions::MessageVecExt;
+use futures::stream::{Stream, StreamExt};
 use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
 use std::pin::Pin;
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::task::{Context, Poll};
-use tokio::sync::mpsc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
 
 /// Client for bidirectional, interactive conversations with Claude Code.
 ///
@@ -39,6 +51,19 @@ pub struct ClaudeSDKClient {
     options: ClaudeAgentOptions,
     query: Option<Query>,
     can_use_tool: Option<CanUseToolCallback>,
+    transport: Option<Box<dyn Transport>>,
+    transport_factory: Option<TransportFactory>,
+    restart_policy: Option<RestartPolicy>,
+    server_info: Option<ServerInfo>,
+    messages: MessageSlot,
+    hook_manager: Option<Arc<Mutex<HookManager>>>,
+    initialize_timeout: Duration,
+    request_timeout: Option<Duration>,
+    // False once a single transport without a factory has been used
+    can_reconnect: bool,
+    ctrl_c: Option<CtrlCHandler>,
+    // Exit status of the CLI stopped by the last `close()`
+    exit_status: Option<ExitStatus>,
 }
 
 impl ClaudeSDKClient {
@@ -48,6 +73,50 @@ impl ClaudeSDKClient {
             options: options.unwrap_or_default(),
             query: None,
             can_use_tool: None,
+            transport: None,
+            transport_factory: None,
+            restart_policy: None,
+            server_info: None,
+            messages: MessageSlot::default(),
+            hook_manager: None,
+            initialize_timeout: DEFAULT_INITIALIZE_TIMEOUT,
+            request_timeout: None,
+            can_reconnect: true,
+            ctrl_c: None,
+            exit_status: None,
+        }
+    }
+
+    /// Configure a client in one chain
+    ///
+    /// Unlike the `with_*`, `resume` and `fork` constructors, the builder
+    /// lets any combination of options, callbacks, hooks, SDK MCP servers,
+    /// session settings and timeouts be set together.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::{ClaudeSDKClient, HookEvent, HookJSONOutput, PermissionResult};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::builder()
+    ///         .resume("session-123")
+    ///         .can_use_tool(Arc::new(|_tool, _input, _ctx| Box::pin(async { Ok(PermissionResult::allow()) })))
+    ///         .hook(HookEvent::PreToolUse, "Bash", Arc::new(|_input, _tool_use_id, _ctx| {
+    ///             Box::pin(async { Ok(HookJSONOutput::default()) })
+    ///         }))
+    ///         .request_timeout(Duration::from_secs(60))
+    ///         .build();
+    ///     client.connect().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn builder() -> ClaudeSDKClientBuilder {
+        ClaudeSDKClientBuilder {
+            inner: Self::new(None),
+            hooks: None,
         }
     }
 
@@ -57,12 +126,100 @@ impl ClaudeSDKClient {
         can_use_tool: CanUseToolCallback,
     ) -> Self {
         Self {
-            options: options.unwrap_or_default(),
-            query: None,
             can_use_tool: Some(can_use_tool),
+            ..Self::new(options)
+        }
+    }
+
+    /// Create a new ClaudeSDKClient that talks through a custom transport
+    ///
+    /// By default `connect()` spawns the Claude Code CLI as a subprocess. Use
+    /// this to plug in any other [`Transport`] implementation instead, such
+    /// as a mock for tests or a remote connection.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::ClaudeSDKClient;
+    /// use claude::transport::SubprocessTransport;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let transport = SubprocessTransport::new(String::new(), Default::default());
+    ///     let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    ///     client.connect().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_transport(options: Option<ClaudeAgentOptions>, transport: Box<dyn Transport>) -> Self {
+        Self {
+            transport: Some(transport),
+            ..Self::new(options)
         }
     }
 
+    /// Use a custom transport for this client
+    ///
+    /// Builder-style counterpart to [`ClaudeSDKClient::with_transport`] that
+    /// composes with the other constructors, e.g. to combine a custom
+    /// transport with a `can_use_tool` callback.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::{ClaudeSDKClient, PermissionResult};
+    /// use claude::transport::SubprocessTransport;
+    /// use std::sync::Arc;
+    ///
+    /// let transport = SubprocessTransport::new(String::new(), Default::default());
+    /// let client = ClaudeSDKClient::with_can_use_tool(
+    ///     None,
+    ///     Arc::new(|_tool, _input, _ctx| Box::pin(async { Ok(PermissionResult::allow()) })),
+    /// )
+    /// .transport(Box::new(transport));
+    /// ```
+    pub fn transport(mut self, transport: Box<dyn Transport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Create transports with a factory instead of spawning the CLI directly
+    ///
+    /// The factory receives the client's options and is used by `connect()`
+    /// and, with a [`restart_policy`](Self::restart_policy), to respawn the
+    /// transport after a crash (with `resume` set to the last session ID).
+    pub fn transport_factory(
+        mut self,
+        factory: impl Fn(ClaudeAgentOptions) -> Box<dyn Transport> + Send + Sync + 'static,
+    ) -> Self {
+        self.transport_factory = Some(Arc::new(factory));
+        self
+    }
+
+    /// Restart the CLI automatically if it exits mid-conversation
+    ///
+    /// The replacement process resumes the last session seen, and the
+    /// message stream continues after a `System` message with subtype
+    /// `reconnect`. Permission mode or model changes made during the
+    /// session are not replayed. Supervision needs a way to build a new
+    /// transport, so it is not available when a single transport was given
+    /// with [`transport`](Self::transport) and no
+    /// [`transport_factory`](Self::transport_factory).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::{ClaudeSDKClient, RestartPolicy};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::new(None).restart_policy(RestartPolicy::default());
+    ///     client.connect().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = Some(policy);
+        self
+    }
+
     /// Connect to Claude Code and start the session
     ///
     /// # Example
@@ -77,36 +234,88 @@ impl ClaudeSDKClient {
     /// }
     /// ```
     pub async fn connect(&mut self) -> Result<()> {
-        // Create transport with empty prompt for interactive mode
-        let mut transport = SubprocessTransport::new(String::new(), self.options.clone());
+        // Use the custom transport if one was supplied, otherwise spawn the CLI
+        // with an empty prompt for interactive mode
+        let can_respawn = self.transport.is_none() || self.transport_factory.is_some();
+        self.can_reconnect = can_respawn;
+        let factory = self
+            .transport_factory
+            .clone()
+            .unwrap_or_else(|| Arc::new(spawn_cli));
+        let mut transport = match self.transport.take() {
+            Some(transport) => transport,
+            None => factory(self.options.clone()),
+        };
 
         // Connect the transport (start the subprocess)
         transport.connect().await?;
 
-        // Create Query instance for control protocol
-        let mut query = if let Some(ref callback) = self.can_use_tool {
-            Query::with_can_use_tool(transport, true, callback.clone())
-        } else {
-            Query::new(transport, true)
-        };
+        // Create Query instance for control protocol, answering tool calls
+        // for in-process servers by the name the CLI knows them by
+        let sdk_mcp_servers: HashMap<String, SdkMcpServer> = self
+            .options
+            .mcp_servers
+            .iter()
+            .filter_map(|(name, config)| match config {
+                McpServerConfig::Sdk(server) => Some((name.clone(), server.clone())),
+                _ => None,
+            })
+            .collect();
+        let mut query = Query::with_options(transport, true, self.can_use_tool.clone(), Some(sdk_mcp_servers));
+        if let Some(ref hook_manager) = self.hook_manager {
+            query = query.with_shared_hooks(Arc::clone(hook_manager));
+        }
+        if let Some(timeout) = self.request_timeout {
+            query = query.with_request_timeout(timeout);
+        }
+        if let Some(capacity) = self.options.message_channel_capacity {
+            query = query.with_channel_capacity(capacity);
+        }
+        query = query.with_parse_mode(self.options.parse_mode);
+        if let Some(budget) = BudgetTracker::from_options(&self.options) {
+            query = query.with_budget(budget);
+        }
+        if let Some(metrics) = self.options.metrics.clone() {
+            query = query.with_metrics(metrics);
+        }
+        if let Some(tracker) = self.options.cost_tracker.clone() {
+            query = query.with_cost_tracker(tracker);
+        }
+        if let Some(policy) = self.restart_policy.clone().filter(|_| can_respawn) {
+            let options = self.options.clone();
+            query = query.with_restart(policy, move |session_id| {
+                let mut options = options.clone();
+                if let Some(session_id) = session_id {
+                    options.resume = Some(session_id);
+                    options.continue_conversation = false;
+                    options.fork_session = false;
+                }
+                factory(options)
+            });
+        }
 
         // Start reading messages
         query.start().await?;
 
         // Initialize control protocol (with timeout to handle CLI versions that don't support it)
         // If initialization fails, we continue anyway - it's only needed for hooks
-        let _ = tokio::time::timeout(
-            tokio::time::Duration::from_secs(2),
-            query.initialize()
-        ).await;
+        let initialized = tokio::time::timeout(self.initialize_timeout, query.initialize()).await;
+        self.server_info = match initialized {
+            Ok(Ok(response)) => serde_json::from_value(response).ok(),
+            _ => None,
+        };
 
+        *self.messages.lock().unwrap() = Some(query.receive_messages());
         self.query = Some(query);
         Ok(())
     }
 
     /// Receive all messages from Claude
     ///
-    /// Returns a stream of messages that you can iterate over.
+    /// Returns a stream of messages that you can iterate over. Only one
+    /// stream reads at a time: while a stream from `receive_messages` or
+    /// `receive_response` is alive, further calls return an empty stream.
+    /// Dropping the stream hands the remaining messages back to the client.
     ///
     /// # Example
     /// ```no_run
@@ -129,13 +338,10 @@ impl ClaudeSDKClient {
     /// }
     /// ```
     pub fn receive_messages(&mut self) -> MessageStream {
-        if let Some(ref mut query) = self.query {
-            let rx = query.receive_messages();
-            MessageStream { receiver: rx }
-        } else {
-            // Return empty stream if not connected
-            let (_tx, rx) = mpsc::unbounded_channel();
-            MessageStream { receiver: rx }
+        // Empty if not connected or another stream holds the receiver
+        MessageStream {
+            receiver: self.messages.lock().unwrap().take(),
+            slot: Arc::clone(&self.messages),
         }
     }
 
@@ -165,23 +371,99 @@ impl ClaudeSDKClient {
 
         let session = session_id.unwrap_or("default");
 
-        // Build user message
-        let message = json!({
-            "type": "user",
-            "message": {
-                "role": "user",
-                "content": prompt
-            },
-            "parent_tool_use_id": null,
-            "session_id": session
-        });
-
         // Send via query's transport
-        query.send_message(message).await?;
+        query.send_message(UserEnvelope::text(prompt).session_id(session).to_wire()).await?;
 
         Ok(())
     }
 
+    /// Send a query made of content blocks, e.g. text plus images or documents
+    ///
+    /// Build the blocks with [`UserContent`](crate::UserContent).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::{ClaudeSDKClient, UserContent};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::new(None);
+    ///     client.connect().await?;
+    ///     client
+    ///         .query_blocks(
+    ///             vec![
+    ///                 UserContent::text("Summarize the attached spec"),
+    ///                 UserContent::file("spec.pdf")?,
+    ///             ],
+    ///             None,
+    ///         )
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_blocks(&mut self, blocks: Vec<ContentBlock>, session_id: Option<&str>) -> Result<()> {
+        let query = self
+            .query
+            .as_mut()
+            .ok_or_else(|| ClaudeSDKError::cli_connection_error("Not connected. Call connect() first.".to_string()))?;
+
+        query
+            .send_message(UserEnvelope::blocks(blocks).session_id(session_id.unwrap_or("default")).to_wire())
+            .await
+    }
+
+    /// Open a conversation that shares this client's CLI process
+    ///
+    /// The returned handle sends prompts tagged with `name` as their session
+    /// ID and receives the turns that answer them, along with any message
+    /// tagged `name`; they no longer appear in
+    /// [`receive_messages`](Self::receive_messages). The CLI tags its output
+    /// with the session ID it assigned, so answers are matched to handles in
+    /// the order the prompts were sent. Don't send prompts with
+    /// [`query`](Self::query) while a handle's prompt is unanswered, or the
+    /// turns get mixed up. Handles can be moved to separate tasks to run
+    /// several conversations at once. Opening the same session again
+    /// replaces the previous handle's stream.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::ClaudeSDKClient;
+    /// use futures::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::new(None);
+    ///     client.connect().await?;
+    ///
+    ///     let mut research = client.session("research")?;
+    ///     let mut review = client.session("review")?;
+    ///     research.query("Summarize the open issues").await?;
+    ///     review.query("Review src/lib.rs").await?;
+    ///
+    ///     let (summary, findings) = tokio::join!(
+    ///         research.receive_response().collect::<Vec<_>>(),
+    ///         review.receive_response().collect::<Vec<_>>(),
+    ///     );
+    ///     println!("{} / {} messages", summary.len(), findings.len());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn session(&self, name: impl Into<String>) -> Result<ClientSession> {
+        let query = self
+            .query
+            .as_ref()
+            .ok_or_else(|| ClaudeSDKError::cli_connection_error("Not connected. Call connect() first.".to_string()))?;
+
+        let name = name.into();
+        let receiver = query.route_session(&name);
+        Ok(ClientSession {
+            name,
+            transport: query.shared_transport(),
+            routes: query.session_routes(),
+            messages: Arc::new(std::sync::Mutex::new(Some(receiver))),
+        })
+    }
+
     /// Receive messages until and including a ResultMessage
     ///
     /// This is a convenience method that yields messages and automatically
@@ -210,10 +492,166 @@ impl ClaudeSDKClient {
     /// ```
     pub fn receive_response(&mut self) -> ResponseStream {
         let messages = self.receive_messages();
-        ResponseStream {
-            inner: messages,
-            terminated: false,
+        ResponseStream::new(messages)
+    }
+
+    /// Receive the current response, stopping early when `cancel` fires
+    ///
+    /// On cancellation the CLI is sent `interrupt`, the rest of the turn is
+    /// discarded, and the stream ends with [`ClaudeSDKError::Interrupted`]
+    /// once the CLI has wrapped up the turn. The client stays connected for
+    /// the next query.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::{CancelHandle, ClaudeSDKClient};
+    /// use futures::StreamExt;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::new(None);
+    ///     client.connect().await?;
+    ///     client.query("Refactor the whole crate", None).await?;
+    ///
+    ///     let cancel = CancelHandle::new();
+    ///     let timer = cancel.clone();
+    ///     tokio::spawn(async move {
+    ///         tokio::time::sleep(Duration::from_secs(30)).await;
+    ///         timer.cancel();
+    ///     });
+    ///
+    ///     let mut response = client.receive_response_with_cancel(&cancel);
+    ///     while let Some(message) = response.next().await {
+    ///         println!("{:?}", message?);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn receive_response_with_cancel(&mut self, cancel: &CancelHandle) -> ResponseStream {
+        let mut response = self.receive_response();
+        let cancel = cancel.clone();
+        response.cancel = Some(Box::pin(async move { cancel.cancelled().await }));
+        response.interrupter = self.query.as_ref().map(Query::interrupter);
+        response
+    }
+
+    /// Receive the current response as text chunks for live display
+    ///
+    /// With `include_partial_messages` enabled the chunks are the CLI's
+    /// `text_delta` events, in order; otherwise each assistant text block is
+    /// one chunk. Text from subagents is skipped. The stream ends after the
+    /// ResultMessage.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::{ClaudeAgentOptions, ClaudeSDKClient};
+    /// use futures::StreamExt;
+    /// use std::io::Write;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let options = ClaudeAgentOptions::builder().include_partial_messages(true).build();
+    ///     let mut client = ClaudeSDKClient::new(Some(options));
+    ///     client.connect().await?;
+    ///     client.query("Tell me a story", None).await?;
+    ///
+    ///     let mut text = client.receive_text_stream();
+    ///     while let Some(chunk) = text.next().await {
+    ///         print!("{}", chunk?);
+    ///         std::io::stdout().flush()?;
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn receive_text_stream(&mut self) -> TextStream {
+        TextStream::new(self.receive_response())
+    }
+
+    /// Send a prompt and collect the response up to and including the ResultMessage
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::{ClaudeSDKClient, MessageVecExt};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::new(None);
+    ///     client.connect().await?;
+    ///
+    ///     let messages = client.query_and_collect("List the files in src/").await?;
+    ///     if let Some(result) = messages.result_message() {
+    ///         println!("Cost: {:?}", result.total_cost_usd);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_and_collect(&mut self, prompt: &str) -> Result<Vec<Message>> {
+        self.query(prompt, None).await?;
+        self.collect_response().await
+    }
+
+    /// Send a query made of content blocks and collect the response, see
+    /// [`query_and_collect`](Self::query_and_collect)
+    pub async fn query_blocks_and_collect(&mut self, blocks: Vec<ContentBlock>) -> Result<Vec<Message>> {
+        self.query_blocks(blocks, None).await?;
+        self.collect_response().await
+    }
+
+    /// Collect the current response up to and including the ResultMessage
+    async fn collect_response(&mut self) -> Result<Vec<Message>> {
+        let mut response = self.receive_response();
+        let mut messages = Vec::new();
+        while let Some(message) = response.next().await {
+            messages.push(message?);
+        }
+
+        match messages.last() {
+            Some(Message::Result(result)) => match result.rate_limit_error() {
+                Some(e) => Err(e),
+                None => Ok(messages),
+            },
+            _ => Err(response_ended_early()),
+        }
+    }
+
+    /// Receive the current response and pass its parts to `handlers`
+    ///
+    /// Drives the stream up to and including the ResultMessage, calling the
+    /// matching handler for each text, thinking, tool use, tool result and
+    /// result as it arrives. See [`Handlers`] for an example.
+    pub async fn run_with_handlers(&mut self, mut handlers: Handlers) -> Result<()> {
+        let mut response = self.receive_response();
+        while let Some(message) = response.next().await {
+            let message = message?;
+            handlers.dispatch(&message);
+            if message.is_result() {
+                return Ok(());
+            }
         }
+        Err(response_ended_early())
+    }
+
+    /// Send a prompt and return the text of Claude's reply
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::ClaudeSDKClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::new(None);
+    ///     client.connect().await?;
+    ///
+    ///     let answer = client.query_for_text("What is 2 + 2?").await?;
+    ///     let follow_up = client.query_for_text("And doubled?").await?;
+    ///     println!("{} / {}", answer, follow_up);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_for_text(&mut self, prompt: &str) -> Result<String> {
+        let messages = self.query_and_collect(prompt).await?;
+        Ok(messages.text_content())
     }
 
     /// Send an interrupt signal to stop the current operation
@@ -228,13 +666,15 @@ impl ClaudeSDKClient {
         query.interrupt().await
     }
 
-    /// Change permission mode during conversation
+    /// Interrupt the running turn when the user presses Ctrl-C
     ///
-    /// # Arguments
-    /// * `mode` - The permission mode to set:
-    ///   - "default": CLI prompts for dangerous tools
-    ///   - "acceptEdits": Auto-accept file edits
-    ///   - "bypassPermissions": Allow all tools (use with caution)
+    /// Installs a handler for SIGINT (Ctrl-C on Windows) so it no longer
+    /// terminates the process. The first Ctrl-C sends `interrupt`; pressing
+    /// it again before the interrupted turn has ended closes the connection,
+    /// which ends the message stream. The handler cannot be uninstalled, so
+    /// once the connection is closed, or the client is closed or dropped, the
+    /// next Ctrl-C exits the process with status 130 unless another client
+    /// is still handling it.
     ///
     /// # Example
     /// ```no_run
@@ -244,92 +684,147 @@ impl ClaudeSDKClient {
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ///     let mut client = ClaudeSDKClient::new(None);
     ///     client.connect().await?;
+    ///     client.interrupt_on_ctrl_c()?;
     ///
-    ///     // Start with default permissions
-    ///     client.query("Help me analyze this codebase", None).await?;
-    ///
-    ///     // Switch to auto-accept edits
-    ///     client.set_permission_mode("acceptEdits").await?;
-    ///     client.query("Now implement the fix", None).await?;
+    ///     while client.is_connected() {
+    ///         let mut line = String::new();
+    ///         if std::io::stdin().read_line(&mut line)? == 0 {
+    ///             break;
+    ///         }
+    ///         match client.query_for_text(&line).await {
+    ///             Ok(answer) => println!("{}", answer),
+    ///             Err(e) => eprintln!("{}", e),
+    ///         }
+    ///     }
     ///     Ok(())
     /// }
     /// ```
-    pub async fn set_permission_mode(&mut self, mode: &str) -> Result<()> {
+    pub fn interrupt_on_ctrl_c(&mut self) -> Result<()> {
         let query = self
             .query
-            .as_mut()
+            .as_ref()
             .ok_or_else(|| ClaudeSDKError::cli_connection_error("Not connected. Call connect() first.".to_string()))?;
+        let interrupter = query.interrupter();
 
-        query.set_permission_mode(mode).await
+        // Register now so no Ctrl-C is missed once this returns
+        let mut signal = ctrl_c_listener()
+            .map_err(|e| ClaudeSDKError::io_error("Failed to install Ctrl-C handler", e))?;
+
+        let handler = CtrlCHandler::new();
+        let released = Arc::clone(&handler.released);
+        tokio::spawn(async move {
+            let mut interrupted_turn = None;
+            while signal.recv().await.is_some() {
+                if released.load(Ordering::SeqCst) {
+                    // Do what Ctrl-C did before the handler was installed
+                    if CTRL_C_HANDLERS.load(Ordering::SeqCst) == 0 {
+                        std::process::exit(130);
+                    }
+                    break;
+                }
+                let turn = interrupter.turns_completed();
+                if interrupted_turn == Some(turn) {
+                    let _ = interrupter.close().await;
+                    release_ctrl_c(&released);
+                    continue;
+                }
+                interrupted_turn = Some(turn);
+                let _ = interrupter.interrupt().await;
+            }
+        });
+        self.ctrl_c = Some(handler);
+        Ok(())
     }
 
-    /// Change the AI model during conversation
+    /// Change permission mode during conversation
     ///
     /// # Arguments
-    /// * `model` - The model to use (e.g., "claude-sonnet-4-5")
+    /// * `mode` - The permission mode to set, as a
+    ///   [`PermissionMode`](crate::PermissionMode) or the CLI's name for it:
+    ///   - `Default`: CLI prompts for dangerous tools
+    ///   - `AcceptEdits`: Auto-accept file edits
+    ///   - `Plan`: Plan only, without executing tools
+    ///   - `BypassPermissions`: Allow all tools (use with caution)
     ///
     /// # Example
     /// ```no_run
-    /// use claude::ClaudeSDKClient;
+    /// use claude::{ClaudeSDKClient, PermissionMode};
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ///     let mut client = ClaudeSDKClient::new(None);
     ///     client.connect().await?;
     ///
-    ///     client.query("Help me understand this problem", None).await?;
+    ///     // Start with default permissions
+    ///     client.query("Help me analyze this codebase", None).await?;
     ///
-    ///     // Switch model
-    ///     client.set_model(Some("claude-sonnet-4-5")).await?;
-    ///     client.query("Now implement the solution", None).await?;
+    ///     // Switch to auto-accept edits
+    ///     client.set_permission_mode(PermissionMode::AcceptEdits).await?;
+    ///     client.query("Now implement the fix", None).await?;
     ///     Ok(())
     /// }
     /// ```
-    pub async fn set_model(&mut self, model: Option<&str>) -> Result<()> {
+    pub async fn set_permission_mode(&mut self, mode: impl AsRef<str>) -> Result<()> {
         let query = self
             .query
             .as_mut()
             .ok_or_else(|| ClaudeSDKError::cli_connection_error("Not connected. Call connect() first.".to_string()))?;
 
-        query.set_model(model).await
+        query.set_permission_mode(mode.as_ref()).await
     }
 
-    // Session Management Methods
-
-    /// Create a client that resumes from an existing session
+    /// Replace the system prompt for the rest of the conversation
     ///
-    /// # Arguments
-    /// * `session_id` - The session ID to resume from
-    /// * `options` - Optional additional configuration
+    /// The CLI only reads the system prompt at startup, so a connected client
+    /// restarts it with the new prompt and resumes the current session. Call
+    /// this between turns: a response in progress is cut off, and message
+    /// streams and [`session`](Self::session) handles opened before the
+    /// restart end. Restarting needs a way to build a new transport, so it
+    /// fails for a client given a single transport without a
+    /// [`transport_factory`](Self::transport_factory).
     ///
     /// # Example
     /// ```no_run
-    /// use claude::ClaudeSDKClient;
+    /// use claude::{ClaudeSDKClient, SystemPromptConfig};
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let mut client = ClaudeSDKClient::resume("session-123", None);
+    ///     let mut client = ClaudeSDKClient::new(None);
     ///     client.connect().await?;
-    ///     client.query("What did we discuss earlier?", None).await?;
+    ///     client.query_for_text("Review this function").await?;
+    ///
+    ///     client
+    ///         .set_system_prompt(SystemPromptConfig::Text("You are a terse security auditor".to_string()))
+    ///         .await?;
+    ///     client.query_for_text("Anything risky in it?").await?;
     ///     Ok(())
     /// }
     /// ```
-    pub fn resume(session_id: impl Into<String>, options: Option<ClaudeAgentOptions>) -> Self {
-        let mut opts = options.unwrap_or_default();
-        opts.resume = Some(session_id.into());
+    pub async fn set_system_prompt(&mut self, prompt: SystemPromptConfig) -> Result<()> {
+        if self.query.is_some() && !self.can_reconnect {
+            return Err(ClaudeSDKError::cli_connection_error(
+                "Changing the system prompt restarts the CLI, which needs a transport factory".to_string(),
+            ));
+        }
 
-        Self {
-            options: opts,
-            query: None,
-            can_use_tool: None,
+        self.options.system_prompt = Some(prompt);
+        let Some(mut query) = self.query.take() else {
+            return Ok(());
+        };
+
+        if let Some(session_id) = query.session_id() {
+            self.options.resume = Some(session_id);
+            self.options.continue_conversation = false;
+            self.options.fork_session = false;
         }
+        query.close().await?;
+        self.connect().await
     }
 
-    /// Create a client that forks from an existing session
+    /// Change the AI model during conversation
     ///
     /// # Arguments
-    /// * `session_id` - The session ID to fork from
-    /// * `options` - Optional additional configuration
+    /// * `model` - The model to use (e.g., "claude-sonnet-4-5")
     ///
     /// # Example
     /// ```no_run
@@ -337,23 +832,288 @@ impl ClaudeSDKClient {
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    ///     // Fork creates a new branch from the original session
-    ///     let mut fork = ClaudeSDKClient::fork("session-123", None);
-    ///     fork.connect().await?;
-    ///     fork.query("Let's try a different approach", None).await?;
+    ///     let mut client = ClaudeSDKClient::new(None);
+    ///     client.connect().await?;
+    ///
+    ///     client.query("Help me understand this problem", None).await?;
+    ///
+    ///     // Switch model
+    ///     client.set_model(Some("claude-sonnet-4-5")).await?;
+    ///     client.query("Now implement the solution", None).await?;
     ///     Ok(())
     /// }
     /// ```
-    pub fn fork(session_id: impl Into<String>, options: Option<ClaudeAgentOptions>) -> Self {
+    pub async fn set_model(&mut self, model: Option<&str>) -> Result<()> {
+        let query = self
+            .query
+            .as_mut()
+            .ok_or_else(|| ClaudeSDKError::cli_connection_error("Not connected. Call connect() first.".to_string()))?;
+
+        query.set_model(model).await
+    }
+
+    /// Summarize the conversation so far to free up context
+    ///
+    /// Sends a `compact` control request, optionally with instructions on
+    /// what to keep. The conversation continues after a `compact_boundary`
+    /// system message on the message stream.
+    pub async fn compact(&mut self, instructions: Option<&str>) -> Result<()> {
+        let query = self
+            .query
+            .as_mut()
+            .ok_or_else(|| ClaudeSDKError::cli_connection_error("Not connected. Call connect() first.".to_string()))?;
+
+        query.compact(instructions).await
+    }
+
+    /// Restore files changed since an earlier turn
+    ///
+    /// `user_message_id` is the [`uuid`](crate::types::UserMessage::uuid) of
+    /// a user message received in this session. The CLI must have file
+    /// checkpointing enabled.
+    pub async fn rewind_files(&mut self, user_message_id: &str) -> Result<()> {
+        let query = self
+            .query
+            .as_mut()
+            .ok_or_else(|| ClaudeSDKError::cli_connection_error("Not connected. Call connect() first.".to_string()))?;
+
+        query.rewind_files(user_message_id).await
+    }
+
+    /// List the MCP servers of the session and their connection status
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::ClaudeSDKClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::new(None);
+    ///     client.connect().await?;
+    ///
+    ///     for server in client.list_mcp_servers().await? {
+    ///         println!("{}: {}", server.name, server.status);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn list_mcp_servers(&mut self) -> Result<Vec<McpServerStatus>> {
+        let query = self
+            .query
+            .as_mut()
+            .ok_or_else(|| ClaudeSDKError::cli_connection_error("Not connected. Call connect() first.".to_string()))?;
+
+        let response = query.mcp_status().await?;
+        let servers = response.get("mcpServers").cloned().unwrap_or_else(|| json!([]));
+        serde_json::from_value(servers)
+            .map_err(|e| ClaudeSDKError::message_parse_error_from(format!("Invalid mcp_status response: {}", e), Some(response), e))
+    }
+
+    /// Capabilities the CLI reported when the session was initialized
+    ///
+    /// `None` before `connect()` or if the CLI did not answer `initialize`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::ClaudeSDKClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::new(None);
+    ///     client.connect().await?;
+    ///
+    ///     if client.server_info().is_some_and(|info| info.has_command("compact")) {
+    ///         client.query("/compact", None).await?;
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn server_info(&self) -> Option<&ServerInfo> {
+        self.server_info.as_ref()
+    }
+
+    /// Whether the client is connected and the CLI is still producing output
+    pub fn is_connected(&self) -> bool {
+        self.query.as_ref().is_some_and(|query| query.is_connected())
+    }
+
+    /// Exit status of the CLI once it has exited
+    ///
+    /// Available after the CLI stops on its own or after [`close`](Self::close).
+    /// `None` while the CLI runs and for transports that don't run a local
+    /// process.
+    pub async fn exit_status(&self) -> Option<ExitStatus> {
+        match &self.query {
+            Some(query) => query.exit_status().await,
+            None => self.exit_status,
+        }
+    }
+
+    /// Check that the CLI responds and measure the round-trip latency
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::ClaudeSDKClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::new(None);
+    ///     client.connect().await?;
+    ///
+    ///     let latency = client.ping().await?;
+    ///     println!("CLI answered in {:?}", latency);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn ping(&self) -> Result<std::time::Duration> {
+        let query = self
+            .query
+            .as_ref()
+            .ok_or_else(|| ClaudeSDKError::cli_connection_error("Not connected. Call connect() first.".to_string()))?;
+
+        query.ping().await
+    }
+
+    /// Ping the CLI in the background and report when the connection degrades
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::{ClaudeSDKClient, HeartbeatConfig, HeartbeatEvent};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::new(None);
+    ///     client.connect().await?;
+    ///
+    ///     let mut health = client.start_heartbeat(HeartbeatConfig::default())?;
+    ///     tokio::spawn(async move {
+    ///         while let Some(event) = health.recv().await {
+    ///             if let HeartbeatEvent::Degraded { error } = event {
+    ///                 eprintln!("Claude Code is not responding: {}", error);
+    ///             }
+    ///         }
+    ///     });
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn start_heartbeat(&mut self, config: HeartbeatConfig) -> Result<mpsc::Receiver<HeartbeatEvent>> {
+        let query = self
+            .query
+            .as_mut()
+            .ok_or_else(|| ClaudeSDKError::cli_connection_error("Not connected. Call connect() first.".to_string()))?;
+
+        Ok(query.start_heartbeat(config))
+    }
+
+    // Session Management Methods
+
+    /// Create a client that resumes from an existing session
+    ///
+    /// # Arguments
+    /// * `session_id` - The session ID to resume from
+    /// * `options` - Optional additional configuration
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::ClaudeSDKClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::resume("session-123", None);
+    ///     client.connect().await?;
+    ///     client.query("What did we discuss earlier?", None).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn resume(session_id: impl Into<SessionId>, options: Option<ClaudeAgentOptions>) -> Self {
+        let mut opts = options.unwrap_or_default();
+        opts.resume = Some(session_id.into());
+
+        Self::new(Some(opts))
+    }
+
+    /// Create a client that forks from an existing session
+    ///
+    /// # Arguments
+    /// * `session_id` - The session ID to fork from
+    /// * `options` - Optional additional configuration
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::ClaudeSDKClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     // Fork creates a new branch from the original session
+    ///     let mut fork = ClaudeSDKClient::fork("session-123", None);
+    ///     fork.connect().await?;
+    ///     fork.query("Let's try a different approach", None).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn fork(session_id: impl Into<SessionId>, options: Option<ClaudeAgentOptions>) -> Self {
         let mut opts = options.unwrap_or_default();
         opts.resume = Some(session_id.into());
         opts.fork_session = true;
 
-        Self {
-            options: opts,
-            query: None,
-            can_use_tool: None,
+        Self::new(Some(opts))
+    }
+
+    /// ID of the current session, once the CLI has reported it
+    ///
+    /// Available after the first system `init` or result message has been
+    /// received, and kept up to date across restarts. Use it to
+    /// [`resume`](Self::resume) or [`fork`](Self::fork) the conversation later.
+    pub fn session_id(&self) -> Option<SessionId> {
+        self.query.as_ref().and_then(Query::session_id)
+    }
+
+    /// Create an unconnected client that forks the current session
+    ///
+    /// The fork gets the same options, callbacks, hooks, MCP servers and
+    /// transport factory as this client, and branches from the conversation
+    /// so far without affecting it.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::ClaudeSDKClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::new(None);
+    ///     client.connect().await?;
+    ///     client.query_for_text("Let's design a cache").await?;
+    ///
+    ///     let mut alternative = client.fork_current()?;
+    ///     alternative.connect().await?;
+    ///     alternative.query_for_text("Try an LRU design instead").await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn fork_current(&self) -> Result<Self> {
+        let session_id = self.session_id().ok_or_else(|| {
+            ClaudeSDKError::cli_connection_error("No session to fork yet; wait for the first response".to_string())
+        })?;
+        if !self.can_reconnect {
+            return Err(ClaudeSDKError::cli_connection_error(
+                "Forking needs a transport factory to create the new connection".to_string(),
+            ));
         }
+
+        let mut options = self.options.clone();
+        options.resume = Some(session_id);
+        options.fork_session = true;
+        options.continue_conversation = false;
+
+        Ok(Self {
+            can_use_tool: self.can_use_tool.clone(),
+            transport_factory: self.transport_factory.clone(),
+            restart_policy: self.restart_policy.clone(),
+            hook_manager: self.hook_manager.clone(),
+            initialize_timeout: self.initialize_timeout,
+            request_timeout: self.request_timeout,
+            ..Self::new(Some(options))
+        })
     }
 
     /// Create a client with continuous conversation enabled
@@ -378,32 +1138,410 @@ impl ClaudeSDKClient {
         let mut opts = options.unwrap_or_default();
         opts.continue_conversation = true;
 
-        Self {
-            options: opts,
-            query: None,
-            can_use_tool: None,
-        }
+        Self::new(Some(opts))
+    }
+
+    /// Split a connected client into halves for writing and reading
+    ///
+    /// The halves can be moved to different tasks, so one task can await
+    /// responses while another sends queries or interrupts. The sender owns
+    /// the connection: dropping or closing it stops the CLI, which ends the
+    /// receiver's streams.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::ClaudeSDKClient;
+    /// use futures::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut client = ClaudeSDKClient::new(None);
+    ///     client.connect().await?;
+    ///     let (mut sender, mut receiver) = client.split()?;
+    ///
+    ///     let reader = tokio::spawn(async move {
+    ///         let mut messages = receiver.receive_messages();
+    ///         while let Some(message) = messages.next().await {
+    ///             println!("{:?}", message);
+    ///         }
+    ///     });
+    ///
+    ///     sender.query("Hello", None).await?;
+    ///     sender.close().await?;
+    ///     reader.await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn split(mut self) -> Result<(ClientSender, ClientReceiver)> {
+        let query = self
+            .query
+            .take()
+            .ok_or_else(|| ClaudeSDKError::cli_connection_error("Not connected. Call connect() first.".to_string()))?;
+
+        let sender = ClientSender {
+            query,
+            _ctrl_c: self.ctrl_c.take(),
+        };
+        let receiver = ClientReceiver {
+            messages: Arc::clone(&self.messages),
+        };
+        Ok((sender, receiver))
     }
 
     /// Disconnect from Claude Code and clean up resources
-    pub async fn close(mut self) -> Result<()> {
-        if let Some(query) = self.query.take() {
-            query.close().await?;
+    ///
+    /// Calling `close` again is a no-op. Dropping the client without closing
+    /// it stops the CLI in the background on a best-effort basis.
+    pub async fn close(&mut self) -> Result<()> {
+        self.ctrl_c = None;
+        if let Some(mut query) = self.query.take() {
+            let result = query.close().await;
+            self.exit_status = query.exit_status().await;
+            result?;
         }
         Ok(())
     }
 }
 
+/// How long `connect` waits for the CLI to answer `initialize`
+const DEFAULT_INITIALIZE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Fluent builder for [`ClaudeSDKClient`], created by [`ClaudeSDKClient::builder`]
+///
+/// Call [`options`](Self::options) first if you pass a full
+/// `ClaudeAgentOptions`; the session and timeout methods adjust the options
+/// set so far.
+pub struct ClaudeSDKClientBuilder {
+    inner: ClaudeSDKClient,
+    hooks: Option<HookManager>,
+}
+
+impl ClaudeSDKClientBuilder {
+    /// Use these options as the starting point
+    ///
+    /// In-process servers added so far with [`mcp_server`](Self::mcp_server)
+    /// are kept unless `options` configures a server of the same name.
+    pub fn options(mut self, options: ClaudeAgentOptions) -> Self {
+        let previous = std::mem::replace(&mut self.inner.options, options);
+        for (name, config) in previous.mcp_servers {
+            if matches!(config, McpServerConfig::Sdk(_)) {
+                self.inner.options.mcp_servers.entry(name).or_insert(config);
+            }
+        }
+        self
+    }
+
+    /// Decide tool permissions with a callback
+    pub fn can_use_tool(mut self, callback: CanUseToolCallback) -> Self {
+        self.inner.can_use_tool = Some(callback);
+        self
+    }
+
+    /// Answer the CLI's permission prompts with a callback over MCP
+    ///
+    /// Registers [`permission_prompt_server`](crate::permissions::permission_prompt_server)
+    /// and passes its tool to `--permission-prompt-tool`, so the decision runs
+    /// in-process even when the CLI routes permission checks through MCP.
+    pub fn permission_prompt(mut self, callback: CanUseToolCallback) -> Self {
+        let server = crate::permissions::permission_prompt_server(callback);
+        self.inner.options.mcp_servers.insert(server.name.clone(), McpServerConfig::Sdk(server));
+        self.inner.options.permission_prompt_tool_name = Some(crate::permissions::PERMISSION_PROMPT_TOOL.to_string());
+        self
+    }
+
+    /// Run `callback` for `event` on tools matching `matcher`
+    ///
+    /// `matcher` is a tool name or `*` for every tool; events other than
+    /// PreToolUse and PostToolUse ignore it. Unknown event names are ignored.
+    pub fn hook(mut self, event: impl IntoHookEvent, matcher: impl Into<String>, callback: HookCallback) -> Self {
+        if let Some(event) = event.into_hook_event() {
+            self.hooks
+                .get_or_insert_with(HookManager::new)
+                .add_hook(event, matcher, callback);
+        }
+        self
+    }
+
+    /// Use a preconfigured hook manager, replacing hooks added so far
+    pub fn hook_manager(mut self, hook_manager: HookManager) -> Self {
+        self.hooks = Some(hook_manager);
+        self
+    }
+
+    /// Serve an in-process MCP server to the CLI
+    pub fn mcp_server(mut self, server: SdkMcpServer) -> Self {
+        self.inner.options.mcp_servers.insert(server.name.clone(), McpServerConfig::Sdk(server));
+        self
+    }
+
+    /// Resume an existing session
+    pub fn resume(mut self, session_id: impl Into<SessionId>) -> Self {
+        self.inner.options.resume = Some(session_id.into());
+        self.inner.options.fork_session = false;
+        self
+    }
+
+    /// Start a new session branched from an existing one
+    pub fn fork(mut self, session_id: impl Into<SessionId>) -> Self {
+        self.inner.options.resume = Some(session_id.into());
+        self.inner.options.fork_session = true;
+        self
+    }
+
+    /// Continue the most recent conversation
+    pub fn continue_conversation(mut self) -> Self {
+        self.inner.options.continue_conversation = true;
+        self
+    }
+
+    /// Set how long `connect` waits for the initialize handshake (default 2 s)
+    pub fn initialize_timeout(mut self, timeout: Duration) -> Self {
+        self.inner.initialize_timeout = timeout;
+        self
+    }
+
+    /// Set how long control requests such as `interrupt` wait (default 30 s)
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.inner.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Set how long `close` waits for the CLI to exit before killing it
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.inner.options.shutdown_timeout = Some(timeout);
+        self
+    }
+
+    /// Use a custom transport, see [`ClaudeSDKClient::transport`]
+    pub fn transport(mut self, transport: Box<dyn Transport>) -> Self {
+        self.inner.transport = Some(transport);
+        self
+    }
+
+    /// Build transports with a factory, see [`ClaudeSDKClient::transport_factory`]
+    pub fn transport_factory(
+        mut self,
+        factory: impl Fn(ClaudeAgentOptions) -> Box<dyn Transport> + Send + Sync + 'static,
+    ) -> Self {
+        self.inner.transport_factory = Some(Arc::new(factory));
+        self
+    }
+
+    /// Restart the CLI after a crash, see [`ClaudeSDKClient::restart_policy`]
+    pub fn restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.inner.restart_policy = Some(policy);
+        self
+    }
+
+    /// Build the client
+    pub fn build(self) -> ClaudeSDKClient {
+        let mut client = self.inner;
+        client.hook_manager = self.hooks.map(|hooks| Arc::new(Mutex::new(hooks)));
+        client
+    }
+}
+
+/// Writing half of a split [`ClaudeSDKClient`], created by [`ClaudeSDKClient::split`]
+pub struct ClientSender {
+    query: Query,
+    _ctrl_c: Option<CtrlCHandler>,
+}
+
+impl ClientSender {
+    /// Send a new query, see [`ClaudeSDKClient::query`]
+    pub async fn query(&mut self, prompt: &str, session_id: Option<&str>) -> Result<()> {
+        let message = UserEnvelope::text(prompt).session_id(session_id.unwrap_or("default")).to_wire();
+        self.query.send_message(message).await
+    }
+
+    /// Send a query made of content blocks, see [`ClaudeSDKClient::query_blocks`]
+    pub async fn query_blocks(&mut self, blocks: Vec<ContentBlock>, session_id: Option<&str>) -> Result<()> {
+        let message = UserEnvelope::blocks(blocks).session_id(session_id.unwrap_or("default")).to_wire();
+        self.query.send_message(message).await
+    }
+
+    /// Stop the current operation
+    pub async fn interrupt(&mut self) -> Result<()> {
+        self.query.interrupt().await
+    }
+
+    /// Change the permission mode, see [`ClaudeSDKClient::set_permission_mode`]
+    pub async fn set_permission_mode(&mut self, mode: impl AsRef<str>) -> Result<()> {
+        self.query.set_permission_mode(mode.as_ref()).await
+    }
+
+    /// Change the model, or reset it to the default with `None`
+    pub async fn set_model(&mut self, model: Option<&str>) -> Result<()> {
+        self.query.set_model(model).await
+    }
+
+    /// Whether the CLI is still producing output
+    pub fn is_connected(&self) -> bool {
+        self.query.is_connected()
+    }
+
+    /// Exit status of the CLI once it has exited, see [`ClaudeSDKClient::exit_status`]
+    pub async fn exit_status(&self) -> Option<ExitStatus> {
+        self.query.exit_status().await
+    }
+
+    /// Disconnect from Claude Code, ending the receiver's streams
+    pub async fn close(&mut self) -> Result<()> {
+        self._ctrl_c = None;
+        self.query.close().await
+    }
+}
+
+/// Reading half of a split [`ClaudeSDKClient`], created by [`ClaudeSDKClient::split`]
+pub struct ClientReceiver {
+    messages: MessageSlot,
+}
+
+impl ClientReceiver {
+    /// Receive all messages, see [`ClaudeSDKClient::receive_messages`]
+    pub fn receive_messages(&mut self) -> MessageStream {
+        MessageStream {
+            receiver: self.messages.lock().unwrap().take(),
+            slot: Arc::clone(&self.messages),
+        }
+    }
+
+    /// Receive messages until and including a ResultMessage
+    pub fn receive_response(&mut self) -> ResponseStream {
+        ResponseStream::new(self.receive_messages())
+    }
+
+    /// Receive the text of the current response, see [`ClaudeSDKClient::receive_text_stream`]
+    pub fn receive_text_stream(&mut self) -> TextStream {
+        TextStream::new(self.receive_response())
+    }
+}
+
+/// One conversation multiplexed over a [`ClaudeSDKClient`] connection
+///
+/// Created by [`ClaudeSDKClient::session`]. The stream ends when the client
+/// disconnects.
+pub struct ClientSession {
+    name: String,
+    transport: Arc<Mutex<Box<dyn Transport>>>,
+    routes: Arc<std::sync::Mutex<SessionRoutes>>,
+    messages: MessageSlot,
+}
+
+impl ClientSession {
+    /// Session ID this handle sends and receives on
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Send a prompt in this session
+    ///
+    /// The CLI answers prompts one turn at a time, in the order they were
+    /// sent, and tags the answer with the session ID it assigned. The next
+    /// turn that is not tagged with a routed ID is delivered to this handle,
+    /// up to and including its result message.
+    pub async fn query(&mut self, prompt: &str) -> Result<()> {
+        self.routes.lock().unwrap().expect_answer(&self.name);
+        let result = write_json(&self.transport, &UserEnvelope::text(prompt).session_id(self.name.as_str()).to_wire()).await;
+        if result.is_err() {
+            self.routes.lock().unwrap().cancel_answer(&self.name);
+        }
+        result
+    }
+
+    /// Receive this session's messages
+    ///
+    /// Behaves like [`ClaudeSDKClient::receive_messages`].
+    pub fn receive_messages(&mut self) -> MessageStream {
+        MessageStream {
+            receiver: self.messages.lock().unwrap().take(),
+            slot: Arc::clone(&self.messages),
+        }
+    }
+
+    /// Receive this session's messages until and including a ResultMessage
+    pub fn receive_response(&mut self) -> ResponseStream {
+        ResponseStream::new(self.receive_messages())
+    }
+}
+
+/// Error for a response stream that closed without a ResultMessage
+fn response_ended_early() -> ClaudeSDKError {
+    ClaudeSDKError::cli_connection_error("Message stream ended before the result message".to_string())
+}
+
+/// Ctrl-C handlers whose client is still open
+static CTRL_C_HANDLERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a client's Ctrl-C handler in [`CTRL_C_HANDLERS`] until the client
+/// is closed or dropped
+struct CtrlCHandler {
+    released: Arc<AtomicBool>,
+}
+
+impl CtrlCHandler {
+    fn new() -> Self {
+        CTRL_C_HANDLERS.fetch_add(1, Ordering::SeqCst);
+        CtrlCHandler {
+            released: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl Drop for CtrlCHandler {
+    fn drop(&mut self) {
+        release_ctrl_c(&self.released);
+    }
+}
+
+/// Stop counting a handler, once
+fn release_ctrl_c(released: &AtomicBool) {
+    if !released.swap(true, Ordering::SeqCst) {
+        CTRL_C_HANDLERS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(unix)]
+fn ctrl_c_listener() -> std::io::Result<tokio::signal::unix::Signal> {
+    tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())
+}
+
+#[cfg(windows)]
+fn ctrl_c_listener() -> std::io::Result<tokio::signal::windows::CtrlC> {
+    tokio::signal::windows::ctrl_c()
+}
+
+/// Spawn the CLI with an empty prompt for interactive mode
+fn spawn_cli(options: ClaudeAgentOptions) -> Box<dyn Transport> {
+    Box::new(SubprocessTransport::new(String::new(), options))
+}
+
+/// Where the client keeps the message receiver between streams
+type MessageSlot = Arc<std::sync::Mutex<Option<mpsc::Receiver<Result<Message>>>>>;
+
 /// Stream of messages from Claude
 pub struct MessageStream {
-    receiver: mpsc::UnboundedReceiver<Result<Message>>,
+    receiver: Option<mpsc::Receiver<Result<Message>>>,
+    slot: MessageSlot,
 }
 
 impl Stream for MessageStream {
     type Item = Result<Message>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.receiver.poll_recv(cx)
+        match self.receiver.as_mut() {
+            Some(receiver) => receiver.poll_recv(cx),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+impl Drop for MessageStream {
+    fn drop(&mut self) {
+        // After a reconnect the slot already holds the new session's receiver
+        if let Some(receiver) = self.receiver.take() {
+            self.slot.lock().unwrap().get_or_insert(receiver);
+        }
     }
 }
 
@@ -411,6 +1549,21 @@ impl Stream for MessageStream {
 pub struct ResponseStream {
     inner: MessageStream,
     terminated: bool,
+    cancel: Option<Pin<Box<dyn Future<Output = ()> + Send + Sync>>>,
+    interrupter: Option<Interrupter>,
+    cancelled: bool,
+}
+
+impl ResponseStream {
+    fn new(inner: MessageStream) -> Self {
+        Self {
+            inner,
+            terminated: false,
+            cancel: None,
+            interrupter: None,
+            cancelled: false,
+        }
+    }
 }
 
 impl Stream for ResponseStream {
@@ -421,20 +1574,101 @@ impl Stream for ResponseStream {
             return Poll::Ready(None);
         }
 
-        match Pin::new(&mut self.inner).poll_next(cx) {
-            Poll::Ready(Some(Ok(msg))) => {
-                // Check if this is a ResultMessage
-                if matches!(msg, Message::Result(_)) {
+        if let Some(cancel) = self.cancel.as_mut() {
+            if cancel.as_mut().poll(cx).is_ready() {
+                self.cancel = None;
+                self.cancelled = true;
+                if let Some(interrupter) = self.interrupter.take() {
+                    tokio::spawn(async move {
+                        let _ = interrupter.interrupt().await;
+                    });
+                }
+            }
+        }
+
+        loop {
+            match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(msg))) => {
+                    // Check if this is a ResultMessage
+                    if matches!(msg, Message::Result(_)) {
+                        self.terminated = true;
+                    } else if self.cancelled {
+                        // Drain the interrupted turn so it does not leak into the next one
+                        continue;
+                    }
+                    if self.cancelled {
+                        return Poll::Ready(Some(Err(ClaudeSDKError::interrupted())));
+                    }
+                    return Poll::Ready(Some(Ok(msg)));
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => {
                     self.terminated = true;
+                    if self.cancelled {
+                        return Poll::Ready(Some(Err(ClaudeSDKError::interrupted())));
+                    }
+                    return Poll::Ready(None);
                 }
-                Poll::Ready(Some(Ok(msg)))
+                Poll::Pending => return Poll::Pending,
             }
-            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
-            Poll::Ready(None) => {
-                self.terminated = true;
-                Poll::Ready(None)
+        }
+    }
+}
+
+/// Text chunks of a response, see [`ClaudeSDKClient::receive_text_stream`]
+///
+/// Also wraps other message streams, e.g. a
+/// [`StreamingQuery`](crate::StreamingQuery) in
+/// [`QuickQuery::stream_text`](crate::QuickQuery::stream_text).
+pub struct TextStream<S = ResponseStream> {
+    inner: S,
+    // Text blocks of a full assistant message not yet yielded
+    pending: VecDeque<String>,
+    saw_delta: bool,
+}
+
+impl<S> TextStream<S> {
+    pub(crate) fn new(inner: S) -> Self {
+        TextStream {
+            inner,
+            pending: VecDeque::new(),
+            saw_delta: false,
+        }
+    }
+}
+
+impl<S: Stream<Item = Result<Message>> + Unpin> Stream for TextStream<S> {
+    type Item = Result<String>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(text) = self.pending.pop_front() {
+                return Poll::Ready(Some(Ok(text)));
+            }
+
+            let message = match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(message))) => message,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            match message {
+                Message::Stream(ref event) if event.parent_tool_use_id.is_none() => {
+                    if let Some(text) = message.text_delta() {
+                        self.saw_delta = true;
+                        return Poll::Ready(Some(Ok(text.to_string())));
+                    }
+                }
+                // Full messages repeat the deltas when partial messages are on
+                Message::Assistant(assistant) if !self.saw_delta && assistant.parent_tool_use_id.is_none() => {
+                    self.pending.extend(assistant.content.into_iter().filter_map(|block| match block {
+                        ContentBlock::Text { text } => Some(text),
+                        _ => None,
+                    }));
+                }
+                _ => {}
             }
-            Poll::Pending => Poll::Pending,
         }
     }
 }
@@ -458,4 +1692,31 @@ mod tests {
         let client = ClaudeSDKClient::new(Some(opts));
         assert_eq!(client.options.max_turns, Some(10));
     }
+
+    #[test]
+    fn test_builder_combines_session_and_callbacks() {
+        let client = ClaudeSDKClient::builder()
+            .options(ClaudeAgentOptions {
+                max_turns: Some(3),
+                ..Default::default()
+            })
+            .fork("session-123")
+            .can_use_tool(Arc::new(|_, _, _| {
+                Box::pin(async { Ok(crate::types::PermissionResult::allow()) })
+            }))
+            .mcp_server(SdkMcpServer::new("tools".to_string(), "1.0.0".to_string(), vec![]))
+            .request_timeout(Duration::from_secs(5))
+            .build();
+
+        assert_eq!(client.options.max_turns, Some(3));
+        assert_eq!(client.options.resume.as_deref(), Some("session-123"));
+        assert!(client.options.fork_session);
+        assert!(client.can_use_tool.is_some());
+        assert!(matches!(
+            client.options.mcp_servers.get("tools"),
+            Some(McpServerConfig::Sdk(server)) if server.name == "tools"
+        ));
+        assert_eq!(client.request_timeout, Some(Duration::from_secs(5)));
+        assert!(client.hook_manager.is_none());
+    }
 }
@@ -0,0 +1,229 @@
+//! Helpers for building multimodal user content
+//!
+//! [`UserContent`] creates the [`ContentBlock`]s accepted by
+//! [`ClaudeSDKClient::query_blocks`](crate::ClaudeSDKClient::query_blocks),
+//! so screenshots and documents can be sent alongside text.
+//!
+//! # Example
+//! ```no_run
+//! use claude::{ClaudeSDKClient, UserContent};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut client = ClaudeSDKClient::new(None);
+//!     client.connect().await?;
+//!
+//!     client
+//!         .query_blocks(
+//!             vec![
+//!                 UserContent::text("What is wrong with this layout?"),
+//!                 UserContent::file("screenshot.png")?,
+//!             ],
+//!             None,
+//!         )
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::{ClaudeSDKError, Result};
+use crate::types::{ContentBlock, ContentSource};
+use base64::Engine;
+use std::path::Path;
+
+/// Largest file [`UserContent::attachment`] accepts unless told otherwise (10 MiB)
+pub const DEFAULT_MAX_ATTACHMENT_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Constructors for user content blocks
+pub struct UserContent;
+
+impl UserContent {
+    /// A text block
+    pub fn text(text: impl Into<String>) -> ContentBlock {
+        ContentBlock::Text { text: text.into() }
+    }
+
+    /// An image from base64-encoded data, e.g. `image/png`
+    pub fn image_base64(media_type: impl Into<String>, data: impl Into<String>) -> ContentBlock {
+        ContentBlock::Image {
+            source: ContentSource::Base64 {
+                media_type: media_type.into(),
+                data: data.into(),
+            },
+        }
+    }
+
+    /// An image from raw bytes, encoded for you
+    pub fn image_bytes(media_type: impl Into<String>, bytes: &[u8]) -> ContentBlock {
+        Self::image_base64(media_type, encode(bytes))
+    }
+
+    /// An image Claude fetches from a URL
+    pub fn image_url(url: impl Into<String>) -> ContentBlock {
+        ContentBlock::Image {
+            source: ContentSource::Url { url: url.into() },
+        }
+    }
+
+    /// A PDF document from raw bytes
+    pub fn pdf_bytes(bytes: &[u8], title: Option<String>) -> ContentBlock {
+        ContentBlock::Document {
+            source: ContentSource::Base64 {
+                media_type: "application/pdf".to_string(),
+                data: encode(bytes),
+            },
+            title,
+        }
+    }
+
+    /// A plain-text document
+    pub fn text_document(text: impl Into<String>, title: Option<String>) -> ContentBlock {
+        ContentBlock::Document {
+            source: ContentSource::Text {
+                media_type: "text/plain".to_string(),
+                data: text.into(),
+            },
+            title,
+        }
+    }
+
+    /// Attach a file, choosing the block type from its extension
+    ///
+    /// PNG, JPEG, GIF and WebP files become images and PDFs become documents.
+    /// Anything else is read as UTF-8 text and sent as a text document. The
+    /// file name is used as the document title.
+    pub fn file(path: impl AsRef<Path>) -> std::io::Result<ContentBlock> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        if let Some(block) = binary_block(path, &bytes) {
+            return Ok(block);
+        }
+        let text = String::from_utf8(bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(Self::text_document(text, title_of(path)))
+    }
+
+    /// Attach a file like [`file`](Self::file), refusing files over `max_bytes`
+    /// and binary files that are neither images nor PDFs
+    ///
+    /// A file counts as binary if it is not valid UTF-8 or has a NUL byte
+    /// near the start. Failures are reported as
+    /// [`ClaudeSDKError::InvalidAttachment`].
+    pub fn attachment(path: impl AsRef<Path>, max_bytes: u64) -> Result<ContentBlock> {
+        let path = path.as_ref();
+        let invalid = |message: String| ClaudeSDKError::invalid_attachment(path.display().to_string(), message);
+
+        let io_error = |e| ClaudeSDKError::invalid_attachment_from(path.display().to_string(), e);
+
+        let size = std::fs::metadata(path).map_err(io_error)?.len();
+        if size > max_bytes {
+            return Err(invalid(format!("file is {} bytes, more than the limit of {}", size, max_bytes)));
+        }
+        let bytes = std::fs::read(path).map_err(io_error)?;
+        if let Some(block) = binary_block(path, &bytes) {
+            return Ok(block);
+        }
+
+        // NUL bytes are valid UTF-8 but don't occur in text files
+        let looks_binary = bytes.iter().take(8000).any(|b| *b == 0);
+        match String::from_utf8(bytes) {
+            Ok(text) if !looks_binary => Ok(Self::text_document(text, title_of(path))),
+            _ => Err(invalid("binary file; only text, images and PDFs can be attached".to_string())),
+        }
+    }
+}
+
+/// An image or PDF block for `path`, chosen by its extension
+fn binary_block(path: &Path, bytes: &[u8]) -> Option<ContentBlock> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    let media_type = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => return Some(UserContent::pdf_bytes(bytes, title_of(path))),
+        _ => return None,
+    };
+    Some(UserContent::image_bytes(media_type, bytes))
+}
+
+fn title_of(path: &Path) -> Option<String> {
+    path.file_name().map(|name| name.to_string_lossy().to_string())
+}
+
+fn encode(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_image_block_json() {
+        let block = UserContent::image_bytes("image/png", b"png");
+        assert_eq!(
+            serde_json::to_value(&block).unwrap(),
+            json!({
+                "type": "image",
+                "source": {"type": "base64", "media_type": "image/png", "data": "cG5n"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_file_picks_block_type() {
+        let dir = std::env::temp_dir().join(format!("claude-rs-content-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("shot.PNG"), b"png").unwrap();
+        std::fs::write(dir.join("spec.pdf"), b"%PDF").unwrap();
+        std::fs::write(dir.join("notes.md"), "# Notes").unwrap();
+
+        assert!(matches!(
+            UserContent::file(dir.join("shot.PNG")).unwrap(),
+            ContentBlock::Image { source: ContentSource::Base64 { ref media_type, .. } } if media_type == "image/png"
+        ));
+        assert_eq!(
+            serde_json::to_value(UserContent::file(dir.join("spec.pdf")).unwrap()).unwrap(),
+            json!({
+                "type": "document",
+                "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBERg=="},
+                "title": "spec.pdf"
+            })
+        );
+        assert!(matches!(
+            UserContent::file(dir.join("notes.md")).unwrap(),
+            ContentBlock::Document { source: ContentSource::Text { ref data, .. }, .. } if data == "# Notes"
+        ));
+        assert!(UserContent::file(dir.join("missing.txt")).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_attachment_limits() {
+        let dir = std::env::temp_dir().join(format!("claude-rs-attachment-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.join("app.bin"), b"\x7fELF\0\0").unwrap();
+        std::fs::write(dir.join("logo.png"), b"\x89PNG\0").unwrap();
+
+        assert!(matches!(
+            UserContent::attachment(dir.join("main.rs"), 100).unwrap(),
+            ContentBlock::Document { title: Some(ref title), .. } if title == "main.rs"
+        ));
+        assert!(matches!(UserContent::attachment(dir.join("logo.png"), 100).unwrap(), ContentBlock::Image { .. }));
+
+        let too_big = UserContent::attachment(dir.join("main.rs"), 4).unwrap_err();
+        assert!(too_big.to_string().contains("more than the limit of 4"), "{}", too_big);
+        let binary = UserContent::attachment(dir.join("app.bin"), 100).unwrap_err();
+        assert!(matches!(binary, ClaudeSDKError::InvalidAttachment { ref message, .. } if message.starts_with("binary file")));
+        assert!(UserContent::attachment(dir.join("missing.txt"), 100).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
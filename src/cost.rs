@@ -0,0 +1,272 @@
+//! Adding up cost and token usage across queries
+//!
+//! A [`CostTracker`] collects the cost and tokens reported in result
+//! messages, in total and broken down by model and by session. The CLI
+//! reports running totals for its session, so each result counts for what it
+//! adds to the previous result of the same session. Attach the
+//! same tracker to as many queries and clients as needed with
+//! [`ClaudeOptionsBuilder::cost_tracker`](crate::ClaudeOptionsBuilder::cost_tracker)
+//! or [`QuickQuery::track_cost`](crate::QuickQuery::track_cost), and read it
+//! from any thread with [`CostTracker::snapshot`].
+//!
+//! # Example
+//! ```no_run
+//! use claude::{CostTracker, QuickQuery};
+//!
+//! #[tokio::main]
+//! async fn main() -> claude::Result<()> {
+//!     let tracker = CostTracker::new();
+//!     for question in ["What is 2 + 2?", "Name a prime number"] {
+//!         QuickQuery::new(question).track_cost(&tracker).ask().await?;
+//!     }
+//!
+//!     let snapshot = tracker.snapshot();
+//!     println!("${:.4} over {} queries", snapshot.total.cost_usd, snapshot.total.queries);
+//!     for (model, totals) in &snapshot.by_model {
+//!         println!("{}: {} tokens", model, totals.usage.total_tokens());
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::budget::RunningTotals;
+use crate::tokens::Usage;
+use crate::types::{Message, SessionId, SystemMessageKind};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Model name used when a result can't be attributed to a model
+pub const UNKNOWN_MODEL: &str = "unknown";
+
+/// Cost and tokens of a set of queries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct CostTotals {
+    pub cost_usd: f64,
+    pub usage: Usage,
+    /// Number of result messages counted
+    pub queries: u64,
+}
+
+impl CostTotals {
+    fn add(&mut self, cost_usd: f64, usage: Usage) {
+        self.cost_usd += cost_usd;
+        self.usage += usage;
+        self.queries += 1;
+    }
+}
+
+/// The figures collected by a [`CostTracker`] at one point in time
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CostSnapshot {
+    pub total: CostTotals,
+    /// Totals per model
+    ///
+    /// Uses the per-model figures of result messages when the CLI reports
+    /// them, and otherwise the model announced when the session started, or
+    /// [`UNKNOWN_MODEL`].
+    pub by_model: HashMap<String, CostTotals>,
+    pub by_session: HashMap<SessionId, CostTotals>,
+}
+
+#[derive(Debug, Default)]
+struct TrackerState {
+    snapshot: CostSnapshot,
+    // Model of each session, from its init message
+    session_models: HashMap<SessionId, String>,
+    // Last totals reported per session, and per session and model
+    results: RunningTotals<SessionId>,
+    model_results: RunningTotals<(SessionId, String)>,
+}
+
+/// Thread-safe running totals of cost and token usage
+///
+/// Clones share the same totals.
+#[derive(Debug, Clone, Default)]
+pub struct CostTracker {
+    state: Arc<Mutex<TrackerState>>,
+}
+
+impl CostTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Account for a message; only init and result messages count
+    pub fn record(&self, message: &Message) {
+        let mut state = self.state.lock().unwrap();
+        match message {
+            Message::System(system) => {
+                if let SystemMessageKind::Init(init) = system.kind() {
+                    if !init.model.is_empty() {
+                        state.session_models.insert(init.session_id, init.model);
+                    }
+                }
+            }
+            Message::Result(result) => {
+                let (cost, usage) = state.results.delta(
+                    result.session_id.clone(),
+                    result.total_cost_usd.unwrap_or(0.0),
+                    result.token_usage().unwrap_or_default(),
+                );
+                state.snapshot.total.add(cost, usage);
+                state.snapshot.by_session.entry(result.session_id.clone()).or_default().add(cost, usage);
+
+                let per_model = result.model_usage.as_ref().map(model_totals).unwrap_or_default();
+                if per_model.is_empty() {
+                    let model = state
+                        .session_models
+                        .get(&result.session_id)
+                        .cloned()
+                        .unwrap_or_else(|| UNKNOWN_MODEL.to_string());
+                    state.snapshot.by_model.entry(model).or_default().add(cost, usage);
+                }
+                for (model, (cost, usage)) in per_model {
+                    let (cost, usage) = state.model_results.delta((result.session_id.clone(), model.clone()), cost, usage);
+                    state.snapshot.by_model.entry(model).or_default().add(cost, usage);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Account for every message in `messages`
+    pub fn record_all<'a>(&self, messages: impl IntoIterator<Item = &'a Message>) {
+        for message in messages {
+            self.record(message);
+        }
+    }
+
+    /// A copy of the totals so far
+    pub fn snapshot(&self) -> CostSnapshot {
+        self.state.lock().unwrap().snapshot.clone()
+    }
+
+    /// Total cost so far in USD
+    pub fn total_cost_usd(&self) -> f64 {
+        self.state.lock().unwrap().snapshot.total.cost_usd
+    }
+
+    /// Start over from zero
+    pub fn reset(&self) {
+        *self.state.lock().unwrap() = TrackerState::default();
+    }
+}
+
+/// Cost and tokens per model from a result's `modelUsage`, whose entries
+/// use camelCase keys such as `inputTokens` and `costUSD`
+fn model_totals(model_usage: &HashMap<String, Value>) -> Vec<(String, (f64, Usage))> {
+    model_usage
+        .iter()
+        .map(|(model, entry)| {
+            let count = |key: &str| entry.get(key).and_then(Value::as_u64).unwrap_or(0);
+            let usage = Usage {
+                input_tokens: count("inputTokens"),
+                output_tokens: count("outputTokens"),
+                cache_creation_input_tokens: count("cacheCreationInputTokens"),
+                cache_read_input_tokens: count("cacheReadInputTokens"),
+            };
+            let cost = entry.get("costUSD").and_then(Value::as_f64).unwrap_or(0.0);
+            (model.clone(), (cost, usage))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message_parser::parse_message;
+    use crate::testing::result_message;
+    use serde_json::json;
+
+    fn result(session_id: &str, cost: f64, extra: Value) -> Message {
+        let mut value = result_message(session_id);
+        value["total_cost_usd"] = json!(cost);
+        value["usage"] = json!({"input_tokens": 100, "output_tokens": 10});
+        value.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        parse_message(&value).unwrap()
+    }
+
+    #[test]
+    fn test_breakdowns() {
+        let tracker = CostTracker::new();
+        let init = parse_message(&json!({
+            "type": "system",
+            "subtype": "init",
+            "session_id": "s1",
+            "model": "claude-sonnet-4-5"
+        }))
+        .unwrap();
+        let second = result("s1", 0.75, json!({"usage": {"input_tokens": 200, "output_tokens": 20}}));
+        tracker.record_all(&[init, result("s1", 0.5, json!({})), second]);
+        tracker.clone().record(&result("s2", 1.0, json!({})));
+
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.total.queries, 3);
+        assert_eq!(snapshot.total.cost_usd, 1.75);
+        assert_eq!(snapshot.total.usage.input_tokens, 300);
+        assert_eq!(snapshot.by_session[&SessionId::from("s1")].cost_usd, 0.75);
+        assert_eq!(snapshot.by_session[&SessionId::from("s2")].queries, 1);
+        assert_eq!(snapshot.by_model["claude-sonnet-4-5"].queries, 2);
+        assert_eq!(snapshot.by_model[UNKNOWN_MODEL].cost_usd, 1.0);
+
+        tracker.reset();
+        assert_eq!(tracker.snapshot(), CostSnapshot::default());
+    }
+
+    #[test]
+    fn test_model_usage_splits_by_model() {
+        let tracker = CostTracker::new();
+        tracker.record(&result(
+            "s1",
+            0.3,
+            json!({"modelUsage": {
+                "claude-sonnet-4-5": {"inputTokens": 80, "outputTokens": 8, "costUSD": 0.25},
+                "claude-haiku-4-5": {"inputTokens": 20, "outputTokens": 2, "cacheReadInputTokens": 5, "costUSD": 0.05}
+            }}),
+        ));
+
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.by_model.len(), 2);
+        assert_eq!(snapshot.by_model["claude-sonnet-4-5"].cost_usd, 0.25);
+        assert_eq!(snapshot.by_model["claude-haiku-4-5"].usage.cache_read_input_tokens, 5);
+        assert_eq!(snapshot.total.cost_usd, 0.3);
+        assert_eq!(tracker.total_cost_usd(), 0.3);
+    }
+
+    #[test]
+    fn test_multi_turn_results_count_once() {
+        let tracker = CostTracker::new();
+        let turn = |cost: f64, input_tokens: u64, sonnet_cost: f64, haiku_cost: f64| {
+            result(
+                "s1",
+                cost,
+                json!({
+                    "usage": {"input_tokens": input_tokens, "output_tokens": 10},
+                    "modelUsage": {
+                        "claude-sonnet-4-5": {"inputTokens": input_tokens, "costUSD": sonnet_cost},
+                        "claude-haiku-4-5": {"outputTokens": 10, "costUSD": haiku_cost}
+                    }
+                }),
+            )
+        };
+        // Three turns of one session, each with the totals so far
+        tracker.record_all(&[turn(0.25, 100, 0.2, 0.05), turn(0.5, 300, 0.4, 0.1), turn(1.0, 700, 0.8, 0.2)]);
+
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.total.queries, 3);
+        assert_eq!(snapshot.total.cost_usd, 1.0);
+        assert_eq!(snapshot.total.usage.input_tokens, 700);
+        assert_eq!(snapshot.total.usage.output_tokens, 10);
+        assert_eq!(snapshot.by_session[&SessionId::from("s1")].cost_usd, 1.0);
+        assert_eq!(snapshot.by_model["claude-sonnet-4-5"].cost_usd, 0.8);
+        assert_eq!(snapshot.by_model["claude-sonnet-4-5"].usage.input_tokens, 700);
+        assert_eq!(snapshot.by_model["claude-haiku-4-5"].cost_usd, 0.2);
+
+        // The session resumed in a new process starts over from zero
+        tracker.record(&turn(0.25, 100, 0.2, 0.05));
+        assert_eq!(tracker.total_cost_usd(), 1.25);
+        assert_eq!(tracker.snapshot().by_model["claude-sonnet-4-5"].cost_usd, 1.0);
+    }
+}
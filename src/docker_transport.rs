@@ -0,0 +1,346 @@
+//! Transport that runs the Claude Code CLI inside a Docker container
+//!
+//! [`DockerTransport`] starts the CLI with `docker run -i --rm <image> claude ...`
+//! so tool execution (Bash, file edits, ...) is confined to the container.
+//! The working directory and every `add_dirs` entry are bind-mounted at the
+//! same path inside the container, so paths in prompts and options stay
+//! valid. Environment variables from [`ClaudeAgentOptions::env`] are set in
+//! the container; host variables such as `ANTHROPIC_API_KEY` can be passed
+//! through by name.
+//!
+//! The image must contain the `claude` executable. Process handling is
+//! shared with [`SubprocessTransport`].
+//!
+//! Requires the `docker-transport` cargo feature.
+//!
+//! # Example
+//! ```no_run
+//! use claude::docker_transport::DockerTransport;
+//! use claude::{ClaudeAgentOptions, ClaudeSDKClient};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let options = ClaudeAgentOptions {
+//!         cwd: Some("/home/me/project".into()),
+//!         ..Default::default()
+//!     };
+//!
+//!     let transport = DockerTransport::new("my-org/claude-code:latest", String::new(), options)
+//!         .with_env_passthrough("ANTHROPIC_API_KEY")
+//!         .with_mount("/home/me/.cache/cargo", "/root/.cargo", true);
+//!
+//!     let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+//!     client.connect().await?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::Result;
+use crate::transport::{CommandSpec, Direction, Interceptor, SubprocessTransport, Transport, TransportStream};
+use crate::types::ClaudeAgentOptions;
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// A bind mount from the host into the container
+#[derive(Debug, Clone, PartialEq)]
+pub struct DockerMount {
+    pub host_path: PathBuf,
+    pub container_path: PathBuf,
+    pub read_only: bool,
+}
+
+/// Container settings used to wrap the CLI command
+#[derive(Debug, Clone)]
+struct DockerConfig {
+    image: String,
+    mounts: Vec<DockerMount>,
+    env_passthrough: Vec<String>,
+    docker_args: Vec<String>,
+    container_cli_path: String,
+    docker_program: String,
+}
+
+/// Transport that runs the CLI in a Docker container
+pub struct DockerTransport {
+    config: DockerConfig,
+    inner: Option<SubprocessTransport>,
+    interceptor: Option<Interceptor>,
+    prompt: String,
+    options: ClaudeAgentOptions,
+}
+
+impl DockerTransport {
+    /// Create a transport that runs the CLI in `image`
+    ///
+    /// `prompt` and `options` have the same meaning as for
+    /// [`SubprocessTransport::new`]. `options.cwd` and `options.add_dirs`
+    /// are mounted read-write at their host paths.
+    pub fn new(image: impl Into<String>, prompt: String, options: ClaudeAgentOptions) -> Self {
+        let mounts = options
+            .cwd
+            .iter()
+            .chain(options.add_dirs.iter())
+            .map(|dir| DockerMount {
+                host_path: dir.clone(),
+                container_path: dir.clone(),
+                read_only: false,
+            })
+            .collect();
+
+        Self {
+            config: DockerConfig {
+                image: image.into(),
+                mounts,
+                env_passthrough: Vec::new(),
+                docker_args: Vec::new(),
+                container_cli_path: "claude".to_string(),
+                docker_program: "docker".to_string(),
+            },
+            inner: None,
+            interceptor: None,
+            prompt,
+            options,
+        }
+    }
+
+    /// Mount an additional host directory into the container
+    pub fn with_mount(
+        mut self,
+        host_path: impl Into<PathBuf>,
+        container_path: impl Into<PathBuf>,
+        read_only: bool,
+    ) -> Self {
+        self.config.mounts.push(DockerMount {
+            host_path: host_path.into(),
+            container_path: container_path.into(),
+            read_only,
+        });
+        self
+    }
+
+    /// Forward a host environment variable into the container by name
+    pub fn with_env_passthrough(mut self, name: impl Into<String>) -> Self {
+        self.config.env_passthrough.push(name.into());
+        self
+    }
+
+    /// Pass an extra argument to `docker run`, e.g. `--network=none`
+    pub fn with_docker_arg(mut self, arg: impl Into<String>) -> Self {
+        self.config.docker_args.push(arg.into());
+        self
+    }
+
+    /// Path of the `claude` executable inside the image (default: `claude`)
+    pub fn with_container_cli_path(mut self, path: impl Into<String>) -> Self {
+        self.config.container_cli_path = path.into();
+        self
+    }
+
+    /// Use a different container CLI, e.g. `podman`
+    pub fn with_docker_program(mut self, program: impl Into<String>) -> Self {
+        self.config.docker_program = program.into();
+        self
+    }
+
+    /// Mounts that will be passed to `docker run`
+    pub fn mounts(&self) -> &[DockerMount] {
+        &self.config.mounts
+    }
+
+    /// Observe every raw line exchanged with the CLI
+    ///
+    /// See [`SubprocessTransport::with_interceptor`].
+    pub fn with_interceptor(mut self, interceptor: impl Fn(Direction, &str) + Send + Sync + 'static) -> Self {
+        self.interceptor = Some(Arc::new(interceptor));
+        self
+    }
+
+    fn inner(&mut self) -> &mut SubprocessTransport {
+        self.inner.get_or_insert_with(|| {
+            let config = self.config.clone();
+            SubprocessTransport::new(std::mem::take(&mut self.prompt), self.options.clone())
+                .with_wrapper(Arc::new(move |spec| config.wrap(spec)))
+                .with_shared_interceptor(self.interceptor.clone())
+        })
+    }
+}
+
+impl DockerConfig {
+    /// Turn a local CLI invocation into a `docker run` invocation
+    fn wrap(&self, spec: CommandSpec) -> CommandSpec {
+        let mut args = vec!["run".to_string(), "-i".to_string(), "--rm".to_string()];
+
+        for mount in &self.mounts {
+            args.push("-v".to_string());
+            let mut volume = format!(
+                "{}:{}",
+                mount.host_path.to_string_lossy(),
+                mount.container_path.to_string_lossy()
+            );
+            if mount.read_only {
+                volume.push_str(":ro");
+            }
+            args.push(volume);
+        }
+
+        for (key, value) in &spec.env {
+            args.push("-e".to_string());
+            args.push(format!("{}={}", key, value));
+        }
+
+        for name in &self.env_passthrough {
+            args.push("-e".to_string());
+            args.push(name.clone());
+        }
+
+        if let Some(ref cwd) = spec.cwd {
+            args.push("-w".to_string());
+            args.push(cwd.to_string_lossy().to_string());
+        }
+
+        args.extend(self.docker_args.iter().cloned());
+        args.push(self.image.clone());
+        args.push(self.container_cli_path.clone());
+        args.extend(spec.args);
+
+        // docker itself runs on the host, so it keeps the host environment
+        // (needed for pass-through variables) and working directory
+        CommandSpec {
+            program: self.docker_program.clone(),
+            args,
+            env: Vec::new(),
+            cwd: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for DockerTransport {
+    async fn connect(&mut self) -> Result<()> {
+        self.inner().connect().await
+    }
+
+    async fn write(&mut self, data: &str) -> Result<()> {
+        self.inner().write(data).await
+    }
+
+    async fn end_input(&mut self) -> Result<()> {
+        self.inner().end_input().await
+    }
+
+    fn read_messages(&mut self) -> TransportStream {
+        self.inner().read_messages()
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.close().await,
+            None => Ok(()),
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.inner.as_ref().is_some_and(|inner| inner.is_ready())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mounts_cwd_and_add_dirs() {
+        let options = ClaudeAgentOptions {
+            cwd: Some(PathBuf::from("/work/app")),
+            add_dirs: vec![PathBuf::from("/work/lib")],
+            ..Default::default()
+        };
+
+        let transport = DockerTransport::new("claude:latest", String::new(), options)
+            .with_mount("/home/me/.gitconfig", "/root/.gitconfig", true);
+
+        let paths: Vec<_> = transport.mounts().iter().map(|m| (m.host_path.clone(), m.read_only)).collect();
+        assert_eq!(
+            paths,
+            [
+                (PathBuf::from("/work/app"), false),
+                (PathBuf::from("/work/lib"), false),
+                (PathBuf::from("/home/me/.gitconfig"), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wrap_builds_docker_run() {
+        let options = ClaudeAgentOptions {
+            cwd: Some(PathBuf::from("/work/app")),
+            ..Default::default()
+        };
+        let transport = DockerTransport::new("claude:latest", String::new(), options)
+            .with_mount("/cache", "/root/.cache", true)
+            .with_env_passthrough("ANTHROPIC_API_KEY")
+            .with_docker_arg("--network=none");
+
+        let wrapped = transport.config.wrap(CommandSpec {
+            program: "/usr/local/bin/claude".to_string(),
+            args: vec!["--output-format".to_string(), "stream-json".to_string()],
+            env: vec![("CLAUDE_CODE_ENTRYPOINT".to_string(), "sdk-rust".to_string())],
+            cwd: Some(PathBuf::from("/work/app")),
+        });
+
+        assert_eq!(wrapped.program, "docker");
+        assert_eq!(
+            wrapped.args,
+            [
+                "run",
+                "-i",
+                "--rm",
+                "-v",
+                "/work/app:/work/app",
+                "-v",
+                "/cache:/root/.cache:ro",
+                "-e",
+                "CLAUDE_CODE_ENTRYPOINT=sdk-rust",
+                "-e",
+                "ANTHROPIC_API_KEY",
+                "-w",
+                "/work/app",
+                "--network=none",
+                "claude:latest",
+                "claude",
+                "--output-format",
+                "stream-json",
+            ]
+        );
+        assert!(wrapped.env.is_empty());
+        assert!(wrapped.cwd.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_runs_through_docker_program() {
+        use futures::StreamExt;
+        use std::os::unix::fs::PermissionsExt;
+
+        // `docker` stand-in that reports the image and command it was asked to run
+        let path = std::env::temp_dir().join(format!("claude-rs-fake-docker-{}", std::process::id()));
+        std::fs::write(
+            &path,
+            "#!/bin/sh\nwhile [ \"$1\" != \"claude:test\" ]; do shift; done\n\
+             printf '{\"image\":\"%s\",\"cli\":\"%s\"}\\n' \"$1\" \"$2\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut transport = DockerTransport::new("claude:test", "hi".to_string(), ClaudeAgentOptions::default())
+            .with_docker_program(path.to_string_lossy());
+        transport.connect().await.unwrap();
+
+        let mut stream = transport.read_messages();
+        let message = stream.next().await.unwrap().unwrap();
+        assert_eq!(message["image"], "claude:test");
+        assert_eq!(message["cli"], "claude");
+        transport.close().await.unwrap();
+    }
+}
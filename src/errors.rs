@@ -1,71 +1,300 @@
-use std::fmt;
+use crate::version::CliVersion;
 
 /// Base error type for all Claude SDK errors
-#[derive(Debug)]
+///
+/// Errors caused by an I/O or JSON failure keep it as their
+/// [`source`](std::error::Error::source), for inspection and error reports
+/// that walk the chain.
+#[derive(Debug, thiserror::Error)]
 pub enum ClaudeSDKError {
     /// Raised when unable to connect to Claude Code
+    #[error("CLI Connection Error: {0}")]
     CLIConnectionError(String),
+    /// Raised when reading from or writing to the CLI fails
+    #[error("CLI Connection Error: {message}")]
+    Io {
+        /// What failed; the I/O error itself is the `source`
+        message: String,
+        #[source]
+        source: std::io::Error,
+    },
     /// Raised when Claude Code is not found or not installed
+    #[error("{message}{}", .cli_path.as_ref().map(|path| format!(": {}", path)).unwrap_or_default())]
     CLINotFoundError { message: String, cli_path: Option<String> },
+    /// Raised when the CLI process cannot be started
+    #[error("Failed to spawn Claude Code{}", detail_text(": ", .message, .source))]
+    SpawnError {
+        /// Why, also the text of `source` when there is one
+        message: String,
+        #[source]
+        source: Option<std::io::Error>,
+    },
+    /// Raised at connect when the CLI is older than `required_version`
+    #[error("Claude Code {found} is too old, version {required} or newer is required")]
+    CliVersionIncompatible { found: CliVersion, required: CliVersion },
     /// Raised when the CLI process fails
+    #[error("{}", process_error_text(.message, .exit_code, .stderr))]
     ProcessError {
         message: String,
         exit_code: Option<i32>,
         stderr: Option<String>,
     },
     /// Raised when unable to decode JSON from CLI output
+    #[error("Failed to decode JSON: {}{}", truncate_line(.line), decode_error_text(.original_error, .source))]
     CLIJSONDecodeError {
         line: String,
+        /// Text of the JSON error, also when it is kept as `source`
         original_error: String,
+        #[source]
+        source: Option<serde_json::Error>,
     },
     /// Raised when unable to parse a message from CLI output
+    #[error("Message Parse Error: {message}")]
     MessageParseError {
         message: String,
         data: Option<serde_json::Value>,
+        #[source]
+        source: Option<serde_json::Error>,
+    },
+    /// Raised when a single line of CLI output exceeds the maximum buffer size
+    #[error("CLI output line exceeded maximum buffer size of {limit} bytes")]
+    BufferOverflow { limit: usize },
+    /// Raised when a query is cancelled through a `CancelHandle`
+    #[error("Query was cancelled")]
+    Interrupted,
+    /// Raised when an operation does not finish within its deadline
+    #[error("{operation} timed out after {duration:?}")]
+    Timeout {
+        /// What timed out, e.g. `Query` or `Control request 'interrupt'`
+        operation: String,
+        duration: std::time::Duration,
+    },
+    /// Raised when the CLI answers a control request with an error
+    #[error("Control request {request_id} failed: {message}")]
+    ControlProtocol { request_id: String, message: String },
+    /// Raised when the API rejected a request with 429 (rate limited) or
+    /// 529 (overloaded)
+    #[error("Rate limited by the API: {message}{}", .retry_after.map(|after| format!(" (retry after {:?})", after)).unwrap_or_default())]
+    RateLimited {
+        message: String,
+        /// How long the API asked to wait, if it said so
+        retry_after: Option<std::time::Duration>,
+    },
+    /// Raised when the spending of a query or session crosses
+    /// `max_cost_usd` or `max_total_tokens`
+    #[error("Budget exceeded after spending ${cost_usd:.4} and {total_tokens} tokens")]
+    BudgetExceeded { cost_usd: f64, total_tokens: u64 },
+    /// Raised when a permission policy file cannot be read or is invalid
+    #[error("Invalid permission policy {path}{}: {message}", location_text(.line, .column))]
+    InvalidPolicy {
+        path: String,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
     },
+    /// Raised when an MCP server config file cannot be read or is invalid
+    #[error("Invalid MCP config {path}{}: {message}", .server.as_ref().map(|server| format!(" (server '{}')", server)).unwrap_or_default())]
+    InvalidMcpConfig {
+        path: String,
+        /// Name of the malformed server entry, if the error is in one
+        server: Option<String>,
+        message: String,
+    },
+    /// Raised when an `SdkMcpServer` builder is given invalid tools
+    #[error("Invalid MCP server '{name}': {message}")]
+    InvalidMcpServer { name: String, message: String },
+    /// Raised when a file cannot be attached to a prompt
+    #[error("Cannot attach {path}{}", detail_text(": ", .message, .source))]
+    InvalidAttachment {
+        path: String,
+        /// Why, also the text of `source` when there is one
+        message: String,
+        #[source]
+        source: Option<std::io::Error>,
+    },
+    /// Raised when a prompt template has a syntax error
+    #[error("Invalid prompt template at byte {position}: {message}")]
+    InvalidTemplate {
+        /// Byte offset of the error in the template
+        position: usize,
+        message: String,
+    },
+    /// Raised when a prompt template is rendered without one of its variables
+    #[error("Prompt template variable '{name}' has no value")]
+    MissingTemplateVariable { name: String },
+}
+
+fn process_error_text(message: &str, exit_code: &Option<i32>, stderr: &Option<String>) -> String {
+    let mut text = message.to_string();
+    if let Some(code) = exit_code {
+        text = format!("{} (exit code: {})", text, code);
+    }
+    if let Some(err) = stderr {
+        text = format!("{}\nError output: {}", text, err);
+    }
+    text
+}
+
+fn truncate_line(line: &str) -> String {
+    if line.len() > 100 {
+        format!("{}...", &line[..100])
+    } else {
+        line.to_string()
+    }
+}
+
+/// `separator` and `message`, unless `message` is the text of `source`,
+/// which error reports print on their own
+fn detail_text<E>(separator: &str, message: &str, source: &Option<E>) -> String {
+    match source {
+        Some(_) => String::new(),
+        None => format!("{}{}", separator, message),
+    }
+}
+
+fn decode_error_text(original_error: &str, source: &Option<serde_json::Error>) -> String {
+    match source {
+        Some(_) => String::new(),
+        None => format!(" (error: {})", original_error),
+    }
+}
+
+/// `:line:column`, or `:line`, or nothing
+fn location_text(line: &Option<usize>, column: &Option<usize>) -> String {
+    match (line, column) {
+        (Some(line), Some(column)) => format!(":{}:{}", line, column),
+        (Some(line), None) => format!(":{}", line),
+        (None, _) => String::new(),
+    }
+}
+
+/// Category of a [`ClaudeSDKError`], for branching without matching on
+/// variants or messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The CLI is not installed or not at the configured path
+    NotFound,
+    /// The CLI process could not be started
+    Spawn,
+    /// The CLI is older than the required version
+    IncompatibleVersion,
+    /// The CLI failed or exited with an error
+    Process,
+    /// The connection to the CLI is missing or was lost
+    Connection,
+    /// The CLI produced output that is not valid protocol data, or
+    /// rejected a control request
+    Protocol,
+    /// A message did not have the expected shape
+    ParseError,
+    Interrupted,
+    Timeout,
+    /// The API is rate limiting or overloaded
+    RateLimited,
+    BudgetExceeded,
+    /// A policy, MCP config or MCP server definition is invalid
+    InvalidConfig,
+    /// A prompt template or attachment is invalid
+    InvalidInput,
 }
 
-impl fmt::Display for ClaudeSDKError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl ErrorKind {
+    /// Name of the kind in snake case, as used for the `kind` label
+    pub fn as_str(&self) -> &'static str {
         match self {
-            ClaudeSDKError::CLIConnectionError(msg) => write!(f, "CLI Connection Error: {}", msg),
-            ClaudeSDKError::CLINotFoundError { message, cli_path } => {
-                if let Some(path) = cli_path {
-                    write!(f, "{}: {}", message, path)
-                } else {
-                    write!(f, "{}", message)
-                }
-            }
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Spawn => "spawn",
+            ErrorKind::IncompatibleVersion => "incompatible_version",
+            ErrorKind::Process => "process",
+            ErrorKind::Connection => "connection",
+            ErrorKind::Protocol => "protocol",
+            ErrorKind::ParseError => "parse_error",
+            ErrorKind::Interrupted => "interrupted",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::RateLimited => "rate_limited",
+            ErrorKind::BudgetExceeded => "budget_exceeded",
+            ErrorKind::InvalidConfig => "invalid_config",
+            ErrorKind::InvalidInput => "invalid_input",
+        }
+    }
+}
+
+impl ClaudeSDKError {
+    /// The category of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ClaudeSDKError::CLINotFoundError { .. } => ErrorKind::NotFound,
+            ClaudeSDKError::SpawnError { .. } => ErrorKind::Spawn,
+            ClaudeSDKError::CliVersionIncompatible { .. } => ErrorKind::IncompatibleVersion,
+            ClaudeSDKError::ProcessError { .. } => ErrorKind::Process,
+            ClaudeSDKError::CLIConnectionError(_) | ClaudeSDKError::Io { .. } => ErrorKind::Connection,
+            ClaudeSDKError::CLIJSONDecodeError { .. }
+            | ClaudeSDKError::BufferOverflow { .. }
+            | ClaudeSDKError::ControlProtocol { .. } => ErrorKind::Protocol,
+            ClaudeSDKError::MessageParseError { .. } => ErrorKind::ParseError,
+            ClaudeSDKError::Interrupted => ErrorKind::Interrupted,
+            ClaudeSDKError::Timeout { .. } => ErrorKind::Timeout,
+            ClaudeSDKError::RateLimited { .. } => ErrorKind::RateLimited,
+            ClaudeSDKError::BudgetExceeded { .. } => ErrorKind::BudgetExceeded,
+            ClaudeSDKError::InvalidPolicy { .. }
+            | ClaudeSDKError::InvalidMcpConfig { .. }
+            | ClaudeSDKError::InvalidMcpServer { .. } => ErrorKind::InvalidConfig,
+            ClaudeSDKError::InvalidAttachment { .. }
+            | ClaudeSDKError::InvalidTemplate { .. }
+            | ClaudeSDKError::MissingTemplateVariable { .. } => ErrorKind::InvalidInput,
+        }
+    }
+
+    /// Whether another attempt is likely to succeed
+    ///
+    /// True for lost connections, timeouts, rate limits, CLI processes that
+    /// were killed, and CLI failures reporting an overloaded API or a network
+    /// problem.
+    pub fn is_retryable(&self) -> bool {
+        const TRANSIENT_MARKERS: &[&str] =
+            &["overloaded", "rate limit", "rate_limit", "429", "529", "503", "econnreset", "etimedout", "network"];
+
+        match self {
+            ClaudeSDKError::CLIConnectionError(_)
+            | ClaudeSDKError::Io { .. }
+            | ClaudeSDKError::Timeout { .. }
+            | ClaudeSDKError::RateLimited { .. } => true,
             ClaudeSDKError::ProcessError {
                 message,
                 exit_code,
                 stderr,
             } => {
-                let mut msg = message.clone();
-                if let Some(code) = exit_code {
-                    msg = format!("{} (exit code: {})", msg, code);
-                }
-                if let Some(err) = stderr {
-                    msg = format!("{}\nError output: {}", msg, err);
-                }
-                write!(f, "{}", msg)
-            }
-            ClaudeSDKError::CLIJSONDecodeError { line, original_error } => {
-                let truncated = if line.len() > 100 {
-                    format!("{}...", &line[..100])
-                } else {
-                    line.clone()
-                };
-                write!(f, "Failed to decode JSON: {} (error: {})", truncated, original_error)
-            }
-            ClaudeSDKError::MessageParseError { message, .. } => {
-                write!(f, "Message Parse Error: {}", message)
+                let output = format!("{} {}", message, stderr.as_deref().unwrap_or_default()).to_lowercase();
+                exit_code.is_none() || TRANSIENT_MARKERS.iter().any(|marker| output.contains(marker))
             }
+            _ => false,
+        }
+    }
+
+    /// How long to wait before retrying, if the API said so
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            ClaudeSDKError::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
         }
     }
-}
 
-impl std::error::Error for ClaudeSDKError {}
+    /// Whether the error needs a change in setup or input before anything
+    /// can succeed: a missing, unstartable or outdated CLI, invalid
+    /// configuration or input, or a spent budget
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self.kind(),
+            ErrorKind::NotFound
+                | ErrorKind::Spawn
+                | ErrorKind::IncompatibleVersion
+                | ErrorKind::InvalidConfig
+                | ErrorKind::InvalidInput
+                | ErrorKind::BudgetExceeded
+        )
+    }
+}
 
 // Convenience constructors
 impl ClaudeSDKError {
@@ -73,6 +302,14 @@ impl ClaudeSDKError {
         ClaudeSDKError::CLIConnectionError(message.into())
     }
 
+    /// An [`Io`](Self::Io) error; `message` says what failed
+    pub fn io_error(message: impl Into<String>, source: std::io::Error) -> Self {
+        ClaudeSDKError::Io {
+            message: message.into(),
+            source,
+        }
+    }
+
     pub fn cli_not_found(cli_path: Option<String>) -> Self {
         ClaudeSDKError::CLINotFoundError {
             message: "Claude Code not found".to_string(),
@@ -80,6 +317,25 @@ impl ClaudeSDKError {
         }
     }
 
+    pub fn spawn_error(message: impl Into<String>) -> Self {
+        ClaudeSDKError::SpawnError {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// A [`SpawnError`](Self::SpawnError) caused by `source`
+    pub fn spawn_error_from(source: std::io::Error) -> Self {
+        ClaudeSDKError::SpawnError {
+            message: source.to_string(),
+            source: Some(source),
+        }
+    }
+
+    pub fn cli_version_incompatible(found: CliVersion, required: CliVersion) -> Self {
+        ClaudeSDKError::CliVersionIncompatible { found, required }
+    }
+
     pub fn process_error(
         message: impl Into<String>,
         exit_code: Option<i32>,
@@ -96,6 +352,16 @@ impl ClaudeSDKError {
         ClaudeSDKError::CLIJSONDecodeError {
             line: line.into(),
             original_error: original_error.into(),
+            source: None,
+        }
+    }
+
+    /// A [`CLIJSONDecodeError`](Self::CLIJSONDecodeError) for `line` caused by `source`
+    pub fn json_decode_error_from(line: impl Into<String>, source: serde_json::Error) -> Self {
+        ClaudeSDKError::CLIJSONDecodeError {
+            line: line.into(),
+            original_error: source.to_string(),
+            source: Some(source),
         }
     }
 
@@ -106,8 +372,153 @@ impl ClaudeSDKError {
         ClaudeSDKError::MessageParseError {
             message: message.into(),
             data,
+            source: None,
         }
     }
+
+    /// A [`MessageParseError`](Self::MessageParseError) caused by `source`
+    ///
+    /// `message` is shown as is, so it should include the text of `source`
+    /// where that helps.
+    pub fn message_parse_error_from(
+        message: impl Into<String>,
+        data: Option<serde_json::Value>,
+        source: serde_json::Error,
+    ) -> Self {
+        ClaudeSDKError::MessageParseError {
+            message: message.into(),
+            data,
+            source: Some(source),
+        }
+    }
+
+    pub fn buffer_overflow(limit: usize) -> Self {
+        ClaudeSDKError::BufferOverflow { limit }
+    }
+
+    pub fn interrupted() -> Self {
+        ClaudeSDKError::Interrupted
+    }
+
+    pub fn timeout(operation: impl Into<String>, duration: std::time::Duration) -> Self {
+        ClaudeSDKError::Timeout {
+            operation: operation.into(),
+            duration,
+        }
+    }
+
+    pub fn control_protocol(request_id: impl Into<String>, message: impl Into<String>) -> Self {
+        ClaudeSDKError::ControlProtocol {
+            request_id: request_id.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn rate_limited(message: impl Into<String>, retry_after: Option<std::time::Duration>) -> Self {
+        ClaudeSDKError::RateLimited {
+            message: message.into(),
+            retry_after,
+        }
+    }
+
+    /// A [`RateLimited`](Self::RateLimited) error if `output` reports that
+    /// the API is rate limiting or overloaded
+    ///
+    /// Recognizes the errors the CLI prints for 429 and 529 responses, such
+    /// as `API Error: 429 {"type":"error","error":{"type":"rate_limit_error",...}}`,
+    /// and picks up a `retry-after` value in seconds if there is one.
+    pub fn rate_limited_from_output(output: &str) -> Option<Self> {
+        const MARKERS: &[&str] = &[
+            "rate_limit_error",
+            "overloaded_error",
+            "rate limit",
+            "too many requests",
+            "overloaded",
+            "api error: 429",
+            "api error: 529",
+            "(429)",
+            "(529)",
+        ];
+
+        let line = output.lines().find(|line| {
+            let line = line.to_lowercase();
+            MARKERS.iter().any(|marker| line.contains(marker))
+        })?;
+        Some(ClaudeSDKError::rate_limited(line.trim(), parse_retry_after(output)))
+    }
+
+    pub fn budget_exceeded(cost_usd: f64, total_tokens: u64) -> Self {
+        ClaudeSDKError::BudgetExceeded { cost_usd, total_tokens }
+    }
+
+    pub fn invalid_policy(
+        path: impl Into<String>,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: impl Into<String>,
+    ) -> Self {
+        ClaudeSDKError::InvalidPolicy {
+            path: path.into(),
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_mcp_config(path: impl Into<String>, server: Option<String>, message: impl Into<String>) -> Self {
+        ClaudeSDKError::InvalidMcpConfig {
+            path: path.into(),
+            server,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_mcp_server(name: impl Into<String>, message: impl Into<String>) -> Self {
+        ClaudeSDKError::InvalidMcpServer {
+            name: name.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_attachment(path: impl Into<String>, message: impl Into<String>) -> Self {
+        ClaudeSDKError::InvalidAttachment {
+            path: path.into(),
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// An [`InvalidAttachment`](Self::InvalidAttachment) error caused by `source`
+    pub fn invalid_attachment_from(path: impl Into<String>, source: std::io::Error) -> Self {
+        ClaudeSDKError::InvalidAttachment {
+            path: path.into(),
+            message: source.to_string(),
+            source: Some(source),
+        }
+    }
+
+    pub fn invalid_template(position: usize, message: impl Into<String>) -> Self {
+        ClaudeSDKError::InvalidTemplate {
+            position,
+            message: message.into(),
+        }
+    }
+
+    pub fn missing_template_variable(name: impl Into<String>) -> Self {
+        ClaudeSDKError::MissingTemplateVariable { name: name.into() }
+    }
+}
+
+/// The number of seconds after `retry-after`, `retry_after` or `retry after`
+fn parse_retry_after(output: &str) -> Option<std::time::Duration> {
+    let output = output.to_lowercase();
+    ["retry-after", "retry_after", "retry after"].iter().find_map(|key| {
+        let start = output.find(key)? + key.len();
+        let value = output[start..].trim_start_matches(|c: char| c.is_whitespace() || matches!(c, ':' | '=' | '"'));
+        let end = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+        let seconds: f64 = value[..end].parse().ok()?;
+        std::time::Duration::try_from_secs_f64(seconds).ok()
+    })
 }
 
 pub type Result<T> = std::result::Result<T, ClaudeSDKError>;
@@ -1,5 +1,7 @@
 /// Extension traits for better ergonomics
+use crate::markdown::{self, CodeBlock};
 use crate::types::{AssistantMessage, ContentBlock, Message, ResultMessage};
+use serde_json::Value;
 
 /// Extension methods for Vec<Message>
 ///
@@ -22,6 +24,12 @@ pub trait MessageVecExt {
 
     /// Get all text blocks from assistant messages
     fn text_blocks(&self) -> Vec<&str>;
+
+    /// Get the fenced code blocks in the assistant text, see [`markdown::code_blocks`]
+    fn code_blocks(&self) -> Vec<CodeBlock>;
+
+    /// Get the first JSON value in the assistant text, see [`markdown::extract_first_json`]
+    fn extract_first_json(&self) -> Option<Value>;
 }
 
 impl MessageVecExt for Vec<Message> {
@@ -63,6 +71,14 @@ impl MessageVecExt for Vec<Message> {
             })
             .collect()
     }
+
+    fn code_blocks(&self) -> Vec<CodeBlock> {
+        markdown::code_blocks(&self.text_content())
+    }
+
+    fn extract_first_json(&self) -> Option<Value> {
+        markdown::extract_first_json(&self.text_content())
+    }
 }
 
 /// Extension methods for Message
@@ -111,6 +127,21 @@ impl Message {
                 .join("\n")
         })
     }
+
+    /// Extract the text of a `text_delta` stream event (partial messages only)
+    pub fn text_delta(&self) -> Option<&str> {
+        let Message::Stream(stream) = self else {
+            return None;
+        };
+        if stream.event.get("type")?.as_str()? != "content_block_delta" {
+            return None;
+        }
+        let delta = stream.event.get("delta")?;
+        if delta.get("type")?.as_str()? != "text_delta" {
+            return None;
+        }
+        delta.get("text")?.as_str()
+    }
 }
 
 #[cfg(test)]
@@ -130,6 +161,8 @@ mod tests {
                 ],
                 model: "test-model".to_string(),
                 parent_tool_use_id: None,
+                uuid: None,
+                timestamp: None,
             }),
             Message::Assistant(AssistantMessage {
                 content: vec![ContentBlock::Text {
@@ -137,6 +170,8 @@ mod tests {
                 }],
                 model: "test-model".to_string(),
                 parent_tool_use_id: None,
+                uuid: None,
+                timestamp: None,
             }),
         ]
     }
@@ -190,6 +225,8 @@ mod tests {
             }],
             model: "test-model".to_string(),
             parent_tool_use_id: None,
+            uuid: None,
+            timestamp: None,
         });
 
         assert!(msg.is_assistant());
@@ -199,10 +236,55 @@ mod tests {
         assert_eq!(msg.text_content(), Some("Test".to_string()));
     }
 
+    #[test]
+    fn test_text_delta() {
+        let stream_event = |event: serde_json::Value| {
+            Message::Stream(crate::types::StreamEvent {
+                uuid: "uuid".to_string(),
+                session_id: "session".into(),
+                event: serde_json::from_value(event).unwrap(),
+                parent_tool_use_id: None,
+            })
+        };
+
+        let delta = stream_event(serde_json::json!({
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": {"type": "text_delta", "text": "Hel"}
+        }));
+        assert_eq!(delta.text_delta(), Some("Hel"));
+
+        let tool_input = stream_event(serde_json::json!({
+            "type": "content_block_delta",
+            "index": 1,
+            "delta": {"type": "input_json_delta", "partial_json": "{"}
+        }));
+        assert_eq!(tool_input.text_delta(), None);
+        assert_eq!(stream_event(serde_json::json!({"type": "message_stop"})).text_delta(), None);
+    }
+
     #[test]
     fn test_text_blocks() {
         let messages = create_test_messages();
         let blocks = messages.text_blocks();
         assert_eq!(blocks, vec!["Hello", "World", "Goodbye"]);
     }
+
+    #[test]
+    fn test_code_blocks_and_json() {
+        let messages = vec![Message::Assistant(AssistantMessage {
+            content: vec![ContentBlock::Text {
+                text: "Config:\n```json\n{\"debug\": true}\n```".to_string(),
+            }],
+            model: "test-model".to_string(),
+            parent_tool_use_id: None,
+            uuid: None,
+            timestamp: None,
+        })];
+        let blocks = messages.code_blocks();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].language.as_deref(), Some("json"));
+        assert_eq!(messages.extract_first_json(), Some(serde_json::json!({"debug": true})));
+        assert!(create_test_messages().code_blocks().is_empty());
+    }
 }
@@ -1,10 +1,27 @@
 /// High-level facade functions for common operations
 use crate::builders::ClaudeOptionsBuilder;
+use crate::cancel::CancelHandle;
+use crate::client::TextStream;
+use crate::cost::CostTracker;
+#[cfg(feature = "schemars")]
+use crate::client::ClaudeSDKClient;
 use crate::extensions::MessageVecExt;
-use crate::simple_query::simple_query;
+use crate::hooks::HookCallback;
+use crate::mcp_server::SdkMcpServer;
+use crate::permissions::CanUseToolCallback;
+use crate::retry::RetryPolicy;
+use crate::simple_query::{simple_query, simple_query_with, simple_query_with_transport, stream_with, QueryExtras};
+use crate::content::{UserContent, DEFAULT_MAX_ATTACHMENT_BYTES};
 use crate::streaming_query::{streaming_query, StreamingQuery};
-use crate::types::{ClaudeAgentOptions, Message};
-use crate::Result;
+use crate::template::PromptTemplate;
+use crate::transport::Transport;
+use crate::tokens::Usage;
+use crate::types::{ClaudeAgentOptions, ContentBlock, HookEvent, Message, PermissionMode, SessionId, ToolUseView};
+use crate::{ClaudeSDKError, Result};
+use futures::stream::{Stream, StreamExt};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Ask Claude a simple question and get the text response
 ///
@@ -27,6 +44,67 @@ pub async fn ask(prompt: impl AsRef<str>) -> Result<String> {
     Ok(messages.text_content())
 }
 
+/// Ask Claude a question and get the answer with its metadata
+///
+/// Like [`ask`], but keeps the messages, session ID, cost and usage.
+///
+/// # Example
+/// ```no_run
+/// use claude::ask_detailed;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let result = ask_detailed("What is 2 + 2?").await?;
+///     println!("{} (cost: {:?}, session: {:?})", result.text, result.total_cost_usd, result.session_id);
+///     Ok(())
+/// }
+/// ```
+pub async fn ask_detailed(prompt: impl AsRef<str>) -> Result<QueryResult> {
+    let messages = simple_query(prompt.as_ref(), None).await?;
+    Ok(QueryResult::from(messages))
+}
+
+/// Answer of a one-shot query together with its metadata
+///
+/// Returned by [`ask_detailed`] and [`QuickQuery::run`]. The metadata comes
+/// from the result message and is `None` if the CLI didn't report it.
+#[derive(Debug, Clone)]
+pub struct QueryResult {
+    /// Text of all assistant messages
+    pub text: String,
+    pub messages: Vec<Message>,
+    pub session_id: Option<SessionId>,
+    pub usage: Option<Usage>,
+    pub total_cost_usd: Option<f64>,
+    /// Duration reported by the CLI
+    pub duration: Option<Duration>,
+}
+
+impl QueryResult {
+    /// Tools Claude called during the query, in order
+    pub fn tool_uses(&self) -> Vec<ToolUseView> {
+        self.messages
+            .assistant_messages()
+            .into_iter()
+            .flat_map(|message| message.content.iter().filter_map(ToolUseView::parse))
+            .collect()
+    }
+}
+
+impl From<Vec<Message>> for QueryResult {
+    fn from(messages: Vec<Message>) -> Self {
+        let result = messages.result_message();
+        QueryResult {
+            text: messages.text_content(),
+            session_id: result.map(|r| r.session_id.clone()),
+            usage: result.and_then(|r| r.token_usage()),
+            total_cost_usd: result.and_then(|r| r.total_cost_usd),
+            duration: result.map(|r| Duration::from_millis(r.duration_ms.max(0) as u64)),
+            messages,
+        }
+    }
+}
+
 /// Ask Claude with custom options and get text response
 ///
 /// # Example
@@ -49,6 +127,117 @@ pub async fn ask_with_options(
     Ok(messages.text_content())
 }
 
+/// Ask Claude about files and get text response
+///
+/// Text files are sent as documents, images and PDFs in their own block
+/// types; see [`QuickQuery::attach_file`] for the limits.
+///
+/// # Example
+/// ```no_run
+/// use claude::ask_with_files;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let review = ask_with_files("Review this file for bugs", ["src/main.rs"]).await?;
+///     println!("{}", review);
+///     Ok(())
+/// }
+/// ```
+pub async fn ask_with_files<I, P>(prompt: impl Into<String>, paths: I) -> Result<String>
+where
+    I: IntoIterator<Item = P>,
+    P: Into<PathBuf>,
+{
+    paths
+        .into_iter()
+        .fold(QuickQuery::new(prompt), QuickQuery::attach_file)
+        .ask()
+        .await
+}
+
+/// Ask Claude and receive the answer as text chunks while it is generated
+///
+/// Partial messages are enabled, so chunks arrive as the model writes them.
+/// Tool activity and other messages are left out; errors, including failing
+/// to start the CLI, are yielded as items.
+///
+/// # Example
+/// ```no_run
+/// use claude::ask_stream;
+/// use futures::StreamExt;
+/// use std::io::Write;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let mut chunks = Box::pin(ask_stream("Write a limerick about Rust"));
+///     while let Some(chunk) = chunks.next().await {
+///         print!("{}", chunk?);
+///         std::io::stdout().flush()?;
+///     }
+///     Ok(())
+/// }
+/// ```
+pub fn ask_stream(prompt: impl Into<String>) -> impl Stream<Item = Result<String>> + Send {
+    QuickQuery::new(prompt).stream_text()
+}
+
+/// Ask Claude through a custom transport and get the text response
+///
+/// The transport carries the prompt and options, e.g. an `ApiTransport`
+/// (with the `api-transport` feature) to skip the CLI entirely.
+///
+/// # Example
+/// ```no_run
+/// use claude::ask_with_transport;
+/// use claude::transport::SubprocessTransport;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let transport = SubprocessTransport::new("What is 2 + 2?".to_string(), Default::default());
+///     let answer = ask_with_transport(Box::new(transport)).await?;
+///     println!("{}", answer);
+///     Ok(())
+/// }
+/// ```
+pub async fn ask_with_transport(transport: Box<dyn Transport>) -> Result<String> {
+    let messages = simple_query_with_transport(transport).await?;
+    Ok(messages.text_content())
+}
+
+/// Ask Claude for a JSON answer and deserialize it into `T`
+///
+/// The prompt is extended with the JSON schema of `T`. The JSON is taken
+/// from the reply, also when wrapped in a Markdown code fence. If it doesn't
+/// deserialize, Claude is told the error and asked once more. Requires the
+/// `schemars` feature.
+///
+/// # Example
+/// ```no_run
+/// use claude::ask_json;
+/// use schemars::JsonSchema;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, JsonSchema)]
+/// struct Capital {
+///     city: String,
+///     population: u64,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let capital: Capital = ask_json("What is the capital of France?").await?;
+///     println!("{} ({} people)", capital.city, capital.population);
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "schemars")]
+pub async fn ask_json<T>(prompt: impl Into<String>) -> Result<T>
+where
+    T: serde::de::DeserializeOwned + schemars::JsonSchema,
+{
+    QuickQuery::new(prompt).ask_as().await
+}
+
 /// Fluent query builder for quick interactions
 ///
 /// Provides a chainable API for common query patterns.
@@ -71,6 +260,11 @@ pub async fn ask_with_options(
 pub struct QuickQuery {
     prompt: String,
     options: ClaudeOptionsBuilder,
+    cancel: Option<CancelHandle>,
+    extras: QueryExtras,
+    retry: Option<RetryPolicy>,
+    attachments: Vec<PathBuf>,
+    max_attachment_bytes: u64,
 }
 
 impl QuickQuery {
@@ -79,9 +273,42 @@ impl QuickQuery {
         Self {
             prompt: prompt.into(),
             options: ClaudeOptionsBuilder::new(),
+            cancel: None,
+            extras: QueryExtras::new(),
+            retry: None,
+            attachments: Vec::new(),
+            max_attachment_bytes: DEFAULT_MAX_ATTACHMENT_BYTES,
         }
     }
 
+    /// Create a quick query whose prompt is `template` filled with `vars`
+    ///
+    /// Fails if the template is malformed or a variable has no value, see
+    /// [`PromptTemplate::render`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::{PromptTemplate, QuickQuery};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let template = PromptTemplate::new("Translate '{{word}}' to {{language}}");
+    ///     let answer = QuickQuery::from_template(&template, [("word", "cat"), ("language", "French")])?
+    ///         .ask()
+    ///         .await?;
+    ///     println!("{}", answer);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_template<I, K, V>(template: &PromptTemplate, vars: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: std::fmt::Display,
+    {
+        Ok(Self::new(template.render(vars)?))
+    }
+
     /// Set system prompt
     pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
         self.options = self.options.system_prompt(prompt);
@@ -110,19 +337,439 @@ impl QuickQuery {
         self
     }
 
+    /// Run `callback` for `event` on tools matching `pattern` (a tool name or `*`)
+    ///
+    /// Queries with hooks run the CLI in streaming mode.
+    pub fn with_hook(mut self, event: HookEvent, pattern: impl Into<String>, callback: HookCallback) -> Self {
+        self.extras = self.extras.hook(event, pattern, callback);
+        self
+    }
+
+    /// Decide tool permissions with `callback`
+    ///
+    /// Queries with a permission callback run the CLI in streaming mode.
+    pub fn with_permission_callback(mut self, callback: CanUseToolCallback) -> Self {
+        self.extras = self.extras.can_use_tool(callback);
+        self
+    }
+
+    /// Serve an in-process MCP server to Claude under `name`
+    ///
+    /// Its tools are called `mcp__<name>__<tool>`; allow them with
+    /// [`allow_tools`](Self::allow_tools). Queries with SDK MCP servers run
+    /// the CLI in streaming mode.
+    pub fn with_mcp_server(mut self, name: impl Into<String>, mut server: SdkMcpServer) -> Self {
+        server.name = name.into();
+        self.extras = self.extras.mcp_server(server);
+        self
+    }
+
+    /// Stop the query when `cancel` fires
+    ///
+    /// `ask` and `query` then return `ClaudeSDKError::Interrupted` and the
+    /// CLI process is stopped.
+    pub fn cancel_on(mut self, cancel: &CancelHandle) -> Self {
+        self.cancel = Some(cancel.clone());
+        self
+    }
+
+    /// Add the cost and tokens of the query to `tracker`
+    pub fn track_cost(mut self, tracker: &CostTracker) -> Self {
+        self.options = self.options.cost_tracker(tracker.clone());
+        self
+    }
+
+    /// Retry failed attempts of `ask`, `query` and `run` according to `retry`
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Send the file at `path` along with the prompt
+    ///
+    /// The file is read when the query runs, see
+    /// [`UserContent::attachment`](crate::UserContent::attachment); a file
+    /// that is missing, too large or binary fails the query.
+    pub fn attach_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.attachments.push(path.into());
+        self
+    }
+
+    /// Refuse attached files larger than `bytes` (default 10 MiB)
+    pub fn max_attachment_bytes(mut self, bytes: u64) -> Self {
+        self.max_attachment_bytes = bytes;
+        self
+    }
+
+    /// Read the attached files into the extras
+    fn load_attachments(&mut self) -> Result<()> {
+        for path in std::mem::take(&mut self.attachments) {
+            let block = UserContent::attachment(&path, self.max_attachment_bytes)?;
+            self.extras.attachments.push(block);
+        }
+        Ok(())
+    }
+
     /// Execute query and get text response
     pub async fn ask(self) -> Result<String> {
-        ask_with_options(self.prompt, self.options.build()).await
+        Ok(self.query().await?.text_content())
+    }
+
+    /// Execute query and get the answer with its metadata, see [`QueryResult`]
+    pub async fn run(self) -> Result<QueryResult> {
+        Ok(QueryResult::from(self.query().await?))
+    }
+
+    /// Let Claude plan without making changes, see [`Plan`]
+    ///
+    /// Runs the query in [`PermissionMode::Plan`]. The plan is the one Claude
+    /// submits with the `ExitPlanMode` tool, or the final text if it doesn't.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::QuickQuery;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let plan = QuickQuery::new("Add input validation to src/form.rs").plan().await?;
+    ///     println!("{}", plan.text);
+    ///
+    ///     let result = plan.execute().await?;
+    ///     println!("{}", result.text);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn plan(mut self) -> Result<Plan> {
+        self.options = self.options.permission_mode(PermissionMode::Plan);
+        let options = self.options.clone().build();
+        let extras = self.extras.clone();
+        let messages = self.query().await?;
+        Ok(Plan::new(messages, options, extras))
     }
 
     /// Execute query and get full message list
-    pub async fn query(self) -> Result<Vec<Message>> {
-        simple_query(&self.prompt, Some(self.options.build())).await
+    pub async fn query(mut self) -> Result<Vec<Message>> {
+        self.load_attachments()?;
+        let options = self.options.build();
+        let (prompt, extras) = (self.prompt, self.extras);
+        let query = async move {
+            let Some(retry) = self.retry else {
+                return simple_query_with(&prompt, Some(options), extras).await;
+            };
+            retry
+                .run(|| simple_query_with(&prompt, Some(options.clone()), extras.clone()))
+                .await
+        };
+        with_cancel(self.cancel, query).await
+    }
+
+    /// Execute query and deserialize the JSON answer into `T`, see [`ask_json`]
+    ///
+    /// Requires the `schemars` feature.
+    #[cfg(feature = "schemars")]
+    pub async fn ask_as<T>(mut self) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned + schemars::JsonSchema,
+    {
+        self.load_attachments()?;
+        // Both attempts run in one conversation, so the retry can refer to the first reply
+        let builder = ClaudeSDKClient::builder().options(self.options.build());
+        let mut extras = self.extras;
+        let attachments = std::mem::take(&mut extras.attachments);
+        let prompt = self.prompt;
+        let query = async move { ask_structured(extras.connect(builder).await?, &prompt, attachments).await };
+        with_cancel(self.cancel, query).await
     }
 
     /// Execute query and get streaming response
-    pub async fn stream(self) -> Result<StreamingQuery> {
-        streaming_query(&self.prompt, Some(self.options.build())).await
+    pub async fn stream(mut self) -> Result<StreamingQuery> {
+        self.load_attachments()?;
+        if self.extras.is_empty() {
+            return streaming_query(&self.prompt, Some(self.options.build())).await;
+        }
+        stream_with(&self.prompt, self.options.build(), self.extras).await
+    }
+
+    /// Execute query and stream the text of the answer, see [`ask_stream`]
+    pub fn stream_text(mut self) -> impl Stream<Item = Result<String>> + Send {
+        self.options = self.options.include_partial_messages(true);
+        async_stream::stream! {
+            match self.stream().await {
+                Ok(query) => {
+                    let mut text = TextStream::new(query);
+                    while let Some(chunk) = text.next().await {
+                        yield chunk;
+                    }
+                }
+                Err(e) => yield Err(e),
+            }
+        }
+    }
+}
+
+/// A plan made by [`QuickQuery::plan`], ready to be carried out
+///
+/// [`execute`](Self::execute) resumes the planning session in
+/// [`PermissionMode::AcceptEdits`], with the same options, hooks and
+/// callbacks, and asks Claude to implement the plan.
+pub struct Plan {
+    /// The plan, as Claude wrote it
+    pub text: String,
+    /// Session the plan was made in
+    pub session_id: Option<SessionId>,
+    /// Messages of the planning query
+    pub messages: Vec<Message>,
+    options: ClaudeAgentOptions,
+    extras: QueryExtras,
+}
+
+impl Plan {
+    fn new(messages: Vec<Message>, options: ClaudeAgentOptions, extras: QueryExtras) -> Self {
+        let submitted = messages
+            .assistant_messages()
+            .into_iter()
+            .flat_map(|message| &message.content)
+            .filter_map(|block| match block {
+                ContentBlock::ToolUse { name, input, .. } if name == "ExitPlanMode" => input.get("plan")?.as_str(),
+                _ => None,
+            })
+            .next_back()
+            .map(str::to_string);
+
+        Plan {
+            text: submitted.unwrap_or_else(|| messages.text_content()),
+            session_id: messages.result_message().map(|result| result.session_id.clone()),
+            messages,
+            options,
+            extras,
+        }
+    }
+
+    /// Carry out the plan
+    pub async fn execute(self) -> Result<QueryResult> {
+        self.execute_with("Go ahead and implement the plan.").await
+    }
+
+    /// Carry out the plan, sending `prompt` to start, e.g. with changes to it
+    pub async fn execute_with(self, prompt: impl AsRef<str>) -> Result<QueryResult> {
+        let (prompt, options) = self.execution(prompt.as_ref());
+        let messages = simple_query_with(&prompt, Some(options), self.extras).await?;
+        Ok(QueryResult::from(messages))
+    }
+
+    /// Prompt and options of the query that carries out the plan
+    fn execution(&self, prompt: &str) -> (String, ClaudeAgentOptions) {
+        let mut options = self.options.clone();
+        options.permission_mode = Some(PermissionMode::AcceptEdits);
+        match &self.session_id {
+            Some(session_id) => {
+                options.resume = Some(session_id.clone());
+                options.continue_conversation = false;
+                options.fork_session = false;
+                (prompt.to_string(), options)
+            }
+            // Without a session to resume, the plan has to be part of the prompt
+            None => (format!("{}\n\nThe plan:\n{}", prompt, self.text), options),
+        }
+    }
+}
+
+impl std::fmt::Debug for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Plan")
+            .field("text", &self.text)
+            .field("session_id", &self.session_id)
+            .field("messages", &self.messages)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Multi-turn conversation made of one-shot queries
+///
+/// Each [`say`](Self::say) runs the CLI once and resumes the session of the
+/// previous turn, so Claude keeps the context without a long-lived
+/// [`ClaudeSDKClient`](crate::ClaudeSDKClient).
+///
+/// # Example
+/// ```no_run
+/// use claude::Conversation;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let mut conversation = Conversation::new();
+///     conversation.say("My name is Ada").await?;
+///     let answer = conversation.say("What is my name?").await?;
+///     println!("{} (${:.4} so far)", answer, conversation.cost_so_far());
+///     Ok(())
+/// }
+/// ```
+#[derive(Default)]
+pub struct Conversation {
+    options: ClaudeAgentOptions,
+    session_id: Option<SessionId>,
+    messages: Vec<Message>,
+    cost_usd: f64,
+    transport_factory: Option<OneShotTransportFactory>,
+}
+
+/// Creates the transport of one turn from its prompt and options
+type OneShotTransportFactory = Arc<dyn Fn(&str, ClaudeAgentOptions) -> Box<dyn Transport> + Send + Sync>;
+
+impl Conversation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a conversation whose turns all use `options`
+    ///
+    /// `options.resume` is managed by the conversation and overwritten.
+    pub fn with_options(options: ClaudeAgentOptions) -> Self {
+        Conversation {
+            options,
+            ..Default::default()
+        }
+    }
+
+    /// Continue an earlier session
+    pub fn resume(session_id: impl Into<SessionId>) -> Self {
+        Conversation {
+            session_id: Some(session_id.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Create transports with a factory instead of spawning the CLI directly
+    ///
+    /// The factory receives the prompt and options of each turn.
+    pub fn transport_factory(
+        mut self,
+        factory: impl Fn(&str, ClaudeAgentOptions) -> Box<dyn Transport> + Send + Sync + 'static,
+    ) -> Self {
+        self.transport_factory = Some(Arc::new(factory));
+        self
+    }
+
+    /// Send the next message and get the text of the reply
+    pub async fn say(&mut self, prompt: impl AsRef<str>) -> Result<String> {
+        let mut options = self.options.clone();
+        options.resume = self.session_id.clone();
+        let messages = match &self.transport_factory {
+            Some(factory) => simple_query_with_transport(factory(prompt.as_ref(), options)).await?,
+            None => simple_query(prompt.as_ref(), Some(options)).await?,
+        };
+
+        if let Some(result) = messages.result_message() {
+            self.session_id = Some(result.session_id.clone());
+            self.cost_usd += result.total_cost_usd.unwrap_or(0.0);
+        }
+        let text = messages.text_content();
+        self.messages.extend(messages);
+        Ok(text)
+    }
+
+    /// All messages received so far, across turns
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// Total cost of the turns so far in USD, as reported by the CLI
+    pub fn cost_so_far(&self) -> f64 {
+        self.cost_usd
+    }
+
+    /// Session the next turn resumes, `None` before the first turn
+    pub fn session_id(&self) -> Option<&SessionId> {
+        self.session_id.as_ref()
+    }
+}
+
+/// Run `query`, failing with `ClaudeSDKError::Interrupted` when `cancel` fires
+async fn with_cancel<T>(cancel: Option<CancelHandle>, query: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+    let Some(cancel) = cancel else {
+        return query.await;
+    };
+
+    // Dropping the query future drops the transport, which kills the CLI
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => Err(ClaudeSDKError::interrupted()),
+        result = query => result,
+    }
+}
+
+/// Ask for JSON matching the schema of `T`, retrying once, and disconnect
+#[cfg(feature = "schemars")]
+async fn ask_structured<T>(mut client: ClaudeSDKClient, prompt: &str, attachments: Vec<ContentBlock>) -> Result<T>
+where
+    T: serde::de::DeserializeOwned + schemars::JsonSchema,
+{
+    let result = ask_structured_on(&mut client, prompt, attachments).await;
+    let closed = client.close().await;
+    let value = result?;
+    closed?;
+    Ok(value)
+}
+
+#[cfg(feature = "schemars")]
+async fn ask_structured_on<T>(client: &mut ClaudeSDKClient, prompt: &str, attachments: Vec<ContentBlock>) -> Result<T>
+where
+    T: serde::de::DeserializeOwned + schemars::JsonSchema,
+{
+    let schema = crate::mcp_server::input_schema_for::<T>();
+    let prompt = format!(
+        "{}\n\nRespond with only a JSON value matching this JSON Schema, without any other text:\n{}",
+        prompt, schema
+    );
+    let reply = if attachments.is_empty() {
+        client.query_and_collect(&prompt).await?
+    } else {
+        let mut blocks = vec![ContentBlock::Text { text: prompt }];
+        blocks.extend(attachments);
+        client.query_blocks_and_collect(blocks).await?
+    };
+    let reply = reply.text_content();
+    let error = match parse_json_reply(&reply) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+
+    let retry = format!(
+        "Your response could not be parsed: {}. Reply with only the corrected JSON value.",
+        error
+    );
+    let reply = client.query_and_collect(&retry).await?.text_content();
+    parse_json_reply(&reply).map_err(|e| {
+        ClaudeSDKError::message_parse_error(
+            format!("Response does not match the requested type: {}", e),
+            Some(serde_json::Value::String(reply.clone())),
+        )
+    })
+}
+
+/// Deserialize the JSON in a reply, which may sit inside a code fence or
+/// be surrounded by prose
+#[cfg(feature = "schemars")]
+fn parse_json_reply<T: serde::de::DeserializeOwned>(reply: &str) -> serde_json::Result<T> {
+    serde_json::from_str(extract_json(reply))
+}
+
+#[cfg(feature = "schemars")]
+fn extract_json(reply: &str) -> &str {
+    let reply = reply.trim();
+    if let Some(start) = reply.find("```") {
+        let fenced = &reply[start + 3..];
+        // Skip the language tag, e.g. ```json
+        let body = fenced.find('\n').map_or(fenced, |newline| &fenced[newline + 1..]);
+        if let Some(end) = body.find("```") {
+            return body[..end].trim();
+        }
+    }
+
+    let start = reply.find(['{', '[']);
+    let end = reply.rfind(['}', ']']);
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => reply,
     }
 }
 
@@ -141,9 +788,247 @@ mod tests {
         assert_eq!(query.prompt, "test prompt");
     }
 
+    #[test]
+    fn test_quick_query_with_hook() {
+        let query = QuickQuery::new("test");
+        assert!(query.extras.is_empty());
+
+        let query = query.with_hook(
+            HookEvent::PreToolUse,
+            "Bash",
+            crate::hook!(|_input| { Ok(crate::HookJSONOutput::default()) }),
+        );
+        let hooks = query.extras.hooks.as_ref().unwrap();
+        assert_eq!(hooks.find_matching_callbacks(HookEvent::PreToolUse, "Bash").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_conversation_resumes_previous_turn() {
+        use crate::testing::{result_message, MockTransport};
+        use std::sync::Mutex;
+
+        // Prompt and `resume` option of every turn
+        type Turns = Vec<(String, Option<SessionId>)>;
+        let turns: Arc<Mutex<Turns>> = Arc::default();
+        let seen = Arc::clone(&turns);
+        let mut conversation = Conversation::new().transport_factory(move |prompt, options| {
+            seen.lock().unwrap().push((prompt.to_string(), options.resume));
+            let transport = MockTransport::new();
+            let handle = transport.handle();
+            handle.push(serde_json::json!({
+                "type": "assistant",
+                "message": {"model": "m", "content": [{"type": "text", "text": format!("re: {}", prompt)}]}
+            }));
+            let mut result = result_message("session-1");
+            result["total_cost_usd"] = serde_json::json!(0.5);
+            handle.push(result);
+            handle.finish();
+            Box::new(transport)
+        });
+
+        assert_eq!(conversation.say("Hi").await.unwrap(), "re: Hi");
+        assert_eq!(conversation.say("Again").await.unwrap(), "re: Again");
+
+        let turns = turns.lock().unwrap();
+        assert_eq!(turns[0], ("Hi".to_string(), None));
+        assert_eq!(turns[1], ("Again".to_string(), Some(SessionId::from("session-1"))));
+        assert_eq!(conversation.messages().len(), 4);
+        assert_eq!(conversation.cost_so_far(), 1.0);
+        assert_eq!(conversation.session_id().unwrap(), "session-1");
+    }
+
+    #[test]
+    fn test_query_result_from_messages() {
+        let mut result = crate::testing::result_message("session-1");
+        result["duration_ms"] = serde_json::json!(1500);
+        result["total_cost_usd"] = serde_json::json!(0.01);
+        result["usage"] = serde_json::json!({"input_tokens": 10, "output_tokens": 20});
+        let messages: Vec<Message> = [
+            serde_json::json!({
+                "type": "assistant",
+                "message": {"model": "m", "content": [
+                    {"type": "text", "text": "Checking"},
+                    {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "ls"}}
+                ]}
+            }),
+            result,
+        ]
+        .iter()
+        .map(|value| crate::parse_message(value).unwrap())
+        .collect();
+
+        let result = QueryResult::from(messages);
+        assert_eq!(result.text, "Checking");
+        assert_eq!(result.session_id.as_deref(), Some("session-1"));
+        assert_eq!(result.total_cost_usd, Some(0.01));
+        assert_eq!(result.usage.unwrap().output_tokens, 20);
+        assert_eq!(result.duration, Some(Duration::from_millis(1500)));
+        let tools = result.tool_uses();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].tool_name(), "Bash");
+
+        let empty = QueryResult::from(Vec::new());
+        assert!(empty.session_id.is_none() && empty.duration.is_none());
+    }
+
+    #[test]
+    fn test_quick_query_with_permission_callback_and_mcp_server() {
+        let server = SdkMcpServer::new("tools".to_string(), "1.0.0".to_string(), vec![]);
+        let query = QuickQuery::new("test")
+            .with_permission_callback(crate::permissions::sandbox("/tmp"))
+            .with_mcp_server("calculator", server);
+
+        assert!(!query.extras.is_empty());
+        assert!(query.extras.can_use_tool.is_some());
+        assert_eq!(query.extras.mcp_servers[0].name, "calculator");
+    }
+
+    #[tokio::test]
+    async fn test_text_stream_over_streaming_query() {
+        let (tx, rx) = tokio::sync::mpsc::channel(10);
+        let delta = |text: &str| {
+            serde_json::json!({
+                "type": "stream_event",
+                "uuid": "e",
+                "session_id": "s",
+                "event": {"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": text}}
+            })
+        };
+        for value in [
+            delta("Hel"),
+            delta("lo"),
+            serde_json::json!({"type": "assistant", "message": {"model": "m", "content": [{"type": "text", "text": "Hello"}]}}),
+        ] {
+            tx.send(crate::parse_message(&value)).await.unwrap();
+        }
+        drop(tx);
+
+        let chunks: Vec<String> = TextStream::new(StreamingQuery::from_receiver(rx))
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks, ["Hel", "lo"]);
+    }
+
     #[test]
     fn test_quick_query_new() {
         let query = QuickQuery::new("test");
         assert_eq!(query.prompt, "test");
     }
+
+    #[test]
+    fn test_quick_query_from_template() {
+        let template = PromptTemplate::new("Summarize {{lang}} code:\n{{code}}");
+        let query = QuickQuery::from_template(&template, [("lang", "Rust"), ("code", "fn main() {}")]).unwrap();
+        assert_eq!(query.prompt, "Summarize Rust code:\nfn main() {}");
+        assert!(QuickQuery::from_template(&template, [("lang", "Rust")]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_quick_query_cancelled() {
+        let cancel = CancelHandle::new();
+        cancel.cancel();
+
+        let result = QuickQuery::new("test").cancel_on(&cancel).ask().await;
+        assert!(matches!(result, Err(ClaudeSDKError::Interrupted)));
+    }
+
+    #[test]
+    fn test_plan_from_exit_plan_mode() {
+        let messages: Vec<Message> = [
+            serde_json::json!({
+                "type": "assistant",
+                "message": {"model": "m", "content": [
+                    {"type": "text", "text": "Here is my plan"},
+                    {"type": "tool_use", "id": "t1", "name": "ExitPlanMode", "input": {"plan": "1. Validate\n2. Test"}}
+                ]}
+            }),
+            crate::testing::result_message("plan-session"),
+        ]
+        .iter()
+        .map(|value| crate::message_parser::parse_message(value).unwrap())
+        .collect();
+        let options = ClaudeOptionsBuilder::new().model("opus").permission_mode(PermissionMode::Plan).build();
+
+        let plan = Plan::new(messages.clone(), options.clone(), QueryExtras::new());
+        assert_eq!(plan.text, "1. Validate\n2. Test");
+        let (prompt, execution) = plan.execution("Do it");
+        assert_eq!(prompt, "Do it");
+        assert_eq!(execution.permission_mode, Some(PermissionMode::AcceptEdits));
+        assert_eq!(execution.resume.as_deref(), Some("plan-session"));
+        assert_eq!(execution.model.as_deref(), Some("opus"));
+
+        // Without the tool call or a session, the text is the plan and goes into the prompt
+        let mut text_only = vec![messages[0].clone()];
+        if let Message::Assistant(assistant) = &mut text_only[0] {
+            assistant.content.truncate(1);
+        }
+        let plan = Plan::new(text_only, options, QueryExtras::new());
+        assert_eq!(plan.text, "Here is my plan");
+        let (prompt, execution) = plan.execution("Do it");
+        assert_eq!(prompt, "Do it\n\nThe plan:\nHere is my plan");
+        assert_eq!(execution.resume, None);
+    }
+
+    #[tokio::test]
+    async fn test_quick_query_attachment_errors_before_running() {
+        let missing = std::env::temp_dir().join("claude-rs-no-such-attachment.rs");
+        let result = ask_with_files("Review this", [&missing]).await;
+        assert!(matches!(result, Err(ClaudeSDKError::InvalidAttachment { path, .. }) if path == missing.display().to_string()));
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn test_extract_json() {
+        assert_eq!(extract_json("  {\"a\": 1}\n"), "{\"a\": 1}");
+        assert_eq!(extract_json("Here you go:\n```json\n[1, 2]\n```\nAnything else?"), "[1, 2]");
+        assert_eq!(extract_json("The answer is {\"a\": {\"b\": 2}}."), "{\"a\": {\"b\": 2}}");
+        assert_eq!(extract_json("no json"), "no json");
+    }
+
+    #[cfg(feature = "schemars")]
+    #[tokio::test]
+    async fn test_ask_structured_retries_once() {
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        struct Answer {
+            value: u32,
+        }
+
+        let transport = crate::testing::MockTransport::new()
+            .with_assistant_text("The value is forty-two")
+            .with_result("s1")
+            .with_assistant_text("```json\n{\"value\": 42}\n```")
+            .with_result("s1");
+        let handle = transport.handle();
+        let mut client = ClaudeSDKClient::builder().transport(Box::new(transport)).build();
+        client.connect().await.unwrap();
+
+        let answer: Answer = ask_structured(client, "Pick a number", Vec::new()).await.unwrap();
+        assert_eq!(answer.value, 42);
+
+        let prompts = handle.user_messages();
+        assert_eq!(prompts.len(), 2);
+        let first = prompts[0]["message"]["content"].as_str().unwrap();
+        assert!(first.starts_with("Pick a number"));
+        assert!(first.contains("\"value\""));
+        assert!(prompts[1]["message"]["content"].as_str().unwrap().contains("could not be parsed"));
+    }
+
+    #[cfg(feature = "schemars")]
+    #[tokio::test]
+    async fn test_ask_structured_gives_up_after_retry() {
+        let transport = crate::testing::MockTransport::new()
+            .with_assistant_text("no")
+            .with_result("s1")
+            .with_assistant_text("still no")
+            .with_result("s1");
+        let mut client = ClaudeSDKClient::builder().transport(Box::new(transport)).build();
+        client.connect().await.unwrap();
+
+        let result = ask_structured::<Vec<String>>(client, "List colors", Vec::new()).await;
+        match result {
+            Err(ClaudeSDKError::MessageParseError { data, .. }) => assert_eq!(data.unwrap(), "still no"),
+            other => panic!("expected parse error, got {:?}", other.map(|_| ())),
+        }
+    }
 }
@@ -0,0 +1,255 @@
+//! Callback-style handling of a response
+//!
+//! [`Handlers`] maps the parts of a response to closures, so a chat UI can
+//! react to text, thinking, and tool activity without matching on
+//! [`Message`] variants. Pass it to
+//! [`ClaudeSDKClient::run_with_handlers`](crate::ClaudeSDKClient::run_with_handlers).
+//!
+//! # Example
+//! ```no_run
+//! use claude::{ClaudeSDKClient, Handlers};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut client = ClaudeSDKClient::new(None);
+//!     client.connect().await?;
+//!     client.query("Which files are in src/?", None).await?;
+//!
+//!     let handlers = Handlers::new()
+//!         .on_text(|text| print!("{}", text))
+//!         .on_tool_use(|_id, name, _input| println!("\n[using {}]", name))
+//!         .on_result(|result| println!("\nDone in {} ms", result.duration_ms));
+//!     client.run_with_handlers(handlers).await?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::types::{ContentBlock, Message, ResultMessage, UserMessageContent};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Called with a text or thinking block
+pub type TextHandler = Box<dyn FnMut(&str) + Send>;
+
+/// Called with the ID, tool name and input of a tool use
+pub type ToolUseHandler = Box<dyn FnMut(&str, &str, &HashMap<String, Value>) + Send>;
+
+/// Called with the tool use ID, content and error flag of a tool result
+pub type ToolResultHandler = Box<dyn FnMut(&str, Option<&Value>, bool) + Send>;
+
+/// Called with the final result of a response
+pub type ResultHandler = Box<dyn FnMut(&ResultMessage) + Send>;
+
+/// Called with every message before the typed handlers
+pub type MessageHandler = Box<dyn FnMut(&Message) + Send>;
+
+/// Closures invoked as the parts of a response arrive
+///
+/// Every handler is optional. Fields can be set directly
+/// (`Handlers { on_text: Some(Box::new(...)), ..Default::default() }`) or
+/// with the methods of the same name.
+#[derive(Default)]
+pub struct Handlers {
+    /// Assistant text blocks
+    pub on_text: Option<TextHandler>,
+    /// Assistant thinking blocks
+    pub on_thinking: Option<TextHandler>,
+    /// Tool calls made by Claude
+    pub on_tool_use: Option<ToolUseHandler>,
+    /// Tool results reported back to Claude
+    pub on_tool_result: Option<ToolResultHandler>,
+    /// The result message that ends the response
+    pub on_result: Option<ResultHandler>,
+    /// Every message, including system and stream events
+    pub on_message: Option<MessageHandler>,
+}
+
+impl Handlers {
+    /// Create an empty set of handlers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle assistant text
+    pub fn on_text(mut self, handler: impl FnMut(&str) + Send + 'static) -> Self {
+        self.on_text = Some(Box::new(handler));
+        self
+    }
+
+    /// Handle assistant thinking
+    pub fn on_thinking(mut self, handler: impl FnMut(&str) + Send + 'static) -> Self {
+        self.on_thinking = Some(Box::new(handler));
+        self
+    }
+
+    /// Handle tool calls (ID, tool name, input)
+    pub fn on_tool_use(
+        mut self,
+        handler: impl FnMut(&str, &str, &HashMap<String, Value>) + Send + 'static,
+    ) -> Self {
+        self.on_tool_use = Some(Box::new(handler));
+        self
+    }
+
+    /// Handle tool results (tool use ID, content, is_error)
+    pub fn on_tool_result(mut self, handler: impl FnMut(&str, Option<&Value>, bool) + Send + 'static) -> Self {
+        self.on_tool_result = Some(Box::new(handler));
+        self
+    }
+
+    /// Handle the result message
+    pub fn on_result(mut self, handler: impl FnMut(&ResultMessage) + Send + 'static) -> Self {
+        self.on_result = Some(Box::new(handler));
+        self
+    }
+
+    /// Handle every message
+    pub fn on_message(mut self, handler: impl FnMut(&Message) + Send + 'static) -> Self {
+        self.on_message = Some(Box::new(handler));
+        self
+    }
+
+    /// Invoke the handlers that apply to `message`
+    pub(crate) fn dispatch(&mut self, message: &Message) {
+        if let Some(ref mut handler) = self.on_message {
+            handler(message);
+        }
+
+        match message {
+            Message::Assistant(assistant) => {
+                for block in &assistant.content {
+                    self.dispatch_block(block);
+                }
+            }
+            Message::User(user) => {
+                if let UserMessageContent::Blocks(ref blocks) = user.content {
+                    for block in blocks {
+                        self.dispatch_block(block);
+                    }
+                }
+            }
+            Message::Result(result) => {
+                if let Some(ref mut handler) = self.on_result {
+                    handler(result);
+                }
+            }
+            Message::System(_) | Message::Stream(_) | Message::Unknown(_) => {}
+        }
+    }
+
+    fn dispatch_block(&mut self, block: &ContentBlock) {
+        match block {
+            ContentBlock::Text { text } => {
+                if let Some(ref mut handler) = self.on_text {
+                    handler(text);
+                }
+            }
+            ContentBlock::Thinking { thinking, .. } => {
+                if let Some(ref mut handler) = self.on_thinking {
+                    handler(thinking);
+                }
+            }
+            ContentBlock::ToolUse { id, name, input } => {
+                if let Some(ref mut handler) = self.on_tool_use {
+                    handler(id, name, input);
+                }
+            }
+            ContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            } => {
+                if let Some(ref mut handler) = self.on_tool_result {
+                    handler(tool_use_id, content.as_ref(), is_error.unwrap_or(false));
+                }
+            }
+            ContentBlock::Image { .. } | ContentBlock::Document { .. } | ContentBlock::Unknown(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AssistantMessage, UserMessage};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_dispatch_routes_blocks_to_handlers() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = |prefix: &'static str| {
+            let events = Arc::clone(&events);
+            move |value: &str| events.lock().unwrap().push(format!("{}:{}", prefix, value))
+        };
+        let tool_events = Arc::clone(&events);
+        let tool_result_events = Arc::clone(&events);
+        let result_events = Arc::clone(&events);
+
+        let mut handlers = Handlers::new()
+            .on_text(log("text"))
+            .on_thinking(log("thinking"))
+            .on_tool_use(move |id, name, _| tool_events.lock().unwrap().push(format!("tool:{}:{}", id, name)))
+            .on_tool_result(move |id, _, is_error| {
+                tool_result_events.lock().unwrap().push(format!("tool_result:{}:{}", id, is_error))
+            });
+        handlers.on_result = Some(Box::new(move |result| {
+            result_events.lock().unwrap().push(format!("result:{}", result.session_id))
+        }));
+
+        handlers.dispatch(&Message::Assistant(AssistantMessage {
+            content: vec![
+                ContentBlock::Thinking {
+                    thinking: "hmm".to_string(),
+                    signature: String::new(),
+                },
+                ContentBlock::Text {
+                    text: "Let me look".to_string(),
+                },
+                ContentBlock::ToolUse {
+                    id: "tool-1".to_string(),
+                    name: "Read".to_string(),
+                    input: HashMap::new(),
+                },
+            ],
+            model: "claude".to_string(),
+            parent_tool_use_id: None,
+            uuid: None,
+            timestamp: None,
+        }));
+        handlers.dispatch(&Message::User(UserMessage {
+            content: UserMessageContent::Blocks(vec![ContentBlock::ToolResult {
+                tool_use_id: "tool-1".to_string(),
+                content: None,
+                is_error: None,
+            }]),
+            parent_tool_use_id: None,
+            uuid: None,
+            timestamp: None,
+        }));
+        handlers.dispatch(&Message::Result(ResultMessage {
+            subtype: "success".to_string(),
+            duration_ms: 0,
+            duration_api_ms: 0,
+            is_error: false,
+            num_turns: 1,
+            session_id: "session-1".into(),
+            total_cost_usd: None,
+            usage: None,
+            result: None,
+            model_usage: None,
+            uuid: None,
+            timestamp: None,
+        }));
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "thinking:hmm",
+                "text:Let me look",
+                "tool:tool-1:Read",
+                "tool_result:tool-1:false",
+                "result:session-1"
+            ]
+        );
+    }
+}
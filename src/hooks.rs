@@ -1,9 +1,16 @@
 use crate::errors::Result;
-use crate::types::{HookContext, HookJSONOutput};
-use std::collections::HashMap;
+use crate::types::{HookContext, HookEvent, HookInput, HookJSONOutput, IntoHookEvent};
+use serde::Serialize;
+use serde_json::json;
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, HashMap};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long a hook callback may run before its error policy applies
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Type alias for async hook callback functions
 ///
@@ -23,7 +30,357 @@ pub type HookCallback = Arc<
         + Sync,
 >;
 
+impl HookJSONOutput {
+    /// An output that blocks the action, with a reason shown to Claude
+    pub fn block(reason: impl Into<String>) -> Self {
+        Self {
+            decision: Some("block".to_string()),
+            system_message: Some(reason.into()),
+            hook_specific_output: None,
+            updated_input: None,
+        }
+    }
+
+    /// An output that replaces the tool's input before it runs
+    ///
+    /// Only PreToolUse hooks can rewrite input. The call still goes through
+    /// the usual permission check unless `hook_specific_output` also carries
+    /// a `permissionDecision`.
+    pub fn update_input(input: HashMap<String, serde_json::Value>) -> Self {
+        Self {
+            updated_input: Some(input),
+            ..Default::default()
+        }
+    }
+
+    /// Whether this output blocks the action
+    pub fn is_block(&self) -> bool {
+        self.decision.as_deref() == Some("block")
+    }
+
+    /// Combine the outputs of several hooks into one
+    ///
+    /// Any block wins. System messages are joined line by line, input
+    /// updates are merged with later hooks overriding earlier keys, and the
+    /// hook-specific output of the first blocking hook (or else the last hook
+    /// that set one) is kept.
+    pub fn aggregate(outputs: impl IntoIterator<Item = HookJSONOutput>) -> Self {
+        let mut aggregated = Self::default();
+        let mut messages = Vec::new();
+
+        for output in outputs {
+            if let Some(message) = output.system_message {
+                messages.push(message);
+            }
+            if aggregated.is_block() {
+                continue;
+            }
+            if output.decision.is_some() {
+                aggregated.decision = output.decision;
+            }
+            if output.hook_specific_output.is_some() {
+                aggregated.hook_specific_output = output.hook_specific_output;
+            }
+            if let Some(input) = output.updated_input {
+                aggregated.updated_input.get_or_insert_with(HashMap::new).extend(input);
+            }
+        }
+
+        if !messages.is_empty() {
+            aggregated.system_message = Some(messages.join("\n"));
+        }
+        aggregated
+    }
+
+    /// The response sent to the CLI for a `hook_callback` request
+    ///
+    /// Uses the CLI's field names. A PreToolUse block without its own
+    /// hook-specific output is turned into a `deny` permission decision so
+    /// the tool does not run, and updated input is sent as the hook-specific
+    /// `updatedInput`.
+    pub(crate) fn to_control_response(&self, hook_event_name: Option<&str>) -> HashMap<String, serde_json::Value> {
+        let mut response = HashMap::new();
+
+        if let Some(ref decision) = self.decision {
+            response.insert("decision".to_string(), json!(decision));
+        }
+        if let Some(ref message) = self.system_message {
+            response.insert("systemMessage".to_string(), json!(message));
+            if self.is_block() {
+                response.insert("reason".to_string(), json!(message));
+            }
+        }
+
+        let hook_specific_output = match self.hook_specific_output {
+            Some(ref output) => Some(output.clone()),
+            None if self.is_block() && hook_event_name == Some("PreToolUse") => Some(json!({
+                "hookEventName": "PreToolUse",
+                "permissionDecision": "deny",
+                "permissionDecisionReason": self.system_message.clone().unwrap_or_default(),
+            })),
+            None => None,
+        };
+
+        // Input rewrites only take effect before the tool runs
+        let hook_specific_output = match self.updated_input {
+            Some(ref input) if !self.is_block() && hook_event_name == Some("PreToolUse") => {
+                let mut output = hook_specific_output.unwrap_or_else(|| json!({"hookEventName": "PreToolUse"}));
+                if let Some(output) = output.as_object_mut() {
+                    output.insert("updatedInput".to_string(), json!(input));
+                }
+                Some(output)
+            }
+            _ => hook_specific_output,
+        };
+        if let Some(output) = hook_specific_output {
+            response.insert("hookSpecificOutput".to_string(), output);
+        }
+
+        response
+    }
+}
+
+/// Typed state shared between hooks
+///
+/// Holds at most one value per type. Clones share the same storage, so a
+/// value inserted by one hook is visible to every other hook of the
+/// [`HookManager`] and to the code that created it.
+///
+/// # Example
+/// ```
+/// use claude::{hook, HookEvent, HookJSONOutput, HookManager};
+///
+/// #[derive(Clone, Default)]
+/// struct BashCalls(u32);
+///
+/// let manager = HookManager::builder()
+///     .on(HookEvent::PreToolUse, "Bash", hook!(|_input, _tool_use_id, ctx| {
+///         ctx.extensions.update(|calls: &mut BashCalls| calls.0 += 1);
+///         Ok(HookJSONOutput::default())
+///     }))
+///     .build();
+///
+/// let calls = manager.extensions().get::<BashCalls>().unwrap_or_default();
+/// assert_eq!(calls.0, 0);
+/// ```
+#[derive(Clone, Default)]
+pub struct Extensions {
+    values: Arc<std::sync::Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>>,
+}
+
+impl Extensions {
+    /// Create an empty container
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `value`, returning the previous value of the same type
+    pub fn insert<T: Send + Sync + 'static>(&self, value: T) -> Option<T> {
+        self.values
+            .lock()
+            .unwrap()
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|previous| previous.downcast().ok())
+            .map(|previous| *previous)
+    }
+
+    /// A copy of the stored value of type `T`
+    pub fn get<T: Clone + Send + Sync + 'static>(&self) -> Option<T> {
+        self.values
+            .lock()
+            .unwrap()
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+            .cloned()
+    }
+
+    /// Modify the value of type `T` in place, starting from `T::default()`
+    pub fn update<T: Default + Send + Sync + 'static, R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut values = self.values.lock().unwrap();
+        let value = values
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()));
+        f(value.downcast_mut::<T>().expect("extension stored under the wrong type"))
+    }
+
+    /// Remove and return the value of type `T`
+    pub fn remove<T: Send + Sync + 'static>(&self) -> Option<T> {
+        self.values
+            .lock()
+            .unwrap()
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Whether a value of type `T` is stored
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.values.lock().unwrap().contains_key(&TypeId::of::<T>())
+    }
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.values.lock().unwrap().len())
+            .finish()
+    }
+}
+
+/// Upper bounds of the latency histogram buckets, in milliseconds
+const LATENCY_BUCKETS_MS: [u64; 8] = [1, 5, 10, 50, 100, 500, 1000, 5000];
+
+/// Invocation statistics of one callback
+#[derive(Debug, Clone, Default)]
+struct CallbackStats {
+    invocations: u64,
+    errors: u64,
+    timeouts: u64,
+    total: Duration,
+    max: Duration,
+    /// One count per bucket, plus one for slower calls
+    buckets: [u64; LATENCY_BUCKETS_MS.len() + 1],
+}
+
+/// How a callback invocation ended
+enum Outcome {
+    Success,
+    Error,
+    Timeout,
+}
+
+/// Collects callback statistics; clones share the same counters
+#[derive(Clone, Default)]
+pub(crate) struct HookMetrics {
+    stats: Arc<std::sync::Mutex<HashMap<String, CallbackStats>>>,
+}
+
+impl HookMetrics {
+    fn record(&self, callback_id: &str, elapsed: Duration, outcome: Outcome) {
+        let mut stats = self.stats.lock().unwrap();
+        let stats = stats.entry(callback_id.to_string()).or_default();
+
+        stats.invocations += 1;
+        match outcome {
+            Outcome::Success => {}
+            Outcome::Error => stats.errors += 1,
+            Outcome::Timeout => stats.timeouts += 1,
+        }
+        stats.total += elapsed;
+        stats.max = stats.max.max(elapsed);
+
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&bound| elapsed <= Duration::from_millis(bound))
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        stats.buckets[bucket] += 1;
+    }
+}
+
+/// Snapshot of hook activity, returned by [`HookManager::metrics`]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HookMetricsReport {
+    /// Statistics per callback ID, for callbacks that have run
+    pub callbacks: BTreeMap<String, CallbackMetrics>,
+}
+
+/// Invocation counts and latency of one callback
+#[derive(Debug, Clone, Serialize)]
+pub struct CallbackMetrics {
+    /// Event the callback is registered for
+    pub event: Option<String>,
+    /// Tool pattern of the callback's matcher
+    pub matcher: Option<String>,
+    /// Number of times the callback ran
+    pub invocations: u64,
+    /// Runs that returned an error
+    pub errors: u64,
+    /// Runs cut off by the matcher's timeout
+    pub timeouts: u64,
+    /// Time spent in all runs, in milliseconds
+    pub total_ms: f64,
+    /// Average time per run, in milliseconds (0 before the first run)
+    pub mean_ms: f64,
+    /// Longest run, in milliseconds
+    pub max_ms: f64,
+    /// Latency histogram, fastest bucket first
+    pub latency: Vec<LatencyBucket>,
+}
+
+/// One bucket of a callback's latency histogram
+///
+/// Buckets are not cumulative: a run is counted only in the first bucket
+/// whose bound it does not exceed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LatencyBucket {
+    /// Upper bound of the bucket in milliseconds, `None` for runs slower
+    /// than every bound
+    pub le_ms: Option<u64>,
+    /// Runs that took longer than the previous bucket's bound and at most
+    /// `le_ms`
+    pub count: u64,
+}
+
+/// Wrap a callback that receives the parsed [`HookInput`]
+///
+/// Input that does not match any known event fails the hook, so its
+/// [`HookErrorPolicy`] applies.
+///
+/// # Example
+/// ```
+/// use claude::{typed_hook, HookInput, HookJSONOutput};
+///
+/// let audit = typed_hook(|input, _tool_use_id, _ctx| async move {
+///     if let HookInput::PostToolUse(post) = input {
+///         println!("{} returned {}", post.tool_name, post.tool_response);
+///     }
+///     Ok(HookJSONOutput::default())
+/// });
+/// ```
+pub fn typed_hook<F, Fut>(callback: F) -> HookCallback
+where
+    F: Fn(HookInput, Option<String>, HookContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<HookJSONOutput>> + Send + 'static,
+{
+    Arc::new(move |input, tool_use_id, context| match HookInput::parse(&input) {
+        Ok(input) => Box::pin(callback(input, tool_use_id, context)),
+        Err(e) => Box::pin(async move { Err(e) }),
+    })
+}
+
+/// Wrap an async closure as a [`HookCallback`]
+///
+/// Used by the code generated for [`hook_fn`](crate::hook_fn).
+#[doc(hidden)]
+pub fn callback_from_fn<F, Fut>(callback: F) -> HookCallback
+where
+    F: Fn(HashMap<String, serde_json::Value>, Option<String>, HookContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<HookJSONOutput>> + Send + 'static,
+{
+    Arc::new(move |input, tool_use_id, context| Box::pin(callback(input, tool_use_id, context)))
+}
+
+/// Read a field of a hook's input as `T`
+///
+/// Looks in `tool_input` first, so `command` finds the Bash command, then
+/// at the top level, so `tool_name` and `prompt` work too. A missing field
+/// is read as `null`, which suits `Option` types.
+#[doc(hidden)]
+pub fn extract_field<T: serde::de::DeserializeOwned>(input: &HashMap<String, serde_json::Value>, name: &str) -> Result<T> {
+    let value = input
+        .get("tool_input")
+        .and_then(|tool_input| tool_input.get(name))
+        .or_else(|| input.get(name))
+        .cloned()
+        .unwrap_or(serde_json::Value::Null);
+
+    serde_json::from_value(value.clone()).map_err(|e| {
+        crate::errors::ClaudeSDKError::message_parse_error_from(format!("Invalid hook field `{}`: {}", name, e), Some(value), e)
+    })
+}
+
 /// Stores registered hook callbacks with their IDs
+#[derive(Clone)]
 pub struct HookRegistry {
     callbacks: HashMap<String, HookCallback>,
     next_id: u64,
@@ -78,15 +435,43 @@ impl Default for HookRegistry {
     }
 }
 
+/// What happens to the tool when a hook callback fails or times out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HookErrorPolicy {
+    /// Ignore the failed hook and let the tool run
+    #[default]
+    FailOpen,
+    /// Block the tool, as if the hook had returned `decision: "block"`
+    FailClosed,
+}
+
 /// Configuration for a hook matcher
 ///
-/// Associates a tool name pattern with hook callbacks
+/// Associates a tool name pattern with hook callbacks.
+///
+/// [`priority`](Self::priority) and [`stop_on_block`](Self::stop_on_block)
+/// only take effect where the SDK runs the hooks itself, in
+/// [`HookManager::execute_hooks`]. In a session the CLI calls each callback
+/// on its own schedule and may run them concurrently; matchers are still
+/// registered in priority order, and a block from any callback keeps the
+/// tool from running.
 #[derive(Clone)]
 pub struct HookMatcherConfig {
     /// Pattern to match against tool names (e.g., "Bash", "Read", "*")
     pub matcher: String,
     /// List of callback IDs to execute when matched
     pub callback_ids: Vec<String>,
+    /// Time each callback may run (default [`DEFAULT_HOOK_TIMEOUT`])
+    pub timeout: Duration,
+    /// Outcome when a callback errors or times out
+    pub error_policy: HookErrorPolicy,
+    /// Matchers with a higher priority run first in
+    /// [`HookManager::execute_hooks`] (default 0); equal priorities run in
+    /// the order they were added
+    pub priority: i32,
+    /// Skip the remaining hooks in [`HookManager::execute_hooks`] once a
+    /// callback of this matcher returns `decision: "block"`
+    pub stop_on_block: bool,
 }
 
 impl HookMatcherConfig {
@@ -95,9 +480,39 @@ impl HookMatcherConfig {
         Self {
             matcher,
             callback_ids,
+            timeout: DEFAULT_HOOK_TIMEOUT,
+            error_policy: HookErrorPolicy::default(),
+            priority: 0,
+            stop_on_block: false,
         }
     }
 
+    /// Set how long each callback may run
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set whether a failing callback allows or blocks the tool
+    pub fn with_error_policy(mut self, error_policy: HookErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
+
+    /// Set the priority; matchers with a higher priority run first in
+    /// [`HookManager::execute_hooks`]
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Set whether a block from this matcher skips the remaining hooks in
+    /// [`HookManager::execute_hooks`]
+    pub fn with_stop_on_block(mut self, stop_on_block: bool) -> Self {
+        self.stop_on_block = stop_on_block;
+        self
+    }
+
     /// Check if this matcher matches the given tool name
     pub fn matches(&self, tool_name: &str) -> bool {
         if self.matcher == "*" {
@@ -108,11 +523,18 @@ impl HookMatcherConfig {
 }
 
 /// Manages hook configurations for different events
+///
+/// Clones share their callbacks, extensions and metrics.
+#[derive(Clone)]
 pub struct HookManager {
     /// Registered callbacks
     registry: HookRegistry,
     /// Hook matchers organized by event type
-    matchers: HashMap<String, Vec<HookMatcherConfig>>,
+    matchers: HashMap<HookEvent, Vec<HookMatcherConfig>>,
+    /// State passed to every callback through [`HookContext::extensions`]
+    extensions: Extensions,
+    /// Invocation statistics, see [`metrics`](Self::metrics)
+    metrics: HookMetrics,
 }
 
 impl HookManager {
@@ -121,25 +543,122 @@ impl HookManager {
         Self {
             registry: HookRegistry::new(),
             matchers: HashMap::new(),
+            extensions: Extensions::new(),
+            metrics: HookMetrics::default(),
         }
     }
 
+    /// Start building a hook manager in one chain
+    ///
+    /// # Example
+    /// ```
+    /// use claude::{hook, HookEvent, HookJSONOutput, HookManager};
+    ///
+    /// let manager = HookManager::builder()
+    ///     .on(HookEvent::PreToolUse, "Bash", hook!(|input| {
+    ///         let command = input.get("tool_input").and_then(|v| v.get("command"));
+    ///         match command.and_then(|c| c.as_str()) {
+    ///             Some(c) if c.contains("rm -rf") => Ok(HookJSONOutput::block("Refusing rm -rf")),
+    ///             _ => Ok(HookJSONOutput::default()),
+    ///         }
+    ///     }))
+    ///     .on(HookEvent::Stop, "*", hook!(|_input| { Ok(HookJSONOutput::default()) }))
+    ///     .build();
+    /// ```
+    pub fn builder() -> HookManagerBuilder {
+        HookManagerBuilder {
+            manager: Self::new(),
+        }
+    }
+
+    /// State shared by this manager's callbacks
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Invocation counts and latency of every callback that has run
+    ///
+    /// Covers callbacks run by the CLI as well as by
+    /// [`execute_hooks`](Self::execute_hooks).
+    pub fn metrics(&self) -> HookMetricsReport {
+        let stats = self.metrics.stats.lock().unwrap();
+        let callbacks = stats
+            .iter()
+            .map(|(callback_id, stats)| {
+                let registration = self.matchers.iter().find_map(|(event, matchers)| {
+                    matchers
+                        .iter()
+                        .find(|matcher| matcher.callback_ids.contains(callback_id))
+                        .map(|matcher| (event.as_str().to_string(), matcher.matcher.clone()))
+                });
+                let (event, matcher) = registration.unzip();
+                let bounds = LATENCY_BUCKETS_MS.iter().map(|&bound| Some(bound)).chain([None]);
+
+                let metrics = CallbackMetrics {
+                    event,
+                    matcher,
+                    invocations: stats.invocations,
+                    errors: stats.errors,
+                    timeouts: stats.timeouts,
+                    total_ms: millis(stats.total),
+                    mean_ms: millis(stats.total) / stats.invocations.max(1) as f64,
+                    max_ms: millis(stats.max),
+                    latency: bounds
+                        .zip(stats.buckets)
+                        .map(|(le_ms, count)| LatencyBucket { le_ms, count })
+                        .collect(),
+                };
+                (callback_id.clone(), metrics)
+            })
+            .collect();
+
+        HookMetricsReport { callbacks }
+    }
+
+    /// The counters behind [`metrics`](Self::metrics)
+    pub(crate) fn metrics_recorder(&self) -> HookMetrics {
+        self.metrics.clone()
+    }
+
     /// Register a callback and return its ID
     pub fn register_callback(&mut self, callback: HookCallback) -> String {
         self.registry.register(callback)
     }
 
+    /// Register `callback` for `event` on tools matching `matcher` and return its ID
+    ///
+    /// `matcher` only applies to PreToolUse and PostToolUse; the other events
+    /// run their callbacks every time.
+    pub fn add_hook(&mut self, event: HookEvent, matcher: impl Into<String>, callback: HookCallback) -> String {
+        let callback_id = self.register_callback(callback);
+        self.add_matcher(event, HookMatcherConfig::new(matcher.into(), vec![callback_id.clone()]));
+        callback_id
+    }
+
+    /// Register `callback` for `event` on tools matching `pattern`, for chaining
+    ///
+    /// Same as [`add_hook`](Self::add_hook) without returning the callback ID.
+    pub fn on(&mut self, event: HookEvent, pattern: impl Into<String>, callback: HookCallback) -> &mut Self {
+        self.add_hook(event, pattern, callback);
+        self
+    }
+
     /// Add a hook matcher for a specific event
-    pub fn add_matcher(&mut self, event: String, matcher: HookMatcherConfig) {
-        self.matchers
-            .entry(event)
-            .or_insert_with(Vec::new)
-            .push(matcher);
+    ///
+    /// The matcher is placed after those with a higher or equal priority.
+    /// Unknown event names are ignored.
+    pub fn add_matcher(&mut self, event: impl IntoHookEvent, matcher: HookMatcherConfig) {
+        let Some(event) = event.into_hook_event() else {
+            return;
+        };
+        let matchers = self.matchers.entry(event).or_default();
+        let position = matchers.partition_point(|existing| existing.priority >= matcher.priority);
+        matchers.insert(position, matcher);
     }
 
-    /// Get all matchers for a specific event
-    pub fn get_matchers(&self, event: &str) -> Option<&Vec<HookMatcherConfig>> {
-        self.matchers.get(event)
+    /// Get all matchers for a specific event, in the order they run
+    pub fn get_matchers(&self, event: impl IntoHookEvent) -> Option<&Vec<HookMatcherConfig>> {
+        self.matchers.get(&event.into_hook_event()?)
     }
 
     /// Get a callback by ID
@@ -148,12 +667,15 @@ impl HookManager {
     }
 
     /// Find matching callback IDs for an event and tool name
-    pub fn find_matching_callbacks(&self, event: &str, tool_name: &str) -> Vec<String> {
+    pub fn find_matching_callbacks(&self, event: impl IntoHookEvent, tool_name: &str) -> Vec<String> {
         let mut callback_ids = Vec::new();
+        let Some(event) = event.into_hook_event() else {
+            return callback_ids;
+        };
 
-        if let Some(matchers) = self.matchers.get(event) {
+        if let Some(matchers) = self.matchers.get(&event) {
             for matcher in matchers {
-                if matcher.matches(tool_name) {
+                if !event.is_tool_event() || matcher.matches(tool_name) {
                     callback_ids.extend(matcher.callback_ids.clone());
                 }
             }
@@ -163,27 +685,80 @@ impl HookManager {
     }
 
     /// Execute all matching hooks for an event
+    ///
+    /// Matchers run in priority order, see [`HookMatcherConfig`]. The
+    /// callbacks receive this manager's [`extensions`](Self::extensions) in
+    /// place of those in `context`. Unknown event names match no hooks.
     pub async fn execute_hooks(
         &self,
-        event: &str,
+        event: impl IntoHookEvent,
         tool_name: &str,
         input_data: HashMap<String, serde_json::Value>,
         tool_use_id: Option<String>,
         context: HookContext,
     ) -> Result<Vec<HookJSONOutput>> {
-        let callback_ids = self.find_matching_callbacks(event, tool_name);
+        let context = HookContext {
+            extensions: self.extensions.clone(),
+            ..context
+        };
         let mut results = Vec::new();
+        let Some(event) = event.into_hook_event() else {
+            return Ok(results);
+        };
 
-        for callback_id in callback_ids {
-            if let Some(callback) = self.get_callback(&callback_id) {
-                let output = callback(input_data.clone(), tool_use_id.clone(), context.clone()).await?;
-                results.push(output);
+        for matcher in self.matchers.get(&event).into_iter().flatten() {
+            if event.is_tool_event() && !matcher.matches(tool_name) {
+                continue;
+            }
+            for callback_id in &matcher.callback_ids {
+                if let Some(callback) = self.get_callback(callback_id) {
+                    let output = run_callback(
+                        callback,
+                        callback_id,
+                        matcher,
+                        &self.metrics,
+                        input_data.clone(),
+                        tool_use_id.clone(),
+                        context.clone(),
+                    )
+                    .await;
+                    let stop = matcher.stop_on_block && output.as_ref().is_some_and(HookJSONOutput::is_block);
+                    results.extend(output);
+                    if stop {
+                        return Ok(results);
+                    }
+                }
             }
         }
 
         Ok(results)
     }
 
+    /// Execute all matching hooks for an event and combine their outputs
+    ///
+    /// See [`HookJSONOutput::aggregate`].
+    pub async fn execute_hooks_aggregated(
+        &self,
+        event: HookEvent,
+        tool_name: &str,
+        input_data: HashMap<String, serde_json::Value>,
+        tool_use_id: Option<String>,
+        context: HookContext,
+    ) -> Result<HookJSONOutput> {
+        let outputs = self
+            .execute_hooks(event, tool_name, input_data, tool_use_id, context)
+            .await?;
+        Ok(HookJSONOutput::aggregate(outputs))
+    }
+
+    /// The matcher a callback was registered under, if any
+    pub fn matcher_for_callback(&self, id: &str) -> Option<&HookMatcherConfig> {
+        self.matchers
+            .values()
+            .flatten()
+            .find(|matcher| matcher.callback_ids.iter().any(|callback_id| callback_id == id))
+    }
+
     /// Get the hook configuration for initialization
     ///
     /// Returns a JSON-serializable structure for the control protocol
@@ -203,11 +778,13 @@ impl HookManager {
                         "callback_ids".to_string(),
                         serde_json::to_value(&matcher.callback_ids).unwrap(),
                     );
+                    // Seconds, so the CLI waits as long as the callback may run
+                    m.insert("timeout".to_string(), json!(matcher.timeout.as_secs_f64()));
                     m
                 })
                 .collect();
 
-            config.insert(event.clone(), matcher_configs);
+            config.insert(event.as_str().to_string(), matcher_configs);
         }
 
         config
@@ -220,6 +797,78 @@ impl Default for HookManager {
     }
 }
 
+/// Builder for a [`HookManager`], created by [`HookManager::builder`]
+pub struct HookManagerBuilder {
+    manager: HookManager,
+}
+
+impl HookManagerBuilder {
+    /// Run `callback` for `event` on tools matching `pattern` (a tool name or `*`)
+    pub fn on(mut self, event: HookEvent, pattern: impl Into<String>, callback: HookCallback) -> Self {
+        self.manager.add_hook(event, pattern, callback);
+        self
+    }
+
+    /// Add a matcher with its own timeout or error policy
+    ///
+    /// The callbacks are registered and their IDs filled into `matcher`.
+    pub fn matcher(mut self, event: HookEvent, matcher: HookMatcherConfig, callbacks: Vec<HookCallback>) -> Self {
+        let callback_ids = callbacks
+            .into_iter()
+            .map(|callback| self.manager.register_callback(callback))
+            .collect();
+        self.manager.add_matcher(event, HookMatcherConfig { callback_ids, ..matcher });
+        self
+    }
+
+    /// Finish building
+    pub fn build(self) -> HookManager {
+        self.manager
+    }
+}
+
+/// Run one callback under its matcher's timeout and error policy
+///
+/// Returns `None` when a failed callback is ignored under
+/// [`HookErrorPolicy::FailOpen`].
+pub(crate) async fn run_callback(
+    callback: &HookCallback,
+    callback_id: &str,
+    matcher: &HookMatcherConfig,
+    metrics: &HookMetrics,
+    input_data: HashMap<String, serde_json::Value>,
+    tool_use_id: Option<String>,
+    context: HookContext,
+) -> Option<HookJSONOutput> {
+    let started = Instant::now();
+    let result = tokio::time::timeout(matcher.timeout, callback(input_data, tool_use_id, context)).await;
+    let elapsed = started.elapsed();
+
+    let failure = match result {
+        Ok(Ok(output)) => {
+            metrics.record(callback_id, elapsed, Outcome::Success);
+            return Some(output);
+        }
+        Ok(Err(e)) => {
+            metrics.record(callback_id, elapsed, Outcome::Error);
+            format!("Hook {} failed: {}", callback_id, e)
+        }
+        Err(_) => {
+            metrics.record(callback_id, elapsed, Outcome::Timeout);
+            format!("Hook {} timed out after {:?}", callback_id, matcher.timeout)
+        }
+    };
+
+    match matcher.error_policy {
+        HookErrorPolicy::FailOpen => None,
+        HookErrorPolicy::FailClosed => Some(HookJSONOutput::block(failure)),
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -317,9 +966,9 @@ mod tests {
         let callback_id = manager.register_callback(callback);
         let matcher = HookMatcherConfig::new("Bash".to_string(), vec![callback_id.clone()]);
 
-        manager.add_matcher("PreToolUse".to_string(), matcher);
+        manager.add_matcher(HookEvent::PreToolUse, matcher);
 
-        let matchers = manager.get_matchers("PreToolUse");
+        let matchers = manager.get_matchers(HookEvent::PreToolUse);
         assert!(matchers.is_some());
         assert_eq!(matchers.unwrap().len(), 1);
     }
@@ -335,16 +984,257 @@ mod tests {
         let callback_id = manager.register_callback(callback);
         let matcher = HookMatcherConfig::new("Bash".to_string(), vec![callback_id.clone()]);
 
-        manager.add_matcher("PreToolUse".to_string(), matcher);
+        manager.add_matcher(HookEvent::PreToolUse, matcher);
 
-        let matches = manager.find_matching_callbacks("PreToolUse", "Bash");
+        let matches = manager.find_matching_callbacks(HookEvent::PreToolUse, "Bash");
         assert_eq!(matches.len(), 1);
         assert_eq!(matches[0], callback_id);
 
-        let no_matches = manager.find_matching_callbacks("PreToolUse", "Read");
+        let no_matches = manager.find_matching_callbacks(HookEvent::PreToolUse, "Read");
         assert_eq!(no_matches.len(), 0);
     }
 
+    #[test]
+    fn test_hook_manager_accepts_event_names() {
+        let mut manager = HookManager::new();
+
+        let callback: HookCallback = Arc::new(|_, _, _| {
+            Box::pin(async { Ok(HookJSONOutput::default()) })
+        });
+
+        let callback_id = manager.register_callback(callback);
+        manager.add_matcher("PreToolUse", HookMatcherConfig::new("Bash".to_string(), vec![callback_id.clone()]));
+        manager.add_matcher("PreToolUze", HookMatcherConfig::new("Bash".to_string(), vec![callback_id.clone()]));
+
+        assert_eq!(manager.get_matchers(HookEvent::PreToolUse).unwrap().len(), 1);
+        assert_eq!(manager.find_matching_callbacks("PreToolUse", "Bash"), vec![callback_id]);
+        assert!(manager.get_matchers("PreToolUze").is_none());
+        assert!(manager.find_matching_callbacks("PreToolUze", "Bash").is_empty());
+    }
+
+    #[test]
+    fn test_aggregate_block_wins() {
+        let aggregated = HookJSONOutput::aggregate([
+            HookJSONOutput {
+                decision: Some("allow".to_string()),
+                system_message: Some("Looks fine".to_string()),
+                hook_specific_output: Some(json!({"hookEventName": "PreToolUse"})),
+                updated_input: None,
+            },
+            HookJSONOutput::block("No rm -rf"),
+            HookJSONOutput {
+                decision: Some("allow".to_string()),
+                system_message: None,
+                hook_specific_output: Some(json!({"permissionDecision": "allow"})),
+                updated_input: None,
+            },
+        ]);
+
+        assert!(aggregated.is_block());
+        assert_eq!(aggregated.system_message.as_deref(), Some("Looks fine\nNo rm -rf"));
+        assert_eq!(aggregated.hook_specific_output, Some(json!({"hookEventName": "PreToolUse"})));
+        assert!(!HookJSONOutput::aggregate([]).is_block());
+    }
+
+    #[test]
+    fn test_pre_tool_use_block_becomes_deny() {
+        let response = HookJSONOutput::block("No rm -rf").to_control_response(Some("PreToolUse"));
+        assert_eq!(response["decision"], "block");
+        assert_eq!(response["reason"], "No rm -rf");
+        assert_eq!(response["systemMessage"], "No rm -rf");
+        assert_eq!(response["hookSpecificOutput"]["permissionDecision"], "deny");
+        assert_eq!(response["hookSpecificOutput"]["permissionDecisionReason"], "No rm -rf");
+
+        let response = HookJSONOutput::block("Stop here").to_control_response(Some("PostToolUse"));
+        assert!(!response.contains_key("hookSpecificOutput"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_hooks_applies_error_policy() {
+        let mut manager = HookManager::new();
+
+        let failing: HookCallback = Arc::new(|_, _, _| {
+            Box::pin(async { Err(crate::errors::ClaudeSDKError::cli_connection_error("boom".to_string())) })
+        });
+        let hanging: HookCallback = Arc::new(|_, _, _| Box::pin(std::future::pending()));
+
+        let failing_id = manager.register_callback(failing);
+        let hanging_id = manager.register_callback(hanging);
+        manager.add_matcher(
+            HookEvent::PreToolUse,
+            HookMatcherConfig::new("Bash".to_string(), vec![failing_id.clone()]),
+        );
+        manager.add_matcher(
+            HookEvent::PreToolUse,
+            HookMatcherConfig::new("Bash".to_string(), vec![hanging_id.clone()])
+                .with_timeout(Duration::from_millis(10))
+                .with_error_policy(HookErrorPolicy::FailClosed),
+        );
+
+        let outputs = manager
+            .execute_hooks(HookEvent::PreToolUse, "Bash", HashMap::new(), None, HookContext::default())
+            .await
+            .unwrap();
+
+        // The failing hook fails open and is skipped, the hanging one blocks
+        assert_eq!(outputs.len(), 1);
+        assert!(outputs[0].is_block());
+        assert!(outputs[0].system_message.as_ref().unwrap().contains("timed out"));
+        assert_eq!(manager.matcher_for_callback(&hanging_id).unwrap().error_policy, HookErrorPolicy::FailClosed);
+        assert_eq!(manager.matcher_for_callback(&failing_id).unwrap().timeout, DEFAULT_HOOK_TIMEOUT);
+    }
+
+    #[test]
+    fn test_updated_input_is_sent_for_pre_tool_use() {
+        let dry_run = HashMap::from([("command".to_string(), json!("terraform apply --dry-run"))]);
+        let timeout = HashMap::from([("timeout".to_string(), json!(60))]);
+        let output = HookJSONOutput::aggregate([
+            HookJSONOutput::update_input(dry_run),
+            HookJSONOutput {
+                hook_specific_output: Some(json!({"hookEventName": "PreToolUse", "permissionDecision": "allow"})),
+                ..HookJSONOutput::update_input(timeout)
+            },
+        ]);
+
+        let response = output.to_control_response(Some("PreToolUse"));
+        assert_eq!(response["hookSpecificOutput"]["permissionDecision"], "allow");
+        assert_eq!(
+            response["hookSpecificOutput"]["updatedInput"],
+            json!({"command": "terraform apply --dry-run", "timeout": 60})
+        );
+
+        let response = output.to_control_response(Some("PostToolUse"));
+        assert!(response["hookSpecificOutput"].get("updatedInput").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_non_tool_events_ignore_matchers() {
+        let mut manager = HookManager::new();
+        let callback = typed_hook(|input, _, _| async move {
+            match input {
+                HookInput::UserPromptSubmit(submit) if submit.prompt.contains("password") => {
+                    Ok(HookJSONOutput::block("Prompt contains a secret"))
+                }
+                _ => Ok(HookJSONOutput::default()),
+            }
+        });
+        manager.add_hook(HookEvent::UserPromptSubmit, "Bash", callback);
+
+        let input: HashMap<String, serde_json::Value> = serde_json::from_value(json!({
+            "hook_event_name": "UserPromptSubmit",
+            "session_id": "session-1",
+            "transcript_path": "/tmp/transcript.jsonl",
+            "cwd": "/tmp",
+            "prompt": "my password is hunter2"
+        }))
+        .unwrap();
+        let outputs = manager
+            .execute_hooks(HookEvent::UserPromptSubmit, "", input, None, HookContext::default())
+            .await
+            .unwrap();
+
+        assert_eq!(outputs.len(), 1);
+        assert!(outputs[0].is_block());
+        assert_eq!(manager.find_matching_callbacks(HookEvent::UserPromptSubmit, "").len(), 1);
+        assert!(manager.find_matching_callbacks(HookEvent::PreToolUse, "Bash").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_builder_registers_hooks() {
+        let mut manager = HookManager::builder()
+            .on(HookEvent::PreToolUse, "Bash", crate::hook!(|_input| { Ok(HookJSONOutput::block("no")) }))
+            .matcher(
+                HookEvent::PreToolUse,
+                HookMatcherConfig::new("Read".to_string(), Vec::new()).with_timeout(Duration::from_secs(1)),
+                vec![crate::hook!(|_input| { Ok(HookJSONOutput::default()) })],
+            )
+            .build();
+        manager.on(HookEvent::PostToolUse, "*", crate::hook!(|_input| { Ok(HookJSONOutput::default()) }));
+
+        assert_eq!(manager.find_matching_callbacks(HookEvent::PreToolUse, "Bash"), ["hook_0"]);
+        assert_eq!(manager.find_matching_callbacks(HookEvent::PreToolUse, "Read"), ["hook_1"]);
+        assert_eq!(manager.matcher_for_callback("hook_1").unwrap().timeout, Duration::from_secs(1));
+        assert_eq!(manager.find_matching_callbacks(HookEvent::PostToolUse, "Write"), ["hook_2"]);
+
+        let outputs = manager
+            .execute_hooks(HookEvent::PreToolUse, "Bash", HashMap::new(), None, HookContext::default())
+            .await
+            .unwrap();
+        assert!(outputs[0].is_block());
+    }
+
+    #[tokio::test]
+    async fn test_extensions_shared_between_hooks() {
+        #[derive(Clone, Default, Debug, PartialEq)]
+        struct Paths(Vec<String>);
+
+        let record: HookCallback = Arc::new(|input, _, ctx| {
+            Box::pin(async move {
+                let path = input["file_path"].as_str().unwrap().to_string();
+                ctx.extensions.update(|paths: &mut Paths| paths.0.push(path));
+                Ok(HookJSONOutput::default())
+            })
+        });
+        let mut manager = HookManager::new();
+        manager.on(HookEvent::PostToolUse, "Read", record);
+        manager.extensions().insert(Paths(vec!["seed.rs".to_string()]));
+
+        for path in ["a.rs", "b.rs"] {
+            let input = HashMap::from([("file_path".to_string(), json!(path))]);
+            manager
+                .execute_hooks(HookEvent::PostToolUse, "Read", input, None, HookContext::default())
+                .await
+                .unwrap();
+        }
+
+        let paths = manager.extensions().get::<Paths>().unwrap();
+        assert_eq!(paths.0, ["seed.rs", "a.rs", "b.rs"]);
+        assert_eq!(manager.extensions().insert(7u32), None);
+        assert_eq!(manager.extensions().remove::<u32>(), Some(7));
+        assert!(!manager.extensions().contains::<u32>());
+    }
+
+    #[tokio::test]
+    async fn test_metrics_count_invocations_and_failures() {
+        let mut manager = HookManager::builder()
+            .on(HookEvent::PreToolUse, "Bash", crate::hook!(|_input| { Ok(HookJSONOutput::default()) }))
+            .matcher(
+                HookEvent::PreToolUse,
+                HookMatcherConfig::new("Bash".to_string(), Vec::new()).with_timeout(Duration::from_millis(5)),
+                vec![Arc::new(|_, _, _| Box::pin(std::future::pending()))],
+            )
+            .build();
+        manager.on(
+            HookEvent::PreToolUse,
+            "Bash",
+            Arc::new(|_, _, _| Box::pin(async { Err(crate::errors::ClaudeSDKError::interrupted()) })),
+        );
+
+        for _ in 0..2 {
+            manager
+                .execute_hooks(HookEvent::PreToolUse, "Bash", HashMap::new(), None, HookContext::default())
+                .await
+                .unwrap();
+        }
+
+        let report = manager.metrics();
+        assert_eq!(report.callbacks.len(), 3);
+
+        let ok = &report.callbacks["hook_0"];
+        assert_eq!(ok.event.as_deref(), Some("PreToolUse"));
+        assert_eq!(ok.matcher.as_deref(), Some("Bash"));
+        assert_eq!((ok.invocations, ok.errors, ok.timeouts), (2, 0, 0));
+        assert_eq!(ok.latency.iter().map(|bucket| bucket.count).sum::<u64>(), 2);
+        assert_eq!(ok.latency.last().unwrap().le_ms, None);
+
+        assert_eq!(report.callbacks["hook_1"].timeouts, 2);
+        assert!(report.callbacks["hook_1"].max_ms >= 5.0);
+        assert_eq!(report.callbacks["hook_2"].errors, 2);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["callbacks"]["hook_0"]["invocations"], 2);
+    }
+
     #[tokio::test]
     async fn test_hook_manager_execute_hooks() {
         let mut manager = HookManager::new();
@@ -356,6 +1246,7 @@ mod tests {
                     decision: Some("allow".to_string()),
                     system_message: Some(format!("Processed: {:?}", value)),
                     hook_specific_output: None,
+                    updated_input: None,
                 };
                 Ok(output)
             })
@@ -364,7 +1255,7 @@ mod tests {
         let callback_id = manager.register_callback(callback);
         let matcher = HookMatcherConfig::new("Bash".to_string(), vec![callback_id]);
 
-        manager.add_matcher("PreToolUse".to_string(), matcher);
+        manager.add_matcher(HookEvent::PreToolUse, matcher);
 
         let mut input_data = HashMap::new();
         input_data.insert(
@@ -372,10 +1263,10 @@ mod tests {
             serde_json::Value::String("test_value".to_string()),
         );
 
-        let context = HookContext { signal: None };
+        let context = HookContext::default();
 
         let results = manager
-            .execute_hooks("PreToolUse", "Bash", input_data, None, context)
+            .execute_hooks(HookEvent::PreToolUse, "Bash", input_data, None, context)
             .await;
 
         assert!(results.is_ok());
@@ -383,4 +1274,48 @@ mod tests {
         assert_eq!(outputs.len(), 1);
         assert_eq!(outputs[0].decision, Some("allow".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_hooks_run_by_priority_and_stop_on_block() {
+        let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut manager = HookManager::new();
+        let mut add = |name: &'static str, decision: Option<&str>, matcher: HookMatcherConfig| {
+            let order = order.clone();
+            let decision = decision.map(str::to_string);
+            let callback: HookCallback = Arc::new(move |_, _, _| {
+                order.lock().unwrap().push(name);
+                let output = HookJSONOutput {
+                    decision: decision.clone(),
+                    ..Default::default()
+                };
+                Box::pin(async move { Ok(output) })
+            });
+            let callback_id = manager.register_callback(callback);
+            manager.add_matcher(HookEvent::PreToolUse, HookMatcherConfig { callback_ids: vec![callback_id], ..matcher });
+        };
+        let any = || HookMatcherConfig::new("*".to_string(), Vec::new());
+
+        add("default", None, any());
+        add("high", None, any().with_priority(10));
+        add("blocking", Some("block"), any().with_priority(-5).with_stop_on_block(true));
+        add("second default", None, any());
+        add("skipped", None, any().with_priority(-10));
+
+        let priorities: Vec<_> = manager
+            .get_matchers(HookEvent::PreToolUse)
+            .unwrap()
+            .iter()
+            .map(|matcher| matcher.priority)
+            .collect();
+        assert_eq!(priorities, [10, 0, 0, -5, -10]);
+
+        let outputs = manager
+            .execute_hooks(HookEvent::PreToolUse, "Bash", HashMap::new(), None, HookContext::default())
+            .await
+            .unwrap();
+
+        assert_eq!(*order.lock().unwrap(), ["high", "default", "second default", "blocking"]);
+        assert_eq!(outputs.len(), 4);
+        assert_eq!(outputs[3].decision.as_deref(), Some("block"));
+    }
 }
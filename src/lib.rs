@@ -10,7 +10,7 @@
 //! use claude::prelude::*;
 //!
 //! #[tokio::main]
-//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
 //!     // Simplest: just ask and get text
 //!     let answer = ask("What is 2 + 2?").await?;
 //!     println!("{}", answer);
@@ -31,6 +31,7 @@
 //! ### Tier 1: Facade (Beginner-Friendly)
 //! - [`ask`] - Simplest function: ask a question, get text
 //! - [`QuickQuery`] - Fluent builder for common configurations
+//! - [`Conversation`] - Multi-turn chat without managing a client
 //!
 //! ### Tier 2: Direct APIs (Full Message Access)
 //! - [`simple_query`] - One-shot queries, collect all messages
@@ -42,17 +43,46 @@
 //! - [`permissions`] module - Runtime tool permission control
 //! - [`mcp`] module - Custom in-process tool servers
 
+// Lets generated code name this crate as `::claude` from inside it too
+extern crate self as claude;
+
 pub mod types;
 pub mod errors;
 pub mod message_parser;
 pub mod transport;
+#[cfg(feature = "api-transport")]
+pub mod api_transport;
+#[cfg(feature = "ssh-transport")]
+pub mod ssh_transport;
+#[cfg(feature = "docker-transport")]
+pub mod docker_transport;
 pub mod simple_query;
 pub mod streaming_query;
 pub mod query;
+pub mod cancel;
 pub mod client;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod content;
+pub mod assembler;
+pub mod markdown;
+pub mod render;
+pub mod tokens;
+pub mod batch;
+pub mod retry;
+pub mod template;
+pub mod metrics;
+pub mod cost;
+pub mod version;
+mod budget;
+pub mod handlers;
 pub mod hooks;
 pub mod permissions;
+pub mod audit;
+pub mod policy;
 pub mod mcp_server;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 // Phase 1 additions: ergonomic improvements
 pub mod builders;
@@ -63,27 +93,118 @@ pub mod macros;
 pub mod prelude;
 
 // Re-export commonly used items at crate root
-pub use errors::{ClaudeSDKError, Result};
-pub use types::{ClaudeAgentOptions, ContentBlock, Message, PermissionMode, SystemPromptConfig};
+pub use errors::{ClaudeSDKError, ErrorKind, Result};
+pub use types::{ClaudeAgentOptions, ContentBlock, ContentSource, HeartbeatConfig, HeartbeatEvent, Message, ParseMode, PermissionMode, RestartPolicy, SessionId, InvalidSessionId, SystemPromptConfig};
 
 // Main APIs
-pub use simple_query::simple_query;
-pub use streaming_query::{streaming_query, StreamingQuery};
-pub use client::{ClaudeSDKClient, MessageStream, ResponseStream};
+pub use simple_query::{simple_query, simple_query_with, simple_query_with_retry, simple_query_with_timeout, simple_query_with_transport, simple_query_with_transport_and_extras, QueryExtras};
+pub use streaming_query::{streaming_query, PartialMessageStream, StreamingQuery};
+pub use client::{ClaudeSDKClient, ClaudeSDKClientBuilder, ClientReceiver, ClientSender, ClientSession, MessageStream, ResponseStream, TextStream};
 
 // Ergonomic additions
 pub use builders::ClaudeOptionsBuilder;
 pub use extensions::MessageVecExt;
-pub use facade::{ask, ask_with_options, QuickQuery};
+pub use cancel::CancelHandle;
+pub use content::UserContent;
+pub use assembler::{AssembledMessage, MessageAssembler, PartialAssistantMessage};
+pub use markdown::CodeBlock;
+pub use render::RenderOptions;
+pub use batch::{ask_many, BatchOptions, BatchResult};
+pub use retry::{Backoff, RetryPolicy};
+pub use template::PromptTemplate;
+pub use version::{cli_version, CliVersion};
+pub use cost::{CostSnapshot, CostTotals, CostTracker};
+pub use handlers::Handlers;
+pub use facade::{ask, ask_detailed, ask_stream, ask_with_files, ask_with_options, ask_with_transport, Conversation, Plan, QueryResult, QuickQuery};
+#[cfg(feature = "schemars")]
+pub use facade::ask_json;
 
 // Advanced features (namespaced for clarity)
-pub use hooks::{HookCallback, HookRegistry, HookMatcherConfig, HookManager};
-pub use permissions::CanUseToolCallback;
-pub use types::{HookContext, HookJSONOutput, ToolPermissionContext, PermissionResult};
+pub use hooks::{CallbackMetrics, Extensions, HookCallback, HookErrorPolicy, HookMetricsReport, LatencyBucket, HookRegistry, HookMatcherConfig, HookManager, HookManagerBuilder, DEFAULT_HOOK_TIMEOUT, typed_hook};
+pub use permissions::{CanUseToolCallback, CanUseToolExt, ChainMode, PermissionCache, PermissionChain, RateLimit, RateLimitAction};
+pub use policy::{PermissionPolicy, PolicyAction};
+pub use audit::{AuditDecision, AuditLogger, AuditRecord};
+
+/// Turn a plain `async fn` into a function returning a [`HookCallback`]
+///
+/// Parameters are filled in by type or name:
+/// - a [`HookInput`] parameter receives the parsed payload
+/// - a [`HookContext`] parameter receives the context
+/// - `tool_use_id: Option<String>` receives the tool use ID
+/// - any other name is read from the tool input or the payload and
+///   deserialized into its type, e.g. `command: Option<String>`
+///
+/// The function may return [`Result<HookJSONOutput>`](Result) or a plain
+/// [`HookJSONOutput`]. A parameter that cannot be extracted fails the hook,
+/// so its [`HookErrorPolicy`] applies.
+///
+/// # Example
+/// ```
+/// use claude::{hook_fn, HookEvent, HookJSONOutput, HookManager};
+///
+/// #[hook_fn]
+/// async fn no_force_push(command: Option<String>) -> HookJSONOutput {
+///     match command {
+///         Some(c) if c.contains("push --force") => HookJSONOutput::block("Force pushes are not allowed"),
+///         _ => HookJSONOutput::default(),
+///     }
+/// }
+///
+/// let manager = HookManager::builder()
+///     .on(HookEvent::PreToolUse, "Bash", no_force_push())
+///     .build();
+/// ```
+pub use claude_macros::hook_fn;
+
+/// Turn a plain `async fn` into a function returning an [`McpTool`](mcp::McpTool)
+///
+/// Each parameter becomes a property of the tool's input schema, derived with
+/// `schemars`; `Option` parameters are optional and `///` comments on
+/// parameters become property descriptions. Arguments Claude sends are
+/// validated against the schema and deserialized into the parameter types (see
+/// [`McpTool::from_schema`](mcp::McpTool::from_schema)); malformed arguments
+/// produce an error result naming the problem.
+///
+/// The description comes from `description = "..."` or the function's doc
+/// comment, and the tool name from `name = "..."` or the function name. The
+/// function may return anything implementing
+/// [`IntoToolResult`](mcp::IntoToolResult), such as `String`, `ToolResult` or
+/// a `Result` of either; an `Err` is shown to Claude as an error result.
+///
+/// Requires the `tool-macro` cargo feature.
+///
+/// # Example
+/// ```
+/// use claude::mcp::SdkMcpServer;
+///
+/// #[claude::tool(description = "Add two numbers")]
+/// async fn add(a: f64, b: f64) -> claude::Result<String> {
+///     Ok((a + b).to_string())
+/// }
+///
+/// let server = SdkMcpServer::new("calc".to_string(), "1.0.0".to_string(), vec![add()]);
+/// ```
+#[cfg(feature = "tool-macro")]
+pub use claude_macros::tool;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde;
+    #[cfg(feature = "schemars")]
+    pub use schemars;
+}
+pub use types::{HookContext, HookEvent, HookInput, IntoHookEvent, HookJSONOutput, TodoItem, ToolInput, ToolPermissionContext, ToolUseView, PermissionResult};
+pub use types::{PermissionBehavior, PermissionRuleValue, PermissionUpdate, PermissionUpdateDestination, PermissionUpdateType};
+pub use types::{McpServerStatus, ModelInfo, ServerInfo, SlashCommand};
+pub use types::{OutgoingMessage, UserEnvelope};
 
 // MCP namespace
 pub mod mcp {
-    pub use crate::mcp_server::{SdkMcpServer, McpTool, ToolHandler, ToolResult, ToolResultContent, ImageSource};
+    pub use crate::mcp_server::{
+        AudioSource, ContextToolHandler, ImageSource, IntoToolResult, McpTool, Next, ProgressSink, SdkMcpServer,
+        SdkMcpServerBuilder, Tool, ToolAnnotations, ToolContext, ToolHandler, ToolLayer, ToolProgress, ToolResult,
+        ToolResultContent,
+    };
 }
 
 // Internal/advanced APIs
@@ -1,4 +1,4 @@
-/// Macros for simplified callback creation
+//! Macros for simplified callback creation
 
 /// Create a hook callback with simplified syntax
 ///
@@ -28,32 +28,27 @@
 /// ```
 #[macro_export]
 macro_rules! hook {
-    (|$input:ident, $tool_id:ident, $ctx:ident| $body:block) => {
-        std::sync::Arc::new(
+    (|$input:ident, $tool_id:ident, $ctx:ident| $body:block) => {{
+        let callback: $crate::HookCallback = std::sync::Arc::new(
             |$input: std::collections::HashMap<String, serde_json::Value>,
              $tool_id: Option<String>,
-             $ctx: $crate::HookContext| {
-                Box::pin(async move { $body })
+             $ctx: $crate::HookContext|
+             -> std::pin::Pin<
+                Box<dyn std::future::Future<Output = $crate::Result<$crate::HookJSONOutput>> + Send>,
+            > {
+                Box::pin(async move {
+                    let output: $crate::Result<$crate::HookJSONOutput> = $body;
+                    output
+                })
             },
-        )
-    };
+        );
+        callback
+    }};
     (|$input:ident, $tool_id:ident| $body:block) => {
-        std::sync::Arc::new(
-            |$input: std::collections::HashMap<String, serde_json::Value>,
-             $tool_id: Option<String>,
-             _ctx: $crate::HookContext| {
-                Box::pin(async move { $body })
-            },
-        )
+        $crate::hook!(|$input, $tool_id, _ctx| $body)
     };
     (|$input:ident| $body:block) => {
-        std::sync::Arc::new(
-            |$input: std::collections::HashMap<String, serde_json::Value>,
-             _tool_id: Option<String>,
-             _ctx: $crate::HookContext| {
-                Box::pin(async move { $body })
-            },
-        )
+        $crate::hook!(|$input, _tool_id, _ctx| $body)
     };
 }
 
@@ -90,43 +85,72 @@ macro_rules! hook {
 /// ```
 #[macro_export]
 macro_rules! permission_callback {
-    (|$tool:ident, $input:ident, $ctx:ident| $body:block) => {
-        std::sync::Arc::new(
+    (|$tool:ident, $input:ident, $ctx:ident| $body:block) => {{
+        let callback: $crate::CanUseToolCallback = std::sync::Arc::new(
             |$tool: String,
              $input: std::collections::HashMap<String, serde_json::Value>,
-             $ctx: $crate::ToolPermissionContext| {
-                Box::pin(async move { $body })
+             $ctx: $crate::ToolPermissionContext|
+             -> std::pin::Pin<
+                Box<dyn std::future::Future<Output = $crate::Result<$crate::PermissionResult>> + Send>,
+            > {
+                Box::pin(async move {
+                    let result: $crate::Result<$crate::PermissionResult> = $body;
+                    result
+                })
             },
-        )
-    };
+        );
+        callback
+    }};
     (|$tool:ident, $input:ident| $body:block) => {
-        std::sync::Arc::new(
-            |$tool: String,
-             $input: std::collections::HashMap<String, serde_json::Value>,
-             _ctx: $crate::ToolPermissionContext| {
-                Box::pin(async move { $body })
-            },
-        )
+        $crate::permission_callback!(|$tool, $input, _ctx| $body)
     };
     (|$tool:ident| $body:block) => {
-        std::sync::Arc::new(
-            |$tool: String,
-             _input: std::collections::HashMap<String, serde_json::Value>,
-             _ctx: $crate::ToolPermissionContext| {
-                Box::pin(async move { $body })
-            },
-        )
+        $crate::permission_callback!(|$tool, _input, _ctx| $body)
     };
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::types::{HookJSONOutput, PermissionResult};
+    use crate::types::{HookContext, HookInput, HookJSONOutput, PermissionResult};
+    use std::collections::HashMap;
+
+    #[crate::hook_fn]
+    async fn audit(input: HookInput, tool_use_id: Option<String>, command: Option<String>) -> crate::Result<HookJSONOutput> {
+        let tool = input.tool_name().unwrap_or_default().to_string();
+        Ok(HookJSONOutput {
+            system_message: Some(format!("{} {:?} {:?}", tool, tool_use_id, command)),
+            ..Default::default()
+        })
+    }
+
+    #[crate::hook_fn]
+    async fn needs_path(file_path: String, _ctx: HookContext) -> HookJSONOutput {
+        HookJSONOutput::block(file_path)
+    }
+
+    #[tokio::test]
+    async fn test_hook_fn_extracts_arguments() {
+        let input: HashMap<String, serde_json::Value> = serde_json::from_value(serde_json::json!({
+            "hook_event_name": "PreToolUse",
+            "tool_name": "Bash",
+            "tool_input": {"command": "ls"}
+        }))
+        .unwrap();
+
+        let output = audit()(input.clone(), Some("tool-1".to_string()), HookContext::default())
+            .await
+            .unwrap();
+        assert_eq!(output.system_message.as_deref(), Some("Bash Some(\"tool-1\") Some(\"ls\")"));
+
+        // A required field that is missing fails the hook
+        assert!(needs_path()(input, None, HookContext::default()).await.is_err());
+        let input = HashMap::from([("tool_input".to_string(), serde_json::json!({"file_path": "a.rs"}))]);
+        let output = needs_path()(input, None, HookContext::default()).await.unwrap();
+        assert_eq!(output.system_message.as_deref(), Some("a.rs"));
+    }
 
     #[test]
     fn test_hook_macro_compiles() {
-        use crate::Result;
-
         let _callback = hook!(|input| {
             let _ = input;
             Ok::<HookJSONOutput, crate::ClaudeSDKError>(HookJSONOutput::default())
@@ -140,8 +164,6 @@ mod tests {
 
     #[test]
     fn test_permission_callback_macro_compiles() {
-        use crate::Result;
-
         let _callback = permission_callback!(|tool| {
             if tool == "Bash" {
                 Ok::<PermissionResult, crate::ClaudeSDKError>(PermissionResult::deny("no bash".to_string()))
@@ -155,4 +177,64 @@ mod tests {
             Ok::<PermissionResult, crate::ClaudeSDKError>(PermissionResult::allow())
         });
     }
+
+    #[cfg(feature = "tool-macro")]
+    mod tool {
+        use crate::mcp::{ToolResult, ToolResultContent};
+        use serde_json::json;
+
+        /// Greet someone by name
+        #[crate::tool]
+        async fn greet(
+            /// Who to greet
+            name: String,
+            times: Option<u32>,
+        ) -> String {
+            vec![format!("Hello, {}!", name); times.unwrap_or(1) as usize].join(" ")
+        }
+
+        #[crate::tool(name = "divide", description = "Divide a by b")]
+        async fn checked_divide(a: f64, b: f64) -> Result<String, String> {
+            if b == 0.0 {
+                return Err("division by zero".to_string());
+            }
+            Ok((a / b).to_string())
+        }
+
+        fn text(result: &ToolResult) -> &str {
+            match &result.content[0] {
+                ToolResultContent::Text { text } => text,
+                other => panic!("unexpected content: {:?}", other),
+            }
+        }
+
+        #[test]
+        fn test_tool_schema() {
+            let tool = greet();
+            assert_eq!(tool.name, "greet");
+            assert_eq!(tool.description, "Greet someone by name");
+            assert_eq!(tool.input_schema["type"], "object");
+            assert_eq!(tool.input_schema["required"], json!(["name"]));
+            assert_eq!(tool.input_schema["properties"]["name"]["description"], "Who to greet");
+            assert!(tool.input_schema.get("$schema").is_none());
+
+            assert_eq!(checked_divide().name, "divide");
+        }
+
+        #[tokio::test]
+        async fn test_tool_calls_function() {
+            let args = |value: serde_json::Value| serde_json::from_value(value).unwrap();
+
+            let result = greet().execute(args(json!({"name": "Ada", "times": 2}))).await.unwrap();
+            assert_eq!(text(&result), "Hello, Ada! Hello, Ada!");
+
+            let result = checked_divide().execute(args(json!({"a": 1.0, "b": 0.0}))).await.unwrap();
+            assert_eq!(result.is_error, Some(true));
+            assert_eq!(text(&result), "division by zero");
+
+            let result = greet().execute(args(json!({"times": 2}))).await.unwrap();
+            assert_eq!(result.is_error, Some(true));
+            assert_eq!(text(&result), "Invalid arguments for greet: /name is required");
+        }
+    }
 }
@@ -0,0 +1,164 @@
+//! Pulling code out of Markdown responses
+//!
+//! Claude usually answers with fenced code blocks. [`code_blocks`] finds them
+//! the way a Markdown renderer would, and [`extract_first_json`] finds the
+//! first JSON value in a reply, fenced or not. Both are also available on
+//! message lists through [`MessageVecExt`](crate::MessageVecExt).
+//!
+//! # Example
+//! ```
+//! use claude::markdown::code_blocks;
+//!
+//! let reply = "Run this:\n\n```bash\ncargo test\n```\n";
+//! let blocks = code_blocks(reply);
+//! assert_eq!(blocks[0].language.as_deref(), Some("bash"));
+//! assert_eq!(blocks[0].content, "cargo test\n");
+//! ```
+
+use serde_json::Value;
+
+/// A fenced code block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// First word of the fence's info string, e.g. `rust` for ```` ```rust ````
+    pub language: Option<String>,
+    /// The lines between the fences, each ending with a newline
+    pub content: String,
+}
+
+/// Opening fence of the block being read
+struct Fence {
+    marker: char,
+    len: usize,
+    indent: usize,
+}
+
+/// All fenced code blocks in `text`, in order
+///
+/// Fences are runs of at least three backticks or tildes. A block ends at a
+/// fence of the same character that is at least as long, so a ```` ```` ````
+/// block can contain ```` ``` ```` lines. Fences may be indented, as in list
+/// items; that indentation is removed from the content lines. A block that is
+/// never closed runs to the end of the text.
+pub fn code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(Fence, CodeBlock)> = None;
+
+    for line in text.lines() {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        let trimmed = &line[indent..];
+
+        match open.take() {
+            None => {
+                let Some((marker, len)) = fence_of(trimmed) else {
+                    continue;
+                };
+                let info = trimmed[len..].trim();
+                // Backticks in the info string mean inline code, not a fence
+                if marker == '`' && info.contains('`') {
+                    continue;
+                }
+                let language = info.split_whitespace().next().map(str::to_string);
+                let fence = Fence { marker, len, indent };
+                open = Some((fence, CodeBlock { language, content: String::new() }));
+            }
+            Some((fence, mut block)) => {
+                let closes = fence_of(trimmed).is_some_and(|(marker, len)| {
+                    marker == fence.marker && len >= fence.len && trimmed[len..].trim().is_empty()
+                });
+                if closes {
+                    blocks.push(block);
+                    continue;
+                }
+                let strip = indent.min(fence.indent);
+                block.content.push_str(&line[strip..]);
+                block.content.push('\n');
+                open = Some((fence, block));
+            }
+        }
+    }
+
+    blocks.extend(open.map(|(_, block)| block));
+    blocks
+}
+
+/// The marker and length of the fence that `line` starts with
+fn fence_of(line: &str) -> Option<(char, usize)> {
+    let marker = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.len() - line.trim_start_matches(marker).len();
+    (len >= 3).then_some((marker, len))
+}
+
+/// The first JSON object or array in `text`
+///
+/// Code blocks are tried first, in order; then the text is scanned for the
+/// first `{` or `[` that starts a valid value. Text after the value is
+/// ignored.
+pub fn extract_first_json(text: &str) -> Option<Value> {
+    code_blocks(text)
+        .iter()
+        .find_map(|block| first_json_value(&block.content))
+        .or_else(|| first_json_value(text))
+}
+
+fn first_json_value(text: &str) -> Option<Value> {
+    text.match_indices(['{', '[']).find_map(|(start, _)| {
+        serde_json::Deserializer::from_str(&text[start..])
+            .into_iter::<Value>()
+            .next()?
+            .ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_code_blocks_with_languages() {
+        let text = "First:\n```rust\nfn main() {}\n```\nthen\n~~~\nplain\n\n~~~\n";
+        let blocks = code_blocks(text);
+        assert_eq!(
+            blocks,
+            [
+                CodeBlock {
+                    language: Some("rust".to_string()),
+                    content: "fn main() {}\n".to_string(),
+                },
+                CodeBlock {
+                    language: None,
+                    content: "plain\n\n".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_fences() {
+        let text = "````markdown\nExample:\n```python\nprint(1)\n```\n````\n```sh\nls\n";
+        let blocks = code_blocks(text);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].language.as_deref(), Some("markdown"));
+        assert_eq!(blocks[0].content, "Example:\n```python\nprint(1)\n```\n");
+        // Unclosed blocks run to the end
+        assert_eq!(blocks[1].content, "ls\n");
+    }
+
+    #[test]
+    fn test_indented_fences() {
+        let text = "1. Install:\n\n   ```bash\n   cargo add claude\n     --offline\n   ```\n```inline``` code is not a fence\n";
+        let blocks = code_blocks(text);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].language.as_deref(), Some("bash"));
+        assert_eq!(blocks[0].content, "cargo add claude\n  --offline\n");
+    }
+
+    #[test]
+    fn test_extract_first_json() {
+        assert_eq!(extract_first_json("Sure:\n```json\n{\"a\": 1}\n```\n{\"b\": 2}"), Some(json!({"a": 1})));
+        assert_eq!(extract_first_json("The list [1, 2] and {\"ok\": true}."), Some(json!([1, 2])));
+        assert_eq!(extract_first_json("Set {x} to {\"x\": [1]} now"), Some(json!({"x": [1]})));
+        assert_eq!(extract_first_json("no json here"), None);
+    }
+}
@@ -1,10 +1,16 @@
-use crate::errors::Result;
+use crate::cancel::CancelHandle;
+use crate::errors::{ClaudeSDKError, Result};
+use async_trait::async_trait;
+use futures::stream::{FuturesUnordered, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{watch, Semaphore};
 
 /// Type alias for async tool handler functions
 ///
@@ -18,13 +24,246 @@ pub type ToolHandler = Arc<
         + Sync,
 >;
 
+/// Tool handler that also receives a [`ToolContext`]
+pub type ContextToolHandler = Arc<
+    dyn Fn(HashMap<String, Value>, ToolContext) -> Pin<Box<dyn Future<Output = Result<ToolResult>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// Progress reported by a running tool
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolProgress {
+    pub tool_name: String,
+    /// Percent complete, from 0 to 100
+    pub progress: f64,
+    pub message: Option<String>,
+    /// Token the client attached to the call (`_meta.progressToken`)
+    pub progress_token: Option<Value>,
+}
+
+/// Receives progress reported through a [`ToolContext`]
+pub type ProgressSink = Arc<dyn Fn(ToolProgress) + Send + Sync>;
+
+/// Per-call handle passed to context-aware tool handlers
+///
+/// When the tool runs inside a connected client, reported progress is sent to
+/// the CLI as an MCP `notifications/progress` message and appears in the
+/// message stream as a `System` message of subtype `tool_progress`.
+/// Interrupting the client cancels the context; long-running handlers can
+/// check [`is_cancelled`](Self::is_cancelled) to stop early.
+#[derive(Clone, Default)]
+pub struct ToolContext {
+    tool_name: String,
+    progress_token: Option<Value>,
+    progress_sink: Option<ProgressSink>,
+    cancel: CancelHandle,
+}
+
+impl ToolContext {
+    /// Create a context for a call of `tool_name` that discards progress
+    pub fn new(tool_name: impl Into<String>) -> Self {
+        Self {
+            tool_name: tool_name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Send progress to `sink`, e.g. to drive a tool outside a client
+    pub fn with_progress_sink(mut self, sink: ProgressSink) -> Self {
+        self.progress_sink = Some(sink);
+        self
+    }
+
+    /// Cancel the call when `handle` is cancelled
+    pub fn with_cancel_handle(mut self, handle: CancelHandle) -> Self {
+        self.cancel = handle;
+        self
+    }
+
+    fn with_progress_token(mut self, token: Option<Value>) -> Self {
+        self.progress_token = token;
+        self
+    }
+
+    /// Name of the tool being called
+    pub fn tool_name(&self) -> &str {
+        &self.tool_name
+    }
+
+    /// Whether the call has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Wait until the call is cancelled
+    pub async fn cancelled(&self) {
+        self.cancel.cancelled().await
+    }
+
+    /// Report how far along the tool is, as a percentage from 0 to 100
+    ///
+    /// Inside a client, updates reported faster than the CLI takes them are
+    /// dropped rather than queued.
+    pub fn report_progress(&self, percent: f64, message: impl Into<String>) {
+        if let Some(ref sink) = self.progress_sink {
+            let message = message.into();
+            sink(ToolProgress {
+                tool_name: self.tool_name.clone(),
+                progress: percent.clamp(0.0, 100.0),
+                message: (!message.is_empty()).then_some(message),
+                progress_token: self.progress_token.clone(),
+            });
+        }
+    }
+}
+
+impl std::fmt::Debug for ToolContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolContext")
+            .field("tool_name", &self.tool_name)
+            .field("progress_token", &self.progress_token)
+            .field("cancelled", &self.is_cancelled())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A tool implemented by a type that carries its own state
+///
+/// Handlers borrow `self`, so database pools, HTTP clients and the like can
+/// be shared across calls and tools without cloning them into closures.
+/// Register with [`SdkMcpServer::with_tool`].
+///
+/// # Example
+/// ```
+/// use async_trait::async_trait;
+/// use claude::mcp::{SdkMcpServer, Tool, ToolResult};
+/// use serde_json::Value;
+/// use std::collections::HashMap;
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+///
+/// struct Counter {
+///     count: AtomicU64,
+/// }
+///
+/// #[async_trait]
+/// impl Tool for Counter {
+///     const NAME: &'static str = "count";
+///     const DESCRIPTION: &'static str = "Increment and return a counter";
+///
+///     fn schema() -> Value {
+///         serde_json::json!({"type": "object"})
+///     }
+///
+///     async fn call(&self, _args: HashMap<String, Value>) -> claude::Result<ToolResult> {
+///         let count = self.count.fetch_add(1, Ordering::SeqCst) + 1;
+///         Ok(ToolResult::text(count.to_string()))
+///     }
+/// }
+///
+/// let server = SdkMcpServer::new("stats".to_string(), "1.0.0".to_string(), vec![])
+///     .with_tool(Arc::new(Counter { count: AtomicU64::new(0) }));
+/// ```
+#[async_trait]
+pub trait Tool: Send + Sync + 'static {
+    /// Name Claude calls the tool by
+    const NAME: &'static str;
+    /// What the tool does, shown to Claude
+    const DESCRIPTION: &'static str;
+
+    /// JSON schema of the tool's arguments
+    fn schema() -> Value;
+
+    /// Hints about the tool's behavior; none by default
+    fn annotations() -> ToolAnnotations {
+        ToolAnnotations::default()
+    }
+
+    /// Run the tool
+    async fn call(&self, args: HashMap<String, Value>) -> Result<ToolResult>;
+
+    /// Run the tool with access to its [`ToolContext`]
+    ///
+    /// Override this instead of [`call`](Self::call) to report progress; the
+    /// default ignores the context.
+    async fn call_with_context(&self, args: HashMap<String, Value>, _context: ToolContext) -> Result<ToolResult> {
+        self.call(args).await
+    }
+}
+
+/// Hints about how a tool behaves, sent to the CLI as MCP tool annotations
+///
+/// Hints are not enforced. They are also passed to permission callbacks in
+/// [`ToolPermissionContext::tool_annotations`](crate::types::ToolPermissionContext::tool_annotations),
+/// e.g. to allow read-only tools without asking.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    /// Human-readable title
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The tool does not modify its environment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    /// The tool may make destructive updates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    /// Repeating a call with the same arguments has no further effect
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    /// The tool talks to the outside world, e.g. the web
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+}
+
+impl ToolAnnotations {
+    /// Annotations of a tool that only reads
+    pub fn read_only() -> Self {
+        Self {
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            ..Default::default()
+        }
+    }
+
+    /// Annotations of a tool that may destroy data
+    pub fn destructive() -> Self {
+        Self {
+            read_only_hint: Some(false),
+            destructive_hint: Some(true),
+            ..Default::default()
+        }
+    }
+
+    /// Set the idempotent hint
+    pub fn idempotent(mut self, idempotent: bool) -> Self {
+        self.idempotent_hint = Some(idempotent);
+        self
+    }
+
+    /// Set the open-world hint
+    pub fn open_world(mut self, open_world: bool) -> Self {
+        self.open_world_hint = Some(open_world);
+        self
+    }
+
+    /// Whether no hint is set
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 /// Tool definition for MCP servers
 #[derive(Clone)]
 pub struct McpTool {
     pub name: String,
     pub description: String,
     pub input_schema: Value,
-    handler: ToolHandler,
+    pub annotations: ToolAnnotations,
+    handler: ContextToolHandler,
+    timeout: Option<Duration>,
+    concurrency: Option<Arc<Semaphore>>,
 }
 
 impl std::fmt::Debug for McpTool {
@@ -33,6 +272,8 @@ impl std::fmt::Debug for McpTool {
             .field("name", &self.name)
             .field("description", &self.description)
             .field("input_schema", &self.input_schema)
+            .field("annotations", &self.annotations)
+            .field("timeout", &self.timeout)
             .finish()
     }
 }
@@ -45,22 +286,245 @@ impl McpTool {
         input_schema: Value,
         handler: ToolHandler,
     ) -> Self {
+        Self::with_context(name, description, input_schema, Arc::new(move |args, _context| handler(args)))
+    }
+
+    /// Create a tool whose handler also receives a [`ToolContext`]
+    ///
+    /// # Example
+    /// ```
+    /// use claude::mcp::{McpTool, ToolResult};
+    /// use std::sync::Arc;
+    ///
+    /// let build = McpTool::with_context(
+    ///     "build".to_string(),
+    ///     "Build the project".to_string(),
+    ///     serde_json::json!({"type": "object"}),
+    ///     Arc::new(|_args, context| {
+    ///         Box::pin(async move {
+    ///             context.report_progress(50.0, "Compiling");
+    ///             Ok(ToolResult::text("Built".to_string()))
+    ///         })
+    ///     }),
+    /// );
+    /// ```
+    pub fn with_context(name: String, description: String, input_schema: Value, handler: ContextToolHandler) -> Self {
         Self {
             name,
             description,
             input_schema,
+            annotations: ToolAnnotations::default(),
             handler,
+            timeout: None,
+            concurrency: None,
         }
     }
 
+    /// Attach behavior hints, e.g. [`ToolAnnotations::read_only`]
+    pub fn with_annotations(mut self, annotations: ToolAnnotations) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
+    /// Fail calls that run longer than `timeout` with an error result
+    ///
+    /// Overrides the server's [default timeout](SdkMcpServer::with_default_timeout).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Run at most `max` calls of this tool at once, queueing the rest
+    ///
+    /// Clones of the tool share the limit.
+    pub fn with_max_concurrency(mut self, max: usize) -> Self {
+        self.concurrency = Some(Arc::new(Semaphore::new(max)));
+        self
+    }
+
+    /// Timeout set with [`with_timeout`](Self::with_timeout)
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Create a tool whose handler takes deserialized arguments
+    ///
+    /// Arguments that do not deserialize into `T` are answered with an error
+    /// result describing the problem, without calling `handler`.
+    ///
+    /// # Example
+    /// ```
+    /// use claude::mcp::{McpTool, ToolResult};
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct AddArgs {
+    ///     a: f64,
+    ///     b: f64,
+    /// }
+    ///
+    /// let add = McpTool::typed(
+    ///     "add",
+    ///     "Add two numbers",
+    ///     serde_json::json!({
+    ///         "type": "object",
+    ///         "properties": {"a": {"type": "number"}, "b": {"type": "number"}},
+    ///         "required": ["a", "b"]
+    ///     }),
+    ///     |args: AddArgs| async move { Ok(ToolResult::text((args.a + args.b).to_string())) },
+    /// );
+    /// ```
+    pub fn typed<T, F, Fut>(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        input_schema: Value,
+        handler: F,
+    ) -> Self
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ToolResult>> + Send + 'static,
+    {
+        let name = name.into();
+        let handler = Arc::new(handler);
+        let tool_name = name.clone();
+
+        Self::new(
+            name,
+            description.into(),
+            input_schema,
+            Arc::new(move |args| {
+                let args = parse_tool_args::<T>(&tool_name, args);
+                let handler = Arc::clone(&handler);
+                Box::pin(async move {
+                    match args {
+                        Ok(args) => handler(args).await,
+                        Err(result) => Ok(result),
+                    }
+                })
+            }),
+        )
+    }
+
+    /// Create a tool whose input schema is derived from its argument type
+    ///
+    /// Like [`typed`](Self::typed), but `input_schema` comes from `T`'s
+    /// [`JsonSchema`](schemars::JsonSchema) implementation, and arguments are
+    /// checked against it (types, required properties, enums, numeric and
+    /// length bounds) before `handler` runs. Requires the `schemars` feature.
+    ///
+    /// # Example
+    /// ```
+    /// use claude::mcp::{McpTool, ToolResult};
+    /// use schemars::JsonSchema;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize, JsonSchema)]
+    /// struct SearchArgs {
+    ///     /// Text to look for
+    ///     query: String,
+    ///     limit: Option<u8>,
+    /// }
+    ///
+    /// let search = McpTool::from_schema("search", "Search the docs", |args: SearchArgs| async move {
+    ///     Ok(ToolResult::text(format!("{} (limit {:?})", args.query, args.limit)))
+    /// });
+    /// assert_eq!(search.input_schema["required"], serde_json::json!(["query"]));
+    /// ```
+    #[cfg(feature = "schemars")]
+    pub fn from_schema<T, F, Fut>(name: impl Into<String>, description: impl Into<String>, handler: F) -> Self
+    where
+        T: schemars::JsonSchema + serde::de::DeserializeOwned + Send + 'static,
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ToolResult>> + Send + 'static,
+    {
+        let schema = input_schema_for::<T>();
+        let mut tool = Self::typed(name, description, schema.clone(), handler);
+
+        let inner = Arc::clone(&tool.handler);
+        let tool_name = tool.name.clone();
+        tool.handler = Arc::new(move |args, context| {
+            let value = Value::Object(args.iter().map(|(k, v)| (k.clone(), v.clone())).collect());
+            match validate_schema(&value, &schema, &schema, "") {
+                Ok(()) => inner(args, context),
+                Err(problem) => {
+                    let result = ToolResult::error(format!("Invalid arguments for {}: {}", tool_name, problem));
+                    Box::pin(async move { Ok(result) })
+                }
+            }
+        });
+        tool
+    }
+
+    /// Create a tool backed by a [`Tool`] implementation
+    pub fn from_tool<T: Tool>(tool: Arc<T>) -> Self {
+        Self::with_context(
+            T::NAME.to_string(),
+            T::DESCRIPTION.to_string(),
+            T::schema(),
+            Arc::new(move |args, context| {
+                let tool = Arc::clone(&tool);
+                Box::pin(async move { tool.call_with_context(args, context).await })
+            }),
+        )
+        .with_annotations(T::annotations())
+    }
+
     /// Execute the tool with given arguments
     pub async fn execute(&self, args: HashMap<String, Value>) -> Result<ToolResult> {
-        (self.handler)(args).await
+        self.execute_with_context(args, ToolContext::new(self.name.clone())).await
+    }
+
+    /// Execute the tool with a caller-provided context
+    ///
+    /// Returns an error result if the tool's timeout expires or the context is
+    /// cancelled before the handler finishes.
+    pub async fn execute_with_context(&self, args: HashMap<String, Value>, context: ToolContext) -> Result<ToolResult> {
+        self.run(args, context, self.timeout, None).await
+    }
+
+    async fn run(
+        &self,
+        args: HashMap<String, Value>,
+        context: ToolContext,
+        timeout: Option<Duration>,
+        server_limit: Option<&Semaphore>,
+    ) -> Result<ToolResult> {
+        let cancel = context.cancel.clone();
+        let timed = async {
+            // Queue for a slot on the server, then on the tool; the timeout
+            // starts once the call runs
+            let _server_permit = match server_limit {
+                Some(limit) => limit.acquire().await.ok(),
+                None => None,
+            };
+            let _tool_permit = match self.concurrency {
+                Some(ref limit) => limit.acquire().await.ok(),
+                None => None,
+            };
+            let call = (self.handler)(args, context);
+            match timeout {
+                Some(limit) => tokio::time::timeout(limit, call).await.unwrap_or_else(|_| {
+                    Ok(ToolResult::error(format!("Tool {} timed out after {:?}", self.name, limit)))
+                }),
+                None => call.await,
+            }
+        };
+
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => Ok(ToolResult::error(format!("Tool {} was cancelled", self.name))),
+            result = timed => result,
+        }
     }
 }
 
 /// Result from a tool execution
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// [`Json`](ToolResultContent::Json) blocks are sent as MCP structured
+/// content: the first one becomes `structuredContent`, and each is also sent
+/// as a text block holding the serialized JSON for clients that ignore it.
+#[derive(Debug, Clone, Deserialize)]
 pub struct ToolResult {
     pub content: Vec<ToolResultContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -91,6 +555,258 @@ impl ToolResult {
             is_error: None,
         }
     }
+
+    /// Create a structured JSON result
+    pub fn json(value: Value) -> Self {
+        Self::with_content(vec![ToolResultContent::Json { value }])
+    }
+
+    /// Create a result pointing at a resource Claude can read
+    pub fn resource_link(uri: impl Into<String>, name: impl Into<String>) -> Self {
+        Self::with_content(vec![ToolResultContent::ResourceLink {
+            uri: uri.into(),
+            name: name.into(),
+        }])
+    }
+
+    /// Create an audio result from base64-encoded data, e.g. `audio/wav`
+    pub fn audio(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        Self::with_content(vec![ToolResultContent::Audio {
+            source: AudioSource {
+                source_type: "base64".to_string(),
+                media_type: media_type.into(),
+                data: data.into(),
+            },
+        }])
+    }
+}
+
+impl Serialize for ToolResult {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let structured = self.content.iter().find_map(|block| match block {
+            ToolResultContent::Json { value } => Some(value),
+            _ => None,
+        });
+        let content: Vec<ToolResultContent> = self
+            .content
+            .iter()
+            .map(|block| match block {
+                ToolResultContent::Json { value } => ToolResultContent::Text { text: value.to_string() },
+                other => other.clone(),
+            })
+            .collect();
+
+        let mut state = serializer.serialize_struct("ToolResult", 3)?;
+        state.serialize_field("content", &content)?;
+        if let Some(structured) = structured {
+            state.serialize_field("structuredContent", structured)?;
+        }
+        if let Some(is_error) = self.is_error {
+            state.serialize_field("is_error", &is_error)?;
+        }
+        state.end()
+    }
+}
+
+/// Values an MCP tool function may return
+///
+/// Used by `#[claude::tool]`: text becomes a text result, and an `Err` becomes
+/// an error result showing the error message to Claude.
+pub trait IntoToolResult {
+    fn into_tool_result(self) -> ToolResult;
+}
+
+impl IntoToolResult for ToolResult {
+    fn into_tool_result(self) -> ToolResult {
+        self
+    }
+}
+
+impl IntoToolResult for String {
+    fn into_tool_result(self) -> ToolResult {
+        ToolResult::text(self)
+    }
+}
+
+impl IntoToolResult for &str {
+    fn into_tool_result(self) -> ToolResult {
+        ToolResult::text(self.to_string())
+    }
+}
+
+impl IntoToolResult for Value {
+    fn into_tool_result(self) -> ToolResult {
+        ToolResult::json(self)
+    }
+}
+
+impl<T: IntoToolResult, E: std::fmt::Display> IntoToolResult for std::result::Result<T, E> {
+    fn into_tool_result(self) -> ToolResult {
+        match self {
+            Ok(value) => value.into_tool_result(),
+            Err(e) => ToolResult::error(e.to_string()),
+        }
+    }
+}
+
+/// Deserialize tool arguments, or describe why they are malformed
+#[doc(hidden)]
+pub fn parse_tool_args<T: serde::de::DeserializeOwned>(
+    tool_name: &str,
+    args: HashMap<String, Value>,
+) -> std::result::Result<T, ToolResult> {
+    let args = Value::Object(args.into_iter().collect());
+    serde_json::from_value(args)
+        .map_err(|e| ToolResult::error(format!("Invalid arguments for {}: {}", tool_name, e)))
+}
+
+/// JSON schema of a tool's argument type, as used for `inputSchema`
+#[cfg(feature = "schemars")]
+pub(crate) fn input_schema_for<T: schemars::JsonSchema>() -> Value {
+    let mut schema = schemars::SchemaGenerator::default()
+        .into_root_schema_for::<T>()
+        .to_value();
+    if let Some(object) = schema.as_object_mut() {
+        object.remove("$schema");
+        object.remove("title");
+    }
+    schema
+}
+
+/// Check `value` against the subset of JSON Schema that `schemars` emits
+///
+/// `path` is the JSON pointer of `value`, used in the returned problem.
+#[cfg(feature = "schemars")]
+fn validate_schema(value: &Value, schema: &Value, root: &Value, path: &str) -> std::result::Result<(), String> {
+    let location = if path.is_empty() { "arguments" } else { path };
+    let schema = match schema {
+        Value::Bool(true) => return Ok(()),
+        Value::Bool(false) => return Err(format!("{} is not allowed", location)),
+        Value::Object(schema) => schema,
+        _ => return Ok(()),
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let target = reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+            .ok_or_else(|| format!("unresolved schema reference {}", reference))?;
+        validate_schema(value, target, root, path)?;
+    }
+
+    if let Some(types) = schema.get("type") {
+        let allowed: Vec<&str> = match types {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let matches = |name: &str| match name {
+            "null" => value.is_null(),
+            "boolean" => value.is_boolean(),
+            "string" => value.is_string(),
+            "array" => value.is_array(),
+            "object" => value.is_object(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+            _ => true,
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|name| matches(name)) {
+            return Err(format!("{} should be of type {}, got {}", location, allowed.join(" or "), value));
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            return Err(format!("{} should be one of {}, got {}", location, Value::Array(options.clone()), value));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            return Err(format!("{} should be {}, got {}", location, expected, value));
+        }
+    }
+
+    for (keyword, all) in [("allOf", true), ("anyOf", false), ("oneOf", false)] {
+        if let Some(branches) = schema.get(keyword).and_then(Value::as_array) {
+            let results: Vec<_> = branches
+                .iter()
+                .map(|branch| validate_schema(value, branch, root, path))
+                .collect();
+            let passed = results.iter().filter(|result| result.is_ok()).count();
+            let ok = match keyword {
+                "oneOf" => passed == 1,
+                _ if all => passed == branches.len(),
+                _ => passed > 0,
+            };
+            if !ok {
+                return match results.into_iter().find_map(|result| result.err()) {
+                    Some(problem) if all || branches.len() == 1 => Err(problem),
+                    _ => Err(format!("{} does not match any allowed schema", location)),
+                };
+            }
+        }
+    }
+
+    if let Some(number) = value.as_f64() {
+        let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+        if bound("minimum").is_some_and(|min| number < min)
+            || bound("maximum").is_some_and(|max| number > max)
+            || bound("exclusiveMinimum").is_some_and(|min| number <= min)
+            || bound("exclusiveMaximum").is_some_and(|max| number >= max)
+        {
+            return Err(format!("{} is out of range: {}", location, value));
+        }
+    }
+
+    if let Some(text) = value.as_str() {
+        let length = text.chars().count() as u64;
+        let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_u64);
+        if bound("minLength").is_some_and(|min| length < min) || bound("maxLength").is_some_and(|max| length > max) {
+            return Err(format!("{} has an invalid length", location));
+        }
+    }
+
+    if let Some(items) = value.as_array() {
+        let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_u64);
+        let count = items.len() as u64;
+        if bound("minItems").is_some_and(|min| count < min) || bound("maxItems").is_some_and(|max| count > max) {
+            return Err(format!("{} has an invalid number of items", location));
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                validate_schema(item, item_schema, root, &format!("{}/{}", path, index))?;
+            }
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            if let Some(missing) = required
+                .iter()
+                .filter_map(Value::as_str)
+                .find(|name| !object.contains_key(*name))
+            {
+                return Err(format!("{}/{} is required", path, missing));
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, item) in object {
+            let item_path = format!("{}/{}", path, name);
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(property) => validate_schema(item, property, root, &item_path)?,
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => return Err(format!("{} is not allowed", item_path)),
+                    Some(additional) => validate_schema(item, additional, root, &item_path)?,
+                    None => {}
+                },
+            }
+        }
+    }
+
+    Ok(())
 }
 
 /// Content block in a tool result
@@ -104,8 +820,19 @@ pub enum ToolResultContent {
         #[serde(rename = "source")]
         source: ImageSource,
     },
+    /// A resource Claude can read, identified by URI
+    #[serde(rename = "resource_link")]
+    ResourceLink { uri: String, name: String },
+    /// Structured JSON, see [`ToolResult`] for how it is sent
+    #[serde(rename = "json")]
+    Json { value: Value },
+    #[serde(rename = "audio")]
+    Audio { source: AudioSource },
 }
 
+/// Audio source for tool results; same shape as [`ImageSource`]
+pub type AudioSource = ImageSource;
+
 /// Image source for tool results
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageSource {
@@ -115,23 +842,111 @@ pub struct ImageSource {
     pub data: String,
 }
 
+/// Middleware run around every tool call of an [`SdkMcpServer`]
+///
+/// Receives the tool name, the call's arguments and the rest of the chain.
+pub type ToolLayer = Arc<
+    dyn Fn(String, HashMap<String, Value>, Next) -> Pin<Box<dyn Future<Output = Result<ToolResult>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// The remaining layers and the tool itself, as seen by a [`ToolLayer`]
+pub struct Next {
+    layers: Arc<Vec<ToolLayer>>,
+    index: usize,
+    tool: McpTool,
+    context: ToolContext,
+    timeout: Option<Duration>,
+    server_limit: Option<Arc<Semaphore>>,
+}
+
+impl Next {
+    /// Context of the call being handled
+    pub fn context(&self) -> &ToolContext {
+        &self.context
+    }
+
+    /// Run the rest of the chain with `args`
+    pub async fn run(self, args: HashMap<String, Value>) -> Result<ToolResult> {
+        match self.layers.get(self.index).cloned() {
+            Some(layer) => {
+                let tool_name = self.tool.name.clone();
+                layer(tool_name, args, Next { index: self.index + 1, ..self }).await
+            }
+            None => {
+                let server_limit = self.server_limit.as_deref();
+                self.tool.run(args, self.context, self.timeout, server_limit).await
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Next {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Next")
+            .field("tool", &self.tool.name)
+            .field("remaining_layers", &(self.layers.len() - self.index))
+            .finish()
+    }
+}
+
 /// SDK MCP Server - in-process MCP server
 ///
 /// SDK MCP servers run within the same process as your application,
 /// eliminating IPC overhead and simplifying deployment.
+///
+/// Clones share their tools, so tools added with [`add_tool`](Self::add_tool)
+/// after the server was passed to a client become available to Claude.
 #[derive(Clone)]
 pub struct SdkMcpServer {
     pub name: String,
     pub version: String,
-    tools: Arc<HashMap<String, McpTool>>,
+    tools: Arc<RwLock<HashMap<String, McpTool>>>,
+    // Bumped on every add or remove; connected clients notify the CLI
+    tools_changed: Arc<watch::Sender<u64>>,
+    default_timeout: Option<Duration>,
+    concurrency: Option<Arc<Semaphore>>,
+    layers: Arc<Vec<ToolLayer>>,
 }
 
 impl SdkMcpServer {
+    /// Start building a server that validates its tools
+    ///
+    /// # Example
+    /// ```
+    /// use claude::mcp::{McpTool, SdkMcpServer, ToolResult};
+    /// use std::sync::Arc;
+    ///
+    /// let ping = McpTool::new(
+    ///     "ping".to_string(),
+    ///     "Reply with pong".to_string(),
+    ///     serde_json::json!({"type": "object"}),
+    ///     Arc::new(|_args| Box::pin(async { Ok(ToolResult::text("pong".to_string())) })),
+    /// );
+    ///
+    /// let server = SdkMcpServer::builder("my-tools")
+    ///     .version("2.1.0")
+    ///     .tool(ping)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(server.version, "2.1.0");
+    /// ```
+    pub fn builder(name: impl Into<String>) -> SdkMcpServerBuilder {
+        SdkMcpServerBuilder {
+            name: name.into(),
+            version: "1.0.0".to_string(),
+            tools: Vec::new(),
+            default_timeout: None,
+            max_concurrent_tools: None,
+        }
+    }
+
     /// Create a new SDK MCP server
     ///
     /// # Example
     /// ```
-    /// use claude::{SdkMcpServer, McpTool, ToolResult};
+    /// use claude::mcp::{SdkMcpServer, McpTool, ToolResult};
     /// use std::sync::Arc;
     /// use std::collections::HashMap;
     ///
@@ -167,21 +982,130 @@ impl SdkMcpServer {
             tool_map.insert(tool.name.clone(), tool);
         }
 
-        Self {
-            name,
-            version,
-            tools: Arc::new(tool_map),
+        Self {
+            name,
+            version,
+            tools: Arc::new(RwLock::new(tool_map)),
+            tools_changed: Arc::new(watch::Sender::new(0)),
+            default_timeout: None,
+            concurrency: None,
+            layers: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Time out calls of tools without their own [`McpTool::with_timeout`]
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    /// Run at most `max` tool calls at once, queueing the rest
+    ///
+    /// Applies across all tools of the server and its clones, on top of any
+    /// per-tool [`McpTool::with_max_concurrency`] limit.
+    pub fn with_max_concurrent_tools(mut self, max: usize) -> Self {
+        self.concurrency = Some(Arc::new(Semaphore::new(max)));
+        self
+    }
+
+    /// Run `layer` around every tool call, e.g. for logging or metrics
+    ///
+    /// Layers run in the order they are added, so the first one added sees
+    /// the call first and the result last. A layer may change the arguments
+    /// passed to `next`, rewrite the result, or answer without calling the
+    /// tool at all.
+    ///
+    /// # Example
+    /// ```
+    /// use claude::mcp::SdkMcpServer;
+    ///
+    /// let server = SdkMcpServer::new("tools".to_string(), "1.0.0".to_string(), vec![])
+    ///     .with_layer(|tool_name, mut args, next| async move {
+    ///         if args.contains_key("api_key") {
+    ///             args.insert("api_key".to_string(), serde_json::json!("<redacted>"));
+    ///         }
+    ///         println!("calling {} with {:?}", tool_name, args);
+    ///         next.run(args).await
+    ///     });
+    /// ```
+    pub fn with_layer<F, Fut>(mut self, layer: F) -> Self
+    where
+        F: Fn(String, HashMap<String, Value>, Next) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ToolResult>> + Send + 'static,
+    {
+        let layer: ToolLayer = Arc::new(move |tool_name, args, next| Box::pin(layer(tool_name, args, next)));
+        Arc::make_mut(&mut self.layers).push(layer);
+        self
+    }
+
+    /// Add a [`Tool`] implementation, replacing any tool with the same name
+    ///
+    /// Takes `Arc<T>` rather than `Arc<dyn Tool>` because the name and schema
+    /// are associated items; keep a clone of the `Arc` to share the state.
+    pub fn with_tool<T: Tool>(self, tool: Arc<T>) -> Self {
+        self.add_tool(McpTool::from_tool(tool));
+        self
+    }
+
+    /// Register a tool at runtime, replacing any tool with the same name
+    ///
+    /// Connected clients send the CLI a `notifications/tools/list_changed`
+    /// message so Claude picks up the new tool list.
+    pub fn add_tool(&self, tool: McpTool) {
+        self.tools.write().unwrap().insert(tool.name.clone(), tool);
+        self.tools_changed.send_modify(|version| *version += 1);
+    }
+
+    /// Unregister a tool at runtime, returning it if it was registered
+    pub fn remove_tool(&self, name: &str) -> Option<McpTool> {
+        let removed = self.tools.write().unwrap().remove(name);
+        if removed.is_some() {
+            self.tools_changed.send_modify(|version| *version += 1);
         }
+        removed
     }
 
     /// Get a tool by name
-    pub fn get_tool(&self, name: &str) -> Option<&McpTool> {
-        self.tools.get(name)
+    pub fn get_tool(&self, name: &str) -> Option<McpTool> {
+        self.tools.read().unwrap().get(name).cloned()
     }
 
     /// List all available tools
-    pub fn list_tools(&self) -> Vec<&McpTool> {
-        self.tools.values().collect()
+    pub fn list_tools(&self) -> Vec<McpTool> {
+        self.tools.read().unwrap().values().cloned().collect()
+    }
+
+    /// Resolves each time the tool list changes after subscribing
+    pub(crate) fn subscribe_tool_changes(&self) -> watch::Receiver<u64> {
+        self.tools_changed.subscribe()
+    }
+
+    fn tool_definitions(&self) -> Vec<Value> {
+        self.tools
+            .read()
+            .unwrap()
+            .values()
+            .map(|tool| {
+                let mut definition = serde_json::json!({
+                    "name": tool.name,
+                    "description": tool.description,
+                    "inputSchema": tool.input_schema,
+                });
+                if !tool.annotations.is_empty() {
+                    definition["annotations"] = serde_json::json!(tool.annotations);
+                }
+                definition
+            })
+            .collect()
+    }
+
+    /// Annotations of the tool Claude knows as `mcp__{server}__{tool}`
+    pub(crate) fn annotations_for(servers: &HashMap<String, SdkMcpServer>, qualified_name: &str) -> Option<ToolAnnotations> {
+        let rest = qualified_name.strip_prefix("mcp__")?;
+        servers.iter().find_map(|(server_name, server)| {
+            let tool_name = rest.strip_prefix(server_name.as_str())?.strip_prefix("__")?;
+            server.tools.read().unwrap().get(tool_name).map(|tool| tool.annotations.clone())
+        })
     }
 
     /// Execute a tool by name
@@ -189,16 +1113,219 @@ impl SdkMcpServer {
         &self,
         tool_name: &str,
         args: HashMap<String, Value>,
+    ) -> Result<ToolResult> {
+        self.execute_tool_with_context(tool_name, args, ToolContext::new(tool_name)).await
+    }
+
+    /// Execute a tool by name with a caller-provided context
+    pub async fn execute_tool_with_context(
+        &self,
+        tool_name: &str,
+        args: HashMap<String, Value>,
+        context: ToolContext,
     ) -> Result<ToolResult> {
         let tool = self
-            .tools
-            .get(tool_name)
+            .get_tool(tool_name)
             .ok_or_else(|| crate::errors::ClaudeSDKError::message_parse_error(
                 format!("Tool not found: {}", tool_name),
                 None
             ))?;
 
-        tool.execute(args).await
+        Next {
+            layers: Arc::clone(&self.layers),
+            index: 0,
+            timeout: tool.timeout.or(self.default_timeout),
+            server_limit: self.concurrency.clone(),
+            tool,
+            context,
+        }
+        .run(args)
+        .await
+    }
+
+    /// Handle an MCP JSON-RPC message addressed to this server
+    ///
+    /// Supports `initialize`, `tools/list`, and `tools/call`. Notifications
+    /// are acknowledged with an empty result and unknown methods produce a
+    /// JSON-RPC "method not found" error.
+    pub async fn handle_message(&self, message: &Value) -> Value {
+        self.handle(message, None, None).await
+    }
+
+    /// Like [`handle_message`](Self::handle_message), sending progress
+    /// reported by the called tool to `sink`
+    pub async fn handle_message_with_progress(&self, message: &Value, sink: ProgressSink) -> Value {
+        self.handle(message, Some(sink), None).await
+    }
+
+    /// Serve the tools as a standalone MCP server over stdin and stdout
+    ///
+    /// Lets MCP clients such as Claude Desktop launch your binary as a stdio
+    /// server. Returns once stdin is closed and pending calls have answered.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use claude::mcp::SdkMcpServer;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> claude::Result<()> {
+    ///     let server = SdkMcpServer::builder("my-tools").build()?;
+    ///     server.serve_stdio().await
+    /// }
+    /// ```
+    pub async fn serve_stdio(&self) -> Result<()> {
+        self.serve(tokio::io::BufReader::new(tokio::io::stdin()), tokio::io::stdout()).await
+    }
+
+    /// Serve newline-delimited MCP JSON-RPC read from `reader`, answering on `writer`
+    ///
+    /// Calls run concurrently. Progress reported by tools is sent as
+    /// `notifications/progress` when the call has a progress token, and tool
+    /// list changes as `notifications/tools/list_changed`.
+    pub async fn serve<R, W>(&self, reader: R, mut writer: W) -> Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut lines = reader.lines();
+        let mut changes = self.subscribe_tool_changes();
+        let (notify_tx, mut notify_rx) = tokio::sync::mpsc::unbounded_channel::<Value>();
+        let sink: ProgressSink = Arc::new(move |progress| {
+            if let Some(token) = progress.progress_token {
+                let _ = notify_tx.send(serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/progress",
+                    "params": {
+                        "progressToken": token,
+                        "progress": progress.progress,
+                        "total": 100.0,
+                        "message": progress.message,
+                    }
+                }));
+            }
+        });
+        let mut calls = FuturesUnordered::new();
+        let mut reading = true;
+
+        while reading || !calls.is_empty() {
+            let outgoing = tokio::select! {
+                line = lines.next_line(), if reading => {
+                    let line = line.map_err(|e| ClaudeSDKError::io_error("Failed to read MCP request", e))?;
+                    let Some(line) = line else {
+                        reading = false;
+                        continue;
+                    };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match serde_json::from_str::<Value>(&line) {
+                        Ok(message) => {
+                            let server = self.clone();
+                            let sink = Arc::clone(&sink);
+                            calls.push(async move {
+                                // Notifications get no response
+                                let is_request = message.get("id").is_some();
+                                let response = server.handle(&message, Some(sink), None).await;
+                                is_request.then_some(response)
+                            });
+                            continue;
+                        }
+                        Err(e) => serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": null,
+                            "error": {"code": -32700, "message": format!("Parse error: {}", e)}
+                        }),
+                    }
+                }
+                Some(response) = calls.next() => match response {
+                    Some(response) => response,
+                    None => continue,
+                },
+                Some(notification) = notify_rx.recv() => notification,
+                Ok(()) = changes.changed() => serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/tools/list_changed"
+                }),
+            };
+            write_line(&mut writer, &outgoing).await?;
+        }
+
+        while let Ok(notification) = notify_rx.try_recv() {
+            write_line(&mut writer, &notification).await?;
+        }
+        Ok(())
+    }
+
+    /// Handle a message, cancelling a called tool when `cancel` is cancelled
+    pub(crate) async fn handle(&self, message: &Value, sink: Option<ProgressSink>, cancel: Option<CancelHandle>) -> Value {
+        let id = message.get("id").cloned().unwrap_or(Value::Null);
+        let method = message.get("method").and_then(|v| v.as_str()).unwrap_or_default();
+
+        match method {
+            "initialize" => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {"tools": {"listChanged": true}},
+                    "serverInfo": {"name": self.name, "version": self.version}
+                }
+            }),
+            "tools/list" => {
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": {"tools": self.tool_definitions()}
+                })
+            }
+            "tools/call" => {
+                let params = message.get("params");
+                let name = params
+                    .and_then(|p| p.get("name"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default();
+                let args: HashMap<String, Value> = params
+                    .and_then(|p| p.get("arguments"))
+                    .and_then(|v| v.as_object())
+                    .map(|o| o.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+                    .unwrap_or_default();
+
+                let mut context = ToolContext::new(name).with_progress_token(
+                    params
+                        .and_then(|p| p.get("_meta"))
+                        .and_then(|meta| meta.get("progressToken"))
+                        .cloned(),
+                );
+                if let Some(sink) = sink {
+                    context = context.with_progress_sink(sink);
+                }
+                if let Some(cancel) = cancel {
+                    context = context.with_cancel_handle(cancel);
+                }
+
+                match self.execute_tool_with_context(name, args, context).await {
+                    Ok(result) => serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "result": result
+                    }),
+                    Err(e) => serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {"code": -32603, "message": e.to_string()}
+                    }),
+                }
+            }
+            _ if method.starts_with("notifications/") => serde_json::json!({
+                "jsonrpc": "2.0",
+                "result": {}
+            }),
+            _ => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": -32601, "message": format!("Method '{}' not found", method)}
+            }),
+        }
     }
 
     /// Get the server configuration for ClaudeAgentOptions
@@ -208,20 +1335,7 @@ impl SdkMcpServer {
         config.insert("version".to_string(), serde_json::json!(self.version));
         config.insert("type".to_string(), serde_json::json!("sdk"));
 
-        // Convert tools to JSON schema
-        let tools: Vec<Value> = self
-            .tools
-            .values()
-            .map(|tool| {
-                serde_json::json!({
-                    "name": tool.name,
-                    "description": tool.description,
-                    "inputSchema": tool.input_schema,
-                })
-            })
-            .collect();
-
-        config.insert("tools".to_string(), serde_json::json!(tools));
+        config.insert("tools".to_string(), serde_json::json!(self.tool_definitions()));
         config
     }
 }
@@ -231,15 +1345,656 @@ impl std::fmt::Debug for SdkMcpServer {
         f.debug_struct("SdkMcpServer")
             .field("name", &self.name)
             .field("version", &self.version)
-            .field("tools", &self.tools.keys().collect::<Vec<_>>())
+            .field("tools", &self.tools.read().unwrap().keys().collect::<Vec<_>>())
             .finish()
     }
 }
 
+async fn write_line<W: AsyncWrite + Unpin>(writer: &mut W, message: &Value) -> Result<()> {
+    let line = format!("{}\n", message);
+    let written = async {
+        writer.write_all(line.as_bytes()).await?;
+        writer.flush().await
+    };
+    written
+        .await
+        .map_err(|e| ClaudeSDKError::io_error("Failed to write MCP response", e))
+}
+
+/// Builder for [`SdkMcpServer`], created with [`SdkMcpServer::builder`]
+#[derive(Debug)]
+pub struct SdkMcpServerBuilder {
+    name: String,
+    version: String,
+    tools: Vec<McpTool>,
+    default_timeout: Option<Duration>,
+    max_concurrent_tools: Option<usize>,
+}
+
+impl SdkMcpServerBuilder {
+    /// Set the version reported to the CLI (default `1.0.0`)
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Add a tool
+    pub fn tool(mut self, tool: McpTool) -> Self {
+        self.tools.push(tool);
+        self
+    }
+
+    /// Add several tools
+    pub fn tools(mut self, tools: impl IntoIterator<Item = McpTool>) -> Self {
+        self.tools.extend(tools);
+        self
+    }
+
+    /// Add a [`Tool`] implementation
+    pub fn tool_impl<T: Tool>(self, tool: Arc<T>) -> Self {
+        self.tool(McpTool::from_tool(tool))
+    }
+
+    /// See [`SdkMcpServer::with_default_timeout`]
+    pub fn default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    /// See [`SdkMcpServer::with_max_concurrent_tools`]
+    pub fn max_concurrent_tools(mut self, max: usize) -> Self {
+        self.max_concurrent_tools = Some(max);
+        self
+    }
+
+    /// Build the server, checking its tools
+    ///
+    /// Fails if two tools share a name, a name is not 1-64 ASCII letters,
+    /// digits, `_` or `-`, or an input schema is not an object schema whose
+    /// `required` entries are declared properties.
+    pub fn build(self) -> Result<SdkMcpServer> {
+        let invalid = |message: String| crate::errors::ClaudeSDKError::invalid_mcp_server(&self.name, message);
+
+        let mut seen = std::collections::HashSet::new();
+        for tool in &self.tools {
+            if !seen.insert(tool.name.as_str()) {
+                return Err(invalid(format!("duplicate tool name '{}'", tool.name)));
+            }
+            let valid_name = (1..=64).contains(&tool.name.len())
+                && tool.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if !valid_name {
+                return Err(invalid(format!(
+                    "tool name '{}' must be 1-64 ASCII letters, digits, '_' or '-'",
+                    tool.name
+                )));
+            }
+            check_input_schema(&tool.input_schema)
+                .map_err(|problem| invalid(format!("tool '{}': {}", tool.name, problem)))?;
+        }
+
+        let mut server = SdkMcpServer::new(self.name, self.version, self.tools);
+        if let Some(timeout) = self.default_timeout {
+            server = server.with_default_timeout(timeout);
+        }
+        if let Some(max) = self.max_concurrent_tools {
+            server = server.with_max_concurrent_tools(max);
+        }
+        Ok(server)
+    }
+}
+
+/// Check that a tool's input schema describes an object
+fn check_input_schema(schema: &Value) -> std::result::Result<(), String> {
+    if schema.get("type").and_then(|t| t.as_str()) != Some("object") {
+        return Err("input schema must have \"type\": \"object\"".to_string());
+    }
+    let properties = match schema.get("properties") {
+        None => None,
+        Some(Value::Object(properties)) => Some(properties),
+        Some(_) => return Err("\"properties\" must be an object".to_string()),
+    };
+    let Some(required) = schema.get("required") else {
+        return Ok(());
+    };
+    let required = required
+        .as_array()
+        .ok_or_else(|| "\"required\" must be an array".to_string())?;
+    for field in required {
+        let field = field
+            .as_str()
+            .ok_or_else(|| "\"required\" entries must be strings".to_string())?;
+        if !properties.is_some_and(|properties| properties.contains_key(field)) {
+            return Err(format!("required property '{}' is not declared", field));
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[derive(Deserialize)]
+    struct EchoArgs {
+        message: String,
+        #[serde(default)]
+        repeat: Option<usize>,
+    }
+
+    #[cfg(feature = "schemars")]
+    #[derive(Deserialize, schemars::JsonSchema)]
+    #[serde(deny_unknown_fields)]
+    struct SearchArgs {
+        query: String,
+        limit: Option<u8>,
+        #[serde(default)]
+        tags: Vec<Tag>,
+    }
+
+    #[cfg(feature = "schemars")]
+    #[derive(Deserialize, schemars::JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    enum Tag {
+        Docs,
+        Code,
+    }
+
+    #[cfg(feature = "schemars")]
+    #[tokio::test]
+    async fn test_from_schema_validates_arguments() {
+        let tool = McpTool::from_schema("search", "Search", |args: SearchArgs| async move {
+            Ok(ToolResult::text(format!("{}:{:?}:{}", args.query, args.limit, args.tags.len())))
+        });
+        assert_eq!(tool.input_schema["type"], "object");
+        assert_eq!(tool.input_schema["required"], serde_json::json!(["query"]));
+
+        let run = |value: Value| {
+            let tool = tool.clone();
+            async move {
+                let result = tool.execute(serde_json::from_value(value).unwrap()).await.unwrap();
+                let ToolResultContent::Text { ref text } = result.content[0] else {
+                    panic!("expected text")
+                };
+                (result.is_error, text.clone())
+            }
+        };
+
+        assert_eq!(
+            run(serde_json::json!({"query": "hooks", "limit": 5, "tags": ["docs"]})).await,
+            (None, "hooks:Some(5):1".to_string())
+        );
+        assert_eq!(
+            run(serde_json::json!({"limit": 5})).await,
+            (Some(true), "Invalid arguments for search: /query is required".to_string())
+        );
+        assert_eq!(
+            run(serde_json::json!({"query": "x", "limit": 300})).await,
+            (Some(true), "Invalid arguments for search: /limit is out of range: 300".to_string())
+        );
+        assert_eq!(
+            run(serde_json::json!({"query": "x", "tags": ["music"]})).await,
+            (
+                Some(true),
+                r#"Invalid arguments for search: /tags/0 should be one of ["docs","code"], got "music""#.to_string()
+            )
+        );
+        assert_eq!(
+            run(serde_json::json!({"query": "x", "extra": true})).await,
+            (Some(true), "Invalid arguments for search: /extra is not allowed".to_string())
+        );
+    }
+
+    struct Notes {
+        notes: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Tool for Notes {
+        const NAME: &'static str = "add_note";
+        const DESCRIPTION: &'static str = "Remember a note";
+
+        fn schema() -> Value {
+            serde_json::json!({"type": "object", "properties": {"note": {"type": "string"}}})
+        }
+
+        async fn call(&self, args: HashMap<String, Value>) -> Result<ToolResult> {
+            let mut notes = self.notes.lock().unwrap();
+            notes.push(args.get("note").and_then(|v| v.as_str()).unwrap_or_default().to_string());
+            Ok(ToolResult::text(format!("{} notes", notes.len())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_with_stateful_tool() {
+        let notes = Arc::new(Notes {
+            notes: std::sync::Mutex::new(Vec::new()),
+        });
+        let server = SdkMcpServer::new("notes".to_string(), "1.0.0".to_string(), vec![]).with_tool(Arc::clone(&notes));
+        assert_eq!(server.get_tool("add_note").unwrap().description, "Remember a note");
+
+        for note in ["a", "b"] {
+            let mut args = HashMap::new();
+            args.insert("note".to_string(), serde_json::json!(note));
+            server.execute_tool("add_note", args).await.unwrap();
+        }
+        assert_eq!(*notes.notes.lock().unwrap(), ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_handle_message_reports_progress() {
+        let tool = McpTool::with_context(
+            "scan".to_string(),
+            "Scan files".to_string(),
+            serde_json::json!({"type": "object"}),
+            Arc::new(|_args, context| {
+                Box::pin(async move {
+                    context.report_progress(40.0, "Scanning src/");
+                    context.report_progress(150.0, "");
+                    Ok(ToolResult::text("done".to_string()))
+                })
+            }),
+        );
+        let server = SdkMcpServer::new("scanner".to_string(), "1.0.0".to_string(), vec![tool]);
+
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink: ProgressSink = {
+            let reports = Arc::clone(&reports);
+            Arc::new(move |progress| reports.lock().unwrap().push(progress))
+        };
+        let response = server
+            .handle_message_with_progress(
+                &serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 7,
+                    "method": "tools/call",
+                    "params": {"name": "scan", "arguments": {}, "_meta": {"progressToken": "tok-1"}}
+                }),
+                sink,
+            )
+            .await;
+
+        assert_eq!(response["result"]["content"][0]["text"], "done");
+        let reports = reports.lock().unwrap().clone();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].tool_name, "scan");
+        assert_eq!(reports[0].progress, 40.0);
+        assert_eq!(reports[0].message.as_deref(), Some("Scanning src/"));
+        assert_eq!(reports[0].progress_token, Some(serde_json::json!("tok-1")));
+        assert_eq!(reports[1].progress, 100.0);
+        assert_eq!(reports[1].message, None);
+
+        // Without a sink, progress is discarded
+        let result = server.execute_tool("scan", HashMap::new()).await.unwrap();
+        assert_eq!(result.is_error, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tool_timeouts() {
+        let sleeper = |name: &str| {
+            McpTool::new(
+                name.to_string(),
+                "Sleep for a minute".to_string(),
+                serde_json::json!({"type": "object"}),
+                Arc::new(|_args| {
+                    Box::pin(async move {
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                        Ok(ToolResult::text("awake".to_string()))
+                    })
+                }),
+            )
+        };
+        let server = SdkMcpServer::new(
+            "sleepers".to_string(),
+            "1.0.0".to_string(),
+            vec![sleeper("default"), sleeper("patient").with_timeout(Duration::from_secs(120))],
+        )
+        .with_default_timeout(Duration::from_secs(5));
+
+        let result = server.execute_tool("default", HashMap::new()).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert!(matches!(
+            result.content[0],
+            ToolResultContent::Text { ref text } if text == "Tool default timed out after 5s"
+        ));
+
+        // A tool's own timeout wins over the server default
+        let result = server.execute_tool("patient", HashMap::new()).await.unwrap();
+        assert_eq!(result.is_error, None);
+
+        // Without a server, only the tool's timeout applies
+        let quick = sleeper("quick").with_timeout(Duration::from_secs(1));
+        assert_eq!(quick.execute(HashMap::new()).await.unwrap().is_error, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_context_stops_tool() {
+        let server = SdkMcpServer::new(
+            "tools".to_string(),
+            "1.0.0".to_string(),
+            vec![McpTool::with_context(
+                "wait".to_string(),
+                "Wait for cancellation".to_string(),
+                serde_json::json!({"type": "object"}),
+                Arc::new(|_args, context| {
+                    Box::pin(async move {
+                        context.cancelled().await;
+                        assert!(context.is_cancelled());
+                        Ok(ToolResult::text("unreachable".to_string()))
+                    })
+                }),
+            )],
+        );
+
+        let cancel = CancelHandle::new();
+        cancel.cancel();
+        let context = ToolContext::new("wait").with_cancel_handle(cancel);
+        let result = server.execute_tool_with_context("wait", HashMap::new(), context).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert!(matches!(
+            result.content[0],
+            ToolResultContent::Text { ref text } if text == "Tool wait was cancelled"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_layers_wrap_tool_calls_in_order() {
+        let echo = McpTool::new(
+            "echo".to_string(),
+            "Echo the token".to_string(),
+            serde_json::json!({"type": "object"}),
+            Arc::new(|args| {
+                Box::pin(async move { Ok(ToolResult::text(args["token"].as_str().unwrap_or_default().to_string())) })
+            }),
+        );
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let server = SdkMcpServer::new("tools".to_string(), "1.0.0".to_string(), vec![echo])
+            .with_layer({
+                let calls = Arc::clone(&calls);
+                move |tool_name, args, next| {
+                    let calls = Arc::clone(&calls);
+                    async move {
+                        calls.lock().unwrap().push(format!("outer:{}", tool_name));
+                        let result = next.run(args).await;
+                        calls.lock().unwrap().push("outer:done".to_string());
+                        result
+                    }
+                }
+            })
+            .with_layer({
+                let calls = Arc::clone(&calls);
+                move |_tool_name, mut args, next| {
+                    let calls = Arc::clone(&calls);
+                    async move {
+                        calls.lock().unwrap().push(format!("inner:{}", args["token"]));
+                        args.insert("token".to_string(), serde_json::json!("<redacted>"));
+                        next.run(args).await
+                    }
+                }
+            });
+
+        let args = HashMap::from([("token".to_string(), serde_json::json!("secret"))]);
+        let result = server.execute_tool("echo", args).await.unwrap();
+
+        assert!(matches!(result.content[0], ToolResultContent::Text { ref text } if text == "<redacted>"));
+        assert_eq!(*calls.lock().unwrap(), ["outer:echo", "inner:\"secret\"", "outer:done"]);
+    }
+
+    #[tokio::test]
+    async fn test_layer_can_short_circuit() {
+        let server = SdkMcpServer::new(
+            "tools".to_string(),
+            "1.0.0".to_string(),
+            vec![McpTool::new(
+                "delete".to_string(),
+                "Delete everything".to_string(),
+                serde_json::json!({"type": "object"}),
+                Arc::new(|_args| Box::pin(async { panic!("tool should not run") })),
+            )],
+        )
+        .with_layer(|tool_name, _args, _next| async move { Ok(ToolResult::error(format!("{} is disabled", tool_name))) });
+
+        let result = server.execute_tool("delete", HashMap::new()).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+    }
+
+    #[test]
+    fn test_rich_result_serialization() {
+        assert_eq!(
+            serde_json::to_value(ToolResult::json(serde_json::json!({"temperature": 21}))).unwrap(),
+            serde_json::json!({
+                "content": [{"type": "text", "text": "{\"temperature\":21}"}],
+                "structuredContent": {"temperature": 21}
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(ToolResult::resource_link("file:///notes.md", "notes.md")).unwrap(),
+            serde_json::json!({
+                "content": [{"type": "resource_link", "uri": "file:///notes.md", "name": "notes.md"}]
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(ToolResult::audio("audio/wav", "UklGRg==")).unwrap()["content"][0],
+            serde_json::json!({
+                "type": "audio",
+                "source": {"type": "base64", "media_type": "audio/wav", "data": "UklGRg=="}
+            })
+        );
+
+        // Results without JSON blocks serialize as before
+        let error = serde_json::to_value(ToolResult::error("boom".to_string())).unwrap();
+        assert_eq!(error, serde_json::json!({"content": [{"type": "text", "text": "boom"}], "is_error": true}));
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_tools_at_runtime() {
+        let server = SdkMcpServer::new("plugins".to_string(), "1.0.0".to_string(), vec![]);
+        let shared = server.clone();
+        let mut changes = server.subscribe_tool_changes();
+
+        shared.add_tool(McpTool::new(
+            "ping".to_string(),
+            "Reply with pong".to_string(),
+            serde_json::json!({"type": "object"}),
+            Arc::new(|_args| Box::pin(async { Ok(ToolResult::text("pong".to_string())) })),
+        ));
+        assert!(changes.has_changed().unwrap());
+        changes.mark_unchanged();
+
+        // Clones share the tool list
+        let listed = server
+            .handle_message(&serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}))
+            .await;
+        assert_eq!(listed["result"]["tools"][0]["name"], "ping");
+        assert!(server.execute_tool("ping", HashMap::new()).await.is_ok());
+
+        assert!(server.remove_tool("missing").is_none());
+        assert!(!changes.has_changed().unwrap());
+        assert_eq!(server.remove_tool("ping").map(|tool| tool.name), Some("ping".to_string()));
+        assert!(changes.has_changed().unwrap());
+        assert!(shared.get_tool("ping").is_none());
+        assert!(server.execute_tool("ping", HashMap::new()).await.is_err());
+    }
+
+    #[test]
+    fn test_builder_validates_tools() {
+        let tool = |name: &str, schema: Value| {
+            McpTool::new(
+                name.to_string(),
+                "Test tool".to_string(),
+                schema,
+                Arc::new(|_args| Box::pin(async { Ok(ToolResult::text("ok".to_string())) })),
+            )
+        };
+        let object = serde_json::json!({"type": "object", "properties": {"q": {"type": "string"}}, "required": ["q"]});
+
+        let server = SdkMcpServer::builder("tools")
+            .tool(tool("search", object.clone()))
+            .default_timeout(Duration::from_secs(3))
+            .build()
+            .unwrap();
+        assert_eq!(server.version, "1.0.0");
+        assert!(server.get_tool("search").is_some());
+
+        let error = |builder: SdkMcpServerBuilder| builder.build().unwrap_err().to_string();
+        assert_eq!(
+            error(SdkMcpServer::builder("tools").tool(tool("a", object.clone())).tool(tool("a", object.clone()))),
+            "Invalid MCP server 'tools': duplicate tool name 'a'"
+        );
+        assert_eq!(
+            error(SdkMcpServer::builder("tools").tool(tool("web search", object.clone()))),
+            "Invalid MCP server 'tools': tool name 'web search' must be 1-64 ASCII letters, digits, '_' or '-'"
+        );
+        assert_eq!(
+            error(SdkMcpServer::builder("tools").tool(tool("list", serde_json::json!({"type": "array"})))),
+            "Invalid MCP server 'tools': tool 'list': input schema must have \"type\": \"object\""
+        );
+        assert_eq!(
+            error(SdkMcpServer::builder("tools").tool(tool("get", serde_json::json!({"type": "object", "required": ["id"]})))),
+            "Invalid MCP server 'tools': tool 'get': required property 'id' is not declared"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_annotations_in_tools_list() {
+        let read = McpTool::new(
+            "read_notes".to_string(),
+            "Read notes".to_string(),
+            serde_json::json!({"type": "object"}),
+            Arc::new(|_args| Box::pin(async { Ok(ToolResult::text("notes".to_string())) })),
+        )
+        .with_annotations(ToolAnnotations::read_only().idempotent(true));
+        let server = SdkMcpServer::new("notes".to_string(), "1.0.0".to_string(), vec![read]);
+
+        let listed = server
+            .handle_message(&serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}))
+            .await;
+        assert_eq!(
+            listed["result"]["tools"][0]["annotations"],
+            serde_json::json!({"readOnlyHint": true, "destructiveHint": false, "idempotentHint": true})
+        );
+
+        let servers = HashMap::from([("notes".to_string(), server)]);
+        let annotations = SdkMcpServer::annotations_for(&servers, "mcp__notes__read_notes").unwrap();
+        assert_eq!(annotations.read_only_hint, Some(true));
+        assert!(SdkMcpServer::annotations_for(&servers, "mcp__notes__missing").is_none());
+        assert!(SdkMcpServer::annotations_for(&servers, "Read").is_none());
+
+        // Tools without annotations omit the field
+        let plain = SdkMcpServer::new("plain".to_string(), "1.0.0".to_string(), vec![]).with_tool(Arc::new(Notes {
+            notes: std::sync::Mutex::new(Vec::new()),
+        }));
+        assert!(plain.tool_definitions()[0].get("annotations").is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_concurrency_limits_queue_calls() {
+        let in_flight = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let peak = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let tracked = |name: &str| {
+            let in_flight = Arc::clone(&in_flight);
+            let peak = Arc::clone(&peak);
+            McpTool::new(
+                name.to_string(),
+                "Hold a slot for a second".to_string(),
+                serde_json::json!({"type": "object"}),
+                Arc::new(move |_args| {
+                    let in_flight = Arc::clone(&in_flight);
+                    let peak = Arc::clone(&peak);
+                    Box::pin(async move {
+                        let now = in_flight.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, std::sync::atomic::Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        in_flight.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+                        Ok(ToolResult::text("done".to_string()))
+                    })
+                }),
+            )
+        };
+        let server = SdkMcpServer::new(
+            "tools".to_string(),
+            "1.0.0".to_string(),
+            vec![tracked("shared"), tracked("single").with_max_concurrency(1)],
+        )
+        .with_max_concurrent_tools(2)
+        // Queueing does not count towards the timeout
+        .with_default_timeout(Duration::from_millis(1500));
+
+        let started = tokio::time::Instant::now();
+        let results = futures::future::join_all((0..5).map(|_| server.execute_tool("shared", HashMap::new()))).await;
+        assert!(results.iter().all(|result| result.as_ref().unwrap().is_error.is_none()));
+        assert_eq!(peak.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(started.elapsed(), Duration::from_secs(3));
+
+        peak.store(0, std::sync::atomic::Ordering::SeqCst);
+        let results = futures::future::join_all((0..3).map(|_| server.execute_tool("single", HashMap::new()))).await;
+        assert!(results.iter().all(|result| result.as_ref().unwrap().is_error.is_none()));
+        assert_eq!(peak.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_serve_speaks_json_rpc_lines() {
+        let tool = McpTool::with_context(
+            "echo".to_string(),
+            "Echo a message".to_string(),
+            serde_json::json!({"type": "object"}),
+            Arc::new(|args, context| {
+                Box::pin(async move {
+                    context.report_progress(50.0, "Halfway");
+                    Ok(ToolResult::text(args["message"].as_str().unwrap_or_default().to_string()))
+                })
+            }),
+        );
+        let server = SdkMcpServer::new("stdio".to_string(), "1.0.0".to_string(), vec![tool]);
+
+        let input = [
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}"#,
+            r#"{"jsonrpc": "2.0", "method": "notifications/initialized"}"#,
+            "",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {"name": "echo", "arguments": {"message": "hi"}, "_meta": {"progressToken": "p"}}}"#,
+            "not json",
+        ]
+        .join("\n");
+        let mut output = Vec::new();
+        server.serve(input.as_bytes(), &mut output).await.unwrap();
+
+        let messages: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(messages.len(), 4);
+        let find = |check: &dyn Fn(&Value) -> bool| messages.iter().find(|m| check(m)).cloned().unwrap();
+
+        assert_eq!(find(&|m| m["id"] == 1)["result"]["serverInfo"]["name"], "stdio");
+        assert_eq!(find(&|m| m["id"] == 2)["result"]["content"][0]["text"], "hi");
+        let progress = find(&|m| m["method"] == "notifications/progress");
+        assert_eq!(progress["params"]["progressToken"], "p");
+        assert_eq!(progress["params"]["message"], "Halfway");
+        assert_eq!(find(&|m| m.get("error").is_some())["error"]["code"], -32700);
+    }
+
+    #[tokio::test]
+    async fn test_typed_tool() {
+        let tool = McpTool::typed(
+            "echo",
+            "Echo input",
+            serde_json::json!({"type": "object"}),
+            |args: EchoArgs| async move { Ok(ToolResult::text(args.message.repeat(args.repeat.unwrap_or(1)))) },
+        );
+
+        let args = |value: Value| serde_json::from_value(value).unwrap();
+        let result = tool.execute(args(serde_json::json!({"message": "hi", "repeat": 2}))).await.unwrap();
+        assert!(matches!(&result.content[0], ToolResultContent::Text { text } if text == "hihi"));
+        assert_eq!(result.is_error, None);
+
+        let result = tool.execute(args(serde_json::json!({"message": 5}))).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert!(matches!(
+            &result.content[0],
+            ToolResultContent::Text { text } if text.starts_with("Invalid arguments for echo: invalid type: integer `5`")
+        ));
+    }
+
     #[tokio::test]
     async fn test_tool_creation() {
         let tool = McpTool::new(
@@ -364,6 +2119,48 @@ mod tests {
         assert_eq!(error_result.is_error, Some(true));
     }
 
+    #[tokio::test]
+    async fn test_handle_message_list_and_call() {
+        let tool = McpTool::new(
+            "echo".to_string(),
+            "Echo input".to_string(),
+            serde_json::json!({"type": "object"}),
+            Arc::new(|args| {
+                Box::pin(async move {
+                    let text = args.get("text").and_then(|v| v.as_str()).unwrap_or_default();
+                    Ok(ToolResult::text(text.to_string()))
+                })
+            }),
+        );
+        let server = SdkMcpServer::new("tools".to_string(), "1.0.0".to_string(), vec![tool]);
+
+        let init = server
+            .handle_message(&serde_json::json!({"jsonrpc": "2.0", "id": 0, "method": "initialize"}))
+            .await;
+        assert_eq!(init["result"]["serverInfo"]["name"], "tools");
+
+        let list = server
+            .handle_message(&serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}))
+            .await;
+        assert_eq!(list["id"], 1);
+        assert_eq!(list["result"]["tools"][0]["name"], "echo");
+
+        let call = server
+            .handle_message(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "tools/call",
+                "params": {"name": "echo", "arguments": {"text": "hi"}}
+            }))
+            .await;
+        assert_eq!(call["result"]["content"][0]["text"], "hi");
+
+        let unknown = server
+            .handle_message(&serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "resources/list"}))
+            .await;
+        assert_eq!(unknown["error"]["code"], -32601);
+    }
+
     #[test]
     fn test_server_to_config() {
         let tool = McpTool::new(
@@ -386,6 +2183,6 @@ mod tests {
         assert_eq!(config.get("name").unwrap(), "my-server");
         assert_eq!(config.get("version").unwrap(), "1.0.0");
         assert_eq!(config.get("type").unwrap(), "sdk");
-        assert!(config.get("tools").is_some());
+        assert!(config.contains_key("tools"));
     }
 }
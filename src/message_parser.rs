@@ -1,7 +1,9 @@
 use crate::errors::{ClaudeSDKError, Result};
 use crate::types::*;
+use serde::de::Error as _;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
-use std::collections::HashMap;
 
 /// Parse message from CLI output into typed Message objects
 ///
@@ -14,8 +16,48 @@ use std::collections::HashMap;
 /// # Errors
 /// Returns `ClaudeSDKError::MessageParseError` if parsing fails or message type is unrecognized
 pub fn parse_message(data: &Value) -> Result<Message> {
-    if !data.is_object() {
-        return Err(ClaudeSDKError::message_parse_error(
+    parse_message_with_mode(data, ParseMode::Strict)
+}
+
+/// Parse message from CLI output, handling unknown types according to `mode`
+///
+/// With `ParseMode::Lenient`, messages and content blocks of unknown types
+/// come back as `Message::Unknown` and `ContentBlock::Unknown` instead of
+/// errors. Malformed messages of known types are errors in either mode.
+///
+/// # Errors
+/// Returns `ClaudeSDKError::MessageParseError` if parsing fails, or if a type
+/// is unrecognized in strict mode
+pub fn parse_message_with_mode(data: &Value, mode: ParseMode) -> Result<Message> {
+    let message = parse_known_message(data, mode)?;
+    if mode == ParseMode::Strict {
+        for block in content_blocks(&message) {
+            if let ContentBlock::Unknown(block) = block {
+                let block_type = block.get("type").and_then(|v| v.as_str()).unwrap_or_default();
+                return Err(ClaudeSDKError::message_parse_error(
+                    format!("Unknown content block type: {}", block_type),
+                    Some(data.clone()),
+                ));
+            }
+        }
+    }
+    Ok(message)
+}
+
+fn content_blocks(message: &Message) -> &[ContentBlock] {
+    match message {
+        Message::User(UserMessage {
+            content: UserMessageContent::Blocks(blocks),
+            ..
+        }) => blocks,
+        Message::Assistant(msg) => &msg.content,
+        _ => &[],
+    }
+}
+
+fn parse_known_message(data: &Value, mode: ParseMode) -> Result<Message> {
+    let obj = data.as_object().ok_or_else(|| {
+        ClaudeSDKError::message_parse_error(
             format!(
                 "Invalid message data type (expected object, got {})",
                 match data {
@@ -28,21 +70,29 @@ pub fn parse_message(data: &Value) -> Result<Message> {
                 }
             ),
             Some(data.clone()),
-        ));
-    }
+        )
+    })?;
 
-    let obj = data.as_object().unwrap();
     let message_type = obj
         .get("type")
         .and_then(|v| v.as_str())
         .ok_or_else(|| ClaudeSDKError::message_parse_error("Message missing 'type' field", Some(data.clone())))?;
 
     match message_type {
-        "user" => parse_user_message(obj, data),
-        "assistant" => parse_assistant_message(obj, data),
-        "system" => parse_system_message(obj, data),
-        "result" => parse_result_message(obj, data),
-        "stream_event" => parse_stream_event(obj, data),
+        "user" => decode::<UserWire>("user", data).map(Message::from),
+        "assistant" => decode::<AssistantWire>("assistant", data).map(Message::from),
+        "system" => {
+            let subtype = decode::<SystemWire>("system", data)?.subtype;
+            // System messages keep the whole object, since their payload varies by subtype
+            let data_map = obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+            Ok(Message::System(SystemMessage {
+                subtype,
+                data: data_map,
+            }))
+        }
+        "result" => decode("result", data).map(Message::Result),
+        "stream_event" => decode("stream_event", data).map(Message::Stream),
+        _ if mode == ParseMode::Lenient => Ok(Message::Unknown(data.clone())),
         _ => Err(ClaudeSDKError::message_parse_error(
             format!("Unknown message type: {}", message_type),
             Some(data.clone()),
@@ -50,376 +100,178 @@ pub fn parse_message(data: &Value) -> Result<Message> {
     }
 }
 
-fn parse_user_message(obj: &serde_json::Map<String, Value>, data: &Value) -> Result<Message> {
-    let message = obj
-        .get("message")
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing required field in user message: message",
-                Some(data.clone()),
-            )
-        })?
-        .as_object()
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Invalid message field type",
-                Some(data.clone()),
-            )
-        })?;
-
-    let parent_tool_use_id = obj.get("parent_tool_use_id").and_then(|v| v.as_str()).map(String::from);
-
-    let content_val = message.get("content").ok_or_else(|| {
-        ClaudeSDKError::message_parse_error(
-            "Missing required field in user message: content",
-            Some(data.clone()),
-        )
-    })?;
-
-    let content = if let Some(arr) = content_val.as_array() {
-        let mut blocks = Vec::new();
-        for block in arr {
-            blocks.push(parse_content_block(block, data)?);
-        }
-        UserMessageContent::Blocks(blocks)
-    } else if let Some(text) = content_val.as_str() {
-        UserMessageContent::Text(text.to_string())
-    } else {
-        return Err(ClaudeSDKError::message_parse_error(
-            "Invalid content type in user message",
-            Some(data.clone()),
-        ));
-    };
-
-    Ok(Message::User(UserMessage {
-        content,
-        parent_tool_use_id,
-    }))
+/// Deserialize one message kind, rewording serde's errors in the parser's terms
+fn decode<'a, T: Deserialize<'a>>(kind: &str, data: &'a Value) -> Result<T> {
+    T::deserialize(data).map_err(|e| {
+        let text = e.to_string();
+        let message = match text
+            .strip_prefix("missing field `")
+            .and_then(|rest| rest.split('`').next())
+        {
+            Some(field) => format!("Missing required field in {} message: {}", kind, field),
+            None => format!("Invalid {} message: {}", kind, text),
+        };
+        ClaudeSDKError::message_parse_error_from(message, Some(data.clone()), e)
+    })
 }
 
-fn parse_assistant_message(obj: &serde_json::Map<String, Value>, data: &Value) -> Result<Message> {
-    let message = obj
-        .get("message")
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing required field in assistant message: message",
-                Some(data.clone()),
-            )
-        })?
-        .as_object()
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Invalid message field type",
-                Some(data.clone()),
-            )
-        })?;
-
-    let content_arr = message
-        .get("content")
-        .and_then(|v| v.as_array())
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing or invalid content field in assistant message",
-                Some(data.clone()),
-            )
-        })?;
-
-    let mut content_blocks = Vec::new();
-    for block in content_arr {
-        content_blocks.push(parse_content_block(block, data)?);
-    }
+// Wire shapes of the CLI's stream-json messages. Result and stream_event
+// messages match their public types field for field and need no wrapper.
+
+#[derive(Deserialize)]
+struct UserWire {
+    message: UserPayload,
+    #[serde(default)]
+    parent_tool_use_id: Option<String>,
+    #[serde(default)]
+    uuid: Option<String>,
+    #[serde(default)]
+    timestamp: Option<String>,
+}
 
-    let model = message
-        .get("model")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing required field in assistant message: model",
-                Some(data.clone()),
-            )
-        })?
-        .to_string();
+#[derive(Deserialize)]
+struct UserPayload {
+    content: UserMessageContent,
+}
 
-    let parent_tool_use_id = obj.get("parent_tool_use_id").and_then(|v| v.as_str()).map(String::from);
+#[derive(Deserialize)]
+struct AssistantWire {
+    message: AssistantPayload,
+    #[serde(default)]
+    parent_tool_use_id: Option<String>,
+    #[serde(default)]
+    uuid: Option<String>,
+    #[serde(default)]
+    timestamp: Option<String>,
+}
 
-    Ok(Message::Assistant(AssistantMessage {
-        content: content_blocks,
-        model,
-        parent_tool_use_id,
-    }))
+#[derive(Deserialize)]
+struct AssistantPayload {
+    content: Vec<ContentBlock>,
+    model: String,
 }
 
-fn parse_system_message(obj: &serde_json::Map<String, Value>, data: &Value) -> Result<Message> {
-    let subtype = obj
-        .get("subtype")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing required field in system message: subtype",
-                Some(data.clone()),
-            )
-        })?
-        .to_string();
+#[derive(Deserialize)]
+struct SystemWire {
+    subtype: String,
+}
 
-    // Convert the entire object to a HashMap
-    let mut data_map = HashMap::new();
-    for (key, value) in obj.iter() {
-        data_map.insert(key.clone(), value.clone());
+impl From<UserWire> for Message {
+    fn from(wire: UserWire) -> Self {
+        Message::User(UserMessage {
+            content: wire.message.content,
+            parent_tool_use_id: wire.parent_tool_use_id,
+            uuid: wire.uuid,
+            timestamp: wire.timestamp,
+        })
     }
-
-    Ok(Message::System(SystemMessage {
-        subtype,
-        data: data_map,
-    }))
 }
 
-fn parse_result_message(obj: &serde_json::Map<String, Value>, data: &Value) -> Result<Message> {
-    let subtype = obj
-        .get("subtype")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing required field in result message: subtype",
-                Some(data.clone()),
-            )
-        })?
-        .to_string();
-
-    let duration_ms = obj
-        .get("duration_ms")
-        .and_then(|v| v.as_i64())
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing required field in result message: duration_ms",
-                Some(data.clone()),
-            )
-        })?;
-
-    let duration_api_ms = obj
-        .get("duration_api_ms")
-        .and_then(|v| v.as_i64())
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing required field in result message: duration_api_ms",
-                Some(data.clone()),
-            )
-        })?;
-
-    let is_error = obj
-        .get("is_error")
-        .and_then(|v| v.as_bool())
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing required field in result message: is_error",
-                Some(data.clone()),
-            )
-        })?;
-
-    let num_turns = obj
-        .get("num_turns")
-        .and_then(|v| v.as_i64())
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing required field in result message: num_turns",
-                Some(data.clone()),
-            )
-        })? as i32;
-
-    let session_id = obj
-        .get("session_id")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing required field in result message: session_id",
-                Some(data.clone()),
-            )
-        })?
-        .to_string();
-
-    let total_cost_usd = obj.get("total_cost_usd").and_then(|v| v.as_f64());
-
-    let usage = obj.get("usage").and_then(|v| {
-        v.as_object().map(|o| {
-            o.iter()
-                .map(|(k, v)| (k.clone(), v.clone()))
-                .collect::<HashMap<String, Value>>()
+impl From<AssistantWire> for Message {
+    fn from(wire: AssistantWire) -> Self {
+        Message::Assistant(AssistantMessage {
+            content: wire.message.content,
+            model: wire.message.model,
+            parent_tool_use_id: wire.parent_tool_use_id,
+            uuid: wire.uuid,
+            timestamp: wire.timestamp,
         })
-    });
-
-    let result = obj.get("result").and_then(|v| v.as_str()).map(String::from);
-
-    Ok(Message::Result(ResultMessage {
-        subtype,
-        duration_ms,
-        duration_api_ms,
-        is_error,
-        num_turns,
-        session_id,
-        total_cost_usd,
-        usage,
-        result,
-    }))
+    }
 }
 
-fn parse_stream_event(obj: &serde_json::Map<String, Value>, data: &Value) -> Result<Message> {
-    let uuid = obj
-        .get("uuid")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing required field in stream_event message: uuid",
-                Some(data.clone()),
-            )
-        })?
-        .to_string();
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WireRef<'a> {
+    User {
+        message: UserPayloadRef<'a>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        parent_tool_use_id: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        uuid: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timestamp: Option<&'a str>,
+    },
+    Assistant {
+        message: AssistantPayloadRef<'a>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        parent_tool_use_id: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        uuid: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timestamp: Option<&'a str>,
+    },
+    System(SystemRef<'a>),
+    Result(&'a ResultMessage),
+    StreamEvent(&'a StreamEvent),
+}
 
-    let session_id = obj
-        .get("session_id")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing required field in stream_event message: session_id",
-                Some(data.clone()),
-            )
-        })?
-        .to_string();
+#[derive(Serialize)]
+struct UserPayloadRef<'a> {
+    role: &'static str,
+    content: &'a UserMessageContent,
+}
 
-    let event = obj
-        .get("event")
-        .and_then(|v| v.as_object())
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Missing required field in stream_event message: event",
-                Some(data.clone()),
-            )
-        })?
-        .iter()
-        .map(|(k, v)| (k.clone(), v.clone()))
-        .collect::<HashMap<String, Value>>();
+#[derive(Serialize)]
+struct AssistantPayloadRef<'a> {
+    role: &'static str,
+    content: &'a [ContentBlock],
+    model: &'a str,
+}
 
-    let parent_tool_use_id = obj.get("parent_tool_use_id").and_then(|v| v.as_str()).map(String::from);
+struct SystemRef<'a>(&'a SystemMessage);
 
-    Ok(Message::Stream(StreamEvent {
-        uuid,
-        session_id,
-        event,
-        parent_tool_use_id,
-    }))
+impl Serialize for SystemRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let extra = self.0.data.iter().filter(|(k, _)| k.as_str() != "type" && k.as_str() != "subtype");
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("subtype", &self.0.subtype)?;
+        for (key, value) in extra {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
 }
 
-fn parse_content_block(block: &Value, data: &Value) -> Result<ContentBlock> {
-    let block_obj = block.as_object().ok_or_else(|| {
-        ClaudeSDKError::message_parse_error(
-            "Invalid content block type (expected object)",
-            Some(data.clone()),
-        )
-    })?;
-
-    let block_type = block_obj
-        .get("type")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            ClaudeSDKError::message_parse_error(
-                "Content block missing 'type' field",
-                Some(data.clone()),
-            )
-        })?;
+/// Messages serialize to the CLI's stream-json format, so the output of
+/// `serde_json::to_value(&message)` parses back with [`parse_message`]
+/// (or [`parse_message_with_mode`] for unknown types)
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let wire = match self {
+            Message::User(msg) => WireRef::User {
+                message: UserPayloadRef {
+                    role: "user",
+                    content: &msg.content,
+                },
+                parent_tool_use_id: msg.parent_tool_use_id.as_deref(),
+                uuid: msg.uuid.as_deref(),
+                timestamp: msg.timestamp.as_deref(),
+            },
+            Message::Assistant(msg) => WireRef::Assistant {
+                message: AssistantPayloadRef {
+                    role: "assistant",
+                    content: &msg.content,
+                    model: &msg.model,
+                },
+                parent_tool_use_id: msg.parent_tool_use_id.as_deref(),
+                uuid: msg.uuid.as_deref(),
+                timestamp: msg.timestamp.as_deref(),
+            },
+            Message::System(msg) => WireRef::System(SystemRef(msg)),
+            Message::Result(msg) => WireRef::Result(msg),
+            Message::Stream(msg) => WireRef::StreamEvent(msg),
+            Message::Unknown(data) => return data.serialize(serializer),
+        };
+        wire.serialize(serializer)
+    }
+}
 
-    match block_type {
-        "text" => {
-            let text = block_obj
-                .get("text")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| {
-                    ClaudeSDKError::message_parse_error(
-                        "Text block missing 'text' field",
-                        Some(data.clone()),
-                    )
-                })?
-                .to_string();
-            Ok(ContentBlock::Text { text })
-        }
-        "thinking" => {
-            let thinking = block_obj
-                .get("thinking")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| {
-                    ClaudeSDKError::message_parse_error(
-                        "Thinking block missing 'thinking' field",
-                        Some(data.clone()),
-                    )
-                })?
-                .to_string();
-            let signature = block_obj
-                .get("signature")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| {
-                    ClaudeSDKError::message_parse_error(
-                        "Thinking block missing 'signature' field",
-                        Some(data.clone()),
-                    )
-                })?
-                .to_string();
-            Ok(ContentBlock::Thinking { thinking, signature })
-        }
-        "tool_use" => {
-            let id = block_obj
-                .get("id")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| {
-                    ClaudeSDKError::message_parse_error(
-                        "Tool use block missing 'id' field",
-                        Some(data.clone()),
-                    )
-                })?
-                .to_string();
-            let name = block_obj
-                .get("name")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| {
-                    ClaudeSDKError::message_parse_error(
-                        "Tool use block missing 'name' field",
-                        Some(data.clone()),
-                    )
-                })?
-                .to_string();
-            let input = block_obj
-                .get("input")
-                .and_then(|v| v.as_object())
-                .ok_or_else(|| {
-                    ClaudeSDKError::message_parse_error(
-                        "Tool use block missing or invalid 'input' field",
-                        Some(data.clone()),
-                    )
-                })?
-                .iter()
-                .map(|(k, v)| (k.clone(), v.clone()))
-                .collect::<HashMap<String, Value>>();
-            Ok(ContentBlock::ToolUse { id, name, input })
-        }
-        "tool_result" => {
-            let tool_use_id = block_obj
-                .get("tool_use_id")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| {
-                    ClaudeSDKError::message_parse_error(
-                        "Tool result block missing 'tool_use_id' field",
-                        Some(data.clone()),
-                    )
-                })?
-                .to_string();
-            let content = block_obj.get("content").cloned();
-            let is_error = block_obj.get("is_error").and_then(|v| v.as_bool());
-            Ok(ContentBlock::ToolResult {
-                tool_use_id,
-                content,
-                is_error,
-            })
-        }
-        _ => Err(ClaudeSDKError::message_parse_error(
-            format!("Unknown content block type: {}", block_type),
-            Some(data.clone()),
-        )),
+/// Deserialization is lenient, like `ContentBlock`'s: unknown types become
+/// `Message::Unknown` rather than errors
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let data = Value::deserialize(deserializer)?;
+        parse_message_with_mode(&data, ParseMode::Lenient).map_err(D::Error::custom)
     }
 }
 
@@ -454,37 +306,27 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_text_block() {
-        let block = json!({
-            "type": "text",
-            "text": "Hello, world!"
+    fn test_missing_field_names_the_message_kind() {
+        let data = json!({
+            "type": "result",
+            "subtype": "success",
+            "duration_api_ms": 1,
+            "is_error": false,
+            "num_turns": 1,
+            "session_id": "s"
         });
-        let data = json!({});
-        let result = parse_content_block(&block, &data).unwrap();
-        match result {
-            ContentBlock::Text { text } => assert_eq!(text, "Hello, world!"),
-            _ => panic!("Expected Text block"),
-        }
+        let err = parse_message(&data).unwrap_err();
+        assert!(
+            err.to_string().contains("Missing required field in result message: duration_ms"),
+            "{}",
+            err
+        );
     }
 
     #[test]
-    fn test_parse_tool_use_block() {
-        let block = json!({
-            "type": "tool_use",
-            "id": "tool123",
-            "name": "test_tool",
-            "input": {
-                "param": "value"
-            }
-        });
-        let data = json!({});
-        let result = parse_content_block(&block, &data).unwrap();
-        match result {
-            ContentBlock::ToolUse { id, name, .. } => {
-                assert_eq!(id, "tool123");
-                assert_eq!(name, "test_tool");
-            }
-            _ => panic!("Expected ToolUse block"),
-        }
+    fn test_system_message_serializes_fields_once() {
+        let data = json!({"type": "system", "subtype": "init", "cwd": "/tmp"});
+        let msg = parse_message(&data).unwrap();
+        assert_eq!(serde_json::to_value(&msg).unwrap(), data);
     }
 }
@@ -0,0 +1,439 @@
+//! Reporting SDK activity to a metrics system
+//!
+//! Implement [`MetricsSink`] to receive counters, gauges and histograms about
+//! queries, token usage, cost, tool calls and errors, and pass it to
+//! [`ClaudeOptionsBuilder::metrics`](crate::ClaudeOptionsBuilder::metrics).
+//! With the `prometheus` feature, [`PrometheusSink`] registers them in a
+//! `prometheus::Registry`.
+//!
+//! | Metric | Kind | Labels |
+//! |---|---|---|
+//! | `claude_queries_total` | counter | `status`: `success` or `error` |
+//! | `claude_query_duration_seconds` | histogram | |
+//! | `claude_query_turns` | histogram | |
+//! | `claude_tokens_total` | counter | `type`: `input`, `output`, `cache_read` or `cache_creation` |
+//! | `claude_cost_usd_total` | counter | |
+//! | `claude_tool_calls_total` | counter | `tool` |
+//! | `claude_errors_total` | counter | `kind`, see [`ErrorKind::as_str`](crate::ErrorKind::as_str) |
+//! | `claude_active_sessions` | gauge | |
+//!
+//! Query figures come from the result message the CLI sends at the end of
+//! each turn. Its cost and usage are the session's running totals, so the
+//! counters get what each result adds to the previous one. Errors are counted when a query or session fails, and the
+//! session gauge counts connected [`ClaudeSDKClient`](crate::ClaudeSDKClient)s.
+//!
+//! # Example
+//! ```no_run
+//! use claude::metrics::{Metrics, MetricsSink};
+//! use claude::ClaudeOptionsBuilder;
+//!
+//! struct Log;
+//!
+//! impl MetricsSink for Log {
+//!     fn counter(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+//!         println!("{} += {} {:?}", name, value, labels);
+//!     }
+//!     fn gauge(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+//!         println!("{} = {} {:?}", name, value, labels);
+//!     }
+//!     fn histogram(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+//!         println!("{} <- {} {:?}", name, value, labels);
+//!     }
+//! }
+//!
+//! # async fn example() -> claude::Result<()> {
+//! let options = ClaudeOptionsBuilder::new().metrics(Metrics::new(Log)).build();
+//! claude::simple_query("What is 2 + 2?", Some(options)).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::budget::RunningTotals;
+use crate::errors::{ClaudeSDKError, Result};
+use crate::types::{ContentBlock, Message, SessionId};
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub const QUERIES_TOTAL: &str = "claude_queries_total";
+pub const QUERY_DURATION_SECONDS: &str = "claude_query_duration_seconds";
+pub const QUERY_TURNS: &str = "claude_query_turns";
+pub const TOKENS_TOTAL: &str = "claude_tokens_total";
+pub const COST_USD_TOTAL: &str = "claude_cost_usd_total";
+pub const TOOL_CALLS_TOTAL: &str = "claude_tool_calls_total";
+pub const ERRORS_TOTAL: &str = "claude_errors_total";
+pub const ACTIVE_SESSIONS: &str = "claude_active_sessions";
+
+/// Receives the metrics reported by the SDK
+///
+/// Called inline while messages are read, so implementations should return
+/// quickly. A metric is always reported with the same label names.
+pub trait MetricsSink: Send + Sync {
+    /// Add `value` to a counter
+    fn counter(&self, name: &str, value: f64, labels: &[(&str, &str)]);
+    /// Set a gauge to `value`
+    fn gauge(&self, name: &str, value: f64, labels: &[(&str, &str)]);
+    /// Record one observation of `value`
+    fn histogram(&self, name: &str, value: f64, labels: &[(&str, &str)]);
+}
+
+/// A shared [`MetricsSink`]
+///
+/// Clones report to the same sink and share the active session count.
+#[derive(Clone)]
+pub struct Metrics {
+    sink: Arc<dyn MetricsSink>,
+    active_sessions: Arc<AtomicI64>,
+    // Last totals reported per session
+    results: Arc<Mutex<RunningTotals<SessionId>>>,
+}
+
+impl Metrics {
+    pub fn new(sink: impl MetricsSink + 'static) -> Self {
+        Self::from_arc(Arc::new(sink))
+    }
+
+    /// Report to a sink that is also used elsewhere
+    pub fn from_arc(sink: Arc<dyn MetricsSink>) -> Self {
+        Metrics {
+            sink,
+            active_sessions: Arc::new(AtomicI64::new(0)),
+            results: Arc::default(),
+        }
+    }
+
+    /// Account for a message read from the CLI
+    pub(crate) fn record_message(&self, message: &Message) {
+        match message {
+            Message::Assistant(assistant) => {
+                for block in &assistant.content {
+                    if let ContentBlock::ToolUse { name, .. } = block {
+                        self.sink.counter(TOOL_CALLS_TOTAL, 1.0, &[("tool", name)]);
+                    }
+                }
+            }
+            Message::Result(result) => {
+                let status = if result.is_error { "error" } else { "success" };
+                self.sink.counter(QUERIES_TOTAL, 1.0, &[("status", status)]);
+                self.sink.histogram(QUERY_DURATION_SECONDS, result.duration_ms as f64 / 1000.0, &[]);
+                self.sink.histogram(QUERY_TURNS, result.num_turns as f64, &[]);
+                let (cost, usage) = self.results.lock().unwrap().delta(
+                    result.session_id.clone(),
+                    result.total_cost_usd.unwrap_or(0.0),
+                    result.token_usage().unwrap_or_default(),
+                );
+                let tokens = [
+                    ("input", usage.input_tokens),
+                    ("output", usage.output_tokens),
+                    ("cache_read", usage.cache_read_input_tokens),
+                    ("cache_creation", usage.cache_creation_input_tokens),
+                ];
+                for (kind, count) in tokens.into_iter().filter(|(_, count)| *count > 0) {
+                    self.sink.counter(TOKENS_TOTAL, count as f64, &[("type", kind)]);
+                }
+                if result.total_cost_usd.is_some() {
+                    self.sink.counter(COST_USD_TOTAL, cost, &[]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Count an error that ended a query or session
+    pub(crate) fn record_error(&self, error: &ClaudeSDKError) {
+        self.sink.counter(ERRORS_TOTAL, 1.0, &[("kind", error.kind().as_str())]);
+    }
+
+    /// Account for the outcome of a one-shot query
+    pub(crate) fn record_outcome(&self, outcome: &Result<Vec<Message>>) {
+        match outcome {
+            Ok(messages) => messages.iter().for_each(|message| self.record_message(message)),
+            Err(e) => self.record_error(e),
+        }
+    }
+
+    /// Count a session as active until the returned guard is dropped
+    pub(crate) fn session(&self) -> ActiveSession {
+        let active = self.active_sessions.fetch_add(1, Ordering::SeqCst) + 1;
+        self.sink.gauge(ACTIVE_SESSIONS, active as f64, &[]);
+        ActiveSession { metrics: self.clone() }
+    }
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics")
+            .field("active_sessions", &self.active_sessions.load(Ordering::SeqCst))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Keeps a session counted in `claude_active_sessions`
+pub(crate) struct ActiveSession {
+    metrics: Metrics,
+}
+
+impl Drop for ActiveSession {
+    fn drop(&mut self) {
+        let active = self.metrics.active_sessions.fetch_sub(1, Ordering::SeqCst) - 1;
+        self.metrics.sink.gauge(ACTIVE_SESSIONS, active as f64, &[]);
+    }
+}
+
+/// A [`MetricsSink`] that registers the SDK metrics in a Prometheus registry
+///
+/// Each metric is registered the first time it is reported.
+///
+/// # Example
+/// ```no_run
+/// use claude::metrics::{Metrics, PrometheusSink};
+/// use claude::ClaudeOptionsBuilder;
+///
+/// let registry = prometheus::Registry::new();
+/// let options = ClaudeOptionsBuilder::new()
+///     .metrics(Metrics::new(PrometheusSink::new(registry.clone())))
+///     .build();
+/// // Serve `registry.gather()` with `prometheus::TextEncoder`
+/// ```
+#[cfg(feature = "prometheus")]
+pub struct PrometheusSink {
+    registry: prometheus::Registry,
+    counters: std::sync::Mutex<std::collections::HashMap<String, prometheus::CounterVec>>,
+    gauges: std::sync::Mutex<std::collections::HashMap<String, prometheus::GaugeVec>>,
+    histograms: std::sync::Mutex<std::collections::HashMap<String, prometheus::HistogramVec>>,
+}
+
+#[cfg(feature = "prometheus")]
+impl PrometheusSink {
+    pub fn new(registry: prometheus::Registry) -> Self {
+        PrometheusSink {
+            registry,
+            counters: Default::default(),
+            gauges: Default::default(),
+            histograms: Default::default(),
+        }
+    }
+
+    pub fn registry(&self) -> &prometheus::Registry {
+        &self.registry
+    }
+
+    /// The metric called `name`, registering it on first use
+    ///
+    /// Metrics that cannot be registered, e.g. because the registry already
+    /// has a different metric of that name, are not reported.
+    fn metric<M, F>(
+        &self,
+        metrics: &std::sync::Mutex<std::collections::HashMap<String, M>>,
+        name: &str,
+        labels: &[(&str, &str)],
+        create: F,
+    ) -> Option<M>
+    where
+        M: prometheus::core::Collector + Clone + 'static,
+        F: FnOnce(prometheus::Opts, &[&str]) -> prometheus::Result<M>,
+    {
+        let mut metrics = metrics.lock().unwrap();
+        if let Some(metric) = metrics.get(name) {
+            return Some(metric.clone());
+        }
+        let label_names: Vec<_> = labels.iter().map(|(label, _)| *label).collect();
+        let metric = create(prometheus::Opts::new(name, help(name)), &label_names).ok()?;
+        self.registry.register(Box::new(metric.clone())).ok()?;
+        metrics.insert(name.to_string(), metric.clone());
+        Some(metric)
+    }
+}
+
+#[cfg(feature = "prometheus")]
+impl MetricsSink for PrometheusSink {
+    fn counter(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+        let counter = self.metric(&self.counters, name, labels, prometheus::CounterVec::new);
+        if let Some(counter) = counter.and_then(|c| c.get_metric_with_label_values(&label_values(labels)).ok()) {
+            counter.inc_by(value);
+        }
+    }
+
+    fn gauge(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+        let gauge = self.metric(&self.gauges, name, labels, prometheus::GaugeVec::new);
+        if let Some(gauge) = gauge.and_then(|g| g.get_metric_with_label_values(&label_values(labels)).ok()) {
+            gauge.set(value);
+        }
+    }
+
+    fn histogram(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+        let histogram = self.metric(&self.histograms, name, labels, |opts, names| {
+            let opts = prometheus::HistogramOpts::from(opts).buckets(buckets(name));
+            prometheus::HistogramVec::new(opts, names)
+        });
+        if let Some(histogram) = histogram.and_then(|h| h.get_metric_with_label_values(&label_values(labels)).ok()) {
+            histogram.observe(value);
+        }
+    }
+}
+
+#[cfg(feature = "prometheus")]
+fn label_values<'a>(labels: &[(&str, &'a str)]) -> Vec<&'a str> {
+    labels.iter().map(|(_, value)| *value).collect()
+}
+
+#[cfg(feature = "prometheus")]
+fn help(name: &str) -> &'static str {
+    match name {
+        QUERIES_TOTAL => "Queries completed by Claude Code",
+        QUERY_DURATION_SECONDS => "Duration of completed queries",
+        QUERY_TURNS => "Turns taken by completed queries",
+        TOKENS_TOTAL => "Tokens used by completed queries",
+        COST_USD_TOTAL => "Cost of completed queries in USD",
+        TOOL_CALLS_TOTAL => "Tool calls made by Claude",
+        ERRORS_TOTAL => "Errors that ended a query or session",
+        ACTIVE_SESSIONS => "Connected interactive sessions",
+        _ => "Claude SDK metric",
+    }
+}
+
+#[cfg(feature = "prometheus")]
+fn buckets(name: &str) -> Vec<f64> {
+    match name {
+        // 0.5s to about 17 minutes
+        QUERY_DURATION_SECONDS => prometheus::exponential_buckets(0.5, 2.0, 12).unwrap_or_default(),
+        QUERY_TURNS => vec![1.0, 2.0, 3.0, 5.0, 8.0, 13.0, 21.0, 34.0, 55.0],
+        _ => prometheus::DEFAULT_BUCKETS.to_vec(),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::message_parser::parse_message;
+    use crate::testing::result_message;
+    use serde_json::json;
+
+    /// Sink that keeps every report as `kind name value labels`
+    #[derive(Default)]
+    pub(crate) struct RecordingSink {
+        pub(crate) reports: Mutex<Vec<String>>,
+    }
+
+    impl RecordingSink {
+        fn report(&self, kind: &str, name: &str, value: f64, labels: &[(&str, &str)]) {
+            let labels: Vec<_> = labels.iter().map(|(label, value)| format!("{}={}", label, value)).collect();
+            self.reports.lock().unwrap().push(format!("{} {} {} {}", kind, name, value, labels.join(",")).trim_end().to_string());
+        }
+    }
+
+    impl MetricsSink for RecordingSink {
+        fn counter(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+            self.report("counter", name, value, labels);
+        }
+        fn gauge(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+            self.report("gauge", name, value, labels);
+        }
+        fn histogram(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+            self.report("histogram", name, value, labels);
+        }
+    }
+
+    #[test]
+    fn test_records_results_tools_and_errors() {
+        let sink = Arc::new(RecordingSink::default());
+        let metrics = Metrics::from_arc(sink.clone());
+
+        let assistant = parse_message(&json!({
+            "type": "assistant",
+            "message": {
+                "model": "claude-sonnet-4-5",
+                "content": [
+                    {"type": "text", "text": "Checking"},
+                    {"type": "tool_use", "id": "t1", "name": "Bash", "input": {}}
+                ]
+            }
+        }))
+        .unwrap();
+        let mut result = result_message("s1");
+        result["duration_ms"] = json!(1500);
+        result["num_turns"] = json!(2);
+        result["total_cost_usd"] = json!(0.25);
+        result["usage"] = json!({"input_tokens": 100, "output_tokens": 20});
+        let result = parse_message(&result).unwrap();
+        metrics.record_outcome(&Ok(vec![assistant, result]));
+        metrics.record_outcome(&Err(ClaudeSDKError::rate_limited("Overloaded", None)));
+
+        assert_eq!(
+            *sink.reports.lock().unwrap(),
+            [
+                "counter claude_tool_calls_total 1 tool=Bash",
+                "counter claude_queries_total 1 status=success",
+                "histogram claude_query_duration_seconds 1.5",
+                "histogram claude_query_turns 2",
+                "counter claude_tokens_total 100 type=input",
+                "counter claude_tokens_total 20 type=output",
+                "counter claude_cost_usd_total 0.25",
+                "counter claude_errors_total 1 kind=rate_limited",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_results_count_what_they_add_to_the_session() {
+        let sink = Arc::new(RecordingSink::default());
+        let metrics = Metrics::from_arc(sink.clone());
+
+        // Each turn of a session reports the totals so far
+        for (cost, input_tokens) in [(0.25, 100), (0.75, 300)] {
+            let mut result = result_message("s1");
+            result["total_cost_usd"] = json!(cost);
+            result["usage"] = json!({"input_tokens": input_tokens});
+            metrics.record_message(&parse_message(&result).unwrap());
+        }
+
+        let reports = sink.reports.lock().unwrap();
+        let counted = |name: &str| reports.iter().filter(|report| report.contains(name)).cloned().collect::<Vec<_>>();
+        assert_eq!(
+            counted(TOKENS_TOTAL),
+            ["counter claude_tokens_total 100 type=input", "counter claude_tokens_total 200 type=input"]
+        );
+        assert_eq!(counted(COST_USD_TOTAL), ["counter claude_cost_usd_total 0.25", "counter claude_cost_usd_total 0.5"]);
+    }
+
+    #[test]
+    fn test_active_sessions_gauge() {
+        let sink = Arc::new(RecordingSink::default());
+        let metrics = Metrics::from_arc(sink.clone());
+
+        let first = metrics.session();
+        let second = metrics.clone().session();
+        drop(first);
+        drop(second);
+
+        assert_eq!(
+            *sink.reports.lock().unwrap(),
+            [
+                "gauge claude_active_sessions 1",
+                "gauge claude_active_sessions 2",
+                "gauge claude_active_sessions 1",
+                "gauge claude_active_sessions 0",
+            ]
+        );
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn test_prometheus_sink() {
+        let registry = prometheus::Registry::new();
+        let sink = PrometheusSink::new(registry.clone());
+        sink.counter(TOOL_CALLS_TOTAL, 1.0, &[("tool", "Bash")]);
+        sink.counter(TOOL_CALLS_TOTAL, 2.0, &[("tool", "Bash")]);
+        sink.histogram(QUERY_TURNS, 3.0, &[]);
+        sink.gauge(ACTIVE_SESSIONS, 1.0, &[]);
+
+        let families = registry.gather();
+        let names: Vec<_> = families.iter().map(|family| family.name()).collect();
+        assert_eq!(names, [ACTIVE_SESSIONS, QUERY_TURNS, TOOL_CALLS_TOTAL]);
+
+        let tool_calls = &families[2].get_metric()[0];
+        assert_eq!(tool_calls.get_label()[0].value(), "Bash");
+        assert_eq!(tool_calls.get_counter().get_value(), 3.0);
+        assert_eq!(families[1].get_metric()[0].get_histogram().get_sample_count(), 1);
+    }
+}
@@ -1,9 +1,17 @@
 use crate::errors::Result;
-use crate::types::{PermissionResult, ToolPermissionContext};
-use std::collections::HashMap;
+use crate::mcp_server::{McpTool, SdkMcpServer, ToolResult};
+use crate::types::{
+    PermissionBehavior, PermissionMode, PermissionResult, PermissionRuleValue, PermissionUpdate, PermissionUpdateDestination,
+    PermissionUpdateType, ToolPermissionContext,
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
 use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Type alias for async can_use_tool callback functions
 ///
@@ -42,22 +50,741 @@ impl PermissionResult {
     }
 
     /// Create a Deny result
-    pub fn deny(message: String) -> Self {
+    pub fn deny(message: impl Into<String>) -> Self {
         PermissionResult::Deny {
-            message,
+            message: message.into(),
             interrupt: false,
         }
     }
 
     /// Create a Deny result with interrupt
-    pub fn deny_with_interrupt(message: String) -> Self {
+    pub fn deny_with_interrupt(message: impl Into<String>) -> Self {
         PermissionResult::Deny {
-            message,
+            message: message.into(),
             interrupt: true,
         }
     }
 }
 
+/// Name of the in-process MCP server created by [`permission_prompt_server`]
+pub const PERMISSION_PROMPT_SERVER: &str = "sdk_permissions";
+
+/// `--permission-prompt-tool` value that targets [`permission_prompt_server`]
+pub const PERMISSION_PROMPT_TOOL: &str = "mcp__sdk_permissions__prompt";
+
+/// Serve a permission callback as an MCP tool for `--permission-prompt-tool`
+///
+/// The CLI calls the tool with `tool_name` and `input` whenever it would ask
+/// for permission, and the callback's decision is returned in the format the
+/// CLI expects. Register the server and point the CLI at
+/// [`PERMISSION_PROMPT_TOOL`], or let
+/// [`ClaudeSDKClientBuilder::permission_prompt`](crate::ClaudeSDKClientBuilder::permission_prompt)
+/// do both.
+pub fn permission_prompt_server(callback: CanUseToolCallback) -> SdkMcpServer {
+    let tool = McpTool::new(
+        "prompt".to_string(),
+        "Decide whether Claude may use a tool".to_string(),
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "tool_name": {"type": "string"},
+                "input": {"type": "object"},
+                "tool_use_id": {"type": "string"}
+            },
+            "required": ["tool_name", "input"]
+        }),
+        Arc::new(move |args| {
+            let callback = Arc::clone(&callback);
+            Box::pin(async move {
+                let tool_name = args
+                    .get("tool_name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                let input: HashMap<String, serde_json::Value> = args
+                    .get("input")
+                    .and_then(|v| v.as_object())
+                    .map(|o| o.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+                    .unwrap_or_default();
+
+                let decision = match callback(tool_name, input.clone(), ToolPermissionContext::default()).await? {
+                    PermissionResult::Allow { updated_input, .. } => serde_json::json!({
+                        "behavior": "allow",
+                        "updatedInput": updated_input.unwrap_or(input),
+                    }),
+                    PermissionResult::Deny { message, .. } => serde_json::json!({
+                        "behavior": "deny",
+                        "message": message,
+                    }),
+                };
+                Ok(ToolResult::text(decision.to_string()))
+            })
+        }),
+    );
+
+    SdkMcpServer::new(PERMISSION_PROMPT_SERVER.to_string(), "1.0.0".to_string(), vec![tool])
+}
+
+/// Helper functions for creating permission updates
+///
+/// Each constructor sets the fields its update type needs; pick where the
+/// update is saved with [`with_destination`](PermissionUpdate::with_destination).
+impl PermissionUpdate {
+    fn of_type(r#type: PermissionUpdateType) -> Self {
+        PermissionUpdate {
+            r#type,
+            rules: None,
+            behavior: None,
+            mode: None,
+            directories: None,
+            destination: None,
+        }
+    }
+
+    fn with_rules(r#type: PermissionUpdateType, rules: Vec<PermissionRuleValue>, behavior: PermissionBehavior) -> Self {
+        PermissionUpdate {
+            rules: Some(rules),
+            behavior: Some(behavior),
+            ..Self::of_type(r#type)
+        }
+    }
+
+    fn with_directories<I, S>(r#type: PermissionUpdateType, directories: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        PermissionUpdate {
+            directories: Some(directories.into_iter().map(Into::into).collect()),
+            ..Self::of_type(r#type)
+        }
+    }
+
+    /// Add permission rules
+    pub fn add_rules(rules: Vec<PermissionRuleValue>, behavior: PermissionBehavior) -> Self {
+        Self::with_rules(PermissionUpdateType::AddRules, rules, behavior)
+    }
+
+    /// Replace the rules that have the given behavior
+    pub fn replace_rules(rules: Vec<PermissionRuleValue>, behavior: PermissionBehavior) -> Self {
+        Self::with_rules(PermissionUpdateType::ReplaceRules, rules, behavior)
+    }
+
+    /// Remove permission rules
+    pub fn remove_rules(rules: Vec<PermissionRuleValue>, behavior: PermissionBehavior) -> Self {
+        Self::with_rules(PermissionUpdateType::RemoveRules, rules, behavior)
+    }
+
+    /// Switch the permission mode
+    pub fn set_mode(mode: PermissionMode) -> Self {
+        PermissionUpdate {
+            mode: Some(mode),
+            ..Self::of_type(PermissionUpdateType::SetMode)
+        }
+    }
+
+    /// Give tools access to more directories
+    pub fn add_directories<I, S>(directories: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::with_directories(PermissionUpdateType::AddDirectories, directories)
+    }
+
+    /// Revoke access to directories
+    pub fn remove_directories<I, S>(directories: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::with_directories(PermissionUpdateType::RemoveDirectories, directories)
+    }
+
+    /// Set where the update is saved
+    pub fn with_destination(mut self, destination: PermissionUpdateDestination) -> Self {
+        self.destination = Some(destination);
+        self
+    }
+}
+
+impl PermissionRuleValue {
+    /// A rule covering every use of a tool
+    pub fn tool(tool_name: impl Into<String>) -> Self {
+        PermissionRuleValue {
+            tool_name: tool_name.into(),
+            rule_content: None,
+        }
+    }
+
+    /// A rule covering uses of a tool that match `rule_content`, e.g. `npm test:*`
+    pub fn new(tool_name: impl Into<String>, rule_content: impl Into<String>) -> Self {
+        PermissionRuleValue {
+            tool_name: tool_name.into(),
+            rule_content: Some(rule_content.into()),
+        }
+    }
+}
+
+/// How a [`PermissionChain`] combines the results of its callbacks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChainMode {
+    /// Stop at the first deny; later callbacks do not run
+    #[default]
+    FirstDenyWins,
+    /// Run every callback and allow only if all of them allow
+    ///
+    /// Useful when some callbacks have side effects, such as audit logging,
+    /// that should see every request. Deny messages are joined with `; `.
+    AllMustAllow,
+}
+
+/// Several permission callbacks run in order as one callback
+///
+/// Each callback sees the input as rewritten by the callbacks before it
+/// (`updated_input`), and the final allow carries the last rewrite and every
+/// `updated_permissions` entry. An error from any callback is returned as is.
+///
+/// ```
+/// use claude::permissions::{ChainMode, PermissionChain};
+/// use claude::{permission_callback, PermissionResult};
+///
+/// let no_bash = permission_callback!(|tool, _input, _ctx| {
+///     if tool == "Bash" {
+///         Ok(PermissionResult::deny("Bash is disabled"))
+///     } else {
+///         Ok(PermissionResult::allow())
+///     }
+/// });
+/// let allow_all = permission_callback!(|_tool, _input, _ctx| { Ok(PermissionResult::allow()) });
+///
+/// let callback = PermissionChain::new()
+///     .mode(ChainMode::AllMustAllow)
+///     .with(no_bash)
+///     .with(allow_all)
+///     .into_callback();
+/// ```
+#[derive(Clone, Default)]
+pub struct PermissionChain {
+    callbacks: Vec<CanUseToolCallback>,
+    mode: ChainMode,
+}
+
+impl PermissionChain {
+    /// Create an empty chain, which allows everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how results are combined (default: [`ChainMode::FirstDenyWins`])
+    pub fn mode(mut self, mode: ChainMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Append a callback to the chain
+    pub fn with(mut self, callback: CanUseToolCallback) -> Self {
+        self.callbacks.push(callback);
+        self
+    }
+
+    /// Number of callbacks in the chain
+    pub fn len(&self) -> usize {
+        self.callbacks.len()
+    }
+
+    /// Whether the chain has no callbacks
+    pub fn is_empty(&self) -> bool {
+        self.callbacks.is_empty()
+    }
+
+    /// Run the chain for one tool request
+    pub async fn evaluate(
+        &self,
+        tool_name: String,
+        input: HashMap<String, serde_json::Value>,
+        context: ToolPermissionContext,
+    ) -> Result<PermissionResult> {
+        let mut input = input;
+        let mut rewritten = false;
+        let mut permissions = Vec::new();
+        let mut denials: Vec<(String, bool)> = Vec::new();
+
+        for callback in &self.callbacks {
+            match callback(tool_name.clone(), input.clone(), context.clone()).await? {
+                PermissionResult::Allow {
+                    updated_input,
+                    updated_permissions,
+                } => {
+                    if let Some(updated) = updated_input {
+                        input = updated;
+                        rewritten = true;
+                    }
+                    permissions.extend(updated_permissions.unwrap_or_default());
+                }
+                PermissionResult::Deny { message, interrupt } => {
+                    if self.mode == ChainMode::FirstDenyWins {
+                        return Ok(PermissionResult::Deny { message, interrupt });
+                    }
+                    denials.push((message, interrupt));
+                }
+            }
+        }
+
+        if !denials.is_empty() {
+            return Ok(PermissionResult::Deny {
+                interrupt: denials.iter().any(|(_, interrupt)| *interrupt),
+                message: denials
+                    .into_iter()
+                    .map(|(message, _)| message)
+                    .filter(|message| !message.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            });
+        }
+
+        Ok(PermissionResult::Allow {
+            updated_input: rewritten.then_some(input),
+            updated_permissions: (!permissions.is_empty()).then_some(permissions),
+        })
+    }
+
+    /// Turn the chain into a single `can_use_tool` callback
+    pub fn into_callback(self) -> CanUseToolCallback {
+        let chain = Arc::new(self);
+        Arc::new(move |tool_name, input, context| {
+            let chain = Arc::clone(&chain);
+            Box::pin(async move { chain.evaluate(tool_name, input, context).await })
+        })
+    }
+}
+
+/// Combinators for [`CanUseToolCallback`]
+pub trait CanUseToolExt {
+    /// Run `next` only when `self` allows, passing it any rewritten input
+    ///
+    /// Equivalent to a two-callback [`PermissionChain`] in
+    /// [`ChainMode::FirstDenyWins`] mode.
+    fn and_then(self, next: CanUseToolCallback) -> CanUseToolCallback;
+
+    /// Run `fallback` only when `self` denies, returning its result instead
+    fn or_else(self, fallback: CanUseToolCallback) -> CanUseToolCallback;
+}
+
+impl CanUseToolExt for CanUseToolCallback {
+    fn and_then(self, next: CanUseToolCallback) -> CanUseToolCallback {
+        PermissionChain::new().with(self).with(next).into_callback()
+    }
+
+    fn or_else(self, fallback: CanUseToolCallback) -> CanUseToolCallback {
+        Arc::new(move |tool_name, input, context| {
+            let first = Arc::clone(&self);
+            let fallback = Arc::clone(&fallback);
+            Box::pin(async move {
+                match first(tool_name.clone(), input.clone(), context.clone()).await? {
+                    PermissionResult::Deny { .. } => fallback(tool_name, input, context).await,
+                    allowed => Ok(allowed),
+                }
+            })
+        })
+    }
+}
+
+/// Tools whose path input is checked by [`sandbox`]
+const SANDBOXED_FILE_TOOLS: [&str; 4] = ["Write", "Edit", "MultiEdit", "NotebookEdit"];
+
+/// Deny file writes and shell commands that reach outside `root`
+///
+/// `Write`, `Edit`, `MultiEdit` and `NotebookEdit` are denied when their
+/// `file_path` or `notebook_path` resolves outside `root`; relative paths are
+/// resolved against `root`. `Bash` is denied when any word of the command is
+/// an absolute path, a `~` path, or a `..` traversal outside `root`. Every
+/// other tool is allowed, so combine the sandbox with other checks through
+/// [`PermissionChain`].
+///
+/// Paths are compared lexically and symlinks are not followed. The `Bash`
+/// check is a heuristic over the command's words, not a shell parser.
+///
+/// ```
+/// use claude::permissions;
+///
+/// let callback = permissions::sandbox("/srv/project");
+/// ```
+pub fn sandbox(root: impl Into<PathBuf>) -> CanUseToolCallback {
+    let root = root.into();
+    let root = if root.is_relative() {
+        std::env::current_dir().map(|cwd| cwd.join(&root)).unwrap_or(root)
+    } else {
+        root
+    };
+    let root = Arc::new(normalize_path(&root));
+
+    Arc::new(move |tool_name, input, _context| {
+        let result = match sandbox_violation(&root, &tool_name, &input) {
+            Some(path) => PermissionResult::deny(format!(
+                "{} is outside the sandbox {}",
+                path,
+                root.display()
+            )),
+            None => PermissionResult::allow(),
+        };
+        Box::pin(async move { Ok(result) })
+    })
+}
+
+/// The first path in a tool request that escapes `root`
+fn sandbox_violation(root: &Path, tool_name: &str, input: &HashMap<String, serde_json::Value>) -> Option<String> {
+    let escapes = |path: &str| path.starts_with('~') || !normalize_path(&root.join(path)).starts_with(root);
+
+    if SANDBOXED_FILE_TOOLS.contains(&tool_name) {
+        return ["file_path", "notebook_path"]
+            .iter()
+            .filter_map(|key| input.get(*key).and_then(|value| value.as_str()))
+            .find(|path| escapes(path))
+            .map(str::to_string);
+    }
+
+    if tool_name == "Bash" {
+        let command = input.get("command").and_then(|value| value.as_str())?;
+        return command_words(command)
+            .filter(|word| word.starts_with('/') || word.starts_with('~') || word.contains(".."))
+            .find(|word| escapes(word))
+            .map(str::to_string);
+    }
+
+    None
+}
+
+/// Words of a shell command, split on whitespace, operators and `=`
+fn command_words(command: &str) -> impl Iterator<Item = &str> {
+    command
+        .split(|c: char| c.is_whitespace() || ";|&<>()=`".contains(c))
+        .map(|word| word.trim_matches(|c| c == '"' || c == '\''))
+        .filter(|word| !word.is_empty())
+}
+
+/// What [`RateLimit`] does with a request over budget
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RateLimitAction {
+    /// Deny the request with a message naming the exceeded budget
+    #[default]
+    Deny,
+    /// Wait until the budget has room again
+    ///
+    /// Only windowed budgets free up over time; a request over a
+    /// [`per_session`](RateLimit::per_session) budget is still denied.
+    Defer,
+}
+
+/// One budget tracked by [`RateLimit`]
+#[derive(Debug, Clone)]
+struct Budget {
+    /// Tool the budget applies to, or every tool
+    tool: Option<String>,
+    max: usize,
+    /// Sliding window, or the lifetime of the session
+    window: Option<Duration>,
+}
+
+impl Budget {
+    fn describe(&self) -> String {
+        let tool = self.tool.as_deref().unwrap_or("tool");
+        match self.window {
+            Some(window) => format!("at most {} {} calls per {:?}", self.max, tool, window),
+            None => format!("at most {} {} calls per session", self.max, tool),
+        }
+    }
+}
+
+/// Budgets on how often tools may run, to bound runaway agent loops
+///
+/// Only requests that fit every applicable budget are counted and passed on
+/// to the wrapped callback.
+///
+/// ```
+/// use claude::permissions::{sandbox, RateLimit};
+/// use std::time::Duration;
+///
+/// let limit = RateLimit::new()
+///     .per_tool("Bash", 10, Duration::from_secs(60))
+///     .per_session(200);
+/// let callback = limit.wrap(sandbox("/srv/project"));
+/// ```
+#[derive(Clone, Default)]
+pub struct RateLimit {
+    budgets: Vec<Budget>,
+    action: RateLimitAction,
+    calls: Arc<Mutex<HashMap<BudgetKey, VecDeque<tokio::time::Instant>>>>,
+}
+
+/// Budget index and, for per-session budgets, the session ID
+type BudgetKey = (usize, Option<String>);
+
+impl RateLimit {
+    /// Create a rate limit with no budgets
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow at most `max` calls of `tool_name` in any `window`
+    pub fn per_tool(mut self, tool_name: impl Into<String>, max: usize, window: Duration) -> Self {
+        self.budgets.push(Budget {
+            tool: Some(tool_name.into()),
+            max,
+            window: Some(window),
+        });
+        self
+    }
+
+    /// Allow at most `max` calls of any tool in any `window`
+    pub fn total(mut self, max: usize, window: Duration) -> Self {
+        self.budgets.push(Budget {
+            tool: None,
+            max,
+            window: Some(window),
+        });
+        self
+    }
+
+    /// Allow at most `max` tool calls per session
+    ///
+    /// Sessions are told apart by
+    /// [`ToolPermissionContext::session_id`](crate::ToolPermissionContext::session_id).
+    pub fn per_session(mut self, max: usize) -> Self {
+        self.budgets.push(Budget {
+            tool: None,
+            max,
+            window: None,
+        });
+        self
+    }
+
+    /// Set what happens to requests over budget (default: deny)
+    pub fn on_exceeded(mut self, action: RateLimitAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Run `callback` only for requests within budget
+    pub fn wrap(&self, callback: CanUseToolCallback) -> CanUseToolCallback {
+        let limit = self.clone();
+        Arc::new(move |tool_name, input, context| {
+            let limit = limit.clone();
+            let callback = Arc::clone(&callback);
+            Box::pin(async move {
+                loop {
+                    match limit.acquire(&tool_name, context.session_id.as_deref()) {
+                        Ok(()) => return callback(tool_name, input, context).await,
+                        Err((_, Some(wait))) if limit.action == RateLimitAction::Defer => {
+                            tokio::time::sleep(wait).await;
+                        }
+                        Err((budget, _)) => {
+                            return Ok(PermissionResult::deny(format!(
+                                "Rate limit exceeded: {}",
+                                budget.describe()
+                            )))
+                        }
+                    }
+                }
+            })
+        })
+    }
+
+    /// A callback that allows every request within budget
+    ///
+    /// Useful as one step of a [`PermissionChain`].
+    pub fn into_callback(self) -> CanUseToolCallback {
+        self.wrap(Arc::new(|_, _, _| Box::pin(async { Ok(PermissionResult::allow()) })))
+    }
+
+    /// Count a call, or return the exceeded budget and how long until it frees
+    fn acquire(&self, tool_name: &str, session_id: Option<&str>) -> std::result::Result<(), (Budget, Option<Duration>)> {
+        let now = tokio::time::Instant::now();
+        let mut calls = self.calls.lock().unwrap();
+
+        let applicable: Vec<BudgetKey> = self
+            .budgets
+            .iter()
+            .enumerate()
+            .filter(|(_, budget)| budget.tool.as_deref().is_none_or(|tool| tool == tool_name))
+            .map(|(index, budget)| {
+                let session = budget.window.is_none().then(|| session_id.unwrap_or_default().to_string());
+                (index, session)
+            })
+            .collect();
+
+        for key in &applicable {
+            let budget = &self.budgets[key.0];
+            let times = calls.entry(key.clone()).or_default();
+            if let Some(window) = budget.window {
+                while times.front().is_some_and(|first| now.duration_since(*first) >= window) {
+                    times.pop_front();
+                }
+            }
+            if times.len() >= budget.max {
+                let wait = budget
+                    .window
+                    .zip(times.front())
+                    .map(|(window, first)| (*first + window).saturating_duration_since(now));
+                return Err((budget.clone(), wait));
+            }
+        }
+
+        for key in applicable {
+            calls.entry(key).or_default().push_back(now);
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for RateLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimit")
+            .field("budgets", &self.budgets)
+            .field("action", &self.action)
+            .finish()
+    }
+}
+
+/// Remembers permission decisions so slow callbacks run once per request
+///
+/// Decisions are keyed by tool name and a hash of the input (key order does
+/// not matter) and expire after the TTL. Errors are never cached. Clones share
+/// the same entries, so keep one to invalidate decisions after
+/// [`wrap`](Self::wrap)ping a callback.
+///
+/// ```
+/// use claude::permissions::PermissionCache;
+/// use claude::{permission_callback, PermissionResult};
+/// use std::time::Duration;
+///
+/// let ask_human = permission_callback!(|_tool, _input, _ctx| { Ok(PermissionResult::allow()) });
+/// let cache = PermissionCache::new(Duration::from_secs(300));
+/// let callback = cache.wrap(ask_human);
+///
+/// // Later, e.g. after the user revokes a grant
+/// cache.invalidate_tool("Bash");
+/// ```
+#[derive(Clone)]
+pub struct PermissionCache {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<CacheKey, (Instant, PermissionResult)>>>,
+}
+
+/// Tool name and [`input_hash`] of a cached request
+type CacheKey = (String, u64);
+
+impl PermissionCache {
+    /// Create an empty cache whose entries live for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Wrap `callback` so its decisions are served from this cache
+    pub fn wrap(&self, callback: CanUseToolCallback) -> CanUseToolCallback {
+        let cache = self.clone();
+        Arc::new(move |tool_name, input, context| {
+            let cache = cache.clone();
+            let callback = Arc::clone(&callback);
+            Box::pin(async move {
+                let key = (tool_name.clone(), input_hash(&input));
+                if let Some(result) = cache.get(&key) {
+                    return Ok(result);
+                }
+
+                let result = callback(tool_name, input, context).await?;
+                cache.insert(key, result.clone());
+                Ok(result)
+            })
+        })
+    }
+
+    /// Forget the decision for one tool request
+    pub fn invalidate(&self, tool_name: &str, input: &HashMap<String, serde_json::Value>) {
+        let key = (tool_name.to_string(), input_hash(input));
+        self.entries.lock().unwrap().remove(&key);
+    }
+
+    /// Forget every decision for a tool
+    pub fn invalidate_tool(&self, tool_name: &str) {
+        self.entries.lock().unwrap().retain(|(tool, _), _| tool != tool_name);
+    }
+
+    /// Forget every decision
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Number of unexpired decisions
+    pub fn len(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (stored, _)| stored.elapsed() < self.ttl);
+        entries.len()
+    }
+
+    /// Whether the cache holds no unexpired decisions
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<PermissionResult> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((stored, result)) if stored.elapsed() < self.ttl => Some(result.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Store a decision, dropping expired ones so requests that are never
+    /// repeated don't pile up
+    fn insert(&self, key: CacheKey, result: PermissionResult) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (stored, _)| stored.elapsed() < self.ttl);
+        entries.insert(key, (Instant::now(), result));
+    }
+}
+
+impl std::fmt::Debug for PermissionCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PermissionCache")
+            .field("ttl", &self.ttl)
+            .field("entries", &self.entries.lock().unwrap().len())
+            .finish()
+    }
+}
+
+/// Hash of a tool input that ignores key order
+fn input_hash(input: &HashMap<String, serde_json::Value>) -> u64 {
+    // `serde_json::Map` keeps keys sorted, so equal inputs serialize the same
+    let normalized: serde_json::Map<String, serde_json::Value> =
+        input.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    let mut hasher = DefaultHasher::new();
+    serde_json::Value::Object(normalized).to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Resolve `.` and `..` without touching the filesystem
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,9 +855,7 @@ mod tests {
         let mut input = HashMap::new();
         input.insert("command".to_string(), serde_json::json!("ls"));
 
-        let context = ToolPermissionContext {
-            suggestions: vec![],
-        };
+        let context = ToolPermissionContext::default();
 
         // Test denying Bash
         let result = callback("Bash".to_string(), input.clone(), context.clone()).await;
@@ -150,4 +875,274 @@ mod tests {
             _ => panic!("Expected Allow"),
         }
     }
+
+    fn deny_tool(name: &'static str) -> CanUseToolCallback {
+        Arc::new(move |tool_name, _input, _context| {
+            Box::pin(async move {
+                if tool_name == name {
+                    Ok(PermissionResult::deny(format!("no {}", name)))
+                } else {
+                    Ok(PermissionResult::allow())
+                }
+            })
+        })
+    }
+
+    fn counting(calls: Arc<std::sync::atomic::AtomicUsize>) -> CanUseToolCallback {
+        Arc::new(move |_tool_name, input, _context| {
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Box::pin(async move {
+                let mut input = input;
+                input.insert("seen".to_string(), serde_json::json!(true));
+                Ok(PermissionResult::allow_with_input(input))
+            })
+        })
+    }
+
+    fn context() -> ToolPermissionContext {
+        ToolPermissionContext::default()
+    }
+
+    #[tokio::test]
+    async fn test_chain_first_deny_wins_short_circuits() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let chain = PermissionChain::new()
+            .with(deny_tool("Bash"))
+            .with(counting(Arc::clone(&calls)));
+
+        let result = chain.evaluate("Bash".to_string(), HashMap::new(), context()).await.unwrap();
+        assert!(matches!(result, PermissionResult::Deny { ref message, .. } if message == "no Bash"));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        let result = chain.evaluate("Read".to_string(), HashMap::new(), context()).await.unwrap();
+        match result {
+            PermissionResult::Allow { updated_input, .. } => {
+                assert_eq!(updated_input.unwrap()["seen"], serde_json::json!(true));
+            }
+            _ => panic!("Expected Allow"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chain_all_must_allow_runs_every_callback() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let callback = PermissionChain::new()
+            .mode(ChainMode::AllMustAllow)
+            .with(deny_tool("Bash"))
+            .with(counting(Arc::clone(&calls)))
+            .with(deny_tool("Bash"))
+            .into_callback();
+
+        let result = callback("Bash".to_string(), HashMap::new(), context()).await.unwrap();
+        assert!(matches!(result, PermissionResult::Deny { ref message, .. } if message == "no Bash; no Bash"));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_and_then_or_else() {
+        let strict = deny_tool("Bash").and_then(deny_tool("Write"));
+        let result = strict("Write".to_string(), HashMap::new(), context()).await.unwrap();
+        assert!(matches!(result, PermissionResult::Deny { ref message, .. } if message == "no Write"));
+
+        let lenient = deny_tool("Bash").or_else(deny_tool("Write"));
+        let result = lenient("Bash".to_string(), HashMap::new(), context()).await.unwrap();
+        assert!(matches!(result, PermissionResult::Allow { .. }));
+        let result = lenient("Read".to_string(), HashMap::new(), context()).await.unwrap();
+        assert!(matches!(result, PermissionResult::Allow { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_sandbox() {
+        let callback = sandbox("/srv/project");
+        let check = |tool: &str, key: &str, value: &str| {
+            let mut input = HashMap::new();
+            input.insert(key.to_string(), serde_json::json!(value));
+            callback(tool.to_string(), input, context())
+        };
+        let allowed = |result: Result<PermissionResult>| matches!(result.unwrap(), PermissionResult::Allow { .. });
+
+        assert!(allowed(check("Write", "file_path", "/srv/project/src/main.rs").await));
+        assert!(allowed(check("Edit", "file_path", "src/main.rs").await));
+        assert!(!allowed(check("Write", "file_path", "/etc/passwd").await));
+        assert!(!allowed(check("Edit", "file_path", "/srv/project/../other/x").await));
+        assert!(!allowed(check("NotebookEdit", "notebook_path", "../notes.ipynb").await));
+        assert!(!allowed(check("Write", "file_path", "/srv/project-evil/x").await));
+
+        assert!(allowed(check("Bash", "command", "cargo test && ls src/").await));
+        assert!(allowed(check("Bash", "command", "cat /srv/project/Cargo.toml").await));
+        assert!(!allowed(check("Bash", "command", "rm -rf /").await));
+        assert!(!allowed(check("Bash", "command", "cd .. && ls").await));
+        assert!(!allowed(check("Bash", "command", "echo hi > '/tmp/out'").await));
+        assert!(!allowed(check("Bash", "command", "cp x ~/.ssh/authorized_keys").await));
+        assert!(!allowed(check("Bash", "command", "tar --file=/etc/shadow").await));
+
+        assert!(allowed(check("Read", "file_path", "/etc/passwd").await));
+    }
+
+    #[tokio::test]
+    async fn test_permission_cache() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let cache = PermissionCache::new(Duration::from_secs(3600));
+        let callback = cache.wrap(counting(Arc::clone(&calls)));
+        let calls = || calls.load(std::sync::atomic::Ordering::SeqCst);
+
+        let input: HashMap<String, serde_json::Value> =
+            serde_json::from_value(serde_json::json!({"a": 1, "b": {"c": 2}})).unwrap();
+        callback("Bash".to_string(), input.clone(), context()).await.unwrap();
+        let cached = callback("Bash".to_string(), input.clone(), context()).await.unwrap();
+        assert_eq!(calls(), 1);
+        assert!(matches!(cached, PermissionResult::Allow { updated_input: Some(ref i), .. } if i["seen"] == true));
+
+        callback("Read".to_string(), input.clone(), context()).await.unwrap();
+        assert_eq!(calls(), 2);
+        assert_eq!(cache.len(), 2);
+
+        cache.invalidate("Bash", &input);
+        callback("Bash".to_string(), input.clone(), context()).await.unwrap();
+        assert_eq!(calls(), 3);
+
+        cache.invalidate_tool("Read");
+        assert_eq!(cache.len(), 1);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_permission_cache_expires_entries() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let cache = PermissionCache::new(Duration::ZERO);
+        let callback = cache.wrap(counting(Arc::clone(&calls)));
+
+        callback("Bash".to_string(), HashMap::new(), context()).await.unwrap();
+        callback("Bash".to_string(), HashMap::new(), context()).await.unwrap();
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        // Expired decisions for other requests are dropped when storing a new one
+        callback("Read".to_string(), HashMap::new(), context()).await.unwrap();
+        callback("Write".to_string(), HashMap::new(), context()).await.unwrap();
+        assert_eq!(cache.entries.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_input_hash_ignores_key_order() {
+        let mut first = HashMap::new();
+        let mut second = HashMap::new();
+        for key in ["a", "b", "c", "d", "e"] {
+            first.insert(key.to_string(), serde_json::json!(key));
+        }
+        for key in ["e", "d", "c", "b", "a"] {
+            second.insert(key.to_string(), serde_json::json!(key));
+        }
+        assert_eq!(input_hash(&first), input_hash(&second));
+        second.insert("a".to_string(), serde_json::json!("changed"));
+        assert_ne!(input_hash(&first), input_hash(&second));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_denies_over_budget() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let callback = RateLimit::new()
+            .per_tool("Bash", 2, Duration::from_secs(60))
+            .wrap(counting(Arc::clone(&calls)));
+
+        for _ in 0..2 {
+            let result = callback("Bash".to_string(), HashMap::new(), context()).await.unwrap();
+            assert!(matches!(result, PermissionResult::Allow { .. }));
+        }
+        let result = callback("Bash".to_string(), HashMap::new(), context()).await.unwrap();
+        assert!(matches!(
+            result,
+            PermissionResult::Deny { ref message, .. } if message == "Rate limit exceeded: at most 2 Bash calls per 60s"
+        ));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        // Other tools have their own budget
+        let result = callback("Read".to_string(), HashMap::new(), context()).await.unwrap();
+        assert!(matches!(result, PermissionResult::Allow { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_per_session() {
+        let callback = RateLimit::new().per_session(1).into_callback();
+        let session = |id: &str| ToolPermissionContext {
+            session_id: Some(id.into()),
+            ..Default::default()
+        };
+
+        let first = callback("Read".to_string(), HashMap::new(), session("a")).await.unwrap();
+        let second = callback("Edit".to_string(), HashMap::new(), session("a")).await.unwrap();
+        let other = callback("Read".to_string(), HashMap::new(), session("b")).await.unwrap();
+        assert!(matches!(first, PermissionResult::Allow { .. }));
+        assert!(matches!(second, PermissionResult::Deny { .. }));
+        assert!(matches!(other, PermissionResult::Allow { .. }));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit_defers_until_window_frees() {
+        let callback = RateLimit::new()
+            .total(1, Duration::from_secs(60))
+            .on_exceeded(RateLimitAction::Defer)
+            .into_callback();
+
+        let start = tokio::time::Instant::now();
+        callback("Bash".to_string(), HashMap::new(), context()).await.unwrap();
+        let result = callback("Read".to_string(), HashMap::new(), context()).await.unwrap();
+
+        assert!(matches!(result, PermissionResult::Allow { .. }));
+        assert_eq!(start.elapsed(), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_permission_update_constructors() {
+        let update = PermissionUpdate::add_rules(vec![PermissionRuleValue::new("Bash", "npm test:*")], PermissionBehavior::Allow)
+            .with_destination(PermissionUpdateDestination::Session);
+        assert_eq!(
+            serde_json::to_value(&update).unwrap(),
+            serde_json::json!({
+                "type": "addRules",
+                "rules": [{"tool_name": "Bash", "rule_content": "npm test:*"}],
+                "behavior": "allow",
+                "destination": "session"
+            })
+        );
+
+        let update = PermissionUpdate::set_mode(PermissionMode::AcceptEdits);
+        assert_eq!(update.r#type, PermissionUpdateType::SetMode);
+        assert_eq!(update.mode, Some(PermissionMode::AcceptEdits));
+
+        let update = PermissionUpdate::remove_directories(["/tmp"]);
+        assert_eq!(
+            serde_json::to_value(&update).unwrap(),
+            serde_json::json!({"type": "removeDirectories", "directories": ["/tmp"]})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_permission_prompt_server() {
+        let server = permission_prompt_server(deny_tool("Bash"));
+        let call = |tool_name: &str| {
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": {"name": "prompt", "arguments": {"tool_name": tool_name, "input": {"command": "ls"}}}
+            })
+        };
+        let decision = |response: serde_json::Value| -> serde_json::Value {
+            serde_json::from_str(response["result"]["content"][0]["text"].as_str().unwrap()).unwrap()
+        };
+
+        assert_eq!(
+            decision(server.handle_message(&call("Bash")).await),
+            serde_json::json!({"behavior": "deny", "message": "no Bash"})
+        );
+        assert_eq!(
+            decision(server.handle_message(&call("Read")).await),
+            serde_json::json!({"behavior": "allow", "updatedInput": {"command": "ls"}})
+        );
+        assert_eq!(
+            PERMISSION_PROMPT_TOOL,
+            format!("mcp__{}__prompt", PERMISSION_PROMPT_SERVER)
+        );
+    }
 }
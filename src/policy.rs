@@ -0,0 +1,432 @@
+//! Declarative permission policies loaded from a file
+//!
+//! A [`PermissionPolicy`] describes which tools Claude may use without writing
+//! a [`CanUseToolCallback`] by hand. Policies are written in JSON, or in TOML
+//! with the `policy-toml` cargo feature, and turned into a callback with
+//! [`PermissionPolicy::into_callback`].
+//!
+//! A tool request is checked in this order:
+//! 1. `paths`: a `file_path`, `path` or `notebook_path` input under a `deny`
+//!    entry, or outside every `allow` entry, is denied. Relative paths are
+//!    resolved against `root`, and denied when it is not set.
+//! 2. `rules`: the first rule whose `tool` (and `contains`, if set) matches
+//!    decides.
+//! 3. `default`: used when no rule matches (`deny` unless set).
+//!
+//! ```toml
+//! default = "deny"
+//!
+//! [[rules]]
+//! tool = "Read"
+//! action = "allow"
+//!
+//! [[rules]]
+//! tool = "Bash"
+//! action = "deny"
+//! contains = ["rm -rf", "sudo"]
+//! message = "Destructive shell commands are not allowed"
+//!
+//! [[rules]]
+//! tool = "mcp__github__*"
+//! action = "allow"
+//!
+//! [paths]
+//! root = "/srv/project"
+//! allow = ["/srv/project"]
+//! deny = ["/srv/project/.env"]
+//! ```
+//!
+//! # Example
+//! ```no_run
+//! use claude::{ClaudeSDKClient, PermissionPolicy};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let policy = PermissionPolicy::from_path("policy.json")?;
+//!     let mut client = ClaudeSDKClient::builder()
+//!         .can_use_tool(policy.into_callback())
+//!         .build();
+//!     client.connect().await?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::{ClaudeSDKError, Result};
+use crate::permissions::{normalize_path, CanUseToolCallback};
+use crate::types::PermissionResult;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Input keys holding the path a tool operates on
+const PATH_KEYS: [&str; 3] = ["file_path", "path", "notebook_path"];
+
+/// Whether a tool request is allowed or denied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Allow,
+    #[default]
+    Deny,
+}
+
+/// A rule matching tool requests by name and input
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolRule {
+    /// Tool name; `*` matches every tool and a trailing `*` matches a prefix
+    #[serde(deserialize_with = "non_empty")]
+    pub tool: String,
+    /// What to do with a matching request
+    pub action: PolicyAction,
+    /// Only match when some string in the input contains one of these
+    #[serde(default)]
+    pub contains: Vec<String>,
+    /// Message returned to Claude when the rule denies a request
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Directories tools may or may not touch
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathRules {
+    /// Absolute directory that relative paths, in tool inputs and in `allow`
+    /// and `deny`, are resolved against, usually the session's working
+    /// directory. When unset, tool inputs with a relative path are denied.
+    #[serde(default)]
+    pub root: Option<PathBuf>,
+    /// When non-empty, paths outside these directories are denied
+    #[serde(default)]
+    pub allow: Vec<PathBuf>,
+    /// Paths inside these directories are always denied
+    #[serde(default)]
+    pub deny: Vec<PathBuf>,
+}
+
+/// Tool permissions parsed from a policy file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PermissionPolicy {
+    /// Action for requests no rule matches
+    #[serde(default)]
+    pub default: PolicyAction,
+    /// Rules checked in order; the first match wins
+    #[serde(default)]
+    pub rules: Vec<ToolRule>,
+    /// Path allow and deny lists
+    #[serde(default)]
+    pub paths: PathRules,
+}
+
+impl PermissionPolicy {
+    /// Load a policy, choosing the format from the file extension
+    ///
+    /// `.toml` files need the `policy-toml` feature; anything else is parsed
+    /// as JSON. Parse and schema errors are reported as
+    /// [`ClaudeSDKError::InvalidPolicy`] with the offending line and column.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ClaudeSDKError::invalid_policy(&name, None, None, e.to_string()))?;
+
+        let is_toml = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+        if is_toml {
+            Self::parse_toml(&name, &contents)
+        } else {
+            Self::parse_json(&name, &contents)
+        }
+    }
+
+    /// Parse a policy written in JSON
+    pub fn from_json_str(contents: &str) -> Result<Self> {
+        Self::parse_json("<json>", contents)
+    }
+
+    /// Parse a policy written in TOML
+    #[cfg(feature = "policy-toml")]
+    pub fn from_toml_str(contents: &str) -> Result<Self> {
+        Self::parse_toml("<toml>", contents)
+    }
+
+    fn parse_json(name: &str, contents: &str) -> Result<Self> {
+        serde_json::from_str(contents).map_err(|e| {
+            // serde_json appends " at line L column C" to its messages
+            let message = e.to_string();
+            let message = message.split(" at line ").next().unwrap_or(&message);
+            ClaudeSDKError::invalid_policy(name, Some(e.line()), Some(e.column()), message)
+        })
+    }
+
+    #[cfg(feature = "policy-toml")]
+    fn parse_toml(name: &str, contents: &str) -> Result<Self> {
+        toml::from_str(contents).map_err(|e| {
+            let (line, column) = match e.span() {
+                Some(span) => {
+                    let (line, column) = line_column(contents, span.start);
+                    (Some(line), Some(column))
+                }
+                None => (None, None),
+            };
+            ClaudeSDKError::invalid_policy(name, line, column, e.message())
+        })
+    }
+
+    #[cfg(not(feature = "policy-toml"))]
+    fn parse_toml(name: &str, _contents: &str) -> Result<Self> {
+        Err(ClaudeSDKError::invalid_policy(
+            name,
+            None,
+            None,
+            "TOML policies require the `policy-toml` feature",
+        ))
+    }
+
+    /// Decide whether `tool_name` may run with `input`
+    pub fn evaluate(&self, tool_name: &str, input: &HashMap<String, Value>) -> PermissionResult {
+        if let Some(denial) = self.paths.check(input) {
+            return PermissionResult::deny(denial);
+        }
+
+        let rule = self
+            .rules
+            .iter()
+            .find(|rule| rule.matches(tool_name, input));
+
+        match rule {
+            Some(rule) if rule.action == PolicyAction::Allow => PermissionResult::allow(),
+            Some(rule) => PermissionResult::deny(
+                rule.message
+                    .clone()
+                    .unwrap_or_else(|| format!("{} is denied by policy", tool_name)),
+            ),
+            None if self.default == PolicyAction::Allow => PermissionResult::allow(),
+            None => PermissionResult::deny(format!("{} is not allowed by policy", tool_name)),
+        }
+    }
+
+    /// Turn the policy into a `can_use_tool` callback
+    pub fn into_callback(self) -> CanUseToolCallback {
+        let policy = Arc::new(self);
+        Arc::new(move |tool_name, input, _context| {
+            let result = policy.evaluate(&tool_name, &input);
+            Box::pin(async move { Ok(result) })
+        })
+    }
+}
+
+impl ToolRule {
+    fn matches(&self, tool_name: &str, input: &HashMap<String, Value>) -> bool {
+        let name_matches = match self.tool.strip_suffix('*') {
+            Some(prefix) => tool_name.starts_with(prefix),
+            None => self.tool == tool_name,
+        };
+
+        name_matches
+            && (self.contains.is_empty() || input.values().any(|value| self.value_contains(value)))
+    }
+
+    fn value_contains(&self, value: &Value) -> bool {
+        match value {
+            Value::String(s) => self.contains.iter().any(|needle| s.contains(needle.as_str())),
+            Value::Array(items) => items.iter().any(|item| self.value_contains(item)),
+            Value::Object(map) => map.values().any(|item| self.value_contains(item)),
+            _ => false,
+        }
+    }
+}
+
+impl PathRules {
+    /// Reason the path in `input` is denied, if any
+    fn check(&self, input: &HashMap<String, Value>) -> Option<String> {
+        if self.allow.is_empty() && self.deny.is_empty() {
+            return None;
+        }
+        let path = PATH_KEYS
+            .iter()
+            .find_map(|key| input.get(*key).and_then(Value::as_str))?;
+        if Path::new(path).is_relative() && self.root.is_none() {
+            return Some(format!("{} is a relative path and the policy sets no `paths.root`", path));
+        }
+        let resolve = |path: &Path| match &self.root {
+            Some(root) => normalize_path(&root.join(path)),
+            None => normalize_path(path),
+        };
+        let normalized = resolve(Path::new(path));
+
+        if self.deny.iter().any(|dir| normalized.starts_with(resolve(dir))) {
+            return Some(format!("{} is in a denied path", path));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|dir| normalized.starts_with(resolve(dir))) {
+            return Some(format!("{} is outside the allowed paths", path));
+        }
+        None
+    }
+}
+
+/// 1-based line and column of a byte offset
+#[cfg(feature = "policy-toml")]
+fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+fn non_empty<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<String, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    if value.trim().is_empty() {
+        return Err(serde::de::Error::custom("tool name must not be empty"));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn input(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn is_allowed(result: &PermissionResult) -> bool {
+        matches!(result, PermissionResult::Allow { .. })
+    }
+
+    const POLICY: &str = r#"{
+        "default": "deny",
+        "rules": [
+            {"tool": "Bash", "action": "deny", "contains": ["rm -rf"], "message": "No deleting"},
+            {"tool": "Bash", "action": "allow"},
+            {"tool": "Read", "action": "allow"},
+            {"tool": "mcp__github__*", "action": "allow"}
+        ],
+        "paths": {"allow": ["/srv/project"], "deny": ["/srv/project/.env"]}
+    }"#;
+
+    #[test]
+    fn test_evaluate_rules_in_order() {
+        let policy = PermissionPolicy::from_json_str(POLICY).unwrap();
+
+        assert!(is_allowed(&policy.evaluate("Bash", &input(json!({"command": "ls"})))));
+        assert!(matches!(
+            policy.evaluate("Bash", &input(json!({"command": "rm -rf /"}))),
+            PermissionResult::Deny { ref message, .. } if message == "No deleting"
+        ));
+        assert!(is_allowed(&policy.evaluate("mcp__github__create_issue", &HashMap::new())));
+        assert!(matches!(
+            policy.evaluate("Write", &HashMap::new()),
+            PermissionResult::Deny { ref message, .. } if message == "Write is not allowed by policy"
+        ));
+    }
+
+    #[test]
+    fn test_evaluate_paths() {
+        let policy = PermissionPolicy::from_json_str(POLICY).unwrap();
+
+        assert!(is_allowed(&policy.evaluate("Read", &input(json!({"file_path": "/srv/project/src/lib.rs"})))));
+        assert!(!is_allowed(&policy.evaluate("Read", &input(json!({"file_path": "/srv/project/.env"})))));
+        assert!(!is_allowed(&policy.evaluate("Read", &input(json!({"file_path": "/etc/passwd"})))));
+        assert!(!is_allowed(&policy.evaluate("Read", &input(json!({"path": "/srv/project/../secrets"})))));
+    }
+
+    #[test]
+    fn test_relative_paths_cannot_bypass_path_rules() {
+        let mut policy = PermissionPolicy::from_json_str(POLICY).unwrap();
+        for path in [".env", "./.env", "src/lib.rs"] {
+            assert!(matches!(
+                policy.evaluate("Read", &input(json!({"file_path": path}))),
+                PermissionResult::Deny { ref message, .. } if message.contains("relative path")
+            ));
+        }
+
+        policy.paths.root = Some(PathBuf::from("/srv/project"));
+        assert!(!is_allowed(&policy.evaluate("Read", &input(json!({"file_path": ".env"})))));
+        assert!(!is_allowed(&policy.evaluate("Read", &input(json!({"file_path": "./src/../.env"})))));
+        assert!(!is_allowed(&policy.evaluate("Read", &input(json!({"file_path": "../other/file"})))));
+        assert!(is_allowed(&policy.evaluate("Read", &input(json!({"file_path": "src/lib.rs"})))));
+
+        // Relative entries are resolved against the root too
+        policy.paths.deny = vec![PathBuf::from("secrets")];
+        assert!(!is_allowed(&policy.evaluate("Read", &input(json!({"file_path": "/srv/project/secrets/key"})))));
+    }
+
+    #[test]
+    fn test_json_errors_point_at_line() {
+        let err = PermissionPolicy::from_json_str("{\n  \"default\": \"deny\",\n  \"rules\": [{\"tool\": \"Bash\", \"action\": \"maybe\"}]\n}")
+            .unwrap_err();
+        match err {
+            ClaudeSDKError::InvalidPolicy { line, message, .. } => {
+                assert_eq!(line, Some(3));
+                assert!(message.contains("unknown variant `maybe`"), "{}", message);
+                assert!(!message.contains(" at line "), "{}", message);
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+
+        let err = PermissionPolicy::from_json_str("{\"rules\": [{\"tool\": \"\", \"action\": \"allow\"}]}").unwrap_err();
+        assert!(err.to_string().contains("tool name must not be empty"), "{}", err);
+
+        let err = PermissionPolicy::from_json_str("{\"defualt\": \"allow\"}").unwrap_err();
+        assert!(err.to_string().contains("unknown field `defualt`"), "{}", err);
+    }
+
+    #[test]
+    fn test_from_path() {
+        let dir = std::env::temp_dir().join(format!("claude-rs-policy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("policy.json"), POLICY).unwrap();
+
+        let policy = PermissionPolicy::from_path(dir.join("policy.json")).unwrap();
+        assert_eq!(policy.rules.len(), 4);
+
+        let err = PermissionPolicy::from_path(dir.join("missing.json")).unwrap_err();
+        assert!(matches!(err, ClaudeSDKError::InvalidPolicy { line: None, .. }));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "policy-toml")]
+    #[test]
+    fn test_toml_policy() {
+        let policy = PermissionPolicy::from_toml_str(
+            r#"
+default = "allow"
+
+[[rules]]
+tool = "Bash"
+action = "deny"
+"#,
+        )
+        .unwrap();
+        assert!(!is_allowed(&policy.evaluate("Bash", &HashMap::new())));
+        assert!(is_allowed(&policy.evaluate("Read", &HashMap::new())));
+
+        let err = PermissionPolicy::from_toml_str("default = \"deny\"\n\n[[rules]]\ntool = \"Bash\"\naction = \"maybe\"\n")
+            .unwrap_err();
+        match err {
+            ClaudeSDKError::InvalidPolicy { line, column, message, .. } => {
+                assert_eq!(line, Some(5));
+                assert_eq!(column, Some(10));
+                assert!(message.contains("unknown variant `maybe`"), "{}", message);
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_into_callback() {
+        let callback = PermissionPolicy::from_json_str(POLICY).unwrap().into_callback();
+        let context = crate::types::ToolPermissionContext::default();
+        let result = callback("Read".to_string(), HashMap::new(), context)
+            .await
+            .unwrap();
+        assert!(is_allowed(&result));
+    }
+}
@@ -1,17 +1,21 @@
-/// Prelude module with commonly used imports
-///
-/// Import everything you need to get started:
-/// ```
-/// use claude::prelude::*;
-/// ```
+//! Prelude module with commonly used imports
+//!
+//! Import everything you need to get started:
+//! ```
+//! use claude::prelude::*;
+//! ```
 
 // Main APIs
 pub use crate::simple_query::simple_query;
 pub use crate::streaming_query::{streaming_query, StreamingQuery};
-pub use crate::client::ClaudeSDKClient;
+pub use crate::client::{ClaudeSDKClient, ClaudeSDKClientBuilder};
+pub use crate::handlers::Handlers;
+pub use crate::template::PromptTemplate;
 
 // Facade (simple entry points)
-pub use crate::facade::{ask, ask_with_options, QuickQuery};
+pub use crate::facade::{ask, ask_detailed, ask_stream, ask_with_files, ask_with_options, Conversation, Plan, QueryResult, QuickQuery};
+#[cfg(feature = "schemars")]
+pub use crate::facade::ask_json;
 
 // Core types
 pub use crate::types::{
@@ -22,11 +26,12 @@ pub use crate::types::{
 pub use crate::builders::ClaudeOptionsBuilder;
 
 // Error handling
-pub use crate::errors::{ClaudeSDKError, Result};
+pub use crate::errors::{ClaudeSDKError, ErrorKind, Result};
 
 // Extension traits
 pub use crate::extensions::MessageVecExt;
+pub use crate::permissions::CanUseToolExt;
 
 // Macros (re-exported at crate root, but mentioned here for discovery)
 #[doc(inline)]
-pub use crate::{hook, permission_callback};
+pub use crate::{hook, hook_fn, permission_callback};
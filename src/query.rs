@@ -1,15 +1,109 @@
+use crate::budget::BudgetTracker;
+use crate::cancel::CancelHandle;
 use crate::errors::{ClaudeSDKError, Result};
-use crate::hooks::HookManager;
-use crate::mcp_server::SdkMcpServer;
-use crate::message_parser::parse_message;
+use crate::hooks::{run_callback, HookManager, HookMatcherConfig};
+use crate::mcp_server::{ProgressSink, SdkMcpServer, ToolProgress};
+use crate::message_parser::parse_message_with_mode;
+use crate::metrics::{ActiveSession, Metrics};
+use crate::cost::CostTracker;
 use crate::permissions::CanUseToolCallback;
-use crate::transport::{SubprocessTransport, Transport};
-use crate::types::{ControlRequest, ControlResponseType, Message, PermissionResult, SDKControlRequest, SDKControlResponse, ToolPermissionContext};
+use crate::transport::Transport;
+use crate::types::{ControlRequest, ControlResponseType, HeartbeatConfig, HeartbeatEvent, HookContext, Message, ParseMode, PermissionResult, RestartPolicy, SDKControlRequest, SDKControlResponse, SessionId, SystemMessage, SystemMessageKind, ToolPermissionContext};
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, Mutex, RwLock};
 
+/// Messages buffered for the consumer when `message_channel_capacity` is unset
+pub(crate) const DEFAULT_MESSAGE_CHANNEL_CAPACITY: usize = 100;
+
+/// How long control requests wait for a response unless configured
+pub(crate) const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long `ping` waits for the CLI to answer
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Progress updates a tool can queue before further ones are dropped
+const TOOL_PROGRESS_CAPACITY: usize = 32;
+
+type PendingResponses = Arc<RwLock<HashMap<String, tokio::sync::oneshot::Sender<Result<Value>>>>>;
+
+/// Where the reader delivers the messages of routed sessions
+///
+/// The CLI tags its output with the session ID it assigned, not the one a
+/// prompt was sent with. Messages with a routed ID go to its sender; the
+/// others go, turn by turn, to the senders of prompts waiting for an answer.
+#[derive(Default)]
+pub(crate) struct SessionRoutes {
+    /// Senders by session ID
+    by_id: HashMap<String, mpsc::Sender<Result<Message>>>,
+    /// Session IDs of prompts that have not been answered, oldest first
+    waiting: VecDeque<String>,
+    /// Sender of the turn in progress, until its result message
+    turn: Option<mpsc::Sender<Result<Message>>>,
+}
+
+impl SessionRoutes {
+    /// Deliver the answer to the next prompt sent as `session_id` to its sender
+    pub(crate) fn expect_answer(&mut self, session_id: &str) {
+        self.waiting.push_back(session_id.to_string());
+    }
+
+    /// Undo [`expect_answer`](Self::expect_answer) for a prompt that was not sent
+    pub(crate) fn cancel_answer(&mut self, session_id: &str) {
+        if let Some(index) = self.waiting.iter().rposition(|id| id == session_id) {
+            self.waiting.remove(index);
+        }
+    }
+
+    /// Sender for a message tagged `session_id`, if it is routed
+    fn route(&mut self, session_id: &str, ends_turn: bool) -> Option<mpsc::Sender<Result<Message>>> {
+        if let Some(tx) = self.by_id.get(session_id) {
+            // The CLI kept this session's ID, so its prompts need no turn
+            self.waiting.retain(|id| id != session_id);
+            return Some(tx.clone());
+        }
+        if self.turn.is_none() {
+            self.turn = self.waiting.pop_front().and_then(|id| self.by_id.get(&id).cloned());
+        }
+        let tx = self.turn.clone();
+        if ends_turn {
+            self.turn = None;
+        }
+        tx
+    }
+
+    /// Stop routing to `tx`, whose receiver is gone
+    fn remove(&mut self, tx: &mpsc::Sender<Result<Message>>) {
+        self.by_id.retain(|_, routed| !routed.same_channel(tx));
+        let by_id = &self.by_id;
+        self.waiting.retain(|id| by_id.contains_key(id));
+        if self.turn.as_ref().is_some_and(|turn| turn.same_channel(tx)) {
+            self.turn = None;
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = SessionRoutes::default();
+    }
+}
+
+/// Builds a replacement transport, given the last session ID seen
+type RespawnFn = Arc<dyn Fn(Option<SessionId>) -> Box<dyn Transport> + Send + Sync>;
+
+/// Cancels in-flight SDK MCP tool calls; replaced with a fresh handle on interrupt
+type ToolCancel = Arc<std::sync::Mutex<CancelHandle>>;
+
+/// Restarts the transport when it stops unexpectedly
+#[derive(Clone)]
+struct Supervisor {
+    policy: RestartPolicy,
+    respawn: RespawnFn,
+}
+
 /// Query handles control protocol for bidirectional communication
 ///
 /// This manages:
@@ -19,16 +113,30 @@ use tokio::sync::{mpsc, Mutex, RwLock};
 /// - Hook callbacks
 /// - Permission callbacks (can_use_tool)
 pub struct Query {
-    transport: Arc<Mutex<SubprocessTransport>>,
+    // Writers and lifecycle calls share this lock; the reader task reads
+    // from a stream taken out of the transport and never holds it
+    transport: Arc<Mutex<Box<dyn Transport>>>,
     is_streaming_mode: bool,
 
     // Control protocol state
-    pending_responses: Arc<RwLock<HashMap<String, tokio::sync::oneshot::Sender<Result<Value>>>>>,
+    pending_responses: PendingResponses,
     request_counter: Arc<Mutex<u64>>,
+    request_timeout: Duration,
 
     // Message channels
-    message_tx: mpsc::UnboundedSender<Result<Message>>,
-    message_rx: Option<mpsc::UnboundedReceiver<Result<Message>>>,
+    message_tx: Option<mpsc::Sender<Result<Message>>>,
+    message_rx: Option<mpsc::Receiver<Result<Message>>>,
+    channel_capacity: usize,
+    parse_mode: ParseMode,
+    sessions: Arc<std::sync::Mutex<SessionRoutes>>,
+
+    // Spending limits; crossing one interrupts the session
+    budget: Option<Arc<std::sync::Mutex<BudgetTracker>>>,
+
+    // Metrics reporting; the session counts as active while the reader runs
+    metrics: Option<Metrics>,
+    active_session: Option<ActiveSession>,
+    cost_tracker: Option<CostTracker>,
 
     // Hooks support
     hook_manager: Option<Arc<Mutex<HookManager>>>,
@@ -36,262 +144,669 @@ pub struct Query {
     // Permission callback
     can_use_tool: Option<CanUseToolCallback>,
 
-    // MCP servers
+    // MCP servers; interrupting cancels the tool calls sharing the handle
     mcp_servers: Arc<HashMap<String, SdkMcpServer>>,
+    tool_cancel: ToolCancel,
+
+    // Crash recovery
+    supervisor: Option<Supervisor>,
+
+    // Set while the reader task is running
+    connected: Arc<AtomicBool>,
+    session_id: Arc<std::sync::Mutex<Option<SessionId>>>,
+    turns_completed: Arc<AtomicU64>,
+    closed: bool,
 
     // Background task handles
     read_task: Option<tokio::task::JoinHandle<()>>,
-    control_task: Option<tokio::task::JoinHandle<()>>,
+    heartbeat_task: Option<tokio::task::JoinHandle<()>>,
+    tool_list_tasks: Vec<tokio::task::JoinHandle<()>>,
 }
 
 impl Query {
     /// Create a new Query instance
     pub fn new(
-        transport: SubprocessTransport,
+        transport: Box<dyn Transport>,
         is_streaming_mode: bool,
     ) -> Self {
-        let (message_tx, message_rx) = mpsc::unbounded_channel();
-
-        Self {
-            transport: Arc::new(Mutex::new(transport)),
-            is_streaming_mode,
-            pending_responses: Arc::new(RwLock::new(HashMap::new())),
-            request_counter: Arc::new(Mutex::new(0)),
-            message_tx,
-            message_rx: Some(message_rx),
-            hook_manager: None,
-            can_use_tool: None,
-            mcp_servers: Arc::new(HashMap::new()),
-            read_task: None,
-            control_task: None,
-        }
+        Self::base(transport, is_streaming_mode, None, None, HashMap::new())
     }
 
     /// Create a Query instance with hooks support
     pub fn with_hooks(
-        transport: SubprocessTransport,
+        transport: Box<dyn Transport>,
         is_streaming_mode: bool,
         hook_manager: HookManager,
     ) -> Self {
-        let (message_tx, message_rx) = mpsc::unbounded_channel();
-
-        Self {
-            transport: Arc::new(Mutex::new(transport)),
-            is_streaming_mode,
-            pending_responses: Arc::new(RwLock::new(HashMap::new())),
-            request_counter: Arc::new(Mutex::new(0)),
-            message_tx,
-            message_rx: Some(message_rx),
-            hook_manager: Some(Arc::new(Mutex::new(hook_manager))),
-            can_use_tool: None,
-            mcp_servers: Arc::new(HashMap::new()),
-            read_task: None,
-            control_task: None,
-        }
+        Self::base(transport, is_streaming_mode, Some(hook_manager), None, HashMap::new())
     }
 
     /// Create a Query instance with permission callback
     pub fn with_can_use_tool(
-        transport: SubprocessTransport,
+        transport: Box<dyn Transport>,
         is_streaming_mode: bool,
         can_use_tool: CanUseToolCallback,
     ) -> Self {
-        let (message_tx, message_rx) = mpsc::unbounded_channel();
-
-        Self {
-            transport: Arc::new(Mutex::new(transport)),
-            is_streaming_mode,
-            pending_responses: Arc::new(RwLock::new(HashMap::new())),
-            request_counter: Arc::new(Mutex::new(0)),
-            message_tx,
-            message_rx: Some(message_rx),
-            hook_manager: None,
-            can_use_tool: Some(can_use_tool),
-            mcp_servers: Arc::new(HashMap::new()),
-            read_task: None,
-            control_task: None,
-        }
+        Self::base(transport, is_streaming_mode, None, Some(can_use_tool), HashMap::new())
     }
 
     /// Create a Query instance with MCP servers
     pub fn with_mcp_servers(
-        transport: SubprocessTransport,
+        transport: Box<dyn Transport>,
         is_streaming_mode: bool,
         mcp_servers: HashMap<String, SdkMcpServer>,
     ) -> Self {
-        let (message_tx, message_rx) = mpsc::unbounded_channel();
-
-        Self {
-            transport: Arc::new(Mutex::new(transport)),
-            is_streaming_mode,
-            pending_responses: Arc::new(RwLock::new(HashMap::new())),
-            request_counter: Arc::new(Mutex::new(0)),
-            message_tx,
-            message_rx: Some(message_rx),
-            hook_manager: None,
-            can_use_tool: None,
-            mcp_servers: Arc::new(mcp_servers),
-            read_task: None,
-            control_task: None,
-        }
+        Self::base(transport, is_streaming_mode, None, None, mcp_servers)
     }
 
     /// Create a Query instance with all options
     pub fn with_options(
-        transport: SubprocessTransport,
+        transport: Box<dyn Transport>,
         is_streaming_mode: bool,
         can_use_tool: Option<CanUseToolCallback>,
         mcp_servers: Option<HashMap<String, SdkMcpServer>>,
     ) -> Self {
-        let (message_tx, message_rx) = mpsc::unbounded_channel();
+        Self::base(transport, is_streaming_mode, None, can_use_tool, mcp_servers.unwrap_or_default())
+    }
+
+    fn base(
+        transport: Box<dyn Transport>,
+        is_streaming_mode: bool,
+        hook_manager: Option<HookManager>,
+        can_use_tool: Option<CanUseToolCallback>,
+        mcp_servers: HashMap<String, SdkMcpServer>,
+    ) -> Self {
+        let (message_tx, message_rx) = mpsc::channel(DEFAULT_MESSAGE_CHANNEL_CAPACITY);
 
         Self {
             transport: Arc::new(Mutex::new(transport)),
             is_streaming_mode,
             pending_responses: Arc::new(RwLock::new(HashMap::new())),
             request_counter: Arc::new(Mutex::new(0)),
-            message_tx,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            message_tx: Some(message_tx),
             message_rx: Some(message_rx),
-            hook_manager: None,
+            channel_capacity: DEFAULT_MESSAGE_CHANNEL_CAPACITY,
+            parse_mode: ParseMode::Strict,
+            sessions: Arc::default(),
+            budget: None,
+            metrics: None,
+            active_session: None,
+            cost_tracker: None,
+            hook_manager: hook_manager.map(|manager| Arc::new(Mutex::new(manager))),
             can_use_tool,
-            mcp_servers: Arc::new(mcp_servers.unwrap_or_default()),
+            mcp_servers: Arc::new(mcp_servers),
+            tool_cancel: Arc::new(std::sync::Mutex::new(CancelHandle::new())),
+            supervisor: None,
+            connected: Arc::new(AtomicBool::new(false)),
+            session_id: Arc::new(std::sync::Mutex::new(None)),
+            turns_completed: Arc::new(AtomicU64::new(0)),
+            closed: false,
             read_task: None,
-            control_task: None,
+            tool_list_tasks: Vec::new(),
+            heartbeat_task: None,
         }
     }
 
+    /// Set how many parsed messages are buffered before the reader pauses
+    ///
+    /// While the buffer is full the reader stops pulling from the transport,
+    /// so control responses are not processed until the consumer catches up.
+    /// Must be called before [`start`](Self::start); a capacity of 0 is
+    /// treated as 1.
+    pub fn with_channel_capacity(mut self, capacity: usize) -> Self {
+        let (message_tx, message_rx) = mpsc::channel(capacity.max(1));
+        self.message_tx = Some(message_tx);
+        self.message_rx = Some(message_rx);
+        self.channel_capacity = capacity.max(1);
+        self
+    }
+
+    /// Set how unknown message and content block types are handled (default strict)
+    pub fn with_parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parse_mode = mode;
+        self
+    }
+
+    /// Enforce the limits tracked by `budget`
+    ///
+    /// Once a limit is crossed the stream yields
+    /// [`ClaudeSDKError::BudgetExceeded`] after the message that crossed it,
+    /// the session is interrupted, and [`send_message`](Self::send_message)
+    /// fails with the same error.
+    pub(crate) fn with_budget(mut self, budget: BudgetTracker) -> Self {
+        self.budget = Some(Arc::new(std::sync::Mutex::new(budget)));
+        self
+    }
+
+    /// Add the cost and tokens of each result to `tracker`
+    pub(crate) fn with_cost_tracker(mut self, tracker: CostTracker) -> Self {
+        self.cost_tracker = Some(tracker);
+        self
+    }
+
+    /// Report the messages read and the errors that end the session to `metrics`
+    pub(crate) fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Set how long control requests wait for the CLI to respond (default 30 s)
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Dispatch hook callbacks to a manager shared with the caller
+    pub(crate) fn with_shared_hooks(mut self, hook_manager: Arc<Mutex<HookManager>>) -> Self {
+        self.hook_manager = Some(hook_manager);
+        self
+    }
+
+    /// Restart the transport if it stops producing output
+    ///
+    /// When the stream ends or fails, `respawn` is called with the last
+    /// session ID seen and the new transport replaces the old one in place.
+    /// The message stream continues with a `System` message of subtype
+    /// `reconnect` (data: `session_id`, `attempt`, `reason`). After
+    /// `policy.max_restarts` restarts the failure is reported as usual.
+    pub fn with_restart(
+        mut self,
+        policy: RestartPolicy,
+        respawn: impl Fn(Option<SessionId>) -> Box<dyn Transport> + Send + Sync + 'static,
+    ) -> Self {
+        self.supervisor = Some(Supervisor {
+            policy,
+            respawn: Arc::new(respawn),
+        });
+        self
+    }
+
     /// Start reading messages from transport
+    ///
+    /// Calling `start` again once the reader is running is a no-op.
     pub async fn start(&mut self) -> Result<()> {
+        // The reader task owns the only sender, so the message stream ends
+        // once the transport stops producing output
+        let Some(message_tx) = self.message_tx.take() else {
+            return Ok(());
+        };
         let transport = Arc::clone(&self.transport);
-        let message_tx = self.message_tx.clone();
         let pending_responses = Arc::clone(&self.pending_responses);
         let can_use_tool = self.can_use_tool.clone();
+        let mcp_servers = Arc::clone(&self.mcp_servers);
+        let tool_cancel = Arc::clone(&self.tool_cancel);
+        let hook_manager = self.hook_manager.clone();
+        let supervisor = self.supervisor.clone();
+        let request_counter = Arc::clone(&self.request_counter);
+        let is_streaming_mode = self.is_streaming_mode;
+        let parse_mode = self.parse_mode;
+        let connected = Arc::clone(&self.connected);
+        let sessions = Arc::clone(&self.sessions);
+        let current_session_id = Arc::clone(&self.session_id);
+        let turns_completed = Arc::clone(&self.turns_completed);
+        let budget = self.budget.clone();
+        let metrics = self.metrics.clone();
+        let report_error = {
+            let metrics = metrics.clone();
+            move |e: &ClaudeSDKError| {
+                if let Some(ref metrics) = metrics {
+                    metrics.record_error(e);
+                }
+            }
+        };
+        self.active_session = self.metrics.as_ref().map(Metrics::session);
+        let cost_tracker = self.cost_tracker.clone();
+        let interrupter = self.interrupter();
+
+        // Take the stream up front so the transport lock is not held while reading
+        let mut stream = transport.lock().await.read_messages();
 
+        self.connected.store(true, Ordering::SeqCst);
         let task = tokio::spawn(async move {
-            let mut transport_guard = transport.lock().await;
-            let stream = transport_guard.read_messages();
+            use futures::StreamExt;
+            let mut last_session_id = None;
+            let mut restarts = 0;
 
-            futures::pin_mut!(stream);
+            'session: loop {
+                let mut failure = None;
 
-            use futures::StreamExt;
-            while let Some(result) = stream.next().await {
-                match result {
-                    Ok(json_value) => {
-                        // Check message type
-                        if let Some(msg_type) = json_value.get("type").and_then(|v| v.as_str()) {
-                            match msg_type {
-                                "control_response" => {
-                                    // Handle control response (from CLI to SDK)
-                                    if let Ok(ctrl_response) = serde_json::from_value::<SDKControlResponse>(json_value.clone()) {
-                                        if let ControlResponseType::Success { request_id, response } = ctrl_response.response {
-                                            let mut responses = pending_responses.write().await;
-                                            if let Some(tx) = responses.remove(&request_id) {
-                                                let result_value = response
-                                                    .map(|r| json!(r))
-                                                    .unwrap_or(json!({}));
-                                                let _ = tx.send(Ok(result_value));
-                                            }
-                                        } else if let ControlResponseType::Error { request_id, error } = ctrl_response.response {
-                                            let mut responses = pending_responses.write().await;
-                                            if let Some(tx) = responses.remove(&request_id) {
-                                                let _ = tx.send(Err(ClaudeSDKError::cli_connection_error(error)));
+                while let Some(result) = stream.next().await {
+                    match result {
+                        Ok(json_value) => {
+                            // Check message type
+                            if let Some(msg_type) = json_value.get("type").and_then(|v| v.as_str()) {
+                                match msg_type {
+                                    "control_response" => {
+                                        // Handle control response (from CLI to SDK)
+                                        if let Ok(ctrl_response) = serde_json::from_value::<SDKControlResponse>(json_value.clone()) {
+                                            if let ControlResponseType::Success { request_id, response } = ctrl_response.response {
+                                                let mut responses = pending_responses.write().await;
+                                                if let Some(tx) = responses.remove(&request_id) {
+                                                    let result_value = response
+                                                        .map(|r| json!(r))
+                                                        .unwrap_or(json!({}));
+                                                    let _ = tx.send(Ok(result_value));
+                                                }
+                                            } else if let ControlResponseType::Error { request_id, error } = ctrl_response.response {
+                                                let mut responses = pending_responses.write().await;
+                                                if let Some(tx) = responses.remove(&request_id) {
+                                                    let _ = tx.send(Err(ClaudeSDKError::control_protocol(request_id, error)));
+                                                }
                                             }
                                         }
+                                        continue;
                                     }
-                                    continue;
-                                }
-                                "control_request" => {
-                                    // Handle control request (from CLI asking SDK)
-                                    if let Ok(ctrl_request) = serde_json::from_value::<SDKControlRequest>(json_value.clone()) {
-                                        // Handle can_use_tool requests
-                                        if let ControlRequest::CanUseTool { tool_name, input, .. } = ctrl_request.request {
-                                            if let Some(ref callback) = can_use_tool {
-                                                let context = ToolPermissionContext {
-                                                    suggestions: vec![], // TODO: Parse permission_suggestions properly
-                                                };
-
+                                    "control_request" => {
+                                        // Handle control request (from CLI asking SDK)
+                                        if let Ok(ctrl_request) = serde_json::from_value::<SDKControlRequest>(json_value.clone()) {
+                                            // Route MCP messages to in-process SDK servers
+                                            if let ControlRequest::McpMessage { server_name, message } = &ctrl_request.request {
                                                 let transport_clone = Arc::clone(&transport);
+                                                let mcp_servers = Arc::clone(&mcp_servers);
                                                 let request_id = ctrl_request.request_id.clone();
-                                                let callback_clone = Arc::clone(callback);
+                                                let server_name = server_name.clone();
+                                                let message = message.clone();
+                                                let request_counter = Arc::clone(&request_counter);
+                                                let cancel = tool_cancel.lock().unwrap().clone();
+                                                // A weak sender so a slow tool does not keep the stream open
+                                                let message_tx = message_tx.downgrade();
 
                                                 tokio::spawn(async move {
-                                                    match callback_clone(tool_name.clone(), input.clone(), context).await {
-                                                        Ok(perm_result) => {
-                                                            // Convert PermissionResult to response
-                                                            let mut response_data = HashMap::new();
-                                                            match perm_result {
-                                                                PermissionResult::Allow { updated_input, .. } => {
-                                                                    response_data.insert("allow".to_string(), json!(true));
-                                                                    if let Some(input) = updated_input {
-                                                                        response_data.insert("input".to_string(), json!(input));
-                                                                    }
-                                                                }
-                                                                PermissionResult::Deny { message, .. } => {
-                                                                    response_data.insert("allow".to_string(), json!(false));
-                                                                    response_data.insert("reason".to_string(), json!(message));
-                                                                }
+                                                    let response = match mcp_servers.get(&server_name) {
+                                                        Some(server) => {
+                                                            // Updates that arrive while the CLI is slow to
+                                                            // take them are dropped, later ones supersede them
+                                                            let (progress_tx, mut progress_rx) = mpsc::channel(TOOL_PROGRESS_CAPACITY);
+                                                            let sink: ProgressSink = Arc::new(move |progress| {
+                                                                let _ = progress_tx.try_send(progress);
+                                                            });
+                                                            let forward = |progress: ToolProgress| {
+                                                                forward_tool_progress(
+                                                                    &transport_clone,
+                                                                    &request_counter,
+                                                                    &message_tx,
+                                                                    &server_name,
+                                                                    progress,
+                                                                )
                                                             };
 
-                                                            // Send response
-                                                            let response = SDKControlResponse {
-                                                                r#type: "control_response".to_string(),
-                                                                response: ControlResponseType::Success {
-                                                                    request_id,
-                                                                    response: Some(response_data),
-                                                                },
+                                                            // Forward progress while the tool runs, and all of it before the result
+                                                            let call = server.handle(&message, Some(sink), Some(cancel));
+                                                            tokio::pin!(call);
+                                                            let mcp_response = loop {
+                                                                tokio::select! {
+                                                                    biased;
+                                                                    Some(progress) = progress_rx.recv() => forward(progress).await,
+                                                                    response = &mut call => break response,
+                                                                }
                                                             };
+                                                            while let Ok(progress) = progress_rx.try_recv() {
+                                                                forward(progress).await;
+                                                            }
 
-                                                            if let Ok(response_str) = serde_json::to_string(&response) {
-                                                                let _ = transport_clone.lock().await.write(&format!("{}\n", response_str)).await;
+                                                            let mut response_data = HashMap::new();
+                                                            response_data.insert("mcp_response".to_string(), mcp_response);
+                                                            ControlResponseType::Success {
+                                                                request_id,
+                                                                response: Some(response_data),
                                                             }
                                                         }
-                                                        Err(e) => {
-                                                            // Send error response
-                                                            let response = SDKControlResponse {
-                                                                r#type: "control_response".to_string(),
-                                                                response: ControlResponseType::Error {
-                                                                    request_id,
-                                                                    error: e.to_string(),
-                                                                },
-                                                            };
+                                                        None => ControlResponseType::Error {
+                                                            request_id,
+                                                            error: format!("SDK MCP server '{}' not found", server_name),
+                                                        },
+                                                    };
 
-                                                            if let Ok(response_str) = serde_json::to_string(&response) {
-                                                                let _ = transport_clone.lock().await.write(&format!("{}\n", response_str)).await;
-                                                            }
+                                                    let response = SDKControlResponse {
+                                                        r#type: "control_response".to_string(),
+                                                        response,
+                                                    };
+
+                                                    let _ = write_json(&transport_clone, &response).await;
+                                                });
+                                                continue;
+                                            }
+
+                                            // Run registered hook callbacks
+                                            if let ControlRequest::HookCallback { callback_id, input, tool_use_id } = &ctrl_request.request {
+                                                let transport_clone = Arc::clone(&transport);
+                                                let hook_manager = hook_manager.clone();
+                                                let request_id = ctrl_request.request_id.clone();
+                                                let callback_id = callback_id.clone();
+                                                let input: HashMap<String, Value> = input
+                                                    .as_object()
+                                                    .map(|o| o.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+                                                    .unwrap_or_default();
+                                                let tool_use_id = tool_use_id.clone();
+                                                // A weak sender so a slow hook does not keep the stream open
+                                                let message_tx = message_tx.downgrade();
+
+                                                tokio::spawn(async move {
+                                                    let hook_event_name = input
+                                                        .get("hook_event_name")
+                                                        .and_then(|v| v.as_str())
+                                                        .map(String::from);
+                                                    let tool_name = input.get("tool_name").cloned();
+
+                                                    // Look up the callback without holding the manager lock while it runs
+                                                    let callback = match hook_manager {
+                                                        Some(ref manager) => {
+                                                            let manager = manager.lock().await;
+                                                            manager.get_callback(&callback_id).cloned().map(|callback| {
+                                                                let matcher = manager
+                                                                    .matcher_for_callback(&callback_id)
+                                                                    .cloned()
+                                                                    .unwrap_or_else(|| HookMatcherConfig::new("*".to_string(), Vec::new()));
+                                                                let context = HookContext {
+                                                                    extensions: manager.extensions().clone(),
+                                                                    ..Default::default()
+                                                                };
+                                                                (callback, matcher, manager.metrics_recorder(), context)
+                                                            })
+                                                        }
+                                                        None => None,
+                                                    };
+
+                                                    // A failed or hung callback must not stall the turn
+                                                    let result = match callback {
+                                                        Some((callback, matcher, metrics, context)) => Ok(run_callback(
+                                                            &callback,
+                                                            &callback_id,
+                                                            &matcher,
+                                                            &metrics,
+                                                            input,
+                                                            tool_use_id.clone(),
+                                                            context,
+                                                        )
+                                                        .await
+                                                        .unwrap_or_default()),
+                                                        None => Err(format!("No hook callback found for ID: {}", callback_id)),
+                                                    };
+
+                                                    // Answer the CLI first so a slow consumer cannot hold up the tool
+                                                    let blocked = result.as_ref().ok().filter(|output| output.is_block()).cloned();
+                                                    let result = result.map(|output| output.to_control_response(hook_event_name.as_deref()));
+
+                                                    let response = match result {
+                                                        Ok(response_data) => ControlResponseType::Success {
+                                                            request_id,
+                                                            response: Some(response_data),
+                                                        },
+                                                        Err(error) => ControlResponseType::Error { request_id, error },
+                                                    };
+
+                                                    let response = SDKControlResponse {
+                                                        r#type: "control_response".to_string(),
+                                                        response,
+                                                    };
+
+                                                    let _ = write_json(&transport_clone, &response).await;
+
+                                                    // Let the consumer see why a tool did not run
+                                                    if let Some(output) = blocked {
+                                                        if let Some(message_tx) = message_tx.upgrade() {
+                                                            let notice = Message::System(SystemMessage {
+                                                                subtype: "hook_blocked".to_string(),
+                                                                data: HashMap::from([
+                                                                    ("callback_id".to_string(), json!(callback_id)),
+                                                                    ("hook_event_name".to_string(), json!(hook_event_name)),
+                                                                    ("tool_name".to_string(), json!(tool_name)),
+                                                                    ("tool_use_id".to_string(), json!(tool_use_id)),
+                                                                    ("reason".to_string(), json!(output.system_message)),
+                                                                ]),
+                                                            });
+                                                            let _ = message_tx.send(Ok(notice)).await;
                                                         }
                                                     }
                                                 });
+                                                continue;
+                                            }
+
+                                            // Handle can_use_tool requests
+                                            if let ControlRequest::CanUseTool { tool_name, input, .. } = ctrl_request.request {
+                                                if let Some(ref callback) = can_use_tool {
+                                                    let context = ToolPermissionContext {
+                                                        suggestions: vec![], // TODO: Parse permission_suggestions properly
+                                                        session_id: current_session_id.lock().unwrap().clone(),
+                                                        tool_annotations: SdkMcpServer::annotations_for(&mcp_servers, &tool_name),
+                                                    };
+
+                                                    let transport_clone = Arc::clone(&transport);
+                                                    let request_id = ctrl_request.request_id.clone();
+                                                    let callback_clone = Arc::clone(callback);
+
+                                                    tokio::spawn(async move {
+                                                        match callback_clone(tool_name.clone(), input.clone(), context).await {
+                                                            Ok(perm_result) => {
+                                                                // Convert PermissionResult to response
+                                                                let mut response_data = HashMap::new();
+                                                                match perm_result {
+                                                                    PermissionResult::Allow { updated_input, .. } => {
+                                                                        response_data.insert("allow".to_string(), json!(true));
+                                                                        if let Some(input) = updated_input {
+                                                                            response_data.insert("input".to_string(), json!(input));
+                                                                        }
+                                                                    }
+                                                                    PermissionResult::Deny { message, .. } => {
+                                                                        response_data.insert("allow".to_string(), json!(false));
+                                                                        response_data.insert("reason".to_string(), json!(message));
+                                                                    }
+                                                                };
+
+                                                                // Send response
+                                                                let response = SDKControlResponse {
+                                                                    r#type: "control_response".to_string(),
+                                                                    response: ControlResponseType::Success {
+                                                                        request_id,
+                                                                        response: Some(response_data),
+                                                                    },
+                                                                };
+
+                                                                let _ = write_json(&transport_clone, &response).await;
+                                                            }
+                                                            Err(e) => {
+                                                                // Send error response
+                                                                let response = SDKControlResponse {
+                                                                    r#type: "control_response".to_string(),
+                                                                    response: ControlResponseType::Error {
+                                                                        request_id,
+                                                                        error: e.to_string(),
+                                                                    },
+                                                                };
+
+                                                                let _ = write_json(&transport_clone, &response).await;
+                                                            }
+                                                        }
+                                                    });
+                                                }
                                             }
                                         }
+                                        continue;
                                     }
-                                    continue;
+                                    _ => {}
                                 }
-                                _ => {}
                             }
-                        }
 
-                        // Regular message - parse and send
-                        match parse_message(&json_value) {
-                            Ok(message) => {
-                                if message_tx.send(Ok(message)).is_err() {
-                                    break;
+                            // Regular message - parse and send
+                            match parse_message_with_mode(&json_value, parse_mode) {
+                                Ok(message) => {
+                                    if let Some(session_id) = session_id_of(&message) {
+                                        *current_session_id.lock().unwrap() = Some(session_id.clone());
+                                        last_session_id = Some(session_id);
+                                    }
+                                    if message.is_result() {
+                                        turns_completed.fetch_add(1, Ordering::SeqCst);
+                                    }
+                                    if let Some(ref metrics) = metrics {
+                                        metrics.record_message(&message);
+                                    }
+                                    if let Some(ref tracker) = cost_tracker {
+                                        tracker.record(&message);
+                                    }
+                                    let budget_error = budget.as_ref().and_then(|budget| budget.lock().unwrap().record(&message));
+                                    if let Some(ref e) = budget_error {
+                                        report_error(e);
+                                        let interrupter = interrupter.clone();
+                                        tokio::spawn(async move {
+                                            let _ = interrupter.interrupt().await;
+                                        });
+                                    }
+
+                                    let ends_turn = message.is_result();
+                                    let route = json_value
+                                        .get("session_id")
+                                        .and_then(|v| v.as_str())
+                                        .and_then(|id| sessions.lock().unwrap().route(id, ends_turn));
+                                    // Waits while the consumer is behind
+                                    let message = match route {
+                                        Some(session_tx) => match session_tx.send(Ok(message)).await {
+                                            Ok(()) => {
+                                                if let Some(e) = budget_error {
+                                                    let _ = session_tx.send(Err(e)).await;
+                                                }
+                                                continue;
+                                            }
+                                            // The session handle is gone, fall back to the shared stream
+                                            Err(mpsc::error::SendError(message)) => {
+                                                sessions.lock().unwrap().remove(&session_tx);
+                                                message
+                                            }
+                                        },
+                                        None => Ok(message),
+                                    };
+                                    if message_tx.send(message).await.is_err() {
+                                        break 'session;
+                                    }
+                                    if let Some(e) = budget_error {
+                                        if message_tx.send(Err(e)).await.is_err() {
+                                            break 'session;
+                                        }
+                                    }
+                                }
+                                Err(e) => {
+                                    report_error(&e);
+                                    let _ = message_tx.send(Err(e)).await;
+                                    break 'session;
                                 }
                             }
-                            Err(e) => {
-                                let _ = message_tx.send(Err(e));
-                                break;
+                        }
+                        // An oversized line was skipped by the transport, later lines are still valid
+                        Err(e @ ClaudeSDKError::BufferOverflow { .. }) => {
+                            report_error(&e);
+                            if message_tx.send(Err(e)).await.is_err() {
+                                break 'session;
                             }
                         }
+                        Err(e) => {
+                            failure = Some(e);
+                            break;
+                        }
                     }
-                    Err(e) => {
-                        let _ = message_tx.send(Err(e));
-                        break;
+                }
+
+                // The transport stopped producing output
+                let Some(ref supervisor) = supervisor else {
+                    if let Some(e) = failure {
+                        report_error(&e);
+                        let _ = message_tx.send(Err(e)).await;
+                    }
+                    break;
+                };
+
+                // Requests sent to the old process will never be answered; dropping
+                // the senders fails them as unanswered rather than as error responses
+                pending_responses.write().await.clear();
+
+                let reason = failure
+                    .as_ref()
+                    .map(|e| e.to_string())
+                    .unwrap_or_else(|| "Claude Code output ended unexpectedly".to_string());
+
+                loop {
+                    if restarts >= supervisor.policy.max_restarts {
+                        let error = failure.unwrap_or_else(|| {
+                            ClaudeSDKError::cli_connection_error(format!(
+                                "Claude Code exited and the restart limit of {} was reached",
+                                supervisor.policy.max_restarts
+                            ))
+                        });
+                        report_error(&error);
+                        let _ = message_tx.send(Err(error)).await;
+                        break 'session;
                     }
+                    restarts += 1;
+                    tokio::time::sleep(supervisor.policy.delay).await;
+
+                    let mut replacement = (supervisor.respawn)(last_session_id.clone());
+                    if let Err(e) = replacement.connect().await {
+                        failure = Some(e);
+                        continue;
+                    }
+
+                    {
+                        let mut transport = transport.lock().await;
+                        let _ = transport.close().await;
+                        *transport = replacement;
+                        stream = transport.read_messages();
+                    }
+                    break;
+                }
+
+                let notice = Message::System(SystemMessage {
+                    subtype: "reconnect".to_string(),
+                    data: HashMap::from([
+                        ("session_id".to_string(), json!(last_session_id)),
+                        ("attempt".to_string(), json!(restarts)),
+                        ("reason".to_string(), json!(reason)),
+                    ]),
+                });
+                if message_tx.send(Ok(notice)).await.is_err() {
+                    break;
+                }
+
+                // Register hooks with the new process; the response is not awaited
+                // because this task is the one that would deliver it
+                if is_streaming_mode {
+                    let request = initialize_request(hook_manager.as_ref()).await;
+                    let request_id = next_request_id(&request_counter).await;
+                    let control_msg = json!({
+                        "type": "control_request",
+                        "request_id": request_id,
+                        "request": request
+                    });
+                    let _ = write_json(&transport, &control_msg).await;
                 }
             }
+
+            // End the per-session streams along with the shared one
+            sessions.lock().unwrap().clear();
+            connected.store(false, Ordering::SeqCst);
         });
 
         self.read_task = Some(task);
+
+        // Tell the CLI when tools are added to or removed from an SDK server
+        for (server_name, server) in self.mcp_servers.iter() {
+            let mut changes = server.subscribe_tool_changes();
+            let transport = Arc::clone(&self.transport);
+            let request_counter = Arc::clone(&self.request_counter);
+            let server_name = server_name.clone();
+            self.tool_list_tasks.push(tokio::spawn(async move {
+                while changes.changed().await.is_ok() {
+                    let notification = json!({
+                        "type": "control_request",
+                        "request_id": next_request_id(&request_counter).await,
+                        "request": {
+                            "subtype": "mcp_message",
+                            "server_name": server_name,
+                            "message": {"jsonrpc": "2.0", "method": "notifications/tools/list_changed"}
+                        }
+                    });
+                    let _ = write_json(&transport, &notification).await;
+                }
+            }));
+        }
         Ok(())
     }
 
@@ -301,29 +816,14 @@ impl Query {
             return Ok(json!(null));
         }
 
-        // Get hooks configuration if available
-        let hooks_config = if let Some(ref hook_manager) = self.hook_manager {
-            let manager = hook_manager.lock().await;
-            let config = manager.get_initialization_config();
-            serde_json::to_value(config).ok()
-        } else {
-            None
-        };
-
-        let request = json!({
-            "subtype": "initialize",
-            "hooks": hooks_config
-        });
-
+        let request = initialize_request(self.hook_manager.as_ref()).await;
         self.send_control_request(request).await
     }
 
     /// Send a control request and wait for response
     async fn send_control_request(&self, request: Value) -> Result<Value> {
-        let mut counter = self.request_counter.lock().await;
-        *counter += 1;
-        let request_id = format!("req_{}", *counter);
-        drop(counter);
+        let request_id = next_request_id(&self.request_counter).await;
+        let subtype = request.get("subtype").and_then(Value::as_str).unwrap_or_default().to_string();
 
         // Create oneshot channel for response
         let (tx, rx) = tokio::sync::oneshot::channel();
@@ -341,37 +841,81 @@ impl Query {
             "request": request
         });
 
-        let msg_str = serde_json::to_string(&control_msg)
-            .map_err(|e| ClaudeSDKError::json_decode_error(String::new(), e.to_string()))?;
-
-        {
-            let mut transport = self.transport.lock().await;
-            transport.write(&format!("{}\n", msg_str)).await?;
-        }
+        write_json(&self.transport, &control_msg).await?;
 
         // Wait for response with timeout
-        tokio::time::timeout(std::time::Duration::from_secs(30), rx)
-            .await
-            .map_err(|_| ClaudeSDKError::cli_connection_error("Control request timeout".to_string()))?
-            .map_err(|_| ClaudeSDKError::cli_connection_error("Response channel closed".to_string()))?
+        match tokio::time::timeout(self.request_timeout, rx).await {
+            Ok(response) => response.map_err(|_| {
+                ClaudeSDKError::cli_connection_error("Connection closed before the control request was answered")
+            })?,
+            Err(_) => {
+                self.pending_responses.write().await.remove(&request_id);
+                Err(ClaudeSDKError::timeout(format!("Control request '{}'", subtype), self.request_timeout))
+            }
+        }
     }
 
     /// Receive messages from the message stream
-    pub fn receive_messages(&mut self) -> mpsc::UnboundedReceiver<Result<Message>> {
+    pub fn receive_messages(&mut self) -> mpsc::Receiver<Result<Message>> {
         self.message_rx.take().expect("Messages already taken")
     }
 
+    /// Receive the messages of one session separately
+    ///
+    /// Messages whose `session_id` matches are delivered to the returned
+    /// receiver instead of the shared one from
+    /// [`receive_messages`](Self::receive_messages), and so are the turns
+    /// answering prompts sent through a
+    /// [`ClientSession`](crate::client::ClientSession); everything else, including errors and reconnect notices, stays on the
+    /// shared stream. Routing a session again replaces the previous receiver.
+    /// All streams share the reader, so a session that is not consumed
+    /// eventually pauses the others.
+    pub fn route_session(&self, session_id: &str) -> mpsc::Receiver<Result<Message>> {
+        let (tx, rx) = mpsc::channel(self.channel_capacity);
+        self.sessions.lock().unwrap().by_id.insert(session_id.to_string(), tx);
+        rx
+    }
+
+    /// Session routes shared with the reader
+    pub(crate) fn session_routes(&self) -> Arc<std::sync::Mutex<SessionRoutes>> {
+        Arc::clone(&self.sessions)
+    }
+
+    /// Handle that sends `interrupt` without borrowing the query
+    pub(crate) fn interrupter(&self) -> Interrupter {
+        Interrupter {
+            transport: Arc::clone(&self.transport),
+            request_counter: Arc::clone(&self.request_counter),
+            turns_completed: Arc::clone(&self.turns_completed),
+            tool_cancel: Arc::clone(&self.tool_cancel),
+        }
+    }
+
+    /// Transport shared with the reader, for writing outside of `Query`
+    pub(crate) fn shared_transport(&self) -> Arc<Mutex<Box<dyn Transport>>> {
+        Arc::clone(&self.transport)
+    }
+
     /// Send a message through the transport
+    ///
+    /// Fails with [`ClaudeSDKError::BudgetExceeded`] once the budget set by
+    /// `max_cost_usd` or `max_total_tokens` is spent.
     pub async fn send_message(&mut self, message: Value) -> Result<()> {
-        let msg_str = serde_json::to_string(&message)
-            .map_err(|e| ClaudeSDKError::json_decode_error(String::new(), e.to_string()))?;
-
-        let mut transport = self.transport.lock().await;
-        transport.write(&format!("{}\n", msg_str)).await
+        if let Some(budget) = &self.budget {
+            let budget = budget.lock().unwrap();
+            if budget.is_exceeded() {
+                return Err(budget.error());
+            }
+        }
+        write_json(&self.transport, &message).await
     }
 
     /// Send an interrupt signal
+    ///
+    /// SDK MCP tool calls still running are cancelled and answered with an
+    /// error result.
     pub async fn interrupt(&mut self) -> Result<()> {
+        cancel_tool_calls(&self.tool_cancel);
         let request = json!({
             "subtype": "interrupt"
         });
@@ -402,37 +946,549 @@ impl Query {
         Ok(())
     }
 
+    /// Restore files changed since a user message
+    pub async fn rewind_files(&mut self, user_message_id: &str) -> Result<()> {
+        let request = json!({
+            "subtype": "rewind_files",
+            "user_message_id": user_message_id
+        });
+
+        self.send_control_request(request).await?;
+        Ok(())
+    }
+
+    /// Summarize the conversation so far, optionally following `instructions`
+    pub async fn compact(&mut self, instructions: Option<&str>) -> Result<()> {
+        let mut request = json!({
+            "subtype": "compact"
+        });
+        if let Some(instructions) = instructions {
+            request["instructions"] = json!(instructions);
+        }
+
+        self.send_control_request(request).await?;
+        Ok(())
+    }
+
+    /// Get the connection status of the session's MCP servers
+    pub async fn mcp_status(&mut self) -> Result<Value> {
+        let request = json!({
+            "subtype": "mcp_status"
+        });
+
+        self.send_control_request(request).await
+    }
+
+    /// ID of the current session, once the CLI has reported it
+    pub fn session_id(&self) -> Option<SessionId> {
+        self.session_id.lock().unwrap().clone()
+    }
+
+    /// Whether the reader is running, i.e. the CLI is still producing output
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    /// Exit status of the CLI process, see [`Transport::exit_status`]
+    pub async fn exit_status(&self) -> Option<ExitStatus> {
+        self.transport.lock().await.exit_status()
+    }
+
+    /// Send a control request and measure how long the CLI takes to answer
+    ///
+    /// Any response counts, including an error response from a CLI that
+    /// does not know the request. Fails if there is no answer within 10
+    /// seconds.
+    pub async fn ping(&self) -> Result<Duration> {
+        ping(&self.transport, &self.pending_responses, &self.request_counter, PING_TIMEOUT).await
+    }
+
+    /// Ping the CLI periodically and report changes in connection health
+    ///
+    /// Events are only sent on transitions, so the receiver stays quiet
+    /// while the connection is healthy. Starting a new heartbeat replaces
+    /// the previous one.
+    pub fn start_heartbeat(&mut self, config: HeartbeatConfig) -> mpsc::Receiver<HeartbeatEvent> {
+        let (tx, rx) = mpsc::channel(16);
+        let transport = Arc::clone(&self.transport);
+        let pending_responses = Arc::clone(&self.pending_responses);
+        let request_counter = Arc::clone(&self.request_counter);
+        let connected = Arc::clone(&self.connected);
+
+        let task = tokio::spawn(async move {
+            let mut degraded = false;
+            let mut interval = tokio::time::interval(config.interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                if !connected.load(Ordering::SeqCst) {
+                    let _ = tx.send(HeartbeatEvent::Disconnected).await;
+                    break;
+                }
+
+                let event = match ping(&transport, &pending_responses, &request_counter, config.timeout).await {
+                    Ok(latency) if degraded => Some(HeartbeatEvent::Recovered { latency }),
+                    Err(e) if !degraded => Some(HeartbeatEvent::Degraded { error: e.to_string() }),
+                    _ => None,
+                };
+
+                if let Some(event) = event {
+                    degraded = matches!(event, HeartbeatEvent::Degraded { .. });
+                    if tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        if let Some(previous) = self.heartbeat_task.replace(task) {
+            previous.abort();
+        }
+        rx
+    }
+
     /// Close the query and clean up
-    pub async fn close(mut self) -> Result<()> {
-        if let Some(task) = self.read_task.take() {
-            task.abort();
+    ///
+    /// Calling `close` again is a no-op.
+    pub async fn close(&mut self) -> Result<()> {
+        self.stop_tasks();
+        if self.closed {
+            return Ok(());
         }
+        self.closed = true;
 
         let mut transport = self.transport.lock().await;
         transport.close().await
     }
+
+    fn stop_tasks(&mut self) {
+        self.active_session = None;
+        if let Some(task) = self.heartbeat_task.take() {
+            task.abort();
+        }
+        if let Some(task) = self.read_task.take() {
+            task.abort();
+        }
+        for task in self.tool_list_tasks.drain(..) {
+            task.abort();
+        }
+        self.connected.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Drop for Query {
+    /// Stop background tasks and close the transport if `close` was not called
+    ///
+    /// Closing needs the async runtime, so it is spawned onto the current one
+    /// when there is one. `SubprocessTransport` also kills the CLI when it is
+    /// dropped, which covers dropping outside a runtime.
+    fn drop(&mut self) {
+        self.stop_tasks();
+        if self.closed {
+            return;
+        }
+
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let transport = Arc::clone(&self.transport);
+            runtime.spawn(async move {
+                let _ = transport.lock().await.close().await;
+            });
+        }
+    }
+}
+
+/// Interrupts or closes the session on behalf of code that does not own the [`Query`]
+///
+/// The `interrupt` response is not awaited; the turn ends with a result
+/// message as usual.
+#[derive(Clone)]
+pub(crate) struct Interrupter {
+    transport: Arc<Mutex<Box<dyn Transport>>>,
+    request_counter: Arc<Mutex<u64>>,
+    turns_completed: Arc<AtomicU64>,
+    tool_cancel: ToolCancel,
+}
+
+impl Interrupter {
+    /// Number of result messages seen so far
+    pub(crate) fn turns_completed(&self) -> u64 {
+        self.turns_completed.load(Ordering::SeqCst)
+    }
+
+    /// Close the transport, which ends the message stream
+    pub(crate) async fn close(&self) -> Result<()> {
+        self.transport.lock().await.close().await
+    }
+
+    pub(crate) async fn interrupt(&self) -> Result<()> {
+        cancel_tool_calls(&self.tool_cancel);
+        let request_id = next_request_id(&self.request_counter).await;
+        let control_msg = json!({
+            "type": "control_request",
+            "request_id": request_id,
+            "request": {"subtype": "interrupt"}
+        });
+        write_json(&self.transport, &control_msg).await
+    }
+}
+
+/// Cancel the tool calls in flight; later calls get a fresh handle
+fn cancel_tool_calls(tool_cancel: &ToolCancel) {
+    std::mem::take(&mut *tool_cancel.lock().unwrap()).cancel();
+}
+
+/// Serialize a message and write it as one line
+///
+/// The reader task owns its stream, so the transport lock only serializes
+/// writers and is held just for the duration of a single write.
+pub(crate) async fn write_json<T: serde::Serialize>(transport: &Mutex<Box<dyn Transport>>, message: &T) -> Result<()> {
+    let line = serde_json::to_string(message)
+        .map_err(|e| ClaudeSDKError::json_decode_error_from(String::new(), e))?;
+
+    transport.lock().await.write(&format!("{}\n", line)).await
+}
+
+/// Build the `initialize` control request, including hook registrations
+async fn initialize_request(hook_manager: Option<&Arc<Mutex<HookManager>>>) -> Value {
+    let hooks_config = match hook_manager {
+        Some(hook_manager) => {
+            let manager = hook_manager.lock().await;
+            serde_json::to_value(manager.get_initialization_config()).ok()
+        }
+        None => None,
+    };
+
+    json!({
+        "subtype": "initialize",
+        "hooks": hooks_config
+    })
+}
+
+/// Send tool progress to the CLI and the message stream
+///
+/// The CLI gets an MCP `notifications/progress` message when the call carried
+/// a progress token; the stream gets a `tool_progress` system message.
+async fn forward_tool_progress(
+    transport: &Mutex<Box<dyn Transport>>,
+    request_counter: &Mutex<u64>,
+    message_tx: &mpsc::WeakSender<Result<Message>>,
+    server_name: &str,
+    progress: ToolProgress,
+) {
+    if let Some(ref token) = progress.progress_token {
+        let notification = json!({
+            "type": "control_request",
+            "request_id": next_request_id(request_counter).await,
+            "request": {
+                "subtype": "mcp_message",
+                "server_name": server_name,
+                "message": {
+                    "jsonrpc": "2.0",
+                    "method": "notifications/progress",
+                    "params": {
+                        "progressToken": token,
+                        "progress": progress.progress,
+                        "total": 100.0,
+                        "message": progress.message,
+                    }
+                }
+            }
+        });
+        let _ = write_json(transport, &notification).await;
+    }
+
+    if let Some(message_tx) = message_tx.upgrade() {
+        let notice = Message::System(SystemMessage {
+            subtype: "tool_progress".to_string(),
+            data: HashMap::from([
+                ("server_name".to_string(), json!(server_name)),
+                ("tool_name".to_string(), json!(progress.tool_name)),
+                ("progress".to_string(), json!(progress.progress)),
+                ("message".to_string(), json!(progress.message)),
+            ]),
+        });
+        let _ = message_tx.send(Ok(notice)).await;
+    }
+}
+
+async fn next_request_id(counter: &Mutex<u64>) -> String {
+    let mut counter = counter.lock().await;
+    *counter += 1;
+    format!("req_{}", *counter)
+}
+
+/// Round-trip a lightweight control request and return its latency
+///
+/// `mcp_status` is read-only on the CLI side; error responses still prove
+/// the process is alive, so only a missing answer counts as failure.
+async fn ping(
+    transport: &Mutex<Box<dyn Transport>>,
+    pending_responses: &PendingResponses,
+    request_counter: &Mutex<u64>,
+    timeout: Duration,
+) -> Result<Duration> {
+    let request_id = next_request_id(request_counter).await;
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    pending_responses.write().await.insert(request_id.clone(), tx);
+
+    let control_msg = json!({
+        "type": "control_request",
+        "request_id": request_id,
+        "request": {"subtype": "mcp_status"}
+    });
+
+    let started = Instant::now();
+    let answered = match write_json(transport, &control_msg).await {
+        Ok(()) => tokio::time::timeout(timeout, rx).await.map(|response| response.is_ok()),
+        Err(e) => {
+            pending_responses.write().await.remove(&request_id);
+            return Err(e);
+        }
+    };
+
+    match answered {
+        Ok(true) => Ok(started.elapsed()),
+        Ok(false) => Err(ClaudeSDKError::cli_connection_error(
+            "Connection closed before ping was answered".to_string(),
+        )),
+        Err(_) => {
+            pending_responses.write().await.remove(&request_id);
+            Err(ClaudeSDKError::timeout("Ping", timeout))
+        }
+    }
+}
+
+/// Session ID reported by the CLI, used to resume after a restart
+fn session_id_of(message: &Message) -> Option<SessionId> {
+    match message {
+        Message::System(system) => match system.kind() {
+            SystemMessageKind::Init(info) => Some(info.session_id),
+            _ => None,
+        },
+        Message::Result(result) => Some(result.session_id.clone()),
+        _ => None,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::transport::SubprocessTransport;
+    use crate::testing::MockTransport;
     use crate::types::ClaudeAgentOptions;
 
     #[tokio::test]
     async fn test_query_creation() {
         let opts = ClaudeAgentOptions::default();
         let transport = SubprocessTransport::new("test".to_string(), opts);
-        let query = Query::new(transport, true);
+        let query = Query::new(Box::new(transport), true);
 
         assert!(query.is_streaming_mode);
     }
 
+    /// Minimal in-memory transport used to check that custom transports plug in
+    struct StaticTransport {
+        messages: Vec<Value>,
+    }
+
+    #[async_trait::async_trait]
+    impl Transport for StaticTransport {
+        async fn connect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn write(&mut self, _data: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn end_input(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn read_messages(&mut self) -> crate::transport::TransportStream {
+            let messages = std::mem::take(&mut self.messages);
+            Box::pin(futures::stream::iter(messages.into_iter().map(Ok)))
+        }
+
+        async fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_ready(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_with_custom_transport() {
+        let transport = StaticTransport {
+            messages: vec![json!({
+                "type": "assistant",
+                "message": {
+                    "model": "claude-sonnet-4-5",
+                    "content": [{"type": "text", "text": "Hello"}]
+                }
+            })],
+        };
+
+        let mut query = Query::new(Box::new(transport), false);
+        query.start().await.unwrap();
+
+        let mut rx = query.receive_messages();
+        let message = rx.recv().await.unwrap().unwrap();
+        assert!(matches!(message, Message::Assistant(_)));
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_query_start_twice_is_noop() {
+        let transport = StaticTransport {
+            messages: vec![crate::testing::result_message("s")],
+        };
+
+        let mut query = Query::new(Box::new(transport), false);
+        query.start().await.unwrap();
+        query.start().await.unwrap();
+
+        let mut rx = query.receive_messages();
+        assert!(matches!(rx.recv().await, Some(Ok(Message::Result(_)))));
+        assert!(rx.recv().await.is_none());
+    }
+
+    /// Transport whose stream counts how many messages have been pulled
+    struct CountingTransport {
+        messages: Vec<Value>,
+        pulled: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl Transport for CountingTransport {
+        async fn connect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn write(&mut self, _data: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn end_input(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn read_messages(&mut self) -> crate::transport::TransportStream {
+            use futures::StreamExt;
+
+            let pulled = Arc::clone(&self.pulled);
+            let messages = std::mem::take(&mut self.messages);
+            Box::pin(futures::stream::iter(messages).map(move |message| {
+                pulled.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(message)
+            }))
+        }
+
+        async fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_ready(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_consumer_applies_backpressure() {
+        let pulled = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let message = json!({"type": "system", "subtype": "status", "data": {}});
+        let transport = CountingTransport {
+            messages: vec![message; 5],
+            pulled: Arc::clone(&pulled),
+        };
+
+        let mut query = Query::new(Box::new(transport), false).with_channel_capacity(1);
+        query.start().await.unwrap();
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+
+        // One message fills the channel, the reader is parked sending the next
+        assert_eq!(pulled.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let mut rx = query.receive_messages();
+        let mut received = 0;
+        while let Some(message) = rx.recv().await {
+            assert!(matches!(message, Ok(Message::System(_))));
+            received += 1;
+        }
+        assert_eq!(received, 5);
+        assert_eq!(pulled.load(std::sync::atomic::Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_query_close_twice() {
+        let transport = StaticTransport { messages: vec![] };
+
+        let mut query = Query::new(Box::new(transport), false);
+        query.start().await.unwrap();
+        query.close().await.unwrap();
+        query.close().await.unwrap();
+        assert!(!query.is_connected());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_control_request_timeout() {
+        let mut transport = MockTransport::new().auto_respond(false);
+        transport.connect().await.unwrap();
+        let mut query = Query::new(Box::new(transport), true).with_request_timeout(Duration::from_secs(5));
+        query.start().await.unwrap();
+
+        match query.interrupt().await {
+            Err(ClaudeSDKError::Timeout { operation, duration }) => {
+                assert_eq!(operation, "Control request 'interrupt'");
+                assert_eq!(duration, Duration::from_secs(5));
+            }
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        assert!(query.pending_responses.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_control_request_error_response() {
+        let mut transport = MockTransport::new().auto_respond(false);
+        transport.connect().await.unwrap();
+        let handle = transport.handle();
+        let mut query = Query::new(Box::new(transport), true);
+        query.start().await.unwrap();
+
+        let responder = tokio::spawn(async move {
+            let request = handle.wait_for(|handle| handle.control_requests().pop()).await.unwrap();
+            handle.push(json!({
+                "type": "control_response",
+                "response": {
+                    "subtype": "error",
+                    "request_id": request["request_id"],
+                    "error": "Unknown mode"
+                }
+            }));
+            request["request_id"].as_str().unwrap().to_string()
+        });
+
+        let result = query.set_permission_mode("sideways").await;
+        let request_id = responder.await.unwrap();
+        match result {
+            Err(ClaudeSDKError::ControlProtocol { request_id: id, message }) => {
+                assert_eq!(id, request_id);
+                assert_eq!(message, "Unknown mode");
+            }
+            other => panic!("expected a control protocol error, got {:?}", other),
+        }
+    }
+
     #[tokio::test]
     async fn test_query_not_streaming() {
         let opts = ClaudeAgentOptions::default();
         let transport = SubprocessTransport::new("test".to_string(), opts);
-        let query = Query::new(transport, false);
+        let query = Query::new(Box::new(transport), false);
 
         assert!(!query.is_streaming_mode);
     }
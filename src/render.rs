@@ -0,0 +1,252 @@
+//! Human-readable transcripts of messages
+//!
+//! [`Message`] and [`ContentBlock`] implement `Display` with
+//! [`RenderOptions::default`], so `println!("{}", message)` prints a
+//! transcript line. Use [`Message::render`] to show thinking, hide tool
+//! inputs and outputs, or color the output for a terminal.
+//!
+//! # Example
+//! ```
+//! use claude::render::RenderOptions;
+//! use claude::types::{AssistantMessage, ContentBlock, Message};
+//!
+//! let message = Message::Assistant(AssistantMessage {
+//!     content: vec![
+//!         ContentBlock::Text { text: "Let me check.".to_string() },
+//!         ContentBlock::ToolUse {
+//!             id: "tool-1".to_string(),
+//!             name: "Bash".to_string(),
+//!             input: [("command".to_string(), "ls".into())].into(),
+//!         },
+//!     ],
+//!     model: "claude-sonnet-4-5".to_string(),
+//!     parent_tool_use_id: None,
+//!     uuid: None,
+//!     timestamp: None,
+//! });
+//!
+//! assert_eq!(message.to_string(), "Claude: Let me check.\n→ Bash {\"command\":\"ls\"}");
+//!
+//! let options = RenderOptions { show_tool_io: false, ..Default::default() };
+//! assert_eq!(message.render(&options), "Claude: Let me check.\n→ Bash");
+//! ```
+
+use crate::types::{ContentBlock, Message, ResultMessage, SystemMessageKind, UserMessageContent};
+use std::fmt;
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// What [`Message::render`] includes and how it looks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Include the model's thinking (default `false`)
+    pub show_thinking: bool,
+    /// Include tool inputs and results, not just tool names (default `true`)
+    pub show_tool_io: bool,
+    /// Color labels with ANSI escape codes (default `false`)
+    pub ansi: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            show_thinking: false,
+            show_tool_io: true,
+            ansi: false,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// `text` in `style`, when colors are on
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.ansi {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+impl Message {
+    /// The message as transcript lines, one per content block
+    ///
+    /// Blocks hidden by `options` are left out, so a user message that only
+    /// carries tool results renders as an empty string when
+    /// [`show_tool_io`](RenderOptions::show_tool_io) is off.
+    pub fn render(&self, options: &RenderOptions) -> String {
+        match self {
+            Message::User(user) => {
+                let label = options.paint(&format!("{}{}", BOLD, CYAN), "You:");
+                match &user.content {
+                    UserMessageContent::Text(text) => format!("{} {}", label, text),
+                    UserMessageContent::Blocks(blocks) => render_blocks(&label, blocks, options),
+                }
+            }
+            Message::Assistant(assistant) => {
+                let label = options.paint(&format!("{}{}", BOLD, GREEN), "Claude:");
+                render_blocks(&label, &assistant.content, options)
+            }
+            Message::System(system) => {
+                let detail = match system.kind() {
+                    SystemMessageKind::Init(init) if !init.model.is_empty() => format!(" (model {})", init.model),
+                    SystemMessageKind::Status { status: Some(status) } => format!(": {}", status),
+                    _ => String::new(),
+                };
+                options.paint(DIM, &format!("[system] {}{}", system.subtype, detail))
+            }
+            Message::Result(result) => render_result(result, options),
+            Message::Stream(event) => {
+                let event_type = event.event.get("type").and_then(|t| t.as_str()).unwrap_or("event");
+                options.paint(DIM, &format!("[stream] {}", event_type))
+            }
+            Message::Unknown(value) => {
+                let message_type = value.get("type").and_then(|t| t.as_str()).unwrap_or("message");
+                options.paint(DIM, &format!("[unknown] {}", message_type))
+            }
+        }
+    }
+}
+
+impl ContentBlock {
+    /// The block as a transcript line, or `None` when `options` hide it
+    pub fn render(&self, options: &RenderOptions) -> Option<String> {
+        match self {
+            ContentBlock::Text { text } => Some(text.clone()),
+            ContentBlock::Thinking { thinking, .. } => {
+                options.show_thinking.then(|| options.paint(DIM, &format!("(thinking) {}", thinking)))
+            }
+            ContentBlock::ToolUse { name, input, .. } => {
+                let mut line = format!("→ {}", name);
+                if options.show_tool_io {
+                    line.push(' ');
+                    line.push_str(&serde_json::to_string(input).unwrap_or_default());
+                }
+                Some(options.paint(YELLOW, &line))
+            }
+            ContentBlock::ToolResult { is_error, .. } => {
+                if !options.show_tool_io {
+                    return None;
+                }
+                let output: Vec<_> = self
+                    .tool_result_blocks()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|block| block.render(options))
+                    .collect();
+                let failed = is_error.unwrap_or(false);
+                let arrow = if failed { "← error:" } else { "←" };
+                let line = format!("{} {}", arrow, output.join(" ")).trim_end().to_string();
+                Some(options.paint(if failed { RED } else { DIM }, &line))
+            }
+            ContentBlock::Image { .. } => Some("[image]".to_string()),
+            ContentBlock::Document { title: Some(title), .. } => Some(format!("[document: {}]", title)),
+            ContentBlock::Document { .. } => Some("[document]".to_string()),
+            ContentBlock::Unknown(value) => {
+                let block_type = value.get("type").and_then(|t| t.as_str()).unwrap_or("block");
+                Some(format!("[{}]", block_type))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(&RenderOptions::default()))
+    }
+}
+
+impl fmt::Display for ContentBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(&RenderOptions::default()).unwrap_or_default())
+    }
+}
+
+/// Visible blocks on their own lines, the first one after `label`
+fn render_blocks(label: &str, blocks: &[ContentBlock], options: &RenderOptions) -> String {
+    let lines: Vec<_> = blocks.iter().filter_map(|block| block.render(options)).collect();
+    if lines.is_empty() {
+        return String::new();
+    }
+    format!("{} {}", label, lines.join("\n"))
+}
+
+fn render_result(result: &ResultMessage, options: &RenderOptions) -> String {
+    let mut line = format!(
+        "[result] {} after {} turn{} in {:.1}s",
+        result.subtype,
+        result.num_turns,
+        if result.num_turns == 1 { "" } else { "s" },
+        result.duration_ms as f64 / 1000.0
+    );
+    if let Some(cost) = result.total_cost_usd {
+        line.push_str(&format!(", ${:.4}", cost));
+    }
+    options.paint(if result.is_error { RED } else { DIM }, &line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message_parser::parse_message;
+    use crate::testing::result_message;
+    use serde_json::json;
+
+    fn parse(value: serde_json::Value) -> Message {
+        parse_message(&value).unwrap()
+    }
+
+    #[test]
+    fn test_render_transcript() {
+        let user = parse(json!({"type": "user", "message": {"role": "user", "content": "List the files"}}));
+        assert_eq!(user.to_string(), "You: List the files");
+
+        let assistant = parse(json!({
+            "type": "assistant",
+            "message": {
+                "model": "claude-sonnet-4-5",
+                "content": [
+                    {"type": "thinking", "thinking": "Use ls", "signature": "sig"},
+                    {"type": "tool_use", "id": "tool-1", "name": "Bash", "input": {"command": "ls"}}
+                ]
+            }
+        }));
+        assert_eq!(assistant.to_string(), "Claude: → Bash {\"command\":\"ls\"}");
+        let verbose = RenderOptions { show_thinking: true, ..Default::default() };
+        assert_eq!(assistant.render(&verbose), "Claude: (thinking) Use ls\n→ Bash {\"command\":\"ls\"}");
+
+        let tool_result = parse(json!({
+            "type": "user",
+            "message": {
+                "role": "user",
+                "content": [{"type": "tool_result", "tool_use_id": "tool-1", "content": "a.txt", "is_error": true}]
+            }
+        }));
+        assert_eq!(tool_result.to_string(), "You: ← error: a.txt");
+        let quiet = RenderOptions { show_tool_io: false, ..Default::default() };
+        assert_eq!(tool_result.render(&quiet), "");
+
+        let mut result = result_message("s1");
+        result["duration_ms"] = json!(1500);
+        result["num_turns"] = json!(2);
+        result["total_cost_usd"] = json!(0.0123);
+        let result = parse(result);
+        assert_eq!(result.to_string(), "[result] success after 2 turns in 1.5s, $0.0123");
+
+        let init = parse(json!({"type": "system", "subtype": "init", "session_id": "s1", "model": "claude-sonnet-4-5"}));
+        assert_eq!(init.to_string(), "[system] init (model claude-sonnet-4-5)");
+    }
+
+    #[test]
+    fn test_render_ansi() {
+        let user = parse(json!({"type": "user", "message": {"role": "user", "content": "Hi"}}));
+        let colored = user.render(&RenderOptions { ansi: true, ..Default::default() });
+        assert_eq!(colored, "\x1b[1m\x1b[36mYou:\x1b[0m Hi");
+    }
+}
@@ -0,0 +1,267 @@
+//! Retrying queries that fail for transient reasons
+//!
+//! A [`RetryPolicy`] reruns a failed query after an exponentially growing,
+//! jittered delay. Pass it to [`simple_query_with_retry`],
+//! [`QuickQuery::retry`] or [`BatchOptions::retry`], or wrap any operation
+//! with [`RetryPolicy::run`].
+//!
+//! # Example
+//! ```no_run
+//! use claude::{QuickQuery, RetryPolicy};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let answer = QuickQuery::new("What is 2 + 2?").retry(RetryPolicy::new(5)).ask().await?;
+//!     println!("{}", answer);
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`simple_query_with_retry`]: crate::simple_query_with_retry
+//! [`QuickQuery::retry`]: crate::QuickQuery::retry
+//! [`BatchOptions::retry`]: crate::BatchOptions::retry
+
+use crate::errors::{ClaudeSDKError, Result};
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Decides whether an error is worth another attempt
+pub type RetryPredicate = Arc<dyn Fn(&ClaudeSDKError) -> bool + Send + Sync>;
+
+/// How often and when a failed query is retried
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first (default 3)
+    pub max_attempts: u32,
+    pub backoff: Backoff,
+    /// Errors to retry, [`is_transient`] by default
+    pub retry_on: RetryPredicate,
+}
+
+impl RetryPolicy {
+    /// Retry transient errors until `max_attempts` attempts were made
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts,
+            ..Default::default()
+        }
+    }
+
+    /// Make a single attempt
+    pub fn never() -> Self {
+        Self::new(1)
+    }
+
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Retry the errors for which `predicate` returns true
+    pub fn retry_on(mut self, predicate: impl Fn(&ClaudeSDKError) -> bool + Send + Sync + 'static) -> Self {
+        self.retry_on = Arc::new(predicate);
+        self
+    }
+
+    /// Run `operation` until it succeeds, fails with an error that is not
+    /// retried, or runs out of attempts
+    ///
+    /// Waits at least as long as a [`ClaudeSDKError::RateLimited`] error asks
+    /// before the next attempt. Returns the result of the last attempt.
+    pub async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Err(e) if attempt < self.max_attempts && (self.retry_on)(&e) => {
+                    let delay = self.backoff.delay(attempt);
+                    tokio::time::sleep(e.retry_after().map_or(delay, |after| after.max(delay))).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            backoff: Backoff::default(),
+            retry_on: Arc::new(is_transient),
+        }
+    }
+}
+
+impl std::fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Delays between attempts
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    /// Delay before the first retry (default 500ms)
+    pub initial: Duration,
+    /// Upper bound for any delay (default 30s)
+    pub max: Duration,
+    /// Growth of the delay per retry (default 2)
+    pub multiplier: f64,
+    /// Pick each delay at random between half and all of its nominal value,
+    /// so clients failing together don't retry in lockstep (default true)
+    pub jitter: bool,
+}
+
+impl Backoff {
+    /// The same delay before every retry, without jitter
+    pub fn fixed(delay: Duration) -> Self {
+        Backoff {
+            initial: delay,
+            max: delay,
+            multiplier: 1.0,
+            jitter: false,
+        }
+    }
+
+    /// Delay before retry number `retry` (starting at 1)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let nominal = self.initial.as_secs_f64() * self.multiplier.powi(exponent);
+        let nominal = Duration::try_from_secs_f64(nominal).unwrap_or(self.max).min(self.max);
+        if !self.jitter {
+            return nominal;
+        }
+
+        let random = RandomState::new().build_hasher().finish();
+        let fraction = (random >> 11) as f64 / (1u64 << 53) as f64;
+        nominal.mul_f64(0.5 + fraction / 2.0)
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: true,
+        }
+    }
+}
+
+/// Whether `error` is likely to go away on another attempt, see
+/// [`ClaudeSDKError::is_retryable`]
+pub fn is_transient(error: &ClaudeSDKError) -> bool {
+    error.is_retryable()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_backoff_delays() {
+        let backoff = Backoff {
+            jitter: false,
+            ..Default::default()
+        };
+        assert_eq!(backoff.delay(1), Duration::from_millis(500));
+        assert_eq!(backoff.delay(3), Duration::from_secs(2));
+        assert_eq!(backoff.delay(100), Duration::from_secs(30));
+
+        let jittered = Backoff::default();
+        for retry in 1..10 {
+            let delay = jittered.delay(retry);
+            let nominal = backoff.delay(retry);
+            assert!(delay >= nominal / 2 && delay <= nominal, "{:?} outside {:?}", delay, nominal);
+        }
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&ClaudeSDKError::cli_connection_error("broken pipe")));
+        assert!(is_transient(&ClaudeSDKError::timeout("Query", Duration::from_secs(1))));
+        assert!(is_transient(&ClaudeSDKError::process_error(
+            "Command failed",
+            Some(1),
+            Some("API Error: 529 Overloaded".to_string())
+        )));
+        assert!(!is_transient(&ClaudeSDKError::process_error("Command failed", Some(1), Some("Invalid model".to_string()))));
+        assert!(!is_transient(&ClaudeSDKError::cli_not_found(None)));
+        assert!(!is_transient(&ClaudeSDKError::interrupted()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_run_retries_transient_errors() {
+        let attempts = AtomicU32::new(0);
+        let policy = RetryPolicy::new(3).backoff(Backoff::fixed(Duration::from_secs(1)));
+
+        let start = tokio::time::Instant::now();
+        let result = policy
+            .run(|| async {
+                match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(ClaudeSDKError::cli_connection_error("CLI exited")),
+                    _ => Ok("done"),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_run_waits_for_retry_after() {
+        let attempts = AtomicU32::new(0);
+        let policy = RetryPolicy::new(3).backoff(Backoff::fixed(Duration::from_secs(1)));
+
+        let start = tokio::time::Instant::now();
+        let result = policy
+            .run(|| async {
+                match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(ClaudeSDKError::rate_limited("Too many requests", Some(Duration::from_secs(10)))),
+                    1 => Err(ClaudeSDKError::rate_limited("Overloaded", None)),
+                    _ => Ok("done"),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(start.elapsed(), Duration::from_secs(11));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_run_stops_on_permanent_errors_and_exhaustion() {
+        let attempts = AtomicU32::new(0);
+        let result: Result<()> = RetryPolicy::new(5)
+            .run(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(ClaudeSDKError::cli_not_found(None))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        let attempts = AtomicU32::new(0);
+        let result: Result<()> = RetryPolicy::new(2)
+            .retry_on(|_| true)
+            .run(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(ClaudeSDKError::interrupted())
+            })
+            .await;
+        assert!(matches!(result, Err(ClaudeSDKError::Interrupted)));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,8 +1,17 @@
-use crate::errors::Result;
-use crate::message_parser::parse_message;
+use crate::budget::BudgetTracker;
+use crate::client::{ClaudeSDKClient, ClaudeSDKClientBuilder};
+use crate::errors::{ClaudeSDKError, Result};
+use crate::hooks::{HookCallback, HookManager};
+use crate::mcp_server::SdkMcpServer;
+use crate::message_parser::parse_message_with_mode;
+use crate::permissions::CanUseToolCallback;
+use crate::retry::RetryPolicy;
+use crate::streaming_query::StreamingQuery;
 use crate::transport::{SubprocessTransport, Transport};
-use crate::types::{ClaudeAgentOptions, Message};
+use crate::types::{ClaudeAgentOptions, ContentBlock, HookEvent, Message, ParseMode, ResultMessage};
 use futures::stream::StreamExt;
+use std::time::Duration;
+use tokio::sync::mpsc;
 
 /// Simple query function that collects all messages from Claude Code
 ///
@@ -16,14 +25,105 @@ use futures::stream::StreamExt;
 ///
 /// # Returns
 ///
-/// A vector of all messages from the conversation
+/// A vector of all messages from the conversation, or
+/// `ClaudeSDKError::Timeout` if `query_timeout` is set and passes first.
+/// Crossing `max_cost_usd` or `max_total_tokens` stops the CLI and fails
+/// with `ClaudeSDKError::BudgetExceeded`.
 pub async fn simple_query(
     prompt: &str,
     options: Option<ClaudeAgentOptions>,
 ) -> Result<Vec<Message>> {
     let opts = options.unwrap_or_default();
-    let mut transport = SubprocessTransport::new(prompt.to_string(), opts);
+    let parse_mode = opts.parse_mode;
+    let timeout = opts.query_timeout;
+    let budget = BudgetTracker::from_options(&opts);
+    let metrics = opts.metrics.clone();
+    let cost_tracker = opts.cost_tracker.clone();
+    let transport = SubprocessTransport::new(prompt.to_string(), opts);
 
+    let outcome = collect_messages(Box::new(transport), parse_mode, timeout, budget).await;
+    if let Some(metrics) = metrics {
+        metrics.record_outcome(&outcome);
+    }
+    if let (Some(tracker), Ok(messages)) = (cost_tracker, &outcome) {
+        tracker.record_all(messages);
+    }
+    outcome
+}
+
+/// [`simple_query`] that gives up after `timeout`
+///
+/// The deadline covers the whole query, from starting the CLI to its last
+/// message. When it passes the CLI is stopped and
+/// `ClaudeSDKError::Timeout` is returned. Overrides `query_timeout` in
+/// `options`.
+pub async fn simple_query_with_timeout(
+    prompt: &str,
+    options: Option<ClaudeAgentOptions>,
+    timeout: Duration,
+) -> Result<Vec<Message>> {
+    let mut opts = options.unwrap_or_default();
+    opts.query_timeout = Some(timeout);
+    simple_query(prompt, Some(opts)).await
+}
+
+/// [`simple_query`] that retries failed attempts according to `retry`
+///
+/// # Example
+/// ```no_run
+/// use claude::{simple_query_with_retry, RetryPolicy};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let messages = simple_query_with_retry("Summarize README.md", None, &RetryPolicy::default()).await?;
+///     println!("{} messages", messages.len());
+///     Ok(())
+/// }
+/// ```
+pub async fn simple_query_with_retry(
+    prompt: &str,
+    options: Option<ClaudeAgentOptions>,
+    retry: &RetryPolicy,
+) -> Result<Vec<Message>> {
+    let options = options.unwrap_or_default();
+    retry.run(|| simple_query(prompt, Some(options.clone()))).await
+}
+
+/// Run a one-shot query over a custom transport and collect all messages
+///
+/// The transport must already carry the prompt, as `SubprocessTransport::new`
+/// does. Use this to run one-shot queries without the CLI, e.g. with
+/// `ApiTransport` (requires the `api-transport` feature). Messages are parsed
+/// in strict mode.
+pub async fn simple_query_with_transport(transport: Box<dyn Transport>) -> Result<Vec<Message>> {
+    collect_messages(transport, ParseMode::Strict, None, None).await
+}
+
+async fn collect_messages(
+    mut transport: Box<dyn Transport>,
+    parse_mode: ParseMode,
+    timeout: Option<Duration>,
+    budget: Option<BudgetTracker>,
+) -> Result<Vec<Message>> {
+    let result = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, read_all(transport.as_mut(), parse_mode, budget))
+            .await
+            .unwrap_or_else(|_| Err(ClaudeSDKError::timeout("Query", timeout))),
+        None => read_all(transport.as_mut(), parse_mode, budget).await,
+    };
+
+    // Stop the CLI when giving up before it finished
+    if let Err(ClaudeSDKError::Timeout { .. } | ClaudeSDKError::BudgetExceeded { .. }) = result {
+        let _ = transport.close().await;
+    }
+    result
+}
+
+async fn read_all(
+    transport: &mut dyn Transport,
+    parse_mode: ParseMode,
+    mut budget: Option<BudgetTracker>,
+) -> Result<Vec<Message>> {
     // Connect to Claude Code
     transport.connect().await?;
 
@@ -32,14 +132,304 @@ pub async fn simple_query(
 
     // Collect all messages
     let mut messages = Vec::new();
-    let stream = transport.read_messages();
-    futures::pin_mut!(stream);
+    let mut stream = transport.read_messages();
 
     while let Some(result) = stream.next().await {
         let json_value = result?;
-        let message = parse_message(&json_value)?;
+        let message = parse_message_with_mode(&json_value, parse_mode)?;
+        if let Some(e) = budget.as_mut().and_then(|budget| budget.record(&message)) {
+            return Err(e);
+        }
         messages.push(message);
     }
 
+    // API errors end the query with an error result; rate limits are
+    // reported as errors so retry policies can back off
+    if let Some(e) = messages.last().and_then(Message::as_result).and_then(ResultMessage::rate_limit_error) {
+        return Err(e);
+    }
     Ok(messages)
 }
+
+/// Callbacks, in-process servers and attachments for a one-shot query
+///
+/// These need the control protocol or a block-based user message, so a
+/// query using any of them runs the CLI in streaming mode and sends the
+/// prompt as the first message.
+#[derive(Clone, Default)]
+pub struct QueryExtras {
+    /// Hooks run by the CLI during the query
+    pub hooks: Option<HookManager>,
+    /// Permission callback for tool use
+    pub can_use_tool: Option<CanUseToolCallback>,
+    /// In-process MCP servers served to the CLI
+    pub mcp_servers: Vec<SdkMcpServer>,
+    /// Content sent along with the prompt, such as files from
+    /// [`UserContent::attachment`](crate::UserContent::attachment)
+    pub attachments: Vec<ContentBlock>,
+}
+
+impl QueryExtras {
+    /// Create empty extras
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `callback` for `event` on tools matching `pattern`
+    pub fn hook(mut self, event: HookEvent, pattern: impl Into<String>, callback: HookCallback) -> Self {
+        self.hooks
+            .get_or_insert_with(HookManager::new)
+            .on(event, pattern, callback);
+        self
+    }
+
+    /// Decide tool permissions with `callback`
+    pub fn can_use_tool(mut self, callback: CanUseToolCallback) -> Self {
+        self.can_use_tool = Some(callback);
+        self
+    }
+
+    /// Serve an in-process MCP server to the CLI
+    pub fn mcp_server(mut self, server: SdkMcpServer) -> Self {
+        self.mcp_servers.push(server);
+        self
+    }
+
+    /// Send `block` after the prompt
+    pub fn attach(mut self, block: ContentBlock) -> Self {
+        self.attachments.push(block);
+        self
+    }
+
+    /// Whether nothing is set, so the plain one-shot mode suffices
+    pub(crate) fn is_empty(&self) -> bool {
+        self.hooks.is_none() && self.can_use_tool.is_none() && self.mcp_servers.is_empty() && self.attachments.is_empty()
+    }
+
+    /// The first user message: the prompt, followed by the attachments
+    pub(crate) fn prompt_blocks(&mut self, prompt: &str) -> Option<Vec<ContentBlock>> {
+        if self.attachments.is_empty() {
+            return None;
+        }
+        let mut blocks = vec![ContentBlock::Text { text: prompt.to_string() }];
+        blocks.append(&mut self.attachments);
+        Some(blocks)
+    }
+
+    /// A connected client carrying these extras
+    pub(crate) async fn connect(self, mut builder: ClaudeSDKClientBuilder) -> Result<ClaudeSDKClient> {
+        if let Some(hooks) = self.hooks {
+            builder = builder.hook_manager(hooks);
+        }
+        if let Some(callback) = self.can_use_tool {
+            builder = builder.can_use_tool(callback);
+        }
+        for server in self.mcp_servers {
+            builder = builder.mcp_server(server);
+        }
+
+        let mut client = builder.build();
+        client.connect().await?;
+        Ok(client)
+    }
+}
+
+/// One-shot query with hooks, a permission callback or SDK MCP servers
+///
+/// Behaves like [`simple_query`] when `extras` is empty, including the
+/// `query_timeout` deadline.
+///
+/// # Example
+/// ```no_run
+/// use claude::{hook, simple_query_with, HookEvent, HookJSONOutput, QueryExtras};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let extras = QueryExtras::new().hook(HookEvent::PreToolUse, "Bash", hook!(|_input| {
+///         Ok(HookJSONOutput::block("No shell commands in this script"))
+///     }));
+///     let messages = simple_query_with("List the files here", None, extras).await?;
+///     println!("{} messages", messages.len());
+///     Ok(())
+/// }
+/// ```
+pub async fn simple_query_with(
+    prompt: &str,
+    options: Option<ClaudeAgentOptions>,
+    mut extras: QueryExtras,
+) -> Result<Vec<Message>> {
+    if extras.is_empty() {
+        return simple_query(prompt, options).await;
+    }
+
+    let options = options.unwrap_or_default();
+    let timeout = options.query_timeout;
+    let metrics = options.metrics.clone();
+    let builder = ClaudeSDKClient::builder().options(options);
+    let blocks = extras.prompt_blocks(prompt);
+    let query = async { collect(extras.connect(builder).await?, prompt, blocks).await };
+    match timeout {
+        // Dropping the client on timeout stops the CLI
+        Some(timeout) => tokio::time::timeout(timeout, query).await.unwrap_or_else(|_| {
+            let e = ClaudeSDKError::timeout("Query", timeout);
+            if let Some(metrics) = metrics {
+                metrics.record_error(&e);
+            }
+            Err(e)
+        }),
+        None => query.await,
+    }
+}
+
+/// [`simple_query_with`] over a custom transport
+///
+/// Unlike [`simple_query_with_transport`], the transport must not carry the
+/// prompt: it is sent as a message once the transport is connected.
+pub async fn simple_query_with_transport_and_extras(
+    prompt: &str,
+    transport: Box<dyn Transport>,
+    mut extras: QueryExtras,
+) -> Result<Vec<Message>> {
+    let builder = ClaudeSDKClient::builder().transport(transport);
+    let blocks = extras.prompt_blocks(prompt);
+    collect(extras.connect(builder).await?, prompt, blocks).await
+}
+
+/// Send `prompt`, or `blocks` if there are attachments, collect the
+/// response and disconnect
+async fn collect(mut client: ClaudeSDKClient, prompt: &str, blocks: Option<Vec<ContentBlock>>) -> Result<Vec<Message>> {
+    let result = match blocks {
+        Some(blocks) => client.query_blocks_and_collect(blocks).await,
+        None => client.query_and_collect(prompt).await,
+    };
+    let closed = client.close().await;
+    let messages = result?;
+    closed?;
+    Ok(messages)
+}
+
+/// Stream a one-shot query that uses `extras`
+pub(crate) async fn stream_with(
+    prompt: &str,
+    options: ClaudeAgentOptions,
+    mut extras: QueryExtras,
+) -> Result<StreamingQuery> {
+    let capacity = options
+        .message_channel_capacity
+        .unwrap_or(crate::query::DEFAULT_MESSAGE_CHANNEL_CAPACITY)
+        .max(1);
+    let blocks = extras.prompt_blocks(prompt);
+    let mut client = extras.connect(ClaudeSDKClient::builder().options(options)).await?;
+    match blocks {
+        Some(blocks) => client.query_blocks(blocks, None).await?,
+        None => client.query(prompt, None).await?,
+    }
+
+    // The task owns the client so hooks keep running while the caller reads
+    let (tx, rx) = mpsc::channel(capacity);
+    tokio::spawn(async move {
+        let mut response = client.receive_response();
+        while let Some(message) = response.next().await {
+            if tx.send(message).await.is_err() {
+                break;
+            }
+        }
+        drop(response);
+        let _ = client.close().await;
+    });
+
+    Ok(StreamingQuery::from_receiver(rx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{result_message, MockTransport};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_collect_messages_times_out() {
+        // The CLI answers but never finishes
+        let transport = MockTransport::new().with_assistant_text("thinking...");
+        let handle = transport.handle();
+
+        let result = collect_messages(Box::new(transport), ParseMode::Strict, Some(Duration::from_secs(5)), None).await;
+        match result {
+            Err(ClaudeSDKError::Timeout { operation, duration }) => {
+                assert_eq!(operation, "Query");
+                assert_eq!(duration, Duration::from_secs(5));
+            }
+            other => panic!("expected timeout, got {:?}", other),
+        }
+        assert!(handle.is_closed());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_collect_messages_within_timeout() {
+        let transport = MockTransport::new();
+        let handle = transport.handle();
+        handle.push(result_message("s1"));
+        handle.finish();
+
+        let messages = collect_messages(Box::new(transport), ParseMode::Strict, Some(Duration::from_secs(5)), None)
+            .await
+            .unwrap();
+        assert!(messages[0].is_result());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_result_is_an_error() {
+        let transport = MockTransport::new();
+        let handle = transport.handle();
+        let mut result = result_message("s1");
+        result["is_error"] = serde_json::json!(true);
+        result["result"] = serde_json::json!(
+            "API Error: 529 {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}"
+        );
+        handle.push(result);
+        handle.finish();
+
+        let result = collect_messages(Box::new(transport), ParseMode::Strict, None, None).await;
+        match result {
+            Err(e @ ClaudeSDKError::RateLimited { .. }) => assert!(e.is_retryable()),
+            other => panic!("expected rate limit error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_messages_stops_at_budget() {
+        let transport = MockTransport::new();
+        let handle = transport.handle();
+        // Each result reports the session's running total
+        for total_cost_usd in [0.4, 0.8, 1.2] {
+            let mut result = result_message("s1");
+            result["total_cost_usd"] = serde_json::json!(total_cost_usd);
+            handle.push(result);
+        }
+        let options = ClaudeAgentOptions {
+            max_cost_usd: Some(1.0),
+            ..Default::default()
+        };
+
+        let result = collect_messages(Box::new(transport), ParseMode::Strict, None, BudgetTracker::from_options(&options)).await;
+        match result {
+            Err(ClaudeSDKError::BudgetExceeded { cost_usd, .. }) => assert!((cost_usd - 1.2).abs() < 1e-9),
+            other => panic!("expected budget error, got {:?}", other),
+        }
+        assert!(handle.is_closed());
+    }
+
+    #[tokio::test]
+    async fn test_attachments_are_sent_as_blocks() {
+        let transport = MockTransport::new().with_assistant_text("Looks fine").with_result("s1");
+        let handle = transport.handle();
+        let extras = QueryExtras::new().attach(crate::UserContent::text_document("fn main() {}", Some("main.rs".to_string())));
+
+        let messages = simple_query_with_transport_and_extras("Review this", Box::new(transport), extras).await.unwrap();
+        assert!(messages.last().unwrap().is_result());
+
+        let content = &handle.user_messages()[0]["message"]["content"];
+        assert_eq!(content[0], serde_json::json!({"type": "text", "text": "Review this"}));
+        assert_eq!(content[1]["type"], "document");
+        assert_eq!(content[1]["title"], "main.rs");
+    }
+}
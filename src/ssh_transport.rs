@@ -0,0 +1,338 @@
+//! Transport that runs the Claude Code CLI on a remote host over SSH
+//!
+//! [`SshTransport`] launches the CLI through the system `ssh` client and
+//! proxies the stream-json protocol over the connection's stdin/stdout. It
+//! reuses [`SubprocessTransport`] for process handling, so stderr capture,
+//! buffer limits, and graceful shutdown behave the same as for a local CLI.
+//!
+//! Authentication is left to `ssh` itself (agent, keys, `~/.ssh/config`);
+//! the session must not prompt for a password. Environment variables and the
+//! working directory from [`ClaudeAgentOptions`] are applied on the remote
+//! host, while paths such as `add_dirs` are passed through unchanged and must
+//! exist there.
+//!
+//! Requires the `ssh-transport` cargo feature.
+//!
+//! # Example
+//! ```no_run
+//! use claude::ssh_transport::SshTransport;
+//! use claude::ClaudeSDKClient;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let transport = SshTransport::new("dev@build-box", String::new(), Default::default())
+//!         .with_port(2222)
+//!         .with_remote_cli_path("/home/dev/.local/bin/claude");
+//!
+//!     let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+//!     client.connect().await?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::Result;
+use crate::transport::{CommandSpec, Direction, Interceptor, SubprocessTransport, Transport, TransportStream};
+use crate::types::ClaudeAgentOptions;
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Connection settings for the remote host
+#[derive(Debug, Clone)]
+struct SshConfig {
+    destination: String,
+    port: Option<u16>,
+    identity_file: Option<PathBuf>,
+    ssh_options: Vec<String>,
+    remote_cli_path: String,
+    ssh_program: String,
+}
+
+/// Transport that runs the CLI on another machine via `ssh`
+pub struct SshTransport {
+    config: SshConfig,
+    inner: Option<SubprocessTransport>,
+    interceptor: Option<Interceptor>,
+    prompt: String,
+    options: ClaudeAgentOptions,
+}
+
+impl SshTransport {
+    /// Create a transport for `destination` (`host` or `user@host`)
+    ///
+    /// `prompt` and `options` have the same meaning as for
+    /// [`SubprocessTransport::new`].
+    pub fn new(destination: impl Into<String>, prompt: String, options: ClaudeAgentOptions) -> Self {
+        Self {
+            config: SshConfig {
+                destination: destination.into(),
+                port: None,
+                identity_file: None,
+                ssh_options: Vec::new(),
+                remote_cli_path: "claude".to_string(),
+                ssh_program: "ssh".to_string(),
+            },
+            inner: None,
+            interceptor: None,
+            prompt,
+            options,
+        }
+    }
+
+    /// Connect to a non-default SSH port
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.config.port = Some(port);
+        self
+    }
+
+    /// Authenticate with a specific private key
+    pub fn with_identity_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.identity_file = Some(path.into());
+        self
+    }
+
+    /// Pass an `-o` option to ssh, e.g. `StrictHostKeyChecking=accept-new`
+    pub fn with_ssh_option(mut self, option: impl Into<String>) -> Self {
+        self.config.ssh_options.push(option.into());
+        self
+    }
+
+    /// Path of the `claude` executable on the remote host (default: `claude`)
+    pub fn with_remote_cli_path(mut self, path: impl Into<String>) -> Self {
+        self.config.remote_cli_path = path.into();
+        self
+    }
+
+    /// Use a different ssh client binary (default: `ssh` from `PATH`)
+    pub fn with_ssh_program(mut self, program: impl Into<String>) -> Self {
+        self.config.ssh_program = program.into();
+        self
+    }
+
+    /// Observe every raw line exchanged with the CLI
+    ///
+    /// See [`SubprocessTransport::with_interceptor`].
+    pub fn with_interceptor(mut self, interceptor: impl Fn(Direction, &str) + Send + Sync + 'static) -> Self {
+        self.interceptor = Some(Arc::new(interceptor));
+        self
+    }
+
+    fn inner(&mut self) -> &mut SubprocessTransport {
+        self.inner.get_or_insert_with(|| {
+            let config = self.config.clone();
+            SubprocessTransport::new(std::mem::take(&mut self.prompt), self.options.clone())
+                .with_wrapper(Arc::new(move |spec| config.wrap(spec)))
+                .with_shared_interceptor(self.interceptor.clone())
+        })
+    }
+}
+
+impl SshConfig {
+    /// Turn a local CLI invocation into an `ssh` invocation
+    fn wrap(&self, spec: CommandSpec) -> CommandSpec {
+        let mut args = vec![
+            // No TTY: the stream-json protocol needs a clean byte stream
+            "-T".to_string(),
+            "-o".to_string(),
+            "BatchMode=yes".to_string(),
+        ];
+
+        if let Some(port) = self.port {
+            args.push("-p".to_string());
+            args.push(port.to_string());
+        }
+
+        if let Some(ref identity) = self.identity_file {
+            args.push("-i".to_string());
+            args.push(identity.to_string_lossy().to_string());
+        }
+
+        for option in &self.ssh_options {
+            args.push("-o".to_string());
+            args.push(option.clone());
+        }
+
+        args.push(self.destination.clone());
+        args.push("--".to_string());
+        args.push(self.remote_command(&spec));
+
+        CommandSpec {
+            program: self.ssh_program.clone(),
+            args,
+            env: Vec::new(),
+            cwd: None,
+        }
+    }
+
+    /// Shell command run by the remote login shell
+    fn remote_command(&self, spec: &CommandSpec) -> String {
+        let mut parts = Vec::new();
+
+        if let Some(ref cwd) = spec.cwd {
+            parts.push(format!("cd {} &&", shell_quote(&cwd.to_string_lossy())));
+        }
+
+        parts.push("exec".to_string());
+
+        if !spec.env.is_empty() {
+            parts.push("env".to_string());
+            parts.extend(spec.env.iter().map(|(k, v)| shell_quote(&format!("{}={}", k, v))));
+        }
+
+        parts.push(shell_quote(&self.remote_cli_path));
+        parts.extend(spec.args.iter().map(|arg| shell_quote(arg)));
+
+        parts.join(" ")
+    }
+}
+
+/// Quote a string for a POSIX shell
+fn shell_quote(value: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c);
+
+    if !value.is_empty() && value.chars().all(is_safe) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+#[async_trait]
+impl Transport for SshTransport {
+    async fn connect(&mut self) -> Result<()> {
+        self.inner().connect().await
+    }
+
+    async fn write(&mut self, data: &str) -> Result<()> {
+        self.inner().write(data).await
+    }
+
+    async fn end_input(&mut self) -> Result<()> {
+        self.inner().end_input().await
+    }
+
+    fn read_messages(&mut self) -> TransportStream {
+        self.inner().read_messages()
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.close().await,
+            None => Ok(()),
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.inner.as_ref().is_some_and(|inner| inner.is_ready())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> CommandSpec {
+        CommandSpec {
+            program: "/usr/local/bin/claude".to_string(),
+            args: vec![
+                "--output-format".to_string(),
+                "stream-json".to_string(),
+                "--print".to_string(),
+                "--".to_string(),
+                "what's up?".to_string(),
+            ],
+            env: vec![("CLAUDE_CODE_ENTRYPOINT".to_string(), "sdk-rust".to_string())],
+            cwd: Some(PathBuf::from("/srv/my project")),
+        }
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("stream-json"), "stream-json");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+    }
+
+    #[test]
+    fn test_wrap_builds_ssh_command() {
+        let transport = SshTransport::new("dev@box", String::new(), ClaudeAgentOptions::default())
+            .with_port(2222)
+            .with_identity_file("/keys/id_ed25519")
+            .with_ssh_option("StrictHostKeyChecking=accept-new")
+            .with_remote_cli_path("/opt/claude/bin/claude");
+
+        let wrapped = transport.config.wrap(spec());
+
+        assert_eq!(wrapped.program, "ssh");
+        assert_eq!(
+            wrapped.args,
+            [
+                "-T",
+                "-o",
+                "BatchMode=yes",
+                "-p",
+                "2222",
+                "-i",
+                "/keys/id_ed25519",
+                "-o",
+                "StrictHostKeyChecking=accept-new",
+                "dev@box",
+                "--",
+                "cd '/srv/my project' && exec env CLAUDE_CODE_ENTRYPOINT=sdk-rust /opt/claude/bin/claude \
+                 --output-format stream-json --print -- 'what'\\''s up?'",
+            ]
+        );
+        assert!(wrapped.env.is_empty());
+        assert!(wrapped.cwd.is_none());
+    }
+
+    /// Write an executable shell script to a temporary path
+    #[cfg(unix)]
+    fn script(name: &str, body: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("claude-rs-{}-{}", name, std::process::id()));
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_proxies_remote_output() {
+        use futures::StreamExt;
+
+        let mut env = std::collections::HashMap::new();
+        env.insert("GREETING".to_string(), "hello from remote".to_string());
+        let options = ClaudeAgentOptions {
+            env,
+            cwd: Some(std::env::temp_dir()),
+            ..Default::default()
+        };
+
+        // `ssh` stand-in runs the remote command with the local shell
+        let ssh = script("fake-ssh", r#"for last; do :; done; exec sh -c "$last""#);
+        let cli = script(
+            "remote-claude",
+            r#"printf '{"greeting":"%s","prompt":"%s","pwd":"%s"}\n' "$GREETING" "$6" "$PWD""#,
+        );
+
+        let mut transport = SshTransport::new("box", "it's me".to_string(), options)
+            .with_ssh_program(ssh)
+            .with_remote_cli_path(cli);
+        transport.connect().await.unwrap();
+
+        let mut stream = transport.read_messages();
+        let message = stream.next().await.unwrap().unwrap();
+        assert_eq!(message["greeting"], "hello from remote");
+        assert_eq!(message["prompt"], "it's me");
+        assert_eq!(
+            std::fs::canonicalize(message["pwd"].as_str().unwrap()).unwrap(),
+            std::fs::canonicalize(std::env::temp_dir()).unwrap()
+        );
+        assert!(stream.next().await.is_none());
+        transport.close().await.unwrap();
+    }
+}
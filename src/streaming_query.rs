@@ -1,5 +1,8 @@
+use crate::assembler::{AssembledMessage, MessageAssembler};
+use crate::budget::BudgetTracker;
 use crate::errors::Result;
-use crate::message_parser::parse_message;
+use crate::message_parser::parse_message_with_mode;
+use crate::query::DEFAULT_MESSAGE_CHANNEL_CAPACITY;
 use crate::transport::{SubprocessTransport, Transport};
 use crate::types::{ClaudeAgentOptions, Message};
 use futures::stream::Stream;
@@ -9,7 +12,7 @@ use tokio::sync::mpsc;
 
 /// A streaming query session that provides true async iteration without collecting all messages
 pub struct StreamingQuery {
-    receiver: mpsc::UnboundedReceiver<Result<Message>>,
+    receiver: mpsc::Receiver<Result<Message>>,
 }
 
 impl StreamingQuery {
@@ -17,8 +20,18 @@ impl StreamingQuery {
     ///
     /// This spawns a background task that reads from the transport and sends
     /// parsed messages through a channel, allowing proper ownership separation.
+    /// The channel holds `message_channel_capacity` messages; reading from the
+    /// CLI pauses while it is full.
     pub async fn new(prompt: String, options: Option<ClaudeAgentOptions>) -> Result<Self> {
         let opts = options.unwrap_or_default();
+        let capacity = opts
+            .message_channel_capacity
+            .unwrap_or(DEFAULT_MESSAGE_CHANNEL_CAPACITY)
+            .max(1);
+        let parse_mode = opts.parse_mode;
+        let mut budget = BudgetTracker::from_options(&opts);
+        let metrics = opts.metrics.clone();
+        let cost_tracker = opts.cost_tracker.clone();
         let mut transport = SubprocessTransport::new(prompt, opts);
 
         // Connect to Claude Code
@@ -28,7 +41,7 @@ impl StreamingQuery {
         transport.end_input().await?;
 
         // Create channel for streaming messages
-        let (tx, rx) = mpsc::unbounded_channel();
+        let (tx, rx) = mpsc::channel(capacity);
 
         // Spawn task to read and parse messages
         tokio::spawn(async move {
@@ -39,21 +52,42 @@ impl StreamingQuery {
             while let Some(result) = stream.next().await {
                 match result {
                     Ok(json_value) => {
-                        match parse_message(&json_value) {
+                        match parse_message_with_mode(&json_value, parse_mode) {
                             Ok(message) => {
-                                if tx.send(Ok(message)).is_err() {
+                                if let Some(ref metrics) = metrics {
+                                    metrics.record_message(&message);
+                                }
+                                if let Some(ref tracker) = cost_tracker {
+                                    tracker.record(&message);
+                                }
+                                let budget_error = budget.as_mut().and_then(|budget| budget.record(&message));
+                                if tx.send(Ok(message)).await.is_err() {
                                     // Receiver dropped, stop reading
                                     break;
                                 }
+                                // Ending the task drops the transport, which stops the CLI
+                                if let Some(e) = budget_error {
+                                    if let Some(ref metrics) = metrics {
+                                        metrics.record_error(&e);
+                                    }
+                                    let _ = tx.send(Err(e)).await;
+                                    break;
+                                }
                             }
                             Err(e) => {
-                                let _ = tx.send(Err(e));
+                                if let Some(ref metrics) = metrics {
+                                    metrics.record_error(&e);
+                                }
+                                let _ = tx.send(Err(e)).await;
                                 break;
                             }
                         }
                     }
                     Err(e) => {
-                        let _ = tx.send(Err(e));
+                        if let Some(ref metrics) = metrics {
+                            metrics.record_error(&e);
+                        }
+                        let _ = tx.send(Err(e)).await;
                         break;
                     }
                 }
@@ -62,6 +96,47 @@ impl StreamingQuery {
 
         Ok(Self { receiver: rx })
     }
+
+    /// Wrap messages produced elsewhere, e.g. by a client task
+    pub(crate) fn from_receiver(receiver: mpsc::Receiver<Result<Message>>) -> Self {
+        Self { receiver }
+    }
+
+    /// Rebuild assistant messages from partial-message stream events
+    ///
+    /// Requires `include_partial_messages`. Only the assembled messages are
+    /// yielded; to also see other messages, feed the plain stream to a
+    /// [`MessageAssembler`] instead.
+    pub fn partial_messages(self) -> PartialMessageStream {
+        PartialMessageStream {
+            inner: self,
+            assembler: MessageAssembler::new(),
+        }
+    }
+}
+
+/// Assistant messages rebuilt from stream events, see [`StreamingQuery::partial_messages`]
+pub struct PartialMessageStream {
+    inner: StreamingQuery,
+    assembler: MessageAssembler,
+}
+
+impl Stream for PartialMessageStream {
+    type Item = Result<AssembledMessage>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let message = match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(message))) => message,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            if let Some(update) = self.assembler.push_message(&message) {
+                return Poll::Ready(Some(Ok(update)));
+            }
+        }
+    }
 }
 
 impl Stream for StreamingQuery {
@@ -116,6 +191,39 @@ pub async fn streaming_query(
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::types::StreamEvent;
+    use futures::stream::StreamExt;
+
+    #[tokio::test]
+    async fn test_partial_messages_assembles_stream_events() {
+        let (tx, rx) = mpsc::channel(16);
+        for data in [
+            serde_json::json!({"type": "message_start", "message": {"model": "m"}}),
+            serde_json::json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            serde_json::json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}),
+            serde_json::json!({"type": "message_stop"}),
+        ] {
+            let event = StreamEvent {
+                uuid: "e".to_string(),
+                session_id: "s".into(),
+                event: serde_json::from_value(data).unwrap(),
+                parent_tool_use_id: None,
+            };
+            tx.send(Ok(Message::Stream(event))).await.unwrap();
+        }
+        drop(tx);
+
+        let updates: Vec<_> = StreamingQuery::from_receiver(rx).partial_messages().collect().await;
+        assert_eq!(updates.len(), 4);
+        match updates.last() {
+            Some(Ok(AssembledMessage::Complete(message))) => {
+                assert!(matches!(&message.content[0], crate::types::ContentBlock::Text { text } if text == "Hi"))
+            }
+            other => panic!("expected complete message, got {:?}", other),
+        }
+    }
+
     #[tokio::test]
     async fn test_streaming_query_creation() {
         // This test just verifies the API compiles and can be created
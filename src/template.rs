@@ -0,0 +1,171 @@
+//! Prompt templates with named variables
+//!
+//! A [`PromptTemplate`] fills `{{name}}` placeholders from a set of
+//! variables. Values are inserted verbatim and never interpreted as template
+//! syntax, so user input containing `{{...}}` cannot pull in other variables,
+//! and a variable without a value is an error instead of an empty string.
+//!
+//! # Example
+//! ```
+//! use claude::PromptTemplate;
+//!
+//! let template = PromptTemplate::new("Summarize this {{lang}} code:\n{{ code }}");
+//! let prompt = template.render([("lang", "Rust"), ("code", "fn main() {}")])?;
+//! assert_eq!(prompt, "Summarize this Rust code:\nfn main() {}");
+//! # Ok::<(), claude::ClaudeSDKError>(())
+//! ```
+//!
+//! Write `\{{` for a literal `{{`.
+
+use crate::errors::{ClaudeSDKError, Result};
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// A prompt with `{{name}}` placeholders
+///
+/// Names are ASCII letters, digits and underscores, not starting with a
+/// digit; whitespace around them is ignored. Syntax errors are reported by
+/// [`render`](Self::render) and [`variables`](Self::variables).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplate {
+    source: String,
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Variable(&'a str),
+}
+
+impl PromptTemplate {
+    pub fn new(source: impl Into<String>) -> Self {
+        PromptTemplate { source: source.into() }
+    }
+
+    /// The template text
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Names of the variables in the template, in order of first use
+    pub fn variables(&self) -> Result<Vec<&str>> {
+        let mut names = Vec::new();
+        for segment in parse(&self.source)? {
+            if let Segment::Variable(name) = segment {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    /// Fill in the placeholders
+    ///
+    /// Variables the template doesn't use are ignored. Fails with
+    /// [`ClaudeSDKError::MissingTemplateVariable`] if a placeholder has no
+    /// value and [`ClaudeSDKError::InvalidTemplate`] on syntax errors.
+    pub fn render<I, K, V>(&self, vars: I) -> Result<String>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Display,
+    {
+        let vars: HashMap<String, String> =
+            vars.into_iter().map(|(name, value)| (name.as_ref().to_string(), value.to_string())).collect();
+
+        let mut prompt = String::with_capacity(self.source.len());
+        for segment in parse(&self.source)? {
+            match segment {
+                Segment::Text(text) => prompt.push_str(text),
+                Segment::Variable(name) => {
+                    let value = vars.get(name).ok_or_else(|| ClaudeSDKError::missing_template_variable(name))?;
+                    prompt.push_str(value);
+                }
+            }
+        }
+        Ok(prompt)
+    }
+
+    /// Escape `text` so it can be embedded in a template as literal text
+    pub fn escape(text: &str) -> String {
+        text.replace("{{", "\\{{")
+    }
+}
+
+impl From<&str> for PromptTemplate {
+    fn from(source: &str) -> Self {
+        Self::new(source)
+    }
+}
+
+impl From<String> for PromptTemplate {
+    fn from(source: String) -> Self {
+        Self::new(source)
+    }
+}
+
+fn parse(source: &str) -> Result<Vec<Segment<'_>>> {
+    let mut segments = Vec::new();
+    let mut rest = 0;
+    while let Some(offset) = source[rest..].find("{{") {
+        let open = rest + offset;
+        if source[..open].ends_with('\\') {
+            segments.push(Segment::Text(&source[rest..open - 1]));
+            segments.push(Segment::Text("{{"));
+            rest = open + 2;
+            continue;
+        }
+        segments.push(Segment::Text(&source[rest..open]));
+
+        let close = source[open + 2..]
+            .find("}}")
+            .map(|offset| open + 2 + offset)
+            .ok_or_else(|| ClaudeSDKError::invalid_template(open, "unclosed `{{`"))?;
+        let name = source[open + 2..close].trim();
+        if !is_valid_name(name) {
+            return Err(ClaudeSDKError::invalid_template(open, format!("invalid variable name '{}'", name)));
+        }
+        segments.push(Segment::Variable(name));
+        rest = close + 2;
+    }
+    segments.push(Segment::Text(&source[rest..]));
+    Ok(segments)
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_does_not_expand_values() {
+        let template = PromptTemplate::new("Review {{file}}: {{ diff }} ({{file}})");
+        let prompt = template.render([("file", "lib.rs"), ("diff", "{{file}} \\{{x}}"), ("unused", "x")]).unwrap();
+        assert_eq!(prompt, "Review lib.rs: {{file}} \\{{x}} (lib.rs)");
+        assert_eq!(template.variables().unwrap(), ["file", "diff"]);
+    }
+
+    #[test]
+    fn test_escaped_braces() {
+        let template = PromptTemplate::new(format!("Fill {} in {{{{name}}}}", PromptTemplate::escape("{{x}}")));
+        assert_eq!(template.render([("name", 42)]).unwrap(), "Fill {{x}} in 42");
+        assert_eq!(template.variables().unwrap(), ["name"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let missing = PromptTemplate::new("Hi {{name}}").render(Vec::<(&str, &str)>::new());
+        assert!(matches!(missing, Err(ClaudeSDKError::MissingTemplateVariable { name }) if name == "name"));
+
+        let unclosed = PromptTemplate::new("Hi {{name").render([("name", "x")]);
+        assert!(matches!(unclosed, Err(ClaudeSDKError::InvalidTemplate { position: 3, .. })));
+
+        let invalid = PromptTemplate::new("{{ first name }}");
+        assert!(matches!(invalid.variables(), Err(ClaudeSDKError::InvalidTemplate { position: 0, .. })));
+    }
+}
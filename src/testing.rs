@@ -0,0 +1,495 @@
+//! Testing utilities for code built on the SDK
+//!
+//! [`MockTransport`] implements [`Transport`] without spawning the Claude Code
+//! CLI. It yields scripted JSON messages, records everything the SDK writes,
+//! and answers the SDK's own control requests (initialize, interrupt, ...)
+//! so `Query` and `ClaudeSDKClient` can be exercised in unit tests.
+//!
+//! Only available with the `testing` cargo feature, typically enabled from
+//! `[dev-dependencies]`.
+//!
+//! # Example
+//! ```
+//! use claude::testing::MockTransport;
+//! use claude::ClaudeSDKClient;
+//! use futures::StreamExt;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> claude::Result<()> {
+//! let transport = MockTransport::new()
+//!     .with_assistant_text("Hello from the mock")
+//!     .with_result("session-1");
+//! let handle = transport.handle();
+//!
+//! let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+//! client.connect().await?;
+//! client.query("Hi", None).await?;
+//!
+//! let mut response = client.receive_response();
+//! while let Some(message) = response.next().await {
+//!     println!("{:?}", message?);
+//! }
+//!
+//! assert_eq!(handle.user_messages().len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::errors::{ClaudeSDKError, Result};
+use crate::transport::{Transport, TransportStream};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, watch};
+
+type Outbox = Arc<Mutex<Option<mpsc::UnboundedSender<Result<Value>>>>>;
+
+/// A successful one-turn result message for the given session
+///
+/// Set further fields by indexing, e.g. `result["total_cost_usd"] = json!(0.5)`.
+pub fn result_message(session_id: impl Into<String>) -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 0,
+        "duration_api_ms": 0,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": session_id.into()
+    })
+}
+
+/// In-memory transport that replays scripted messages
+///
+/// Scripted messages are delivered in order once `read_messages()` is
+/// called. The stream stays open until the transport is closed or
+/// [`MockHandle::finish`] is called, so control responses can still be
+/// delivered after the script is exhausted.
+pub struct MockTransport {
+    script: Vec<Result<Value>>,
+    outbox: Outbox,
+    inbox: Option<mpsc::UnboundedReceiver<Result<Value>>>,
+    written: Arc<Mutex<Vec<String>>>,
+    write_count: watch::Sender<usize>,
+    control_responses: HashMap<String, Value>,
+    auto_respond: bool,
+    connected: bool,
+}
+
+impl MockTransport {
+    /// Create an empty mock transport
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        Self {
+            script: Vec::new(),
+            outbox: Arc::new(Mutex::new(Some(tx))),
+            inbox: Some(rx),
+            written: Arc::new(Mutex::new(Vec::new())),
+            write_count: watch::Sender::new(0),
+            control_responses: HashMap::new(),
+            auto_respond: true,
+            connected: false,
+        }
+    }
+
+    /// Queue a raw JSON message to be read by the SDK
+    pub fn with_message(mut self, message: Value) -> Self {
+        self.script.push(Ok(message));
+        self
+    }
+
+    /// Queue several raw JSON messages
+    pub fn with_messages<I>(mut self, messages: I) -> Self
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        self.script.extend(messages.into_iter().map(Ok));
+        self
+    }
+
+    /// Queue a transport error
+    pub fn with_error(mut self, error: ClaudeSDKError) -> Self {
+        self.script.push(Err(error));
+        self
+    }
+
+    /// Queue an assistant message containing a single text block
+    pub fn with_assistant_text(self, text: impl Into<String>) -> Self {
+        self.with_message(json!({
+            "type": "assistant",
+            "message": {
+                "model": "mock-model",
+                "content": [{"type": "text", "text": text.into()}]
+            }
+        }))
+    }
+
+    /// Queue a successful result message for the given session
+    pub fn with_result(self, session_id: impl Into<String>) -> Self {
+        self.with_message(result_message(session_id))
+    }
+
+    /// Queue a control request sent by the CLI to the SDK
+    pub fn with_control_request(self, request_id: impl Into<String>, request: Value) -> Self {
+        self.with_message(json!({
+            "type": "control_request",
+            "request_id": request_id.into(),
+            "request": request
+        }))
+    }
+
+    /// Queue a `can_use_tool` permission request
+    pub fn with_can_use_tool(
+        self,
+        request_id: impl Into<String>,
+        tool_name: impl Into<String>,
+        input: Value,
+    ) -> Self {
+        self.with_control_request(
+            request_id,
+            json!({
+                "subtype": "can_use_tool",
+                "tool_name": tool_name.into(),
+                "input": input
+            }),
+        )
+    }
+
+    /// Queue a `hook_callback` request
+    pub fn with_hook_callback(
+        self,
+        request_id: impl Into<String>,
+        callback_id: impl Into<String>,
+        input: Value,
+    ) -> Self {
+        self.with_control_request(
+            request_id,
+            json!({
+                "subtype": "hook_callback",
+                "callback_id": callback_id.into(),
+                "input": input
+            }),
+        )
+    }
+
+    /// Set the response payload returned for SDK control requests of a subtype
+    ///
+    /// Subtypes without a configured payload are answered with `{}`.
+    pub fn respond_to(mut self, subtype: impl Into<String>, response: Value) -> Self {
+        self.control_responses.insert(subtype.into(), response);
+        self
+    }
+
+    /// Enable or disable automatic success responses to SDK control requests
+    pub fn auto_respond(mut self, enabled: bool) -> Self {
+        self.auto_respond = enabled;
+        self
+    }
+
+    /// Get a handle for inspecting and driving the transport after it is boxed
+    pub fn handle(&self) -> MockHandle {
+        MockHandle {
+            outbox: Arc::clone(&self.outbox),
+            written: Arc::clone(&self.written),
+            write_count: self.write_count.subscribe(),
+        }
+    }
+
+    fn push(&self, item: Result<Value>) {
+        if let Some(tx) = self.outbox.lock().unwrap().as_ref() {
+            let _ = tx.send(item);
+        }
+    }
+
+    fn respond_to_control_request(&self, message: &Value) {
+        let Some(request_id) = message.get("request_id").and_then(|v| v.as_str()) else {
+            return;
+        };
+        let subtype = message
+            .get("request")
+            .and_then(|r| r.get("subtype"))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let response = self
+            .control_responses
+            .get(subtype)
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+
+        self.push(Ok(json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "success",
+                "request_id": request_id,
+                "response": response
+            }
+        })));
+    }
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Transport for MockTransport {
+    async fn connect(&mut self) -> Result<()> {
+        self.connected = true;
+        Ok(())
+    }
+
+    async fn write(&mut self, data: &str) -> Result<()> {
+        if !self.connected {
+            return Err(ClaudeSDKError::cli_connection_error(
+                "Transport is not ready for writing".to_string(),
+            ));
+        }
+
+        self.written.lock().unwrap().push(data.to_string());
+        self.write_count.send_modify(|count| *count += 1);
+
+        if self.auto_respond {
+            for line in data.lines() {
+                if let Ok(value) = serde_json::from_str::<Value>(line) {
+                    if value.get("type").and_then(|v| v.as_str()) == Some("control_request") {
+                        self.respond_to_control_request(&value);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn end_input(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_messages(&mut self) -> TransportStream {
+        for item in self.script.drain(..) {
+            if let Some(tx) = self.outbox.lock().unwrap().as_ref() {
+                let _ = tx.send(item);
+            }
+        }
+
+        match self.inbox.take() {
+            Some(mut rx) => Box::pin(async_stream::stream! {
+                while let Some(item) = rx.recv().await {
+                    yield item;
+                }
+            }),
+            None => Box::pin(futures::stream::empty()),
+        }
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.connected = false;
+        self.outbox.lock().unwrap().take();
+        Ok(())
+    }
+
+    fn is_ready(&self) -> bool {
+        self.connected
+    }
+}
+
+/// Handle to a [`MockTransport`] that remains usable after the transport is boxed
+#[derive(Clone)]
+pub struct MockHandle {
+    outbox: Outbox,
+    written: Arc<Mutex<Vec<String>>>,
+    write_count: watch::Receiver<usize>,
+}
+
+impl MockHandle {
+    /// Push an additional message into the read stream
+    pub fn push(&self, message: Value) {
+        if let Some(tx) = self.outbox.lock().unwrap().as_ref() {
+            let _ = tx.send(Ok(message));
+        }
+    }
+
+    /// End the read stream, as if the CLI had exited
+    pub fn finish(&self) {
+        self.outbox.lock().unwrap().take();
+    }
+
+    /// Whether the read stream has ended, through `close()` or [`finish`](Self::finish)
+    pub fn is_closed(&self) -> bool {
+        self.outbox.lock().unwrap().is_none()
+    }
+
+    /// All raw data written by the SDK, in order
+    pub fn written(&self) -> Vec<String> {
+        self.written.lock().unwrap().clone()
+    }
+
+    /// All written lines that parse as JSON
+    pub fn written_json(&self) -> Vec<Value> {
+        self.written()
+            .iter()
+            .flat_map(|data| data.lines().map(str::to_string).collect::<Vec<_>>())
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect()
+    }
+
+    /// Written messages of the given `type` field
+    pub fn written_of_type(&self, message_type: &str) -> Vec<Value> {
+        self.written_json()
+            .into_iter()
+            .filter(|v| v.get("type").and_then(|t| t.as_str()) == Some(message_type))
+            .collect()
+    }
+
+    /// User messages written by the SDK
+    pub fn user_messages(&self) -> Vec<Value> {
+        self.written_of_type("user")
+    }
+
+    /// Control requests written by the SDK
+    pub fn control_requests(&self) -> Vec<Value> {
+        self.written_of_type("control_request")
+    }
+
+    /// Control responses written by the SDK (e.g. answers to `can_use_tool`)
+    pub fn control_responses(&self) -> Vec<Value> {
+        self.written_of_type("control_response")
+    }
+
+    /// Find the control response the SDK sent for a given request id
+    pub fn control_response_for(&self, request_id: &str) -> Option<Value> {
+        self.control_responses().into_iter().find(|v| {
+            v.get("response")
+                .and_then(|r| r.get("request_id"))
+                .and_then(|id| id.as_str())
+                == Some(request_id)
+        })
+    }
+
+    /// Wait until the SDK writes the control response for a request id
+    ///
+    /// Returns `None` if the transport is dropped first. Wrap the call in
+    /// `tokio::time::timeout` to bound how long a test waits.
+    pub async fn wait_for_control_response(&self, request_id: &str) -> Option<Value> {
+        self.wait_for(|handle| handle.control_response_for(request_id)).await
+    }
+
+    /// Wait until `check` returns `Some`, re-checking after every write
+    pub async fn wait_for<T>(&self, check: impl Fn(&Self) -> Option<T>) -> Option<T> {
+        let mut write_count = self.write_count.clone();
+
+        loop {
+            write_count.borrow_and_update();
+            if let Some(value) = check(self) {
+                return Some(value);
+            }
+            if write_count.changed().await.is_err() {
+                return check(self);
+            }
+        }
+    }
+
+    /// Panic unless a written user message has the given text content
+    pub fn assert_user_message(&self, text: &str) {
+        let found = self.user_messages().iter().any(|m| {
+            m.get("message")
+                .and_then(|msg| msg.get("content"))
+                .and_then(|c| c.as_str())
+                == Some(text)
+        });
+        assert!(found, "no user message with content {:?} was written", text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_mock_yields_script_in_order() {
+        let mut transport = MockTransport::new()
+            .with_assistant_text("one")
+            .with_result("session-1");
+        let handle = transport.handle();
+        transport.connect().await.unwrap();
+
+        let mut stream = transport.read_messages();
+        handle.finish();
+
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(first["type"], "assistant");
+        let second = stream.next().await.unwrap().unwrap();
+        assert_eq!(second["session_id"], "session-1");
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_mock_records_writes() {
+        let mut transport = MockTransport::new();
+        let handle = transport.handle();
+        transport.connect().await.unwrap();
+
+        transport
+            .write("{\"type\":\"user\",\"message\":{\"role\":\"user\",\"content\":\"hi\"}}\n")
+            .await
+            .unwrap();
+
+        assert_eq!(handle.written().len(), 1);
+        handle.assert_user_message("hi");
+    }
+
+    #[tokio::test]
+    async fn test_mock_write_requires_connect() {
+        let mut transport = MockTransport::new();
+        assert!(transport.write("data\n").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_answers_control_requests() {
+        let mut transport = MockTransport::new().respond_to("initialize", json!({"ok": true}));
+        transport.connect().await.unwrap();
+        let mut stream = transport.read_messages();
+
+        transport
+            .write("{\"type\":\"control_request\",\"request_id\":\"req_1\",\"request\":{\"subtype\":\"initialize\"}}\n")
+            .await
+            .unwrap();
+
+        let response = stream.next().await.unwrap().unwrap();
+        assert_eq!(response["type"], "control_response");
+        assert_eq!(response["response"]["request_id"], "req_1");
+        assert_eq!(response["response"]["response"]["ok"], true);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_control_response() {
+        let mut transport = MockTransport::new();
+        let handle = transport.handle();
+        transport.connect().await.unwrap();
+
+        let waiter = tokio::spawn(async move { handle.wait_for_control_response("req_1").await });
+
+        transport
+            .write("{\"type\":\"control_response\",\"response\":{\"subtype\":\"success\",\"request_id\":\"req_1\"}}\n")
+            .await
+            .unwrap();
+
+        let response = waiter.await.unwrap().unwrap();
+        assert_eq!(response["response"]["request_id"], "req_1");
+    }
+
+    #[tokio::test]
+    async fn test_close_ends_stream() {
+        let mut transport = MockTransport::new();
+        transport.connect().await.unwrap();
+        let mut stream = transport.read_messages();
+
+        transport.close().await.unwrap();
+        assert!(stream.next().await.is_none());
+    }
+}
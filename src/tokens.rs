@@ -0,0 +1,155 @@
+//! Token estimates and usage counts
+//!
+//! [`estimate`] gives a rough token count for text without calling the API,
+//! good enough to budget prompts locally. Exact counts for a finished query
+//! are in its result message, see [`ResultMessage::token_usage`].
+//!
+//! # Example
+//! ```
+//! use claude::tokens;
+//!
+//! let prompt = "Summarize the changes in this pull request";
+//! assert!(tokens::estimate(prompt) < 20);
+//!
+//! let stats = tokens::TextStats::of(prompt);
+//! assert_eq!(stats.words, 7);
+//! ```
+//!
+//! [`ResultMessage::token_usage`]: crate::types::ResultMessage::token_usage
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Rough number of tokens in `text`
+///
+/// Counts about four ASCII characters per token and one token per other
+/// character (CJK text and emoji tokenize densely). Never less than the
+/// number of words. Real counts differ by model; treat this as an estimate.
+pub fn estimate(text: &str) -> usize {
+    let ascii = text.bytes().filter(u8::is_ascii).count();
+    let other = text.chars().filter(|c| !c.is_ascii()).count();
+    let words = text.split_whitespace().count();
+    (ascii.div_ceil(4) + other).max(words)
+}
+
+/// Size of a piece of text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextStats {
+    pub chars: usize,
+    pub words: usize,
+    pub lines: usize,
+    /// See [`estimate`]
+    pub estimated_tokens: usize,
+}
+
+impl TextStats {
+    pub fn of(text: &str) -> Self {
+        TextStats {
+            chars: text.chars().count(),
+            words: text.split_whitespace().count(),
+            lines: text.lines().count(),
+            estimated_tokens: estimate(text),
+        }
+    }
+}
+
+/// Token counts reported by the API
+///
+/// Missing counts are zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    /// Input tokens written to the prompt cache
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+    /// Input tokens served from the prompt cache
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+}
+
+impl Usage {
+    /// Read the counts from a `usage` object, as found on result messages
+    pub fn from_map(usage: &HashMap<String, Value>) -> Self {
+        let count = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+        Usage {
+            input_tokens: count("input_tokens"),
+            output_tokens: count("output_tokens"),
+            cache_creation_input_tokens: count("cache_creation_input_tokens"),
+            cache_read_input_tokens: count("cache_read_input_tokens"),
+        }
+    }
+
+    /// All input tokens, cached or not
+    pub fn total_input_tokens(&self) -> u64 {
+        self.input_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.total_input_tokens() + self.output_tokens
+    }
+
+    /// Counts added since `previous`, an earlier reading of the same running
+    /// totals; `None` if any count went down
+    pub(crate) fn since(&self, previous: &Usage) -> Option<Usage> {
+        Some(Usage {
+            input_tokens: self.input_tokens.checked_sub(previous.input_tokens)?,
+            output_tokens: self.output_tokens.checked_sub(previous.output_tokens)?,
+            cache_creation_input_tokens: self
+                .cache_creation_input_tokens
+                .checked_sub(previous.cache_creation_input_tokens)?,
+            cache_read_input_tokens: self.cache_read_input_tokens.checked_sub(previous.cache_read_input_tokens)?,
+        })
+    }
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_estimate() {
+        assert_eq!(estimate(""), 0);
+        assert_eq!(estimate("abcd"), 1);
+        assert_eq!(estimate("hello world"), 3);
+        // Short words count at least one token each
+        assert_eq!(estimate("a b c d e"), 5);
+        assert_eq!(estimate("日本語"), 3);
+    }
+
+    #[test]
+    fn test_text_stats() {
+        let stats = TextStats::of("fn main() {\n    println!(\"hi\");\n}\n");
+        assert_eq!(stats.lines, 3);
+        assert_eq!(stats.words, 5);
+        assert_eq!(stats.chars, 34);
+    }
+
+    #[test]
+    fn test_usage_from_map() {
+        let map: HashMap<String, Value> = serde_json::from_value(json!({
+            "input_tokens": 10,
+            "output_tokens": 25,
+            "cache_read_input_tokens": 100,
+            "service_tier": "standard"
+        }))
+        .unwrap();
+        let usage = Usage::from_map(&map);
+        assert_eq!(usage.cache_creation_input_tokens, 0);
+        assert_eq!(usage.total_input_tokens(), 110);
+        assert_eq!(usage.total_tokens(), 135);
+    }
+}
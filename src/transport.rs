@@ -1,31 +1,214 @@
 use crate::errors::{ClaudeSDKError, Result};
 use crate::types::ClaudeAgentOptions;
+use crate::version::{check_version, CliVersion};
 use async_trait::async_trait;
-use futures::stream::Stream;
-use futures::FutureExt;
+use futures::stream::BoxStream;
 use serde_json::Value;
+use std::collections::VecDeque;
 use std::path::PathBuf;
-use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
 use tokio::io::AsyncWriteExt;
-use tokio::process::{Child, Command, ChildStdin};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 
+/// Maximum number of stderr bytes kept for error reporting
+const MAX_STDERR_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Default limit for a single line of CLI output when `max_buffer_size` is unset
+const DEFAULT_MAX_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Default grace period between SIGTERM and SIGKILL on close
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for the CLI to exit after stdout closes
+const EXIT_WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long `claude --version` may take
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Stream of raw JSON values read from a transport
+pub type TransportStream = BoxStream<'static, Result<Value>>;
+
+/// Creates a transport for the given options
+pub type TransportFactory = Arc<dyn Fn(ClaudeAgentOptions) -> Box<dyn Transport> + Send + Sync>;
+
+/// Abstraction over the channel used to talk to Claude Code
+///
+/// The trait is object safe, so `Query` and `ClaudeSDKClient` can hold any
+/// implementation as a `Box<dyn Transport>` (subprocess, mock, remote, ...).
 #[async_trait]
 pub trait Transport: Send + Sync {
+    /// Establish the connection (e.g. spawn the CLI process)
     async fn connect(&mut self) -> Result<()>;
+    /// Write a raw line of data to the other side
     async fn write(&mut self, data: &str) -> Result<()>;
+    /// Signal that no more input will be written
     async fn end_input(&mut self) -> Result<()>;
-    fn read_messages(&mut self) -> impl Stream<Item = Result<Value>> + Send;
+    /// Take the stream of incoming JSON messages
+    ///
+    /// The returned stream is owned and does not borrow the transport, so
+    /// writes can continue while messages are being read.
+    fn read_messages(&mut self) -> TransportStream;
+    /// Close the connection and release resources
     async fn close(&mut self) -> Result<()>;
+    /// Whether the transport is ready for writing
     fn is_ready(&self) -> bool;
+    /// Exit status of the CLI process once it has exited
+    ///
+    /// Transports that don't run a local process return `None`.
+    fn exit_status(&self) -> Option<ExitStatus> {
+        None
+    }
+}
+
+/// Bounded tail of the CLI's stderr output
+///
+/// Keeps the most recent lines up to `MAX_STDERR_BUFFER_SIZE` bytes so a
+/// chatty process cannot grow memory without limit.
+#[derive(Debug, Default)]
+struct StderrBuffer {
+    lines: VecDeque<String>,
+    size: usize,
+}
+
+impl StderrBuffer {
+    fn push(&mut self, line: String) {
+        self.size += line.len();
+        self.lines.push_back(line);
+
+        while self.size > MAX_STDERR_BUFFER_SIZE {
+            match self.lines.pop_front() {
+                Some(dropped) => self.size -= dropped.len(),
+                None => break,
+            }
+        }
+    }
+
+    fn contents(&self) -> Option<String> {
+        if self.lines.is_empty() {
+            None
+        } else {
+            Some(self.lines.iter().map(String::as_str).collect::<Vec<_>>().join("\n"))
+        }
+    }
 }
 
+/// Read one `\n`-terminated line without buffering more than `limit` bytes
+///
+/// Returns `Ok(None)` at end of input. A line longer than `limit` is
+/// discarded up to its newline and reported as `BufferOverflow`, so the
+/// caller can keep reading the following lines.
+async fn read_line_bounded<R>(reader: &mut R, limit: usize) -> Result<Option<String>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = Vec::new();
+    let mut overflowed = false;
+
+    loop {
+        let available = reader
+            .fill_buf()
+            .await
+            .map_err(|e| ClaudeSDKError::io_error("Failed to read from process stdout", e))?;
+
+        if available.is_empty() {
+            return match (overflowed, line.is_empty()) {
+                (true, _) => Err(ClaudeSDKError::buffer_overflow(limit)),
+                (false, true) => Ok(None),
+                (false, false) => Ok(Some(String::from_utf8_lossy(&line).into_owned())),
+            };
+        }
+
+        let newline = available.iter().position(|&b| b == b'\n');
+        let chunk = &available[..newline.unwrap_or(available.len())];
+
+        if !overflowed {
+            if line.len() + chunk.len() > limit {
+                overflowed = true;
+                line = Vec::new();
+            } else {
+                line.extend_from_slice(chunk);
+            }
+        }
+
+        let consumed = chunk.len() + usize::from(newline.is_some());
+        reader.consume(consumed);
+
+        if newline.is_some() {
+            if overflowed {
+                return Err(ClaudeSDKError::buffer_overflow(limit));
+            }
+            return Ok(Some(String::from_utf8_lossy(&line).into_owned()));
+        }
+    }
+}
+
+/// Resolve a user name or numeric uid to the ids used to run the CLI
+///
+/// Names are looked up in `/etc/passwd`, which also yields the user's
+/// primary group. A numeric uid is used as is.
+#[cfg(unix)]
+fn resolve_user(user: &str) -> Result<(u32, Option<u32>)> {
+    if let Ok(uid) = user.parse::<u32>() {
+        return Ok((uid, None));
+    }
+
+    let passwd = std::fs::read_to_string("/etc/passwd")
+        .map_err(|e| ClaudeSDKError::io_error("Failed to read /etc/passwd", e))?;
+
+    passwd
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.len() > 3 && fields[0] == user)
+        .and_then(|fields| Some((fields[2].parse().ok()?, fields[3].parse().ok())))
+        .ok_or_else(|| ClaudeSDKError::cli_connection_error(format!("Unknown user: {}", user)))
+}
+
+/// A process to launch: program, arguments, environment, and working directory
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CommandSpec {
+    pub(crate) program: String,
+    pub(crate) args: Vec<String>,
+    pub(crate) env: Vec<(String, String)>,
+    pub(crate) cwd: Option<PathBuf>,
+}
+
+/// Rewrites the local CLI command, e.g. to run it on another host
+pub(crate) type CommandWrapper = Arc<dyn Fn(CommandSpec) -> CommandSpec + Send + Sync>;
+
+/// Which way a raw protocol line is travelling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Written by the SDK to the CLI's stdin
+    Outgoing,
+    /// Read by the SDK from the CLI's stdout
+    Incoming,
+}
+
+/// Observer for every raw line written to and read from the CLI
+///
+/// Lines are passed without their trailing newline. The interceptor runs
+/// inline on the I/O path, so it should return quickly.
+pub type Interceptor = Arc<dyn Fn(Direction, &str) + Send + Sync>;
+
+type SharedStderr = Arc<std::sync::Mutex<StderrBuffer>>;
+
 pub struct SubprocessTransport {
     prompt: String,
     options: ClaudeAgentOptions,
     cli_path: String,
-    process: Option<Child>,
+    process: Arc<Mutex<Option<Child>>>,
     stdin: Option<ChildStdin>,
+    stdout: Option<ChildStdout>,
+    stderr: SharedStderr,
+    stderr_task: Option<JoinHandle<()>>,
+    exit_status: Arc<std::sync::Mutex<Option<ExitStatus>>>,
+    wrapper: Option<CommandWrapper>,
+    interceptor: Option<Interceptor>,
     ready: bool,
 }
 
@@ -37,12 +220,64 @@ impl SubprocessTransport {
             prompt,
             options,
             cli_path,
-            process: None,
+            process: Arc::new(Mutex::new(None)),
             stdin: None,
+            stdout: None,
+            stderr: Arc::new(std::sync::Mutex::new(StderrBuffer::default())),
+            stderr_task: None,
+            exit_status: Arc::new(std::sync::Mutex::new(None)),
+            wrapper: None,
+            interceptor: None,
             ready: false,
         }
     }
 
+    /// Stop the child: SIGTERM, wait up to `timeout`, then SIGKILL
+    async fn terminate(child: &mut Child, timeout: Duration) -> Option<ExitStatus> {
+        if let Ok(Some(status)) = child.try_wait() {
+            return Some(status);
+        }
+
+        #[cfg(unix)]
+        if let Some(pid) = child.id() {
+            // SAFETY: kill(2) has no memory-safety requirements, and the pid
+            // still belongs to our child because it has not been reaped yet
+            unsafe {
+                libc::kill(pid as libc::pid_t, libc::SIGTERM);
+            }
+
+            if let Ok(Ok(status)) = tokio::time::timeout(timeout, child.wait()).await {
+                return Some(status);
+            }
+        }
+
+        let _ = child.start_kill();
+        child.wait().await.ok()
+    }
+
+    /// Captured stderr output of the CLI, if any was written
+    pub fn stderr_output(&self) -> Option<String> {
+        self.stderr.lock().unwrap().contents()
+    }
+
+    /// Append captured stderr to an error message
+    fn with_stderr(&self, message: String) -> String {
+        match self.stderr_output() {
+            Some(stderr) => format!("{}\nError output: {}", message, stderr),
+            None => message,
+        }
+    }
+
+    /// Read stderr line by line into the shared bounded buffer
+    fn spawn_stderr_reader(stderr: ChildStderr, buffer: SharedStderr) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                buffer.lock().unwrap().push(line);
+            }
+        })
+    }
+
     fn find_claude_cli() -> Result<String> {
         // Try to find claude in PATH
         if let Ok(path) = which::which("claude") {
@@ -65,6 +300,102 @@ impl SubprocessTransport {
         Err(ClaudeSDKError::cli_not_found(None))
     }
 
+    /// Describe how to launch the CLI locally
+    fn command_spec(&self) -> CommandSpec {
+        let mut env = vec![
+            ("CLAUDE_CODE_ENTRYPOINT".to_string(), "sdk-rust".to_string()),
+            ("CLAUDE_AGENT_SDK_VERSION".to_string(), env!("CARGO_PKG_VERSION").to_string()),
+        ];
+
+        // User-supplied environment overrides the SDK defaults
+        let mut user_env: Vec<_> = self.options.env.iter().collect();
+        user_env.sort();
+        for (key, value) in user_env {
+            env.retain(|(k, _)| k != key);
+            env.push((key.clone(), value.clone()));
+        }
+
+        CommandSpec {
+            program: self.cli_path.clone(),
+            args: self.build_command(),
+            env,
+            cwd: self.options.cwd.clone(),
+        }
+    }
+
+    /// Version of the CLI this transport starts, from `claude --version`
+    pub async fn cli_version(&self) -> Result<CliVersion> {
+        let mut spec = CommandSpec {
+            args: vec!["--version".to_string()],
+            ..self.command_spec()
+        };
+        if let Some(ref wrapper) = self.wrapper {
+            spec = wrapper(spec);
+        }
+
+        let mut command = Command::new(&spec.program);
+        command
+            .args(&spec.args)
+            .envs(spec.env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+        if let Some(ref cwd) = spec.cwd {
+            command.current_dir(cwd);
+        }
+
+        let output = tokio::time::timeout(VERSION_TIMEOUT, command.output())
+            .await
+            .map_err(|_| ClaudeSDKError::timeout("`claude --version`", VERSION_TIMEOUT))?
+            .map_err(ClaudeSDKError::spawn_error_from)?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Err(ClaudeSDKError::process_error(
+                "`claude --version` failed",
+                output.status.code(),
+                (!stderr.is_empty()).then_some(stderr),
+            ));
+        }
+        CliVersion::from_output(&stdout).ok_or_else(|| {
+            ClaudeSDKError::message_parse_error(
+                format!("Unrecognized `claude --version` output: {}", stdout.trim()),
+                None,
+            )
+        })
+    }
+
+    /// Observe every raw line exchanged with the CLI
+    ///
+    /// Useful for wire-level logging or capturing a session to a file:
+    ///
+    /// ```no_run
+    /// use claude::transport::{Direction, SubprocessTransport};
+    ///
+    /// let transport = SubprocessTransport::new(String::new(), Default::default())
+    ///     .with_interceptor(|direction, line| match direction {
+    ///         Direction::Outgoing => eprintln!(">> {}", line),
+    ///         Direction::Incoming => eprintln!("<< {}", line),
+    ///     });
+    /// ```
+    pub fn with_interceptor(mut self, interceptor: impl Fn(Direction, &str) + Send + Sync + 'static) -> Self {
+        self.interceptor = Some(Arc::new(interceptor));
+        self
+    }
+
+    /// Use an already shared interceptor, e.g. from a wrapping transport
+    #[cfg(any(feature = "ssh-transport", feature = "docker-transport"))]
+    pub(crate) fn with_shared_interceptor(mut self, interceptor: Option<Interceptor>) -> Self {
+        self.interceptor = interceptor;
+        self
+    }
+
+    /// Launch the CLI through a wrapper command (ssh, docker, ...)
+    #[cfg(any(feature = "ssh-transport", feature = "docker-transport"))]
+    pub(crate) fn with_wrapper(mut self, wrapper: CommandWrapper) -> Self {
+        self.wrapper = Some(wrapper);
+        self
+    }
+
     fn build_command(&self) -> Vec<String> {
         let mut cmd = vec![
             "--output-format".to_string(),
@@ -103,12 +434,13 @@ impl SubprocessTransport {
         // Permission mode
         if let Some(ref mode) = self.options.permission_mode {
             cmd.push("--permission-mode".to_string());
-            cmd.push(match mode {
-                crate::types::PermissionMode::Default => "default",
-                crate::types::PermissionMode::AcceptEdits => "acceptEdits",
-                crate::types::PermissionMode::Plan => "plan",
-                crate::types::PermissionMode::BypassPermissions => "bypassPermissions",
-            }.to_string());
+            cmd.push(mode.as_str().to_string());
+        }
+
+        // Disallowed tools
+        if !self.options.disallowed_tools.is_empty() {
+            cmd.push("--disallowedTools".to_string());
+            cmd.push(self.options.disallowed_tools.join(","));
         }
 
         // Model
@@ -117,6 +449,79 @@ impl SubprocessTransport {
             cmd.push(model.clone());
         }
 
+        // Permission prompt tool
+        if let Some(ref tool) = self.options.permission_prompt_tool_name {
+            cmd.push("--permission-prompt-tool".to_string());
+            cmd.push(tool.clone());
+        }
+
+        // Session continuation
+        if self.options.continue_conversation {
+            cmd.push("--continue".to_string());
+        }
+
+        if let Some(ref session_id) = self.options.resume {
+            cmd.push("--resume".to_string());
+            cmd.push(session_id.to_string());
+        }
+
+        if self.options.fork_session {
+            cmd.push("--fork-session".to_string());
+        }
+
+        // Settings
+        if let Some(ref settings) = self.options.settings {
+            cmd.push("--settings".to_string());
+            cmd.push(settings.clone());
+        }
+
+        if let Some(ref sources) = self.options.setting_sources {
+            cmd.push("--setting-sources".to_string());
+            cmd.push(
+                sources
+                    .iter()
+                    .map(|source| match source {
+                        crate::types::SettingSource::User => "user",
+                        crate::types::SettingSource::Project => "project",
+                        crate::types::SettingSource::Local => "local",
+                    })
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+        }
+
+        // Additional directories
+        for dir in &self.options.add_dirs {
+            cmd.push("--add-dir".to_string());
+            cmd.push(dir.to_string_lossy().to_string());
+        }
+
+        // MCP servers
+        if !self.options.mcp_servers.is_empty() {
+            cmd.push("--mcp-config".to_string());
+            cmd.push(serde_json::json!({ "mcpServers": self.options.mcp_servers }).to_string());
+        }
+
+        // Custom agents
+        if let Some(ref agents) = self.options.agents {
+            cmd.push("--agents".to_string());
+            cmd.push(serde_json::json!(agents).to_string());
+        }
+
+        if self.options.include_partial_messages {
+            cmd.push("--include-partial-messages".to_string());
+        }
+
+        // Extra CLI flags, sorted so the command line is deterministic
+        let mut extra_args: Vec<_> = self.options.extra_args.iter().collect();
+        extra_args.sort_by(|a, b| a.0.cmp(b.0));
+        for (flag, value) in extra_args {
+            cmd.push(format!("--{}", flag));
+            if let Some(value) = value {
+                cmd.push(value.clone());
+            }
+        }
+
         // Add the prompt for one-shot mode
         // For interactive mode (empty prompt), don't add --print flag
         if !self.prompt.is_empty() {
@@ -132,32 +537,56 @@ impl SubprocessTransport {
 #[async_trait]
 impl Transport for SubprocessTransport {
     async fn connect(&mut self) -> Result<()> {
-        if self.process.is_some() {
+        let mut process = self.process.lock().await;
+        if process.is_some() {
             return Ok(());
         }
 
-        let args = self.build_command();
+        if let Some(required) = self.options.required_version {
+            check_version(self.cli_version().await?, required)?;
+        }
+
+        let mut spec = self.command_spec();
+        if let Some(ref wrapper) = self.wrapper {
+            spec = wrapper(spec);
+        }
 
-        let mut command = Command::new(&self.cli_path);
+        let mut command = Command::new(&spec.program);
         command
-            .args(&args)
+            .args(&spec.args)
+            .envs(spec.env.iter().map(|(k, v)| (k, v)))
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .env("CLAUDE_CODE_ENTRYPOINT", "sdk-rust")
-            .env("CLAUDE_AGENT_SDK_VERSION", env!("CARGO_PKG_VERSION"));
+            // Last resort if the transport is dropped without close()
+            .kill_on_drop(true);
 
-        if let Some(ref cwd) = self.options.cwd {
+        if let Some(ref cwd) = spec.cwd {
             command.current_dir(cwd);
         }
 
+        #[cfg(unix)]
+        if let Some(ref user) = self.options.user {
+            let (uid, gid) = resolve_user(user)?;
+            command.uid(uid);
+            if let Some(gid) = gid {
+                command.gid(gid);
+            }
+        }
+
         let mut child = command
             .spawn()
-            .map_err(|e| ClaudeSDKError::cli_connection_error(format!("Failed to spawn Claude Code: {}", e)))?;
+            .map_err(ClaudeSDKError::spawn_error_from)?;
 
-        // Take ownership of stdin for writing
+        // Take ownership of the pipes; the child itself stays shared so the
+        // reader can collect its exit status and close() can kill it
         self.stdin = child.stdin.take();
-        self.process = Some(child);
+        self.stdout = child.stdout.take();
+        if let Some(stderr) = child.stderr.take() {
+            self.stderr_task = Some(Self::spawn_stderr_reader(stderr, Arc::clone(&self.stderr)));
+        }
+        *process = Some(child);
+        drop(process);
         self.ready = true;
 
         Ok(())
@@ -171,41 +600,41 @@ impl Transport for SubprocessTransport {
             ));
         }
 
-        // Check if stdin is available
-        let stdin = self.stdin.as_mut().ok_or_else(|| {
-            ClaudeSDKError::cli_connection_error("Stdin not available for writing".to_string())
-        })?;
-
         // Check if process is still alive
-        if let Some(ref mut process) = self.process {
+        if let Some(ref mut process) = *self.process.lock().await {
             if let Ok(Some(exit_status)) = process.try_wait() {
                 return Err(ClaudeSDKError::process_error(
                     format!("Cannot write to terminated process (exit code: {:?})", exit_status.code()),
                     exit_status.code(),
-                    None,
+                    self.stderr_output(),
                 ));
             }
         }
 
-        // Write data to stdin
-        stdin
-            .write_all(data.as_bytes())
-            .await
-            .map_err(|e| {
-                self.ready = false;
-                ClaudeSDKError::cli_connection_error(format!(
-                    "Failed to write to process stdin: {}",
-                    e
-                ))
-            })?;
-
-        // Flush to ensure data is sent
-        stdin.flush().await.map_err(|e| {
-            self.ready = false;
-            ClaudeSDKError::cli_connection_error(format!("Failed to flush stdin: {}", e))
-        })?;
+        // Check if stdin is available
+        let Some(stdin) = self.stdin.as_mut() else {
+            return Err(ClaudeSDKError::cli_connection_error(
+                "Stdin not available for writing".to_string(),
+            ));
+        };
 
-        Ok(())
+        if let Some(ref interceptor) = self.interceptor {
+            interceptor(Direction::Outgoing, data.strip_suffix('\n').unwrap_or(data));
+        }
+
+        // Write data to stdin and flush to ensure it is sent
+        let result = match stdin.write_all(data.as_bytes()).await {
+            Ok(()) => stdin.flush().await.map_err(|e| ("Failed to flush stdin", e)),
+            Err(e) => Err(("Failed to write to process stdin", e)),
+        };
+
+        result.map_err(|(message, e)| {
+            self.ready = false;
+            ClaudeSDKError::Io {
+                message: self.with_stderr(format!("{}: {}", message, e)),
+                source: e,
+            }
+        })
     }
 
     async fn end_input(&mut self) -> Result<()> {
@@ -215,48 +644,98 @@ impl Transport for SubprocessTransport {
         Ok(())
     }
 
-    fn read_messages(&mut self) -> impl Stream<Item = Result<Value>> + Send {
-        let process = self.process.take();
-
-        async move {
-            let mut results = Vec::new();
-
-            if let Some(mut process) = process {
-                if let Some(stdout) = process.stdout.take() {
-                    let reader = BufReader::new(stdout);
-                    let mut lines = reader.lines();
-
-                    while let Ok(Some(line)) = lines.next_line().await {
-                        let line = line.trim();
-                        if line.is_empty() {
-                            continue;
-                        }
-
-                        match serde_json::from_str::<Value>(line) {
-                            Ok(value) => results.push(Ok(value)),
-                            Err(e) => {
-                                results.push(Err(ClaudeSDKError::json_decode_error(
-                                    line.to_string(),
-                                    e.to_string(),
-                                )));
-                            }
-                        }
+    fn read_messages(&mut self) -> TransportStream {
+        let stdout = self.stdout.take();
+        let stderr_task = self.stderr_task.take();
+        let stderr = Arc::clone(&self.stderr);
+        let process = Arc::clone(&self.process);
+        let exit_status = Arc::clone(&self.exit_status);
+        let max_buffer_size = self.options.max_buffer_size.unwrap_or(DEFAULT_MAX_BUFFER_SIZE);
+        let interceptor = self.interceptor.clone();
+
+        Box::pin(async_stream::stream! {
+            let Some(stdout) = stdout else {
+                return;
+            };
+
+            let mut reader = BufReader::new(stdout);
+            loop {
+                let line = match read_line_bounded(&mut reader, max_buffer_size).await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => break,
+                    Err(e @ ClaudeSDKError::BufferOverflow { .. }) => {
+                        yield Err(e);
+                        continue;
+                    }
+                    Err(e) => {
+                        yield Err(e);
+                        break;
+                    }
+                };
+
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+
+                if let Some(ref interceptor) = interceptor {
+                    interceptor(Direction::Incoming, line);
+                }
+
+                match serde_json::from_str::<Value>(line) {
+                    Ok(value) => yield Ok(value),
+                    Err(e) => {
+                        yield Err(ClaudeSDKError::json_decode_error_from(line, e));
                     }
                 }
             }
 
-            futures::stream::iter(results)
-        }
-        .flatten_stream()
+            // stdout closed: let stderr drain, then report a failed exit.
+            // If close() already took the child there is nothing to report.
+            if let Some(task) = stderr_task {
+                let _ = tokio::time::timeout(EXIT_WAIT_TIMEOUT, task).await;
+            }
+
+            let status = match process.lock().await.as_mut() {
+                Some(child) => tokio::time::timeout(EXIT_WAIT_TIMEOUT, child.wait())
+                    .await
+                    .ok()
+                    .and_then(|status| status.ok()),
+                None => None,
+            };
+
+            if let Some(status) = status {
+                *exit_status.lock().unwrap() = Some(status);
+
+                if !status.success() {
+                    let stderr = stderr.lock().unwrap().contents();
+                    let rate_limited = stderr.as_deref().and_then(ClaudeSDKError::rate_limited_from_output);
+                    yield Err(rate_limited.unwrap_or_else(|| {
+                        ClaudeSDKError::process_error("Claude Code process exited with an error", status.code(), stderr)
+                    }));
+                }
+            }
+        })
     }
 
     async fn close(&mut self) -> Result<()> {
         self.ready = false;
 
-        if let Some(mut process) = self.process.take() {
-            // Try to kill the process
-            let _ = process.kill().await;
-            let _ = process.wait().await;
+        // Closing stdin first lets the CLI finish writing its session files
+        if let Some(mut stdin) = self.stdin.take() {
+            let _ = stdin.shutdown().await;
+        }
+
+        if let Some(task) = self.stderr_task.take() {
+            task.abort();
+        }
+
+        let child = self.process.lock().await.take();
+        if let Some(mut child) = child {
+            let timeout = self.options.shutdown_timeout.unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
+            if let Some(status) = Self::terminate(&mut child, timeout).await {
+                *self.exit_status.lock().unwrap() = Some(status);
+            }
         }
 
         Ok(())
@@ -265,11 +744,17 @@ impl Transport for SubprocessTransport {
     fn is_ready(&self) -> bool {
         self.ready
     }
+
+    /// Set when the output stream ends or after `close()`
+    fn exit_status(&self) -> Option<ExitStatus> {
+        *self.exit_status.lock().unwrap()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     #[tokio::test]
     async fn test_transport_write_not_ready() {
@@ -303,4 +788,416 @@ mod tests {
         assert!(!transport.is_ready());
         assert_eq!(transport.prompt, "test prompt");
     }
+
+    #[test]
+    fn test_stderr_buffer_keeps_tail() {
+        let mut buffer = StderrBuffer::default();
+        assert!(buffer.contents().is_none());
+
+        let line = "x".repeat(MAX_STDERR_BUFFER_SIZE / 2);
+        buffer.push("first".to_string());
+        buffer.push(line.clone());
+        buffer.push(line.clone());
+
+        let contents = buffer.contents().unwrap();
+        assert!(!contents.starts_with("first"));
+        assert!(buffer.size <= MAX_STDERR_BUFFER_SIZE);
+    }
+
+    fn argv(prompt: &str, options: ClaudeAgentOptions) -> Vec<String> {
+        SubprocessTransport::new(prompt.to_string(), options).build_command()
+    }
+
+    #[test]
+    fn test_build_command_defaults() {
+        assert_eq!(
+            argv("", ClaudeAgentOptions::default()),
+            ["--output-format", "stream-json", "--verbose"]
+        );
+        assert_eq!(
+            argv("Hello", ClaudeAgentOptions::default()),
+            ["--output-format", "stream-json", "--verbose", "--print", "--", "Hello"]
+        );
+    }
+
+    #[test]
+    fn test_build_command_all_options() {
+        let mut mcp_servers = HashMap::new();
+        mcp_servers.insert(
+            "fs".to_string(),
+            crate::types::McpServerConfig::Stdio {
+                command: "mcp-fs".to_string(),
+                args: Some(vec!["/tmp".to_string()]),
+                env: None,
+            },
+        );
+
+        let mut agents = HashMap::new();
+        agents.insert(
+            "reviewer".to_string(),
+            crate::types::AgentDefinition {
+                description: "Reviews code".to_string(),
+                prompt: "You review code".to_string(),
+                tools: Some(vec!["Read".to_string()]),
+                model: None,
+            },
+        );
+
+        let mut extra_args = HashMap::new();
+        extra_args.insert("debug-to-stderr".to_string(), None);
+        extra_args.insert("betas".to_string(), Some("context-1m".to_string()));
+
+        let options = ClaudeAgentOptions {
+            allowed_tools: vec!["Read".to_string(), "Grep".to_string()],
+            disallowed_tools: vec!["Bash".to_string(), "Write".to_string()],
+            max_turns: Some(3),
+            permission_mode: Some(crate::types::PermissionMode::AcceptEdits),
+            model: Some("claude-sonnet-4-5".to_string()),
+            permission_prompt_tool_name: Some("mcp__auth__prompt".to_string()),
+            continue_conversation: true,
+            resume: Some("session-1".into()),
+            fork_session: true,
+            settings: Some("/etc/claude/settings.json".to_string()),
+            setting_sources: Some(vec![
+                crate::types::SettingSource::User,
+                crate::types::SettingSource::Project,
+            ]),
+            add_dirs: vec![PathBuf::from("/src/a"), PathBuf::from("/src/b")],
+            mcp_servers,
+            agents: Some(agents),
+            include_partial_messages: true,
+            extra_args,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            argv("Hi", options),
+            [
+                "--output-format",
+                "stream-json",
+                "--verbose",
+                "--allowedTools",
+                "Read,Grep",
+                "--max-turns",
+                "3",
+                "--permission-mode",
+                "acceptEdits",
+                "--disallowedTools",
+                "Bash,Write",
+                "--model",
+                "claude-sonnet-4-5",
+                "--permission-prompt-tool",
+                "mcp__auth__prompt",
+                "--continue",
+                "--resume",
+                "session-1",
+                "--fork-session",
+                "--settings",
+                "/etc/claude/settings.json",
+                "--setting-sources",
+                "user,project",
+                "--add-dir",
+                "/src/a",
+                "--add-dir",
+                "/src/b",
+                "--mcp-config",
+                r#"{"mcpServers":{"fs":{"args":["/tmp"],"command":"mcp-fs","type":"stdio"}}}"#,
+                "--agents",
+                r#"{"reviewer":{"description":"Reviews code","prompt":"You review code","tools":["Read"]}}"#,
+                "--include-partial-messages",
+                "--betas",
+                "context-1m",
+                "--debug-to-stderr",
+                "--print",
+                "--",
+                "Hi",
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_user() {
+        assert_eq!(resolve_user("1234").unwrap(), (1234, None));
+        assert_eq!(resolve_user("root").unwrap(), (0, Some(0)));
+        assert!(resolve_user("no-such-user-claude-rs").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_line_bounded_splits_lines() {
+        let mut reader: &[u8] = b"{\"a\":1}\r\n\n{\"b\":2}";
+
+        assert_eq!(read_line_bounded(&mut reader, 64).await.unwrap().as_deref(), Some("{\"a\":1}\r"));
+        assert_eq!(read_line_bounded(&mut reader, 64).await.unwrap().as_deref(), Some(""));
+        assert_eq!(read_line_bounded(&mut reader, 64).await.unwrap().as_deref(), Some("{\"b\":2}"));
+        assert!(read_line_bounded(&mut reader, 64).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_line_bounded_rejects_oversized_line() {
+        let input = format!("{}\nok\n{}", "x".repeat(100), "y".repeat(100));
+        // A small BufReader capacity forces the long line across several reads
+        let mut reader = BufReader::with_capacity(8, input.as_bytes());
+
+        let err = read_line_bounded(&mut reader, 16).await.unwrap_err();
+        assert!(matches!(err, ClaudeSDKError::BufferOverflow { limit: 16 }));
+
+        // The rest of the oversized line was skipped
+        assert_eq!(read_line_bounded(&mut reader, 16).await.unwrap().as_deref(), Some("ok"));
+
+        // An oversized final line without a newline still overflows
+        assert!(read_line_bounded(&mut reader, 16).await.is_err());
+        assert!(read_line_bounded(&mut reader, 16).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_line_bounded_accepts_line_at_limit() {
+        let mut reader: &[u8] = b"12345678\n";
+        let line = read_line_bounded(&mut reader, 8).await.unwrap();
+        assert_eq!(line.as_deref(), Some("12345678"));
+    }
+
+    /// Write an executable shell script that stands in for the CLI
+    #[cfg(unix)]
+    fn fake_cli(name: &str, script: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("claude-rs-{}-{}", name, std::process::id()));
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_exit_surfaces_stderr() {
+        use futures::StreamExt;
+
+        let mut transport = SubprocessTransport::new("test".to_string(), ClaudeAgentOptions::default());
+        transport.cli_path = fake_cli(
+            "stderr",
+            "echo '{\"type\":\"system\",\"subtype\":\"init\"}'\necho 'Invalid API key' >&2\nexit 3",
+        );
+        transport.connect().await.unwrap();
+
+        let mut stream = transport.read_messages();
+        assert!(stream.next().await.unwrap().is_ok());
+
+        match stream.next().await {
+            Some(Err(ClaudeSDKError::ProcessError { exit_code, stderr, .. })) => {
+                assert_eq!(exit_code, Some(3));
+                assert_eq!(stderr.as_deref(), Some("Invalid API key"));
+            }
+            other => panic!("expected process error, got {:?}", other),
+        }
+        assert!(stream.next().await.is_none());
+        assert_eq!(transport.stderr_output().as_deref(), Some("Invalid API key"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_connect_checks_required_version() {
+        let script = "if [ \"$1\" = --version ]; then echo '1.0.3 (Claude Code)'; exit 0; fi\necho started >&2";
+        let options = ClaudeAgentOptions {
+            required_version: Some(CliVersion::new(2, 0, 0)),
+            ..Default::default()
+        };
+        let mut transport = SubprocessTransport::new("test".to_string(), options);
+        transport.cli_path = fake_cli("version", script);
+
+        assert_eq!(transport.cli_version().await.unwrap(), CliVersion::new(1, 0, 3));
+        match transport.connect().await {
+            Err(ClaudeSDKError::CliVersionIncompatible { found, required }) => {
+                assert_eq!(found, CliVersion::new(1, 0, 3));
+                assert_eq!(required, CliVersion::new(2, 0, 0));
+            }
+            other => panic!("expected version error, got {:?}", other),
+        }
+        assert!(!transport.is_ready());
+
+        transport.options.required_version = Some(CliVersion::new(1, 0, 0));
+        transport.connect().await.unwrap();
+        assert!(transport.is_ready());
+        transport.close().await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_oversized_output_line_yields_buffer_overflow() {
+        use futures::StreamExt;
+
+        let options = ClaudeAgentOptions {
+            max_buffer_size: Some(64),
+            ..Default::default()
+        };
+        let mut transport = SubprocessTransport::new("test".to_string(), options);
+        transport.cli_path = fake_cli(
+            "overflow",
+            &format!("echo '{{\"text\":\"{}\"}}'\necho '{{\"type\":\"system\"}}'", "a".repeat(200)),
+        );
+        transport.connect().await.unwrap();
+
+        let mut stream = transport.read_messages();
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(ClaudeSDKError::BufferOverflow { limit: 64 }))
+        ));
+        assert_eq!(stream.next().await.unwrap().unwrap()["type"], "system");
+        assert!(stream.next().await.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_interceptor_sees_raw_lines() {
+        use futures::StreamExt;
+
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorder = Arc::clone(&seen);
+        let mut transport = SubprocessTransport::new("test".to_string(), ClaudeAgentOptions::default())
+            .with_interceptor(move |direction, line| {
+                recorder.lock().unwrap().push((direction, line.to_string()));
+            });
+        transport.cli_path = fake_cli("interceptor", r#"read line; echo "{\"echo\":$line}""#);
+        transport.connect().await.unwrap();
+
+        transport.write("{\"type\":\"user\"}\n").await.unwrap();
+        let mut stream = transport.read_messages();
+        assert_eq!(stream.next().await.unwrap().unwrap()["echo"]["type"], "user");
+        assert!(stream.next().await.is_none());
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                (Direction::Outgoing, r#"{"type":"user"}"#.to_string()),
+                (Direction::Incoming, r#"{"echo":{"type":"user"}}"#.to_string()),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dropping_query_terminates_cli() {
+        let marker = std::env::temp_dir().join(format!("claude-rs-dropped-{}", std::process::id()));
+        let ready = marker.with_extension("ready");
+        let _ = std::fs::remove_file(&marker);
+        let _ = std::fs::remove_file(&ready);
+
+        let wait_for = |path: std::path::PathBuf| {
+            tokio::time::timeout(Duration::from_secs(5), async move {
+                while !path.exists() {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+        };
+
+        let mut transport = SubprocessTransport::new("test".to_string(), ClaudeAgentOptions::default());
+        transport.cli_path = fake_cli(
+            "drop",
+            &format!(
+                "trap 'touch {}; exit 0' TERM\ntouch {}\nwhile :; do sleep 0.05; done",
+                marker.display(),
+                ready.display()
+            ),
+        );
+        transport.connect().await.unwrap();
+
+        // SIGTERM before the trap is installed would kill the CLI silently
+        wait_for(ready.clone()).await.expect("CLI did not start");
+
+        let mut query = crate::query::Query::new(Box::new(transport), false);
+        query.start().await.unwrap();
+        drop(query);
+
+        // The CLI gets SIGTERM from a cleanup task spawned by Drop
+        wait_for(marker.clone())
+            .await
+            .expect("CLI was not terminated after the query was dropped");
+        let _ = std::fs::remove_file(&marker);
+        let _ = std::fs::remove_file(&ready);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_env_is_passed_to_cli() {
+        use futures::StreamExt;
+
+        let mut env = HashMap::new();
+        env.insert("CLAUDE_RS_TEST_VALUE".to_string(), "from-options".to_string());
+        env.insert("CLAUDE_CODE_ENTRYPOINT".to_string(), "custom".to_string());
+        let options = ClaudeAgentOptions {
+            env,
+            ..Default::default()
+        };
+
+        let mut transport = SubprocessTransport::new("test".to_string(), options);
+        transport.cli_path = fake_cli(
+            "env",
+            r#"printf '{"value":"%s","entrypoint":"%s"}\n' "$CLAUDE_RS_TEST_VALUE" "$CLAUDE_CODE_ENTRYPOINT""#,
+        );
+        transport.connect().await.unwrap();
+
+        let mut stream = transport.read_messages();
+        let message = stream.next().await.unwrap().unwrap();
+        assert_eq!(message["value"], "from-options");
+        assert_eq!(message["entrypoint"], "custom");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_close_sends_sigterm_first() {
+        use futures::StreamExt;
+
+        let mut transport = SubprocessTransport::new("test".to_string(), ClaudeAgentOptions::default());
+        transport.cli_path = fake_cli(
+            "sigterm",
+            "trap 'exit 7' TERM\necho '{\"type\":\"ready\"}'\nwhile true; do sleep 0.05; done",
+        );
+        transport.connect().await.unwrap();
+
+        // Wait until the trap is installed before closing
+        let mut stream = transport.read_messages();
+        assert_eq!(stream.next().await.unwrap().unwrap()["type"], "ready");
+
+        transport.close().await.unwrap();
+        assert_eq!(transport.exit_status().and_then(|status| status.code()), Some(7));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_close_kills_after_shutdown_timeout() {
+        use futures::StreamExt;
+        use std::os::unix::process::ExitStatusExt;
+
+        let options = ClaudeAgentOptions {
+            shutdown_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let mut transport = SubprocessTransport::new("test".to_string(), options);
+        transport.cli_path = fake_cli(
+            "sigkill",
+            "trap '' TERM\necho '{\"type\":\"ready\"}'\nwhile true; do sleep 0.05; done",
+        );
+        transport.connect().await.unwrap();
+
+        let mut stream = transport.read_messages();
+        assert_eq!(stream.next().await.unwrap().unwrap()["type"], "ready");
+
+        transport.close().await.unwrap();
+        assert_eq!(transport.exit_status().and_then(|status| status.signal()), Some(libc::SIGKILL));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_successful_exit_ends_stream() {
+        use futures::StreamExt;
+
+        let mut transport = SubprocessTransport::new("test".to_string(), ClaudeAgentOptions::default());
+        transport.cli_path = fake_cli("success", "echo 'warning: noisy' >&2\nexit 0");
+        let mut transport: Box<dyn Transport> = Box::new(transport);
+        transport.connect().await.unwrap();
+
+        let mut stream = transport.read_messages();
+        assert!(stream.next().await.is_none());
+        assert!(transport.exit_status().unwrap().success());
+    }
 }
@@ -12,6 +12,154 @@ pub enum PermissionMode {
     BypassPermissions,
 }
 
+impl PermissionMode {
+    /// Name of the mode as used by the CLI (`acceptEdits`, ...)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PermissionMode::Default => "default",
+            PermissionMode::AcceptEdits => "acceptEdits",
+            PermissionMode::Plan => "plan",
+            PermissionMode::BypassPermissions => "bypassPermissions",
+        }
+    }
+}
+
+impl AsRef<str> for PermissionMode {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// Identifier of a CLI session
+///
+/// Wraps the string the CLI reports as `session_id`, so session IDs can't be
+/// mixed up with other strings. Use [`str::parse`] (or [`SessionId::new`]) to
+/// validate an ID from user input; conversions from `String` and `&str`, and
+/// IDs read from CLI output, are taken as is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SessionId(String);
+
+impl SessionId {
+    /// Longest ID accepted by [`SessionId::new`]
+    pub const MAX_LEN: usize = 256;
+
+    /// Validate `id`: it must be non-empty, at most [`MAX_LEN`](Self::MAX_LEN)
+    /// bytes, and free of whitespace and control characters
+    pub fn new(id: impl Into<String>) -> Result<Self, InvalidSessionId> {
+        let id = id.into();
+        let reason = if id.is_empty() {
+            "is empty"
+        } else if id.len() > Self::MAX_LEN {
+            "is too long"
+        } else if id.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            "contains whitespace or control characters"
+        } else {
+            return Ok(SessionId(id));
+        };
+        Err(InvalidSessionId { id, reason })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl std::fmt::Display for SessionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::str::FromStr for SessionId {
+    type Err = InvalidSessionId;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SessionId::new(s)
+    }
+}
+
+impl std::ops::Deref for SessionId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for SessionId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::borrow::Borrow<str> for SessionId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SessionId {
+    fn from(id: String) -> Self {
+        SessionId(id)
+    }
+}
+
+impl From<&str> for SessionId {
+    fn from(id: &str) -> Self {
+        SessionId(id.to_string())
+    }
+}
+
+impl From<&SessionId> for SessionId {
+    fn from(id: &SessionId) -> Self {
+        id.clone()
+    }
+}
+
+impl From<SessionId> for String {
+    fn from(id: SessionId) -> Self {
+        id.0
+    }
+}
+
+impl PartialEq<str> for SessionId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for SessionId {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<String> for SessionId {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+/// Error returned when parsing an invalid [`SessionId`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSessionId {
+    id: String,
+    reason: &'static str,
+}
+
+impl std::fmt::Display for InvalidSessionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid session ID {:?}: {}", self.id, self.reason)
+    }
+}
+
+impl std::error::Error for InvalidSessionId {}
+
 // Agent definitions
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -23,12 +171,17 @@ pub enum SettingSource {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemPromptPreset {
+    #[serde(default = "preset_type")]
     pub r#type: String, // "preset"
     pub preset: String, // "claude_code"
     #[serde(skip_serializing_if = "Option::is_none")]
     pub append: Option<String>,
 }
 
+fn preset_type() -> String {
+    "preset".to_string()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentDefinition {
     pub description: String,
@@ -64,9 +217,21 @@ pub struct PermissionRuleValue {
     pub rule_content: Option<String>,
 }
 
+/// Kind of change a [`PermissionUpdate`] makes
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionUpdateType {
+    AddRules,
+    ReplaceRules,
+    RemoveRules,
+    SetMode,
+    AddDirectories,
+    RemoveDirectories,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PermissionUpdate {
-    pub r#type: String, // "addRules" | "replaceRules" | "removeRules" | "setMode" | "addDirectories" | "removeDirectories"
+    pub r#type: PermissionUpdateType,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rules: Option<Vec<PermissionRuleValue>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -80,9 +245,183 @@ pub struct PermissionUpdate {
 }
 
 // Tool permission types
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ToolPermissionContext {
     pub suggestions: Vec<PermissionUpdate>,
+    /// Session the request belongs to, once the CLI has reported one
+    pub session_id: Option<SessionId>,
+    /// Hints declared by the tool, when it belongs to an in-process SDK MCP server
+    pub tool_annotations: Option<crate::mcp_server::ToolAnnotations>,
+}
+
+/// Typed view of the input of a built-in tool
+///
+/// Built from the untyped input passed to permission callbacks and hooks with
+/// [`ToolInput::parse`], or from a `tool_use` block with [`ToolUseView::parse`].
+/// Tools without a typed variant, and inputs missing a required field, become
+/// [`ToolInput::Other`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolInput {
+    Bash {
+        command: String,
+        /// Timeout in milliseconds
+        timeout: Option<u64>,
+    },
+    Edit {
+        file_path: String,
+        old_string: String,
+        new_string: String,
+    },
+    Read {
+        file_path: String,
+    },
+    Write {
+        file_path: String,
+        content: String,
+    },
+    Glob {
+        pattern: String,
+        path: Option<String>,
+    },
+    Grep {
+        pattern: String,
+        path: Option<String>,
+        /// File filter, e.g. `*.rs`
+        glob: Option<String>,
+    },
+    WebFetch {
+        url: String,
+    },
+    /// A subagent launch
+    Task {
+        description: String,
+        prompt: String,
+        subagent_type: String,
+    },
+    TodoWrite {
+        todos: Vec<TodoItem>,
+    },
+    Other {
+        tool_name: String,
+        input: HashMap<String, serde_json::Value>,
+    },
+}
+
+impl ToolInput {
+    /// Interpret `input` as the input of `tool_name`
+    pub fn parse(tool_name: &str, input: &HashMap<String, serde_json::Value>) -> Self {
+        let string = |key: &str| input.get(key).and_then(|v| v.as_str()).map(str::to_string);
+
+        let typed = match tool_name {
+            "Bash" => string("command").map(|command| ToolInput::Bash {
+                command,
+                timeout: input.get("timeout").and_then(|v| v.as_u64()),
+            }),
+            "Edit" => match (string("file_path"), string("old_string"), string("new_string")) {
+                (Some(file_path), Some(old_string), Some(new_string)) => Some(ToolInput::Edit {
+                    file_path,
+                    old_string,
+                    new_string,
+                }),
+                _ => None,
+            },
+            "Read" => string("file_path").map(|file_path| ToolInput::Read { file_path }),
+            "Write" => match (string("file_path"), string("content")) {
+                (Some(file_path), Some(content)) => Some(ToolInput::Write { file_path, content }),
+                _ => None,
+            },
+            "Glob" => string("pattern").map(|pattern| ToolInput::Glob {
+                pattern,
+                path: string("path"),
+            }),
+            "Grep" => string("pattern").map(|pattern| ToolInput::Grep {
+                pattern,
+                path: string("path"),
+                glob: string("glob"),
+            }),
+            "WebFetch" => string("url").map(|url| ToolInput::WebFetch { url }),
+            "Task" => match (string("description"), string("prompt"), string("subagent_type")) {
+                (Some(description), Some(prompt), Some(subagent_type)) => Some(ToolInput::Task {
+                    description,
+                    prompt,
+                    subagent_type,
+                }),
+                _ => None,
+            },
+            "TodoWrite" => input
+                .get("todos")
+                .and_then(|todos| Vec::<TodoItem>::deserialize(todos).ok())
+                .map(|todos| ToolInput::TodoWrite { todos }),
+            _ => None,
+        };
+
+        typed.unwrap_or_else(|| ToolInput::Other {
+            tool_name: tool_name.to_string(),
+            input: input.clone(),
+        })
+    }
+
+    /// Name of the tool this input belongs to
+    pub fn tool_name(&self) -> &str {
+        match self {
+            ToolInput::Bash { .. } => "Bash",
+            ToolInput::Edit { .. } => "Edit",
+            ToolInput::Read { .. } => "Read",
+            ToolInput::Write { .. } => "Write",
+            ToolInput::Glob { .. } => "Glob",
+            ToolInput::Grep { .. } => "Grep",
+            ToolInput::WebFetch { .. } => "WebFetch",
+            ToolInput::Task { .. } => "Task",
+            ToolInput::TodoWrite { .. } => "TodoWrite",
+            ToolInput::Other { tool_name, .. } => tool_name,
+        }
+    }
+
+    /// The file the tool operates on, for `Edit`, `Read` and `Write`
+    pub fn file_path(&self) -> Option<&str> {
+        match self {
+            ToolInput::Edit { file_path, .. } | ToolInput::Read { file_path } | ToolInput::Write { file_path, .. } => {
+                Some(file_path)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Entry of a `TodoWrite` tool call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TodoItem {
+    pub content: String,
+    /// `pending`, `in_progress` or `completed`
+    pub status: String,
+    #[serde(default, rename = "activeForm", skip_serializing_if = "Option::is_none")]
+    pub active_form: Option<String>,
+}
+
+/// Typed view of a `tool_use` content block
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolUseView {
+    /// ID of the tool use, matched by the `tool_use_id` of its result
+    pub id: String,
+    pub input: ToolInput,
+}
+
+impl ToolUseView {
+    /// Interpret a `tool_use` block; returns `None` for other block types
+    pub fn parse(block: &ContentBlock) -> Option<Self> {
+        match block {
+            ContentBlock::ToolUse { id, name, input } => Some(ToolUseView {
+                id: id.clone(),
+                input: ToolInput::parse(name, input),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Name of the tool being called
+    pub fn tool_name(&self) -> &str {
+        self.input.tool_name()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -115,6 +454,204 @@ pub enum HookEvent {
     PreCompact,
 }
 
+impl HookEvent {
+    /// The event name used by the CLI
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::PreToolUse => "PreToolUse",
+            HookEvent::PostToolUse => "PostToolUse",
+            HookEvent::UserPromptSubmit => "UserPromptSubmit",
+            HookEvent::Stop => "Stop",
+            HookEvent::SubagentStop => "SubagentStop",
+            HookEvent::PreCompact => "PreCompact",
+        }
+    }
+}
+
+impl HookEvent {
+    /// Look up an event by the CLI's name for it
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "PreToolUse" => Some(HookEvent::PreToolUse),
+            "PostToolUse" => Some(HookEvent::PostToolUse),
+            "UserPromptSubmit" => Some(HookEvent::UserPromptSubmit),
+            "Stop" => Some(HookEvent::Stop),
+            "SubagentStop" => Some(HookEvent::SubagentStop),
+            "PreCompact" => Some(HookEvent::PreCompact),
+            _ => None,
+        }
+    }
+
+    /// Whether matchers apply to this event; the others run for every occurrence
+    pub fn is_tool_event(&self) -> bool {
+        matches!(self, HookEvent::PreToolUse | HookEvent::PostToolUse)
+    }
+}
+
+impl From<HookEvent> for String {
+    fn from(event: HookEvent) -> Self {
+        event.as_str().to_string()
+    }
+}
+
+/// A [`HookEvent`] or the CLI's name for one
+///
+/// Accepted by the [`HookManager`](crate::HookManager) methods that used to
+/// take event names. Names the CLI does not use convert to `None` and match
+/// no hooks.
+pub trait IntoHookEvent {
+    fn into_hook_event(self) -> Option<HookEvent>;
+}
+
+impl IntoHookEvent for HookEvent {
+    fn into_hook_event(self) -> Option<HookEvent> {
+        Some(self)
+    }
+}
+
+impl IntoHookEvent for &str {
+    fn into_hook_event(self) -> Option<HookEvent> {
+        HookEvent::from_name(self)
+    }
+}
+
+impl IntoHookEvent for String {
+    fn into_hook_event(self) -> Option<HookEvent> {
+        HookEvent::from_name(&self)
+    }
+}
+
+impl IntoHookEvent for &String {
+    fn into_hook_event(self) -> Option<HookEvent> {
+        HookEvent::from_name(self)
+    }
+}
+
+/// Fields the CLI sends with every hook event
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BaseHookInput {
+    #[serde(default)]
+    pub session_id: String,
+    #[serde(default)]
+    pub transcript_path: String,
+    #[serde(default)]
+    pub cwd: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permission_mode: Option<String>,
+}
+
+/// Payload of a PreToolUse hook, sent before a tool runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreToolUseHookInput {
+    #[serde(flatten)]
+    pub base: BaseHookInput,
+    pub tool_name: String,
+    #[serde(default)]
+    pub tool_input: serde_json::Value,
+}
+
+impl PreToolUseHookInput {
+    /// The tool input as a [`ToolInput`]
+    pub fn typed_input(&self) -> ToolInput {
+        let input = serde_json::from_value(self.tool_input.clone()).unwrap_or_default();
+        ToolInput::parse(&self.tool_name, &input)
+    }
+}
+
+/// Payload of a PostToolUse hook, sent after a tool has run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostToolUseHookInput {
+    #[serde(flatten)]
+    pub base: BaseHookInput,
+    pub tool_name: String,
+    #[serde(default)]
+    pub tool_input: serde_json::Value,
+    #[serde(default)]
+    pub tool_response: serde_json::Value,
+}
+
+/// Payload of a UserPromptSubmit hook, sent before a prompt reaches Claude
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserPromptSubmitHookInput {
+    #[serde(flatten)]
+    pub base: BaseHookInput,
+    pub prompt: String,
+}
+
+/// Payload of a Stop or SubagentStop hook, sent when Claude finishes responding
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StopHookInput {
+    #[serde(flatten)]
+    pub base: BaseHookInput,
+    /// Whether Claude is already continuing because of a Stop hook
+    #[serde(default)]
+    pub stop_hook_active: bool,
+}
+
+/// Payload of a PreCompact hook, sent before the conversation is compacted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreCompactHookInput {
+    #[serde(flatten)]
+    pub base: BaseHookInput,
+    /// `manual` (from `/compact`) or `auto`
+    pub trigger: String,
+    #[serde(default)]
+    pub custom_instructions: Option<String>,
+}
+
+/// Typed hook payload, selected by the `hook_event_name` field
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "hook_event_name")]
+pub enum HookInput {
+    PreToolUse(PreToolUseHookInput),
+    PostToolUse(PostToolUseHookInput),
+    UserPromptSubmit(UserPromptSubmitHookInput),
+    Stop(StopHookInput),
+    SubagentStop(StopHookInput),
+    PreCompact(PreCompactHookInput),
+}
+
+impl HookInput {
+    /// Parse the raw input passed to a [`HookCallback`](crate::HookCallback)
+    pub fn parse(input: &HashMap<String, serde_json::Value>) -> crate::errors::Result<Self> {
+        let value = serde_json::to_value(input).unwrap_or_default();
+        serde_json::from_value(value.clone())
+            .map_err(|e| crate::errors::ClaudeSDKError::message_parse_error_from(format!("Invalid hook input: {}", e), Some(value), e))
+    }
+
+    /// The event this payload belongs to
+    pub fn event(&self) -> HookEvent {
+        match self {
+            HookInput::PreToolUse(_) => HookEvent::PreToolUse,
+            HookInput::PostToolUse(_) => HookEvent::PostToolUse,
+            HookInput::UserPromptSubmit(_) => HookEvent::UserPromptSubmit,
+            HookInput::Stop(_) => HookEvent::Stop,
+            HookInput::SubagentStop(_) => HookEvent::SubagentStop,
+            HookInput::PreCompact(_) => HookEvent::PreCompact,
+        }
+    }
+
+    /// Fields shared by every event
+    pub fn base(&self) -> &BaseHookInput {
+        match self {
+            HookInput::PreToolUse(input) => &input.base,
+            HookInput::PostToolUse(input) => &input.base,
+            HookInput::UserPromptSubmit(input) => &input.base,
+            HookInput::Stop(input) | HookInput::SubagentStop(input) => &input.base,
+            HookInput::PreCompact(input) => &input.base,
+        }
+    }
+
+    /// The tool name for tool events
+    pub fn tool_name(&self) -> Option<&str> {
+        match self {
+            HookInput::PreToolUse(input) => Some(&input.tool_name),
+            HookInput::PostToolUse(input) => Some(&input.tool_name),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct HookJSONOutput {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -123,11 +660,16 @@ pub struct HookJSONOutput {
     pub system_message: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hook_specific_output: Option<serde_json::Value>,
+    /// Replacement tool input for a PreToolUse hook
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_input: Option<HashMap<String, serde_json::Value>>,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct HookContext {
     pub signal: Option<String>, // Future: abort signal support
+    /// State shared by every hook of the same [`HookManager`](crate::HookManager)
+    pub extensions: crate::hooks::Extensions,
 }
 
 #[derive(Debug, Clone)]
@@ -159,10 +701,120 @@ pub enum McpServerConfig {
         #[serde(skip_serializing_if = "Option::is_none")]
         headers: Option<HashMap<String, String>>,
     },
+    /// Server using the MCP streamable HTTP transport
+    ///
+    /// The CLI calls this transport `http`, so it is sent with that type and
+    /// reads back as [`Http`](Self::Http). Configs may spell it
+    /// `streamable-http` or `streamableHttp`.
+    #[serde(rename(serialize = "http", deserialize = "streamable-http"), alias = "streamableHttp")]
+    StreamableHttp {
+        url: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        headers: Option<HashMap<String, String>>,
+    },
+    /// In-process server whose tool calls are answered by the SDK
+    ///
+    /// Only the server's name is sent to the CLI; clients route its tool
+    /// calls to the server. Cannot be deserialized.
+    #[serde(rename = "sdk", serialize_with = "serialize_sdk_server", skip_deserializing)]
+    Sdk(crate::mcp_server::SdkMcpServer),
+}
+
+impl From<crate::mcp_server::SdkMcpServer> for McpServerConfig {
+    fn from(server: crate::mcp_server::SdkMcpServer) -> Self {
+        McpServerConfig::Sdk(server)
+    }
+}
+
+fn serialize_sdk_server<S: serde::Serializer>(
+    server: &crate::mcp_server::SdkMcpServer,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeStruct;
+
+    let mut state = serializer.serialize_struct("Sdk", 1)?;
+    state.serialize_field("name", &server.name)?;
+    state.end()
+}
+
+impl McpServerConfig {
+    /// A streamable HTTP server at `url`
+    pub fn streamable_http(url: impl Into<String>) -> Self {
+        McpServerConfig::StreamableHttp {
+            url: url.into(),
+            headers: None,
+        }
+    }
+
+    /// Send `name: value` with every request to an SSE or HTTP server
+    ///
+    /// Has no effect on stdio and SDK servers.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        if let McpServerConfig::SSE { ref mut headers, .. }
+        | McpServerConfig::Http { ref mut headers, .. }
+        | McpServerConfig::StreamableHttp { ref mut headers, .. } = self
+        {
+            headers.get_or_insert_with(HashMap::new).insert(name.into(), value.into());
+        }
+        self
+    }
+
+    /// Load the servers of a `.mcp.json` or `claude_desktop_config.json` file
+    ///
+    /// Both formats list servers by name under `mcpServers`. Entries without
+    /// a `type` are stdio servers, as in Claude Desktop's config.
+    pub fn load_file(path: impl AsRef<std::path::Path>) -> crate::errors::Result<HashMap<String, McpServerConfig>> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| crate::errors::ClaudeSDKError::invalid_mcp_config(&name, None, e.to_string()))?;
+        Self::parse_file(&name, &contents)
+    }
+
+    fn parse_file(name: &str, contents: &str) -> crate::errors::Result<HashMap<String, McpServerConfig>> {
+        use crate::errors::ClaudeSDKError;
+
+        let document: serde_json::Value = serde_json::from_str(contents)
+            .map_err(|e| ClaudeSDKError::invalid_mcp_config(name, None, e.to_string()))?;
+        let Some(servers) = document.get("mcpServers") else {
+            return Err(ClaudeSDKError::invalid_mcp_config(name, None, "missing \"mcpServers\" object"));
+        };
+        let servers = servers
+            .as_object()
+            .ok_or_else(|| ClaudeSDKError::invalid_mcp_config(name, None, "\"mcpServers\" must be an object"))?;
+
+        servers
+            .iter()
+            .map(|(server, entry)| {
+                let invalid = |message: String| ClaudeSDKError::invalid_mcp_config(name, Some(server.clone()), message);
+                let mut entry = entry
+                    .as_object()
+                    .cloned()
+                    .ok_or_else(|| invalid("expected an object".to_string()))?;
+                match entry.get("type").and_then(|t| t.as_str()) {
+                    None if entry.contains_key("type") => return Err(invalid("\"type\" must be a string".to_string())),
+                    None => {
+                        entry.insert("type".to_string(), serde_json::json!("stdio"));
+                    }
+                    Some("sdk") => return Err(invalid("in-process \"sdk\" servers cannot be loaded from a file".to_string())),
+                    Some("stdio" | "sse" | "http" | "streamable-http" | "streamableHttp") => {}
+                    Some(other) => {
+                        return Err(invalid(format!(
+                            "unknown type \"{}\", expected \"stdio\", \"sse\", \"http\" or \"streamable-http\"",
+                            other
+                        )))
+                    }
+                }
+
+                let config = serde_json::from_value(serde_json::Value::Object(entry)).map_err(|e| invalid(e.to_string()))?;
+                Ok((server.clone(), config))
+            })
+            .collect()
+    }
 }
 
 // Content block types
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type")]
 pub enum ContentBlock {
     #[serde(rename = "text")]
@@ -183,6 +835,114 @@ pub enum ContentBlock {
         #[serde(skip_serializing_if = "Option::is_none")]
         is_error: Option<bool>,
     },
+    #[serde(rename = "image")]
+    Image { source: ContentSource },
+    #[serde(rename = "document")]
+    Document {
+        source: ContentSource,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    /// Block of a type this SDK does not know, kept as sent by the CLI.
+    /// Only produced with `ParseMode::Lenient`.
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+impl ContentBlock {
+    /// Content of a `ToolResult` block as content blocks
+    ///
+    /// Tool results carry either a string, returned as one text block, or a
+    /// list of blocks such as text and images. Returns `None` for other block
+    /// types and for results without usable content.
+    pub fn tool_result_blocks(&self) -> Option<Vec<ContentBlock>> {
+        let ContentBlock::ToolResult { content: Some(content), .. } = self else {
+            return None;
+        };
+        match content {
+            serde_json::Value::String(text) => Some(vec![ContentBlock::Text { text: text.clone() }]),
+            serde_json::Value::Array(_) => Vec::<ContentBlock>::deserialize(content).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Derived parser for the known block types, so their errors keep serde's detail
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum KnownContentBlock {
+    Text { text: String },
+    Thinking { thinking: String, signature: String },
+    ToolUse {
+        id: String,
+        name: String,
+        input: HashMap<String, serde_json::Value>,
+    },
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default)]
+        content: Option<serde_json::Value>,
+        #[serde(default)]
+        is_error: Option<bool>,
+    },
+    Image { source: ContentSource },
+    Document {
+        source: ContentSource,
+        #[serde(default)]
+        title: Option<String>,
+    },
+}
+
+impl KnownContentBlock {
+    const TYPES: &'static [&'static str] = &["text", "thinking", "tool_use", "tool_result", "image", "document"];
+}
+
+impl From<KnownContentBlock> for ContentBlock {
+    fn from(block: KnownContentBlock) -> Self {
+        match block {
+            KnownContentBlock::Text { text } => ContentBlock::Text { text },
+            KnownContentBlock::Thinking { thinking, signature } => ContentBlock::Thinking { thinking, signature },
+            KnownContentBlock::ToolUse { id, name, input } => ContentBlock::ToolUse { id, name, input },
+            KnownContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            } => ContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            },
+            KnownContentBlock::Image { source } => ContentBlock::Image { source },
+            KnownContentBlock::Document { source, title } => ContentBlock::Document { source, title },
+        }
+    }
+}
+
+/// Blocks with an unrecognized `type` deserialize to `ContentBlock::Unknown`;
+/// `parse_message` rejects them unless the parse mode is lenient
+impl<'de> Deserialize<'de> for ContentBlock {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        match value.get("type").and_then(|t| t.as_str()) {
+            Some(block_type) if !KnownContentBlock::TYPES.contains(&block_type) => Ok(ContentBlock::Unknown(value)),
+            _ => KnownContentBlock::deserialize(value)
+                .map(ContentBlock::from)
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// Data of an image or document block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ContentSource {
+    #[serde(rename = "base64")]
+    Base64 { media_type: String, data: String },
+    #[serde(rename = "url")]
+    Url { url: String },
+    /// Plain-text document content
+    #[serde(rename = "text")]
+    Text { media_type: String, data: String },
 }
 
 // Message types
@@ -191,6 +951,12 @@ pub struct UserMessage {
     pub content: UserMessageContent,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_tool_use_id: Option<String>,
+    /// ID assigned by the CLI, used as the target of `rewind_files`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    /// When the CLI recorded the message (RFC 3339)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -206,6 +972,34 @@ pub struct AssistantMessage {
     pub model: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_tool_use_id: Option<String>,
+    /// ID assigned by the CLI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    /// When the CLI recorded the message (RFC 3339)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+}
+
+impl AssistantMessage {
+    /// Rough number of tokens the model generated for this message
+    ///
+    /// Estimated locally with [`tokens::estimate`](crate::tokens::estimate)
+    /// from text, thinking and tool inputs; exact counts are only reported in
+    /// the result message.
+    pub fn approx_output_tokens(&self) -> usize {
+        self.content
+            .iter()
+            .map(|block| match block {
+                ContentBlock::Text { text } => crate::tokens::estimate(text),
+                ContentBlock::Thinking { thinking, .. } => crate::tokens::estimate(thinking),
+                ContentBlock::ToolUse { name, input, .. } => {
+                    let input = serde_json::to_string(input).unwrap_or_default();
+                    crate::tokens::estimate(name) + crate::tokens::estimate(&input)
+                }
+                _ => 0,
+            })
+            .sum()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -214,6 +1008,65 @@ pub struct SystemMessage {
     pub data: HashMap<String, serde_json::Value>,
 }
 
+impl SystemMessage {
+    /// Typed view of the message, chosen by `subtype`
+    ///
+    /// Unrecognized subtypes, and malformed messages, come back as
+    /// `SystemMessageKind::Unknown`; the raw data stays available in `data`.
+    pub fn kind(&self) -> SystemMessageKind {
+        serde_json::to_value(&self.data)
+            .and_then(serde_json::from_value)
+            .unwrap_or(SystemMessageKind::Unknown)
+    }
+}
+
+/// Subtype of a system message
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "subtype", rename_all = "snake_case")]
+pub enum SystemMessageKind {
+    /// Session metadata sent when the CLI starts
+    Init(InitInfo),
+    /// The conversation was compacted; earlier messages are summarized
+    CompactBoundary { compact_metadata: CompactMetadata },
+    /// Progress of a long-running CLI operation (e.g. `compacting`)
+    Status {
+        #[serde(default)]
+        status: Option<String>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// Contents of the `system/init` message
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct InitInfo {
+    pub session_id: SessionId,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    /// Names of the tools available in the session
+    #[serde(default)]
+    pub tools: Vec<String>,
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServerStatus>,
+    /// Permission mode name as reported by the CLI (`default`, `acceptEdits`, ...)
+    #[serde(default, rename = "permissionMode")]
+    pub permission_mode: Option<String>,
+    #[serde(default)]
+    pub slash_commands: Vec<String>,
+}
+
+/// Details of a `compact_boundary` system message
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CompactMetadata {
+    /// `manual` or `auto`
+    pub trigger: String,
+    /// Token count of the conversation before compaction
+    #[serde(default)]
+    pub pre_tokens: Option<u64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResultMessage {
     pub subtype: String,
@@ -221,58 +1074,459 @@ pub struct ResultMessage {
     pub duration_api_ms: i64,
     pub is_error: bool,
     pub num_turns: i32,
-    pub session_id: String,
+    pub session_id: SessionId,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total_cost_usd: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub usage: Option<HashMap<String, serde_json::Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub result: Option<String>,
+    /// Tokens and cost per model, keyed by model name
+    #[serde(default, rename = "modelUsage", skip_serializing_if = "Option::is_none")]
+    pub model_usage: Option<HashMap<String, serde_json::Value>>,
+    /// ID assigned by the CLI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    /// When the CLI recorded the message (RFC 3339)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+}
+
+impl ResultMessage {
+    /// Outcome of the query, parsed from `subtype`
+    pub fn kind(&self) -> ResultSubtype {
+        ResultSubtype::from(self.subtype.as_str())
+    }
+
+    /// Whether the query completed normally
+    pub fn is_success(&self) -> bool {
+        self.kind() == ResultSubtype::Success
+    }
+
+    /// Whether the query stopped because it reached `max_turns`
+    pub fn is_max_turns(&self) -> bool {
+        self.kind() == ResultSubtype::ErrorMaxTurns
+    }
+
+    /// Whether the query stopped because of an error while running
+    pub fn is_execution_error(&self) -> bool {
+        self.kind() == ResultSubtype::ErrorDuringExecution
+    }
+
+    /// The error to report if the query failed because the API was rate
+    /// limiting or overloaded
+    pub fn rate_limit_error(&self) -> Option<crate::errors::ClaudeSDKError> {
+        if !self.is_error {
+            return None;
+        }
+        self.result.as_deref().and_then(crate::errors::ClaudeSDKError::rate_limited_from_output)
+    }
+
+    /// Exact token counts of the query, if the CLI reported them
+    pub fn token_usage(&self) -> Option<crate::tokens::Usage> {
+        self.usage.as_ref().map(crate::tokens::Usage::from_map)
+    }
+}
+
+/// Outcome reported by a result message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResultSubtype {
+    Success,
+    /// The turn limit set with `max_turns` was reached
+    ErrorMaxTurns,
+    ErrorDuringExecution,
+    /// The spending limit for the query was reached
+    ErrorMaxBudgetUsd,
+    /// A subtype this SDK does not know
+    Other(String),
+}
+
+impl ResultSubtype {
+    /// Name of the subtype as used by the CLI (`success`, `error_max_turns`, ...)
+    pub fn as_str(&self) -> &str {
+        match self {
+            ResultSubtype::Success => "success",
+            ResultSubtype::ErrorMaxTurns => "error_max_turns",
+            ResultSubtype::ErrorDuringExecution => "error_during_execution",
+            ResultSubtype::ErrorMaxBudgetUsd => "error_max_budget_usd",
+            ResultSubtype::Other(subtype) => subtype,
+        }
+    }
+
+    /// Whether this is any of the error subtypes
+    pub fn is_error(&self) -> bool {
+        match self {
+            ResultSubtype::Success => false,
+            ResultSubtype::Other(subtype) => subtype.starts_with("error"),
+            _ => true,
+        }
+    }
+}
+
+impl From<&str> for ResultSubtype {
+    fn from(subtype: &str) -> Self {
+        match subtype {
+            "success" => ResultSubtype::Success,
+            "error_max_turns" => ResultSubtype::ErrorMaxTurns,
+            "error_during_execution" => ResultSubtype::ErrorDuringExecution,
+            "error_max_budget_usd" => ResultSubtype::ErrorMaxBudgetUsd,
+            other => ResultSubtype::Other(other.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for ResultSubtype {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamEvent {
     pub uuid: String,
-    pub session_id: String,
+    pub session_id: SessionId,
     pub event: HashMap<String, serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_tool_use_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(untagged)]
+impl StreamEvent {
+    /// Typed view of `event`
+    ///
+    /// Events of unrecognized types, and malformed events, come back as
+    /// `StreamEventKind::Unknown`; the raw data stays available in `event`.
+    pub fn kind(&self) -> StreamEventKind {
+        serde_json::to_value(&self.event)
+            .and_then(serde_json::from_value)
+            .unwrap_or(StreamEventKind::Unknown)
+    }
+}
+
+/// Type of a partial-message stream event, as sent by the Anthropic API
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEventKind {
+    /// A new assistant message begins
+    MessageStart { message: serde_json::Value },
+    /// A content block begins at `index`
+    ContentBlockStart { index: usize, content_block: ContentBlock },
+    /// Partial content for the block at `index`
+    ContentBlockDelta { index: usize, delta: ContentDelta },
+    /// The block at `index` is complete
+    ContentBlockStop { index: usize },
+    /// Top-level changes to the message, such as the stop reason
+    MessageDelta {
+        delta: MessageDeltaInfo,
+        #[serde(default)]
+        usage: Option<HashMap<String, serde_json::Value>>,
+    },
+    /// The assistant message is complete
+    MessageStop,
+    /// Any other event type (e.g. `ping`)
+    #[serde(other)]
+    Unknown,
+}
+
+/// Partial content of a `content_block_delta` event
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentDelta {
+    TextDelta { text: String },
+    ThinkingDelta { thinking: String },
+    /// Fragment of a tool use input's JSON
+    InputJsonDelta { partial_json: String },
+    SignatureDelta { signature: String },
+    #[serde(other)]
+    Unknown,
+}
+
+/// Payload of a `message_delta` event
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct MessageDeltaInfo {
+    #[serde(default)]
+    pub stop_reason: Option<String>,
+    #[serde(default)]
+    pub stop_sequence: Option<String>,
+}
+
+/// Serializes to and deserializes from the CLI's stream-json format
+#[derive(Debug, Clone)]
 pub enum Message {
     User(UserMessage),
     Assistant(AssistantMessage),
     System(SystemMessage),
     Result(ResultMessage),
     Stream(StreamEvent),
+    /// Message of a type this SDK does not know, kept as sent by the CLI.
+    /// Only produced with `ParseMode::Lenient`.
+    Unknown(serde_json::Value),
+}
+
+/// How strictly CLI output is parsed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParseMode {
+    /// Unknown message and content block types are errors
+    #[default]
+    Strict,
+    /// Unknown message and content block types are passed through as
+    /// `Message::Unknown` and `ContentBlock::Unknown`, so newer CLI releases
+    /// don't break the stream
+    Lenient,
+}
+
+// Outgoing messages
+
+/// A message the SDK writes to the CLI's stdin
+///
+/// [`to_wire`](Self::to_wire) produces the JSON the CLI expects, for custom
+/// transports and tests that speak the protocol directly.
+#[derive(Debug, Clone)]
+pub enum OutgoingMessage {
+    User(UserEnvelope),
+    ControlRequest(SDKControlRequest),
+    ControlResponse(SDKControlResponse),
+}
+
+impl OutgoingMessage {
+    pub fn to_wire(&self) -> serde_json::Value {
+        match self {
+            OutgoingMessage::User(envelope) => envelope.to_wire(),
+            OutgoingMessage::ControlRequest(request) => serde_json::to_value(request).unwrap_or_default(),
+            OutgoingMessage::ControlResponse(response) => serde_json::to_value(response).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<UserEnvelope> for OutgoingMessage {
+    fn from(envelope: UserEnvelope) -> Self {
+        OutgoingMessage::User(envelope)
+    }
+}
+
+impl From<SDKControlRequest> for OutgoingMessage {
+    fn from(request: SDKControlRequest) -> Self {
+        OutgoingMessage::ControlRequest(request)
+    }
+}
+
+impl From<SDKControlResponse> for OutgoingMessage {
+    fn from(response: SDKControlResponse) -> Self {
+        OutgoingMessage::ControlResponse(response)
+    }
+}
+
+/// A user turn in streaming mode
+///
+/// # Example
+/// ```
+/// use claude::types::UserEnvelope;
+///
+/// let wire = UserEnvelope::text("Hello").session_id("session-1").to_wire();
+/// assert_eq!(wire["type"], "user");
+/// assert_eq!(wire["message"]["content"], "Hello");
+/// assert_eq!(wire["session_id"], "session-1");
+/// ```
+#[derive(Debug, Clone)]
+pub struct UserEnvelope {
+    pub content: UserMessageContent,
+    /// Conversation the turn belongs to, `"default"` unless set
+    pub session_id: SessionId,
+    pub parent_tool_use_id: Option<String>,
+}
+
+impl UserEnvelope {
+    pub fn text(prompt: impl Into<String>) -> Self {
+        Self::new(UserMessageContent::Text(prompt.into()))
+    }
+
+    /// A turn made of content blocks, e.g. text plus images
+    pub fn blocks(blocks: Vec<ContentBlock>) -> Self {
+        Self::new(UserMessageContent::Blocks(blocks))
+    }
+
+    fn new(content: UserMessageContent) -> Self {
+        UserEnvelope {
+            content,
+            session_id: SessionId::from("default"),
+            parent_tool_use_id: None,
+        }
+    }
+
+    pub fn session_id(mut self, session_id: impl Into<SessionId>) -> Self {
+        self.session_id = session_id.into();
+        self
+    }
+
+    pub fn parent_tool_use_id(mut self, parent_tool_use_id: impl Into<String>) -> Self {
+        self.parent_tool_use_id = Some(parent_tool_use_id.into());
+        self
+    }
+
+    pub fn to_wire(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "user",
+            "message": {
+                "role": "user",
+                "content": self.content
+            },
+            "parent_tool_use_id": self.parent_tool_use_id,
+            "session_id": self.session_id
+        })
+    }
 }
 
 // Agent options
-#[derive(Debug, Clone, Default)]
+///
+/// Options can be loaded from configuration files with serde, e.g.
+/// `serde_json::from_str::<ClaudeAgentOptions>(...)`. Field names match the
+/// Rust fields, every field is optional, and `shutdown_timeout` and
+/// `query_timeout` are given in seconds. In-process SDK MCP servers can't be expressed in a file and are
+/// left out when serializing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ClaudeAgentOptions {
     pub allowed_tools: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub system_prompt: Option<SystemPromptConfig>,
+    #[serde(serialize_with = "serialize_file_mcp_servers")]
     pub mcp_servers: HashMap<String, McpServerConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub permission_mode: Option<PermissionMode>,
     pub continue_conversation: bool,
-    pub resume: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resume: Option<SessionId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_turns: Option<i32>,
     pub disallowed_tools: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub permission_prompt_tool_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub cwd: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub settings: Option<String>,
     pub add_dirs: Vec<PathBuf>,
     pub env: HashMap<String, String>,
     pub extra_args: HashMap<String, Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_buffer_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
     pub include_partial_messages: bool,
     pub fork_session: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub agents: Option<HashMap<String, AgentDefinition>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub setting_sources: Option<Vec<SettingSource>>,
+    /// How long `close()` waits for the CLI to exit after SIGTERM before killing it
+    #[serde(with = "optional_duration_secs", skip_serializing_if = "Option::is_none")]
+    pub shutdown_timeout: Option<std::time::Duration>,
+    /// Deadline for a whole one-shot query (`simple_query`,
+    /// `simple_query_with`), after which the CLI is stopped and the query
+    /// fails with `ClaudeSDKError::Timeout`
+    #[serde(with = "optional_duration_secs", skip_serializing_if = "Option::is_none")]
+    pub query_timeout: Option<std::time::Duration>,
+    /// Spending limit in USD, checked by the SDK against the cost reported
+    /// in result messages. Crossing it interrupts the session and yields
+    /// `ClaudeSDKError::BudgetExceeded`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_cost_usd: Option<f64>,
+    /// Limit for input plus output tokens, enforced like `max_cost_usd`.
+    /// With `include_partial_messages` it can trip in the middle of a turn.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total_tokens: Option<u64>,
+    /// Number of parsed messages buffered for the consumer before reading from
+    /// the CLI pauses (default 100)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_channel_capacity: Option<usize>,
+    /// How unknown message and content block types are handled
+    pub parse_mode: ParseMode,
+    /// Oldest CLI version to accept, e.g. `"2.0.0"`. Connecting to an older
+    /// CLI fails with `ClaudeSDKError::CliVersionIncompatible`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_version: Option<crate::version::CliVersion>,
+    /// Where query, token, cost, tool call and error metrics are reported
+    #[serde(skip)]
+    pub metrics: Option<crate::metrics::Metrics>,
+    /// Running totals that the cost and tokens of each query are added to
+    #[serde(skip)]
+    pub cost_tracker: Option<crate::cost::CostTracker>,
+}
+
+/// Serialize the MCP servers that can be written to a config file
+fn serialize_file_mcp_servers<S: serde::Serializer>(
+    servers: &HashMap<String, McpServerConfig>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(servers.iter().filter(|(_, config)| !matches!(config, McpServerConfig::Sdk(_))))
+}
+
+/// `Option<Duration>` as a number of seconds
+mod optional_duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_f64(duration.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        Option::<f64>::deserialize(deserializer)?
+            .map(|secs| Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+/// How a supervised client restarts the CLI after it exits unexpectedly
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// Maximum number of restarts over the client's lifetime
+    pub max_restarts: u32,
+    /// Delay before each restart attempt
+    pub delay: std::time::Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 3,
+            delay: std::time::Duration::from_secs(1),
+        }
+    }
+}
+
+/// Settings for the background connection heartbeat
+#[derive(Debug, Clone)]
+pub struct HeartbeatConfig {
+    /// Time between pings
+    pub interval: std::time::Duration,
+    /// How long to wait for a ping response before the connection counts as degraded
+    pub timeout: std::time::Duration,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: std::time::Duration::from_secs(30),
+            timeout: std::time::Duration::from_secs(10),
+        }
+    }
+}
+
+/// Change in connection health reported by the heartbeat
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeartbeatEvent {
+    /// A ping failed while the connection was healthy
+    Degraded { error: String },
+    /// A ping succeeded again after the connection degraded
+    Recovered { latency: std::time::Duration },
+    /// The CLI stopped producing output; the heartbeat stops
+    Disconnected,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -323,6 +1577,15 @@ pub enum ControlRequest {
         server_name: String,
         message: serde_json::Value,
     },
+    #[serde(rename = "rewind_files")]
+    RewindFiles { user_message_id: String },
+    #[serde(rename = "mcp_status")]
+    McpStatus {},
+    #[serde(rename = "compact")]
+    Compact {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        instructions: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -343,3 +1606,65 @@ pub enum ControlResponseType {
     #[serde(rename = "error")]
     Error { request_id: String, error: String },
 }
+
+/// Capabilities reported by the CLI in its `initialize` response
+///
+/// Fields the SDK does not model yet are kept in `extra`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerInfo {
+    #[serde(default)]
+    pub commands: Vec<SlashCommand>,
+    #[serde(default)]
+    pub models: Vec<ModelInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_style: Option<String>,
+    #[serde(default)]
+    pub available_output_styles: Vec<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl ServerInfo {
+    /// Whether the CLI offers a slash command, with or without the leading `/`
+    pub fn has_command(&self, name: &str) -> bool {
+        let name = name.trim_start_matches('/');
+        self.commands.iter().any(|command| command.name == name)
+    }
+
+    /// Whether the CLI lists a model by its identifier
+    pub fn has_model(&self, value: &str) -> bool {
+        self.models.iter().any(|model| model.value == value)
+    }
+}
+
+/// Slash command available in the CLI session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlashCommand {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub argument_hint: Option<String>,
+}
+
+/// Model the CLI can switch to with `set_model`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelInfo {
+    pub value: String,
+    #[serde(default)]
+    pub display_name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Connection state of an MCP server in the CLI session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpServerStatus {
+    pub name: String,
+    /// `connected`, `failed`, `pending`, ...
+    pub status: String,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
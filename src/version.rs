@@ -0,0 +1,129 @@
+//! Detecting the installed Claude Code CLI version
+//!
+//! Some features need a recent CLI; older versions ignore the control
+//! protocol messages they don't know, so the SDK would just wait for answers
+//! that never come. Set [`ClaudeAgentOptions::required_version`] to fail at
+//! connect with [`ClaudeSDKError::CliVersionIncompatible`] instead.
+//!
+//! # Example
+//! ```no_run
+//! # async fn example() -> claude::Result<()> {
+//! let version = claude::cli_version().await?;
+//! println!("Claude Code {}", version);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ClaudeAgentOptions::required_version`]: crate::ClaudeAgentOptions::required_version
+
+use crate::errors::{ClaudeSDKError, Result};
+use crate::transport::SubprocessTransport;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// A `major.minor.patch` version number
+///
+/// Pre-release and build suffixes such as `-beta.1` are ignored, and missing
+/// components count as zero, so `"2"` parses as `2.0.0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CliVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl CliVersion {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        CliVersion { major, minor, patch }
+    }
+
+    /// The version in the output of `claude --version`, e.g.
+    /// `2.0.14 (Claude Code)`
+    pub fn from_output(output: &str) -> Option<Self> {
+        output.split_whitespace().find_map(|word| word.parse().ok())
+    }
+}
+
+impl FromStr for CliVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("invalid version '{}'", s);
+        let number = s.trim().trim_start_matches('v');
+        let number = number.split(['-', '+']).next().unwrap_or_default();
+
+        let mut parts = [0; 3];
+        let components: Vec<_> = number.split('.').collect();
+        if components.len() > 3 {
+            return Err(invalid());
+        }
+        for (part, component) in parts.iter_mut().zip(components) {
+            *part = component.parse().map_err(|_| invalid())?;
+        }
+        Ok(CliVersion::new(parts[0], parts[1], parts[2]))
+    }
+}
+
+impl fmt::Display for CliVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl Serialize for CliVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CliVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Version of the Claude Code CLI found on this machine
+///
+/// Runs `claude --version` with the CLI that [`SubprocessTransport`] would
+/// start. Use [`SubprocessTransport::cli_version`] to include its
+/// environment and command wrapper.
+pub async fn cli_version() -> Result<CliVersion> {
+    SubprocessTransport::new(String::new(), Default::default()).cli_version().await
+}
+
+/// Fail unless `found` is at least `required`
+pub(crate) fn check_version(found: CliVersion, required: CliVersion) -> Result<()> {
+    if found < required {
+        return Err(ClaudeSDKError::cli_version_incompatible(found, required));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_versions() {
+        assert_eq!("2.0.14".parse(), Ok(CliVersion::new(2, 0, 14)));
+        assert_eq!("v1.2".parse(), Ok(CliVersion::new(1, 2, 0)));
+        assert_eq!("3.1.0-beta.2+abc".parse(), Ok(CliVersion::new(3, 1, 0)));
+        assert!("2.x".parse::<CliVersion>().is_err());
+        assert!("1.2.3.4".parse::<CliVersion>().is_err());
+
+        assert_eq!(CliVersion::from_output("2.0.14 (Claude Code)\n"), Some(CliVersion::new(2, 0, 14)));
+        assert_eq!(CliVersion::from_output("Claude Code version 1.0.3"), Some(CliVersion::new(1, 0, 3)));
+        assert_eq!(CliVersion::from_output("command not found"), None);
+    }
+
+    #[test]
+    fn test_check_version() {
+        assert!(check_version(CliVersion::new(2, 0, 14), CliVersion::new(2, 0, 0)).is_ok());
+        assert!(check_version(CliVersion::new(2, 0, 0), CliVersion::new(2, 0, 0)).is_ok());
+        assert!(matches!(
+            check_version(CliVersion::new(1, 9, 99), CliVersion::new(2, 0, 0)),
+            Err(ClaudeSDKError::CliVersionIncompatible { .. })
+        ));
+    }
+}
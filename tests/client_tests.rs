@@ -1,4 +1,9 @@
-use claude::{ClaudeSDKClient, ClaudeAgentOptions};
+use claude::testing::{result_message, MockHandle, MockTransport};
+use claude::transport::Transport;
+use claude::mcp::SdkMcpServer;
+use claude::{CanUseToolCallback, CancelHandle, ClaudeSDKClient, CostTracker, ClaudeSDKError, ClaudeAgentOptions, Handlers, HeartbeatConfig, HeartbeatEvent, HookEvent, HookJSONOutput, Message, MessageVecExt, PermissionMode, PermissionResult, RestartPolicy, SessionId, SystemPromptConfig, UserContent};
+use futures::StreamExt;
+use std::sync::Arc;
 
 #[tokio::test]
 async fn test_client_creation_with_default_options() {
@@ -41,7 +46,7 @@ async fn test_client_interrupt_before_connect_fails() {
 async fn test_client_set_permission_mode_before_connect_fails() {
     let mut client = ClaudeSDKClient::new(None);
 
-    let result = client.set_permission_mode("default").await;
+    let result = client.set_permission_mode(PermissionMode::Default).await;
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("Not connected"));
 }
@@ -56,9 +61,16 @@ async fn test_client_set_model_before_connect_fails() {
 }
 
 #[tokio::test]
-async fn test_receive_messages_before_connect_returns_empty() {
-    use futures::StreamExt;
+async fn test_client_ping_before_connect_fails() {
+    let client = ClaudeSDKClient::new(None);
+
+    assert!(!client.is_connected());
+    let result = client.ping().await;
+    assert!(result.unwrap_err().to_string().contains("Not connected"));
+}
 
+#[tokio::test]
+async fn test_receive_messages_before_connect_returns_empty() {
     let mut client = ClaudeSDKClient::new(None);
     let mut stream = client.receive_messages();
 
@@ -79,3 +91,827 @@ async fn test_client_options_preserved() {
     // Verify options are stored (indirectly through creation success)
     drop(client);
 }
+
+#[tokio::test]
+async fn test_client_with_mock_transport_round_trip() {
+    let transport = MockTransport::new()
+        .with_assistant_text("4")
+        .with_result("session-abc");
+    let handle = transport.handle();
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    client.connect().await.unwrap();
+    client.query("What is 2 + 2?", None).await.unwrap();
+
+    let messages: Vec<Message> = client
+        .receive_response()
+        .map(|m| m.unwrap())
+        .collect()
+        .await;
+
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].text_content(), Some("4".to_string()));
+    assert!(messages[1].is_result());
+
+    handle.assert_user_message("What is 2 + 2?");
+    assert_eq!(handle.control_requests()[0]["request"]["subtype"], "initialize");
+}
+
+#[tokio::test]
+async fn test_client_set_permission_mode_sends_cli_name() {
+    let transport = MockTransport::new();
+    let handle = transport.handle();
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    client.connect().await.unwrap();
+    client.set_permission_mode(PermissionMode::BypassPermissions).await.unwrap();
+
+    let request = handle.control_requests().pop().unwrap();
+    assert_eq!(request["request"]["subtype"], "set_permission_mode");
+    assert_eq!(request["request"]["mode"], "bypassPermissions");
+
+    client.set_permission_mode("plan").await.unwrap();
+    let request = handle.control_requests().pop().unwrap();
+    assert_eq!(request["request"]["mode"], "plan");
+}
+
+#[tokio::test]
+async fn test_client_exposes_server_info() {
+    let transport = MockTransport::new().respond_to(
+        "initialize",
+        serde_json::json!({
+            "commands": [{"name": "compact", "description": "Compact the conversation"}],
+            "models": [{"value": "opus", "displayName": "Opus", "description": ""}]
+        }),
+    );
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    assert!(client.server_info().is_none());
+    client.connect().await.unwrap();
+
+    let info = client.server_info().expect("initialize response was not parsed");
+    assert!(info.has_command("compact"));
+    assert!(info.has_model("opus"));
+}
+
+#[tokio::test]
+async fn test_client_transport_setter_keeps_can_use_tool() {
+    let transport = MockTransport::new().with_can_use_tool(
+        "perm_1",
+        "Write",
+        serde_json::json!({"file_path": "/etc/passwd"}),
+    );
+    let handle = transport.handle();
+
+    let callback: CanUseToolCallback = Arc::new(|_tool_name, _input, _ctx| {
+        Box::pin(async move { Ok(PermissionResult::deny("read-only session")) })
+    });
+    let mut client = ClaudeSDKClient::with_can_use_tool(None, callback).transport(Box::new(transport));
+    client.connect().await.unwrap();
+
+    let response = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        handle.wait_for_control_response("perm_1"),
+    )
+    .await
+    .expect("timed out waiting for permission response")
+    .expect("permission response was not written");
+    assert_eq!(response["response"]["response"]["allow"], false);
+    assert_eq!(response["response"]["response"]["reason"], "read-only session");
+}
+
+#[tokio::test]
+async fn test_client_restarts_and_resumes_after_crash() {
+    // `resume` option and handle of every transport the factory created
+    type Spawned = Vec<(Option<SessionId>, MockHandle)>;
+    let spawned: Arc<std::sync::Mutex<Spawned>> = Arc::default();
+    let factory_spawned = Arc::clone(&spawned);
+    let factory = move |options: ClaudeAgentOptions| {
+        let mut spawned = factory_spawned.lock().unwrap();
+        let transport = if spawned.is_empty() {
+            MockTransport::new().with_result("session-1")
+        } else {
+            MockTransport::new().with_assistant_text("back again")
+        };
+        spawned.push((options.resume, transport.handle()));
+        Box::new(transport) as Box<dyn Transport>
+    };
+    let handle = |index: usize| spawned.lock().unwrap()[index].1.clone();
+
+    let mut client = ClaudeSDKClient::new(None)
+        .transport_factory(factory)
+        .restart_policy(RestartPolicy {
+            max_restarts: 1,
+            delay: std::time::Duration::ZERO,
+        });
+    client.connect().await.unwrap();
+    let mut messages = client.receive_messages();
+    assert!(messages.next().await.unwrap().unwrap().is_result());
+
+    // The CLI dies: a new one resumes the session and the stream continues
+    handle(0).finish();
+    match messages.next().await {
+        Some(Ok(Message::System(notice))) => {
+            assert_eq!(notice.subtype, "reconnect");
+            assert_eq!(notice.data["session_id"], "session-1");
+            assert_eq!(notice.data["attempt"], 1);
+        }
+        other => panic!("expected reconnect notice, got {:?}", other),
+    }
+    let message = messages.next().await.unwrap().unwrap();
+    assert_eq!(message.text_content(), Some("back again".to_string()));
+
+    let resumed: Vec<_> = spawned.lock().unwrap().iter().map(|(resume, _)| resume.clone()).collect();
+    assert_eq!(resumed, [None, Some("session-1".into())]);
+
+    // Writes go to the replacement, which is initialized again
+    let replacement = handle(1);
+    client.query("still there?", None).await.unwrap();
+    replacement.assert_user_message("still there?");
+    let initialize = replacement.wait_for(|h| {
+        h.control_requests()
+            .into_iter()
+            .find(|r| r["request"]["subtype"] == "initialize")
+    });
+    tokio::time::timeout(std::time::Duration::from_secs(5), initialize)
+        .await
+        .expect("timed out waiting for initialize")
+        .expect("initialize was not written");
+
+    // Once the restart budget is used up the failure ends the stream
+    replacement.finish();
+    assert!(messages.next().await.unwrap().is_err());
+    assert!(messages.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_client_ping_and_disconnect() {
+    let transport = MockTransport::new();
+    let handle = transport.handle();
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    client.connect().await.unwrap();
+    assert!(client.is_connected());
+
+    client.ping().await.unwrap();
+    assert_eq!(handle.control_requests().last().unwrap()["request"]["subtype"], "mcp_status");
+
+    let mut health = client
+        .start_heartbeat(HeartbeatConfig {
+            interval: std::time::Duration::from_millis(10),
+            timeout: std::time::Duration::from_secs(1),
+        })
+        .unwrap();
+
+    // The CLI goes away: the stream ends and the heartbeat reports it
+    handle.finish();
+    let mut messages = client.receive_messages();
+    assert!(messages.next().await.is_none());
+    assert!(!client.is_connected());
+    assert_eq!(health.recv().await, Some(HeartbeatEvent::Disconnected));
+    assert_eq!(health.recv().await, None);
+
+    // A mock runs no process, so there is no exit status
+    client.close().await.unwrap();
+    assert!(client.exit_status().await.is_none());
+}
+
+#[tokio::test]
+async fn test_client_session_control_requests() {
+    let transport = MockTransport::new().respond_to(
+        "mcp_status",
+        serde_json::json!({
+            "mcpServers": [
+                {"name": "github", "status": "connected"},
+                {"name": "jira", "status": "failed"}
+            ]
+        }),
+    );
+    let handle = transport.handle();
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    client.connect().await.unwrap();
+
+    let servers = client.list_mcp_servers().await.unwrap();
+    let statuses: Vec<_> = servers.iter().map(|s| (s.name.as_str(), s.status.as_str())).collect();
+    assert_eq!(statuses, [("github", "connected"), ("jira", "failed")]);
+
+    client.rewind_files("msg-42").await.unwrap();
+    let request = handle.control_requests().pop().unwrap();
+    assert_eq!(request["request"]["subtype"], "rewind_files");
+    assert_eq!(request["request"]["user_message_id"], "msg-42");
+
+    client.compact(Some("keep the API design")).await.unwrap();
+    let request = handle.control_requests().pop().unwrap();
+    assert_eq!(request["request"]["subtype"], "compact");
+    assert_eq!(request["request"]["instructions"], "keep the API design");
+    assert!(handle.user_messages().is_empty());
+}
+
+#[tokio::test]
+async fn test_client_query_and_collect_across_turns() {
+    let transport = MockTransport::new()
+        .with_assistant_text("4")
+        .with_result("session-1");
+    let handle = transport.handle();
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    client.connect().await.unwrap();
+
+    let messages = client.query_and_collect("What is 2 + 2?").await.unwrap();
+    assert_eq!(messages.len(), 2);
+    assert!(messages[1].is_result());
+
+    handle.push(serde_json::json!({
+        "type": "assistant",
+        "message": {"model": "mock-model", "content": [{"type": "text", "text": "8"}]}
+    }));
+    handle.push(result_message("session-1"));
+    assert_eq!(client.query_for_text("And doubled?").await.unwrap(), "8");
+
+    // The CLI exits mid-turn
+    handle.finish();
+    let error = client.query_and_collect("Still there?").await.unwrap_err();
+    assert!(error.to_string().contains("before the result message"));
+}
+
+#[tokio::test]
+async fn test_client_split_reads_and_writes_concurrently() {
+    let transport = MockTransport::new();
+    let handle = transport.handle();
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    client.connect().await.unwrap();
+    let (mut sender, mut receiver) = client.split().unwrap();
+
+    let reader = tokio::spawn(async move {
+        let messages: Vec<_> = receiver.receive_response().collect().await;
+        messages.into_iter().collect::<Result<Vec<_>, _>>().unwrap().text_content()
+    });
+
+    sender.query("Hello", None).await.unwrap();
+    handle.assert_user_message("Hello");
+    handle.push(serde_json::json!({
+        "type": "assistant",
+        "message": {"model": "mock-model", "content": [{"type": "text", "text": "Hi there"}]}
+    }));
+    handle.push(result_message("session-1"));
+
+    assert_eq!(reader.await.unwrap(), "Hi there");
+    sender.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_split_requires_connection() {
+    let client = ClaudeSDKClient::new(None);
+    assert!(client.split().is_err());
+}
+
+#[tokio::test]
+async fn test_client_sessions_receive_only_their_messages() {
+    let transport = MockTransport::new();
+    let handle = transport.handle();
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    client.connect().await.unwrap();
+
+    let mut alpha = client.session("alpha").unwrap();
+    let mut beta = client.session("beta").unwrap();
+    alpha.query("first").await.unwrap();
+    beta.query("second").await.unwrap();
+
+    let sessions: Vec<_> = handle
+        .user_messages()
+        .iter()
+        .map(|m| m["session_id"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(sessions, ["alpha", "beta"]);
+
+    let assistant = |session: &str, text: &str| {
+        serde_json::json!({
+            "type": "assistant",
+            "session_id": session,
+            "message": {"model": "mock-model", "content": [{"type": "text", "text": text}]}
+        })
+    };
+    handle.push(assistant("beta", "from beta"));
+    handle.push(assistant("alpha", "from alpha"));
+    handle.push(assistant("other", "unrouted"));
+    handle.push(result_message("alpha"));
+    handle.push(result_message("beta"));
+
+    let (alpha_messages, beta_messages) = tokio::join!(
+        alpha.receive_response().collect::<Vec<_>>(),
+        beta.receive_response().collect::<Vec<_>>(),
+    );
+    let alpha_messages: Vec<Message> = alpha_messages.into_iter().map(Result::unwrap).collect();
+    let beta_messages: Vec<Message> = beta_messages.into_iter().map(Result::unwrap).collect();
+
+    assert_eq!(alpha_messages.len(), 2);
+    assert_eq!(alpha_messages.text_content(), "from alpha");
+    assert_eq!(beta_messages.len(), 2);
+    assert_eq!(beta_messages.text_content(), "from beta");
+
+    // Messages for sessions without a handle stay on the shared stream
+    handle.finish();
+    let shared: Vec<Message> = client.receive_messages().map(Result::unwrap).collect().await;
+    assert_eq!(shared.text_content(), "unrouted");
+
+    // Session streams end with the connection
+    assert!(alpha.receive_messages().next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_client_sessions_receive_turns_tagged_with_cli_session_id() {
+    let transport = MockTransport::new();
+    let handle = transport.handle();
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    client.connect().await.unwrap();
+
+    let mut alpha = client.session("alpha").unwrap();
+    let mut beta = client.session("beta").unwrap();
+    alpha.query("first").await.unwrap();
+    beta.query("second").await.unwrap();
+
+    // The CLI answers each prompt in turn, tagged with its own session ID
+    let cli_session = "3f1c9a52-8d4e-4b7a-9c2f-6e0d1b5a7c38";
+    for text in ["from alpha", "from beta"] {
+        handle.push(serde_json::json!({
+            "type": "system",
+            "subtype": "init",
+            "session_id": cli_session,
+            "model": "mock-model"
+        }));
+        handle.push(serde_json::json!({
+            "type": "assistant",
+            "session_id": cli_session,
+            "message": {"model": "mock-model", "content": [{"type": "text", "text": text}]}
+        }));
+        handle.push(result_message(cli_session));
+    }
+
+    let (alpha_messages, beta_messages) = tokio::join!(
+        alpha.receive_response().collect::<Vec<_>>(),
+        beta.receive_response().collect::<Vec<_>>(),
+    );
+    let alpha_messages: Vec<Message> = alpha_messages.into_iter().map(Result::unwrap).collect();
+    let beta_messages: Vec<Message> = beta_messages.into_iter().map(Result::unwrap).collect();
+
+    assert_eq!(alpha_messages.len(), 3);
+    assert_eq!(alpha_messages.text_content(), "from alpha");
+    assert_eq!(beta_messages.len(), 3);
+    assert_eq!(beta_messages.text_content(), "from beta");
+    assert_eq!(client.session_id().unwrap().as_str(), cli_session);
+
+    // Output outside of the handles' turns stays on the shared stream
+    handle.push(serde_json::json!({
+        "type": "assistant",
+        "session_id": cli_session,
+        "message": {"model": "mock-model", "content": [{"type": "text", "text": "unrouted"}]}
+    }));
+    handle.finish();
+    let shared: Vec<Message> = client.receive_messages().map(Result::unwrap).collect().await;
+    assert_eq!(shared.text_content(), "unrouted");
+}
+
+#[tokio::test]
+async fn test_client_builder_wires_hooks_and_mcp_servers() {
+    let transport = MockTransport::new().with_control_request(
+        "cli-1",
+        serde_json::json!({
+            "subtype": "mcp_message",
+            "server_name": "tools",
+            "message": {"jsonrpc": "2.0", "id": 1, "method": "tools/list"}
+        }),
+    );
+    let handle = transport.handle();
+
+    let mut client = ClaudeSDKClient::builder()
+        .hook(HookEvent::PreToolUse, "Bash", Arc::new(|_, _, _| Box::pin(async { Ok(HookJSONOutput::default()) })))
+        .mcp_server(SdkMcpServer::new("tools".to_string(), "1.0.0".to_string(), vec![]))
+        .transport(Box::new(transport))
+        .build();
+    client.connect().await.unwrap();
+
+    let initialize = &handle.control_requests()[0]["request"];
+    assert_eq!(initialize["subtype"], "initialize");
+    assert_eq!(initialize["hooks"]["PreToolUse"][0]["matcher"], "Bash");
+
+    let response = handle.wait_for_control_response("cli-1").await.unwrap();
+    assert_eq!(response["response"]["subtype"], "success");
+}
+
+
+#[tokio::test]
+async fn test_client_serves_sdk_servers_from_options() {
+    let transport = MockTransport::new().with_control_request(
+        "cli-1",
+        serde_json::json!({
+            "subtype": "mcp_message",
+            "server_name": "local",
+            "message": {"jsonrpc": "2.0", "id": 1, "method": "initialize"}
+        }),
+    );
+    let handle = transport.handle();
+
+    let options = ClaudeAgentOptions::builder()
+        .mcp_server("local", SdkMcpServer::builder("my-tools").build().unwrap())
+        .build();
+    assert_eq!(
+        serde_json::to_value(&options.mcp_servers["local"]).unwrap(),
+        serde_json::json!({"type": "sdk", "name": "my-tools"})
+    );
+
+    let mut client = ClaudeSDKClient::builder().options(options).transport(Box::new(transport)).build();
+    client.connect().await.unwrap();
+
+    let response = handle.wait_for_control_response("cli-1").await.unwrap();
+    assert_eq!(response["response"]["response"]["mcp_response"]["result"]["serverInfo"]["name"], "my-tools");
+}
+
+
+#[tokio::test]
+async fn test_client_builder_permission_prompt() {
+    let transport = MockTransport::new().with_control_request(
+        "cli-1",
+        serde_json::json!({
+            "subtype": "mcp_message",
+            "server_name": claude::permissions::PERMISSION_PROMPT_SERVER,
+            "message": {
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": {"name": "prompt", "arguments": {"tool_name": "Bash", "input": {"command": "ls"}}}
+            }
+        }),
+    );
+    let handle = transport.handle();
+
+    let mut client = ClaudeSDKClient::builder()
+        .permission_prompt(Arc::new(|_, _, _| Box::pin(async { Ok(PermissionResult::deny("not now")) })))
+        .transport(Box::new(transport))
+        .build();
+    client.connect().await.unwrap();
+
+    let response = handle.wait_for_control_response("cli-1").await.unwrap();
+    let text = response["response"]["response"]["mcp_response"]["result"]["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&text).unwrap(),
+        serde_json::json!({"behavior": "deny", "message": "not now"})
+    );
+}
+
+#[tokio::test]
+async fn test_client_run_with_handlers() {
+    let transport = MockTransport::new()
+        .with_assistant_text("Hello")
+        .with_assistant_text(" there")
+        .with_result("session-1");
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    client.connect().await.unwrap();
+    client.query("Hi", None).await.unwrap();
+
+    let text = Arc::new(std::sync::Mutex::new(String::new()));
+    let session = Arc::new(std::sync::Mutex::new(None));
+    let handlers = Handlers::new()
+        .on_text({
+            let text = Arc::clone(&text);
+            move |chunk| text.lock().unwrap().push_str(chunk)
+        })
+        .on_result({
+            let session = Arc::clone(&session);
+            move |result| *session.lock().unwrap() = Some(result.session_id.clone())
+        });
+    client.run_with_handlers(handlers).await.unwrap();
+
+    assert_eq!(*text.lock().unwrap(), "Hello there");
+    assert_eq!(session.lock().unwrap().as_deref(), Some("session-1"));
+}
+
+#[tokio::test]
+async fn test_client_query_blocks_sends_content_array() {
+    let transport = MockTransport::new();
+    let handle = transport.handle();
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    client.connect().await.unwrap();
+    client
+        .query_blocks(
+            vec![
+                UserContent::text("What is in this image?"),
+                UserContent::image_base64("image/png", "iVBORw0KGgo="),
+            ],
+            None,
+        )
+        .await
+        .unwrap();
+
+    let sent = &handle.user_messages()[0];
+    assert_eq!(sent["session_id"], "default");
+    assert_eq!(
+        sent["message"]["content"],
+        serde_json::json!([
+            {"type": "text", "text": "What is in this image?"},
+            {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_client_set_system_prompt_restarts_and_resumes() {
+    // Options of every transport the factory created
+    let spawned: Arc<std::sync::Mutex<Vec<ClaudeAgentOptions>>> = Arc::default();
+    let factory_spawned = Arc::clone(&spawned);
+    let factory = move |options: ClaudeAgentOptions| {
+        factory_spawned.lock().unwrap().push(options);
+        Box::new(MockTransport::new().with_result("session-1")) as Box<dyn Transport>
+    };
+
+    let mut client = ClaudeSDKClient::new(None).transport_factory(factory);
+    client.connect().await.unwrap();
+    assert!(client.receive_response().next().await.unwrap().unwrap().is_result());
+
+    client
+        .set_system_prompt(SystemPromptConfig::Text("Talk like a pirate".to_string()))
+        .await
+        .unwrap();
+    assert!(client.is_connected());
+
+    let spawned = spawned.lock().unwrap();
+    assert_eq!(spawned.len(), 2);
+    assert!(spawned[0].system_prompt.is_none());
+    assert_eq!(spawned[1].resume.as_deref(), Some("session-1"));
+    assert!(matches!(
+        spawned[1].system_prompt,
+        Some(SystemPromptConfig::Text(ref text)) if text == "Talk like a pirate"
+    ));
+}
+
+#[tokio::test]
+async fn test_client_set_system_prompt_needs_factory_for_custom_transport() {
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(MockTransport::new()));
+    client.connect().await.unwrap();
+
+    let result = client
+        .set_system_prompt(SystemPromptConfig::Text("Be brief".to_string()))
+        .await;
+    assert!(result.is_err());
+    assert!(client.is_connected());
+}
+
+#[tokio::test]
+async fn test_client_cancel_interrupts_response() {
+    let transport = MockTransport::new().with_assistant_text("partial");
+    let handle = transport.handle();
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    client.connect().await.unwrap();
+    client.query("Write a novel", None).await.unwrap();
+
+    let cancel = CancelHandle::new();
+    let mut response = client.receive_response_with_cancel(&cancel);
+    assert_eq!(response.next().await.unwrap().unwrap().text_content(), Some("partial".to_string()));
+
+    // The rest of the interrupted turn is dropped
+    cancel.cancel();
+    handle.push(serde_json::json!({
+        "type": "assistant",
+        "message": {"model": "mock-model", "content": [{"type": "text", "text": "more"}]}
+    }));
+    let mut interrupted = result_message("session-1");
+    interrupted["subtype"] = serde_json::json!("error_during_execution");
+    interrupted["is_error"] = serde_json::json!(true);
+    handle.push(interrupted);
+    assert!(matches!(response.next().await, Some(Err(ClaudeSDKError::Interrupted))));
+    assert!(response.next().await.is_none());
+    drop(response);
+
+    let interrupt = handle.wait_for(|h| {
+        h.control_requests()
+            .into_iter()
+            .find(|r| r["request"]["subtype"] == "interrupt")
+    });
+    tokio::time::timeout(std::time::Duration::from_secs(5), interrupt)
+        .await
+        .expect("timed out waiting for interrupt")
+        .expect("interrupt was not written");
+
+    // The next turn starts cleanly
+    handle.push(serde_json::json!({
+        "type": "assistant",
+        "message": {"model": "mock-model", "content": [{"type": "text", "text": "fresh"}]}
+    }));
+    handle.push(result_message("session-1"));
+    assert_eq!(client.query_for_text("Just a haiku").await.unwrap(), "fresh");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_client_ctrl_c_interrupts_then_closes() {
+    let transport = MockTransport::new();
+    let handle = transport.handle();
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    client.connect().await.unwrap();
+    client.query("Take your time", None).await.unwrap();
+    client.interrupt_on_ctrl_c().unwrap();
+
+    let interrupts = |h: &MockHandle| {
+        let count = h
+            .control_requests()
+            .iter()
+            .filter(|r| r["request"]["subtype"] == "interrupt")
+            .count();
+        (count > 0).then_some(count)
+    };
+
+    unsafe { libc::raise(libc::SIGINT) };
+    let sent = tokio::time::timeout(std::time::Duration::from_secs(5), handle.wait_for(interrupts))
+        .await
+        .expect("timed out waiting for interrupt");
+    assert_eq!(sent, Some(1));
+
+    // The turn has not ended, so the second Ctrl-C closes the connection
+    unsafe { libc::raise(libc::SIGINT) };
+    let mut messages = client.receive_messages();
+    let end = tokio::time::timeout(std::time::Duration::from_secs(5), messages.next())
+        .await
+        .expect("timed out waiting for the connection to close");
+    assert!(end.is_none());
+    assert_eq!(interrupts(&handle), Some(1));
+}
+
+#[tokio::test]
+async fn test_client_text_stream_assembles_deltas() {
+    let delta = |text: &str| {
+        serde_json::json!({
+            "type": "stream_event",
+            "uuid": "event",
+            "session_id": "session-1",
+            "event": {"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": text}}
+        })
+    };
+    let transport = MockTransport::new()
+        .with_message(serde_json::json!({
+            "type": "stream_event",
+            "uuid": "start",
+            "session_id": "session-1",
+            "event": {"type": "message_start"}
+        }))
+        .with_message(delta("Once "))
+        .with_message(delta("upon "))
+        .with_message(delta("a time"))
+        // The complete message repeats the deltas and is skipped
+        .with_assistant_text("Once upon a time")
+        .with_result("session-1")
+        // Without partial messages each text block is a chunk
+        .with_assistant_text("The end")
+        .with_result("session-1");
+
+    let mut client = ClaudeSDKClient::with_transport(None, Box::new(transport));
+    client.connect().await.unwrap();
+
+    let chunks: Vec<String> = client.receive_text_stream().map(Result::unwrap).collect().await;
+    assert_eq!(chunks, ["Once ", "upon ", "a time"]);
+
+    let chunks: Vec<String> = client.receive_text_stream().map(Result::unwrap).collect().await;
+    assert_eq!(chunks, ["The end"]);
+}
+
+#[tokio::test]
+async fn test_client_tracks_session_id_and_forks() {
+    let spawned: Arc<std::sync::Mutex<Vec<ClaudeAgentOptions>>> = Arc::default();
+    let factory_spawned = Arc::clone(&spawned);
+    let factory = move |options: ClaudeAgentOptions| {
+        factory_spawned.lock().unwrap().push(options);
+        Box::new(MockTransport::new().with_result("session-1")) as Box<dyn Transport>
+    };
+
+    let mut client = ClaudeSDKClient::new(None).transport_factory(factory);
+    assert_eq!(client.session_id(), None);
+    assert!(client.fork_current().is_err());
+    client.connect().await.unwrap();
+
+    assert!(client.receive_response().next().await.unwrap().unwrap().is_result());
+    assert_eq!(client.session_id().as_deref(), Some("session-1"));
+
+    let mut fork = client.fork_current().unwrap();
+    fork.connect().await.unwrap();
+
+    let spawned = spawned.lock().unwrap();
+    assert_eq!(spawned.len(), 2);
+    assert_eq!(spawned[1].resume.as_deref(), Some("session-1"));
+    assert!(spawned[1].fork_session);
+}
+
+#[tokio::test]
+async fn test_client_interrupts_when_budget_is_exceeded() {
+    let event = |event: serde_json::Value| {
+        serde_json::json!({"type": "stream_event", "uuid": "event", "session_id": "session-1", "event": event})
+    };
+    let transport = MockTransport::new();
+    let handle = transport.handle();
+    let options = ClaudeAgentOptions::builder().max_total_tokens(1000).build();
+    let mut client = ClaudeSDKClient::builder().options(options).transport(Box::new(transport)).build();
+    client.connect().await.unwrap();
+
+    handle.push(event(serde_json::json!({"type": "message_start", "message": {"usage": {"input_tokens": 600}}})));
+    handle.push(event(serde_json::json!({"type": "message_delta", "delta": {}, "usage": {"output_tokens": 500}})));
+    match client.query_and_collect("Loop forever").await {
+        Err(ClaudeSDKError::BudgetExceeded { total_tokens, .. }) => assert_eq!(total_tokens, 1100),
+        other => panic!("expected budget error, got {:?}", other),
+    }
+
+    // The interrupt is written by a background task
+    let interrupted = || handle.control_requests().iter().any(|r| r["request"]["subtype"] == "interrupt");
+    tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        while !interrupted() {
+            tokio::task::yield_now().await;
+        }
+    })
+    .await
+    .expect("interrupt was not sent");
+    assert!(matches!(client.query("More", None).await, Err(ClaudeSDKError::BudgetExceeded { .. })));
+}
+
+#[derive(Default)]
+struct CountingSink {
+    reports: std::sync::Mutex<Vec<(String, f64)>>,
+}
+
+impl claude::metrics::MetricsSink for CountingSink {
+    fn counter(&self, name: &str, value: f64, _labels: &[(&str, &str)]) {
+        self.reports.lock().unwrap().push((name.to_string(), value));
+    }
+    fn gauge(&self, name: &str, value: f64, _labels: &[(&str, &str)]) {
+        self.reports.lock().unwrap().push((name.to_string(), value));
+    }
+    fn histogram(&self, name: &str, value: f64, _labels: &[(&str, &str)]) {
+        self.reports.lock().unwrap().push((name.to_string(), value));
+    }
+}
+
+#[tokio::test]
+async fn test_client_reports_metrics() {
+    let sink = Arc::new(CountingSink::default());
+    let options = ClaudeAgentOptions {
+        metrics: Some(claude::metrics::Metrics::from_arc(sink.clone())),
+        ..Default::default()
+    };
+    let transport = MockTransport::new().with_assistant_text("4").with_result("session-1");
+
+    let mut client = ClaudeSDKClient::with_transport(Some(options), Box::new(transport));
+    client.connect().await.unwrap();
+    client.query_and_collect("What is 2 + 2?").await.unwrap();
+    client.close().await.unwrap();
+
+    let reports = sink.reports.lock().unwrap();
+    let names: Vec<_> = reports.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "claude_active_sessions",
+            "claude_queries_total",
+            "claude_query_duration_seconds",
+            "claude_query_turns",
+            "claude_active_sessions",
+        ]
+    );
+    assert_eq!(reports[0].1, 1.0);
+    assert_eq!(reports[4].1, 0.0);
+}
+
+#[tokio::test]
+async fn test_client_tracks_cost() {
+    let tracker = CostTracker::new();
+    let mut result = result_message("session-1");
+    result["total_cost_usd"] = serde_json::json!(0.25);
+    result["usage"] = serde_json::json!({"input_tokens": 40, "output_tokens": 2});
+    // The second turn reports the session's totals so far
+    let mut second = result.clone();
+    second["total_cost_usd"] = serde_json::json!(0.5);
+    second["usage"] = serde_json::json!({"input_tokens": 80, "output_tokens": 4});
+    let transport = MockTransport::new()
+        .with_assistant_text("4")
+        .with_message(result)
+        .with_assistant_text("8")
+        .with_message(second);
+
+    let options = ClaudeAgentOptions::builder().cost_tracker(tracker.clone()).build();
+    let mut client = ClaudeSDKClient::with_transport(Some(options), Box::new(transport));
+    client.connect().await.unwrap();
+    client.query_and_collect("What is 2 + 2?").await.unwrap();
+    client.query_and_collect("And doubled?").await.unwrap();
+    client.close().await.unwrap();
+
+    let snapshot = tracker.snapshot();
+    assert_eq!(snapshot.total.queries, 2);
+    assert_eq!(snapshot.total.cost_usd, 0.5);
+    assert_eq!(snapshot.total.usage.input_tokens, 80);
+    assert_eq!(snapshot.by_session[&SessionId::from("session-1")].queries, 2);
+}
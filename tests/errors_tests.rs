@@ -126,3 +126,155 @@ fn test_error_chain() {
         assert!(e.to_string().contains("inner error"));
     }
 }
+
+#[test]
+fn test_buffer_overflow() {
+    let error = ClaudeSDKError::buffer_overflow(1024);
+    assert!(matches!(error, ClaudeSDKError::BufferOverflow { limit: 1024 }));
+    assert!(error.to_string().contains("1024 bytes"));
+}
+
+#[test]
+fn test_interrupted() {
+    let error = ClaudeSDKError::interrupted();
+    assert!(matches!(error, ClaudeSDKError::Interrupted));
+    assert_eq!(error.to_string(), "Query was cancelled");
+}
+
+#[test]
+fn test_invalid_policy() {
+    let error = ClaudeSDKError::invalid_policy("policy.toml", Some(4), Some(10), "unknown variant `maybe`");
+    assert!(matches!(error, ClaudeSDKError::InvalidPolicy { line: Some(4), .. }));
+    assert_eq!(
+        error.to_string(),
+        "Invalid permission policy policy.toml:4:10: unknown variant `maybe`"
+    );
+
+    let error = ClaudeSDKError::invalid_policy("policy.json", None, None, "No such file or directory");
+    assert_eq!(
+        error.to_string(),
+        "Invalid permission policy policy.json: No such file or directory"
+    );
+}
+
+#[test]
+fn test_invalid_mcp_config() {
+    let error = ClaudeSDKError::invalid_mcp_config(".mcp.json", Some("git".to_string()), "missing field `command`");
+    assert!(matches!(error, ClaudeSDKError::InvalidMcpConfig { server: Some(_), .. }));
+    assert_eq!(error.to_string(), "Invalid MCP config .mcp.json (server 'git'): missing field `command`");
+}
+
+#[test]
+fn test_invalid_mcp_server() {
+    let error = ClaudeSDKError::invalid_mcp_server("tools", "duplicate tool name 'a'");
+    assert!(matches!(error, ClaudeSDKError::InvalidMcpServer { .. }));
+    assert_eq!(error.to_string(), "Invalid MCP server 'tools': duplicate tool name 'a'");
+}
+
+#[test]
+fn test_timeout_and_control_protocol() {
+    use std::time::Duration;
+
+    let error = ClaudeSDKError::timeout("Control request 'interrupt'", Duration::from_secs(30));
+    assert!(matches!(&error, ClaudeSDKError::Timeout { operation, .. } if operation == "Control request 'interrupt'"));
+    assert_eq!(error.to_string(), "Control request 'interrupt' timed out after 30s");
+
+    let error = ClaudeSDKError::control_protocol("req_1_ab", "Unknown subtype");
+    assert!(matches!(&error, ClaudeSDKError::ControlProtocol { request_id, .. } if request_id == "req_1_ab"));
+    assert_eq!(error.to_string(), "Control request req_1_ab failed: Unknown subtype");
+    assert_eq!(error.kind(), ErrorKind::Protocol);
+    assert!(!error.is_retryable());
+}
+
+#[test]
+fn test_rate_limited_from_output() {
+    use std::time::Duration;
+
+    let output = "API Error: 429 {\"type\":\"error\",\"error\":{\"type\":\"rate_limit_error\"}}\nretry-after: 20";
+    let error = ClaudeSDKError::rate_limited_from_output(output).unwrap();
+    assert_eq!(error.kind(), ErrorKind::RateLimited);
+    assert_eq!(error.retry_after(), Some(Duration::from_secs(20)));
+    assert!(error.is_retryable());
+    assert_eq!(
+        error.to_string(),
+        "Rate limited by the API: API Error: 429 {\"type\":\"error\",\"error\":{\"type\":\"rate_limit_error\"}} (retry after 20s)"
+    );
+
+    let error = ClaudeSDKError::rate_limited_from_output("Error: API is overloaded, please retry after 1.5 seconds").unwrap();
+    assert_eq!(error.retry_after(), Some(Duration::from_millis(1500)));
+
+    assert!(ClaudeSDKError::rate_limited_from_output("Invalid model: claude-9").is_none());
+    assert!(ClaudeSDKError::cli_connection_error("429").retry_after().is_none());
+}
+
+#[test]
+fn test_cli_version_incompatible() {
+    use claude::CliVersion;
+
+    let error = ClaudeSDKError::cli_version_incompatible(CliVersion::new(1, 0, 3), CliVersion::new(2, 0, 0));
+    assert_eq!(error.kind(), ErrorKind::IncompatibleVersion);
+    assert!(error.is_fatal());
+    assert!(!error.is_retryable());
+    assert_eq!(error.to_string(), "Claude Code 1.0.3 is too old, version 2.0.0 or newer is required");
+}
+
+#[test]
+fn test_error_kinds() {
+    use std::time::Duration;
+
+    assert_eq!(ClaudeSDKError::cli_not_found(None).kind(), ErrorKind::NotFound);
+    assert_eq!(ClaudeSDKError::spawn_error("permission denied").kind(), ErrorKind::Spawn);
+    assert_eq!(ClaudeSDKError::json_decode_error("{", "EOF").kind(), ErrorKind::Protocol);
+    assert_eq!(ClaudeSDKError::message_parse_error("bad", None).kind(), ErrorKind::ParseError);
+    assert_eq!(ClaudeSDKError::timeout("Query", Duration::from_secs(1)).kind(), ErrorKind::Timeout);
+    assert_eq!(ClaudeSDKError::missing_template_variable("x").kind(), ErrorKind::InvalidInput);
+    assert_eq!(
+        ClaudeSDKError::spawn_error("permission denied").to_string(),
+        "Failed to spawn Claude Code: permission denied"
+    );
+}
+
+#[test]
+fn test_retryable_and_fatal() {
+    let overloaded = ClaudeSDKError::process_error("Command failed", Some(1), Some("API Error: 529 Overloaded".to_string()));
+    assert!(overloaded.is_retryable());
+    assert!(!overloaded.is_fatal());
+
+    let bad_model = ClaudeSDKError::process_error("Command failed", Some(1), Some("Invalid model".to_string()));
+    assert!(!bad_model.is_retryable());
+    assert!(!bad_model.is_fatal());
+
+    assert!(ClaudeSDKError::cli_connection_error("broken pipe").is_retryable());
+    assert!(ClaudeSDKError::cli_not_found(None).is_fatal());
+    assert!(ClaudeSDKError::budget_exceeded(1.0, 10).is_fatal());
+    assert!(!ClaudeSDKError::budget_exceeded(1.0, 10).is_retryable());
+
+    let interrupted = ClaudeSDKError::interrupted();
+    assert!(!interrupted.is_retryable() && !interrupted.is_fatal());
+}
+
+#[test]
+fn test_source_chains() {
+    use std::error::Error;
+
+    let io = ClaudeSDKError::io_error("Failed to read from process stdout", std::io::Error::from(std::io::ErrorKind::BrokenPipe));
+    assert_eq!(io.to_string(), "CLI Connection Error: Failed to read from process stdout");
+    assert_eq!(io.kind(), ErrorKind::Connection);
+    assert!(io.is_retryable());
+    let source = io.source().unwrap().downcast_ref::<std::io::Error>().unwrap();
+    assert_eq!(source.kind(), std::io::ErrorKind::BrokenPipe);
+
+    let spawn = ClaudeSDKError::spawn_error_from(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+    assert_eq!(spawn.to_string(), "Failed to spawn Claude Code");
+    assert!(spawn.source().unwrap().is::<std::io::Error>());
+
+    let json_error = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+    let decode = ClaudeSDKError::json_decode_error_from("{", json_error);
+    assert_eq!(decode.to_string(), "Failed to decode JSON: {");
+    assert!(decode.source().unwrap().downcast_ref::<serde_json::Error>().unwrap().is_eof());
+
+    let without_source = ClaudeSDKError::json_decode_error("{", "EOF");
+    assert_eq!(without_source.to_string(), "Failed to decode JSON: { (error: EOF)");
+    assert!(without_source.source().is_none());
+    assert!(ClaudeSDKError::cli_connection_error("offline").source().is_none());
+}
@@ -1,4 +1,5 @@
-use claude::message_parser::parse_message;
+use claude::message_parser::{parse_message, parse_message_with_mode};
+use claude::testing::result_message;
 use claude::types::*;
 use serde_json::json;
 
@@ -21,6 +22,22 @@ fn test_parse_user_message_text() {
     }
 }
 
+#[test]
+fn test_parse_user_message_uuid() {
+    let data = json!({
+        "type": "user",
+        "uuid": "3f1c2a9e-0000-4000-8000-000000000001",
+        "message": {
+            "content": "Hello, Claude!"
+        }
+    });
+
+    match parse_message(&data).unwrap() {
+        Message::User(msg) => assert_eq!(msg.uuid.as_deref(), Some("3f1c2a9e-0000-4000-8000-000000000001")),
+        _ => panic!("Expected user message"),
+    }
+}
+
 #[test]
 fn test_parse_user_message_with_blocks() {
     let data = json!({
@@ -300,6 +317,39 @@ fn test_parse_tool_result_block() {
     }
 }
 
+#[test]
+fn test_parse_user_message_with_image() {
+    let data = json!({
+        "type": "user",
+        "message": {
+            "content": [
+                {"type": "text", "text": "Look at this"},
+                {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}}
+            ]
+        }
+    });
+
+    let result = parse_message(&data).unwrap();
+    match result {
+        Message::User(msg) => match msg.content {
+            UserMessageContent::Blocks(blocks) => match &blocks[1] {
+                ContentBlock::Image { source } => {
+                    assert_eq!(
+                        *source,
+                        ContentSource::Base64 {
+                            media_type: "image/png".to_string(),
+                            data: "iVBORw0KGgo=".to_string(),
+                        }
+                    );
+                }
+                _ => panic!("Expected image block"),
+            },
+            _ => panic!("Expected content blocks"),
+        },
+        _ => panic!("Expected user message"),
+    }
+}
+
 #[test]
 fn test_parse_missing_required_field() {
     let data = json!({
@@ -395,3 +445,334 @@ fn test_parse_mixed_content_blocks() {
         _ => panic!("Expected assistant message"),
     }
 }
+
+fn sample_messages() -> Vec<serde_json::Value> {
+    vec![
+        json!({
+            "type": "user",
+            "message": {"role": "user", "content": "Hello"},
+            "uuid": "u1"
+        }),
+        json!({
+            "type": "user",
+            "message": {
+                "role": "user",
+                "content": [
+                    {"type": "tool_result", "tool_use_id": "t1", "content": "ok", "is_error": false},
+                    {"type": "image", "source": {"type": "url", "url": "https://example.com/a.png"}}
+                ]
+            },
+            "parent_tool_use_id": "p1"
+        }),
+        json!({
+            "type": "assistant",
+            "uuid": "a1",
+            "timestamp": "2025-01-01T00:00:00.000Z",
+            "message": {
+                "role": "assistant",
+                "model": "claude-3-opus",
+                "content": [
+                    {"type": "text", "text": "Hi"},
+                    {"type": "thinking", "thinking": "Hmm", "signature": "s1"},
+                    {"type": "tool_use", "id": "t1", "name": "bash", "input": {"command": "ls"}}
+                ]
+            }
+        }),
+        json!({
+            "type": "system",
+            "subtype": "init",
+            "cwd": "/tmp",
+            "tools": ["Bash"]
+        }),
+        json!({
+            "type": "result",
+            "subtype": "success",
+            "duration_ms": 10,
+            "duration_api_ms": 8,
+            "is_error": false,
+            "num_turns": 1,
+            "session_id": "s1",
+            "total_cost_usd": 0.01,
+            "usage": {"input_tokens": 3},
+            "result": "done"
+        }),
+        json!({
+            "type": "stream_event",
+            "uuid": "e1",
+            "session_id": "s1",
+            "event": {"type": "message_start"},
+            "parent_tool_use_id": "p1"
+        }),
+    ]
+}
+
+#[test]
+fn test_messages_roundtrip_through_serialize() {
+    for data in sample_messages() {
+        let message = parse_message(&data).unwrap();
+        let serialized = serde_json::to_value(&message).unwrap();
+        assert_eq!(serialized, data, "serialized form drifted from the wire format");
+
+        let reparsed: Message = serde_json::from_value(serialized.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&reparsed).unwrap(), serialized);
+    }
+}
+
+#[test]
+fn test_serialized_messages_carry_type_tag() {
+    let message = Message::Result(ResultMessage {
+        subtype: "success".to_string(),
+        duration_ms: 1,
+        duration_api_ms: 1,
+        is_error: false,
+        num_turns: 1,
+        session_id: "s1".into(),
+        total_cost_usd: None,
+        usage: None,
+        result: None,
+        model_usage: None,
+        uuid: None,
+        timestamp: None,
+    });
+    let value = serde_json::to_value(&message).unwrap();
+    assert_eq!(value["type"], "result");
+    assert!(parse_message(&value).is_ok());
+}
+
+#[test]
+fn test_deserialize_reports_parse_errors() {
+    let err = serde_json::from_value::<Message>(json!({"type": "assistant", "message": {"content": []}})).unwrap_err();
+    assert!(
+        err.to_string().contains("Missing required field in assistant message: model"),
+        "{}",
+        err
+    );
+}
+
+/// Replace every value in `data`, one at a time, with each of `replacements`,
+/// and also drop every object key, collecting the mutated documents
+fn mutations(data: &serde_json::Value, replacements: &[serde_json::Value]) -> Vec<serde_json::Value> {
+    let mut out = Vec::new();
+    match data {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                let mut without = map.clone();
+                without.remove(key);
+                out.push(serde_json::Value::Object(without));
+                for mutated in mutations(value, replacements) {
+                    let mut with = map.clone();
+                    with.insert(key.clone(), mutated);
+                    out.push(serde_json::Value::Object(with));
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                for mutated in mutations(item, replacements) {
+                    let mut with = items.clone();
+                    with[i] = mutated;
+                    out.push(serde_json::Value::Array(with));
+                }
+            }
+        }
+        _ => {}
+    }
+    out.extend(replacements.iter().cloned());
+    out
+}
+
+#[test]
+fn test_mutated_messages_never_panic() {
+    let replacements = [
+        json!(null),
+        json!(true),
+        json!(-1),
+        json!(u64::MAX),
+        json!(1.5),
+        json!(""),
+        json!("unknown"),
+        json!([]),
+        json!([{}]),
+        json!({}),
+        json!({"type": "text"}),
+    ];
+    let mut checked = 0;
+    for data in sample_messages() {
+        for mutated in mutations(&data, &replacements) {
+            match parse_message(&mutated) {
+                Ok(message) => {
+                    // Anything accepted must survive a roundtrip
+                    let serialized = serde_json::to_value(&message).unwrap();
+                    parse_message(&serialized).unwrap();
+                }
+                Err(e) => assert!(!e.to_string().is_empty()),
+            }
+            checked += 1;
+        }
+    }
+    assert!(checked > 500, "only {} mutations checked", checked);
+}
+
+#[test]
+fn test_strict_mode_rejects_unknown_types() {
+    let message = json!({"type": "rate_limit_event"});
+    let err = parse_message(&message).unwrap_err();
+    assert!(err.to_string().contains("Unknown message type: rate_limit_event"), "{}", err);
+
+    let block = json!({
+        "type": "assistant",
+        "message": {"model": "m", "content": [{"type": "server_tool_use", "id": "x"}]}
+    });
+    let err = parse_message_with_mode(&block, ParseMode::Strict).unwrap_err();
+    assert!(err.to_string().contains("Unknown content block type: server_tool_use"), "{}", err);
+}
+
+#[test]
+fn test_lenient_mode_keeps_unknown_types() {
+    let message = json!({"type": "rate_limit_event", "retry_after": 3});
+    match parse_message_with_mode(&message, ParseMode::Lenient).unwrap() {
+        Message::Unknown(data) => assert_eq!(data, message),
+        other => panic!("Expected unknown message, got {:?}", other),
+    }
+
+    let data = json!({
+        "type": "assistant",
+        "message": {
+            "model": "m",
+            "content": [
+                {"type": "text", "text": "Hi"},
+                {"type": "server_tool_use", "id": "x"}
+            ]
+        }
+    });
+    let message = parse_message_with_mode(&data, ParseMode::Lenient).unwrap();
+    match &message {
+        Message::Assistant(msg) => match &msg.content[1] {
+            ContentBlock::Unknown(block) => assert_eq!(block["id"], "x"),
+            other => panic!("Expected unknown block, got {:?}", other),
+        },
+        other => panic!("Expected assistant message, got {:?}", other),
+    }
+
+    // Unknown parts serialize back unchanged
+    let mut expected = data.clone();
+    expected["message"]["role"] = json!("assistant");
+    assert_eq!(serde_json::to_value(&message).unwrap(), expected);
+}
+
+#[test]
+fn test_lenient_mode_still_rejects_malformed_known_types() {
+    let data = json!({
+        "type": "assistant",
+        "message": {"model": "m", "content": [{"type": "text"}]}
+    });
+    let err = parse_message_with_mode(&data, ParseMode::Lenient).unwrap_err();
+    assert!(
+        err.to_string().contains("Missing required field in assistant message: text"),
+        "{}",
+        err
+    );
+
+    let data = json!({"type": "result", "subtype": "success"});
+    assert!(parse_message_with_mode(&data, ParseMode::Lenient).is_err());
+}
+
+#[test]
+fn test_parse_document_block() {
+    let data = json!({
+        "type": "user",
+        "message": {
+            "content": [
+                {"type": "document", "source": {"type": "url", "url": "https://example.com/a.pdf"}, "title": "Spec"}
+            ]
+        }
+    });
+
+    match parse_message(&data).unwrap() {
+        Message::User(UserMessage {
+            content: UserMessageContent::Blocks(blocks),
+            ..
+        }) => match &blocks[0] {
+            ContentBlock::Document { source, title } => {
+                assert_eq!(*source, ContentSource::Url { url: "https://example.com/a.pdf".to_string() });
+                assert_eq!(title.as_deref(), Some("Spec"));
+            }
+            other => panic!("Expected document block, got {:?}", other),
+        },
+        other => panic!("Expected user message with blocks, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_tool_result_with_image_blocks() {
+    let data = json!({
+        "type": "user",
+        "message": {
+            "content": [{
+                "type": "tool_result",
+                "tool_use_id": "t1",
+                "content": [
+                    {"type": "text", "text": "Screenshot taken"},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}}
+                ]
+            }]
+        }
+    });
+
+    let blocks = match parse_message(&data).unwrap() {
+        Message::User(UserMessage {
+            content: UserMessageContent::Blocks(blocks),
+            ..
+        }) => blocks,
+        other => panic!("Expected user message with blocks, got {:?}", other),
+    };
+    let inner = blocks[0].tool_result_blocks().unwrap();
+    assert_eq!(inner.len(), 2);
+    assert!(matches!(inner[1], ContentBlock::Image { source: ContentSource::Base64 { .. } }));
+
+    let text_result = ContentBlock::ToolResult {
+        tool_use_id: "t2".to_string(),
+        content: Some(json!("done")),
+        is_error: None,
+    };
+    assert!(matches!(&text_result.tool_result_blocks().unwrap()[..], [ContentBlock::Text { text }] if text == "done"));
+    assert!(ContentBlock::Text { text: "x".to_string() }.tool_result_blocks().is_none());
+}
+
+#[test]
+fn test_parse_uuid_and_timestamp() {
+    let assistant = json!({
+        "type": "assistant",
+        "uuid": "a1",
+        "timestamp": "2025-01-01T00:00:01.000Z",
+        "message": {"model": "m", "content": []}
+    });
+    match parse_message(&assistant).unwrap() {
+        Message::Assistant(msg) => {
+            assert_eq!(msg.uuid.as_deref(), Some("a1"));
+            assert_eq!(msg.timestamp.as_deref(), Some("2025-01-01T00:00:01.000Z"));
+        }
+        other => panic!("Expected assistant message, got {:?}", other),
+    }
+
+    let user = json!({
+        "type": "user",
+        "uuid": "u1",
+        "timestamp": "2025-01-01T00:00:00.000Z",
+        "message": {"content": "Hi"}
+    });
+    match parse_message(&user).unwrap() {
+        Message::User(msg) => assert_eq!(msg.timestamp.as_deref(), Some("2025-01-01T00:00:00.000Z")),
+        other => panic!("Expected user message, got {:?}", other),
+    }
+
+    let mut result = result_message("s");
+    result["uuid"] = json!("r1");
+    match parse_message(&result).unwrap() {
+        Message::Result(msg) => {
+            assert_eq!(msg.uuid.as_deref(), Some("r1"));
+            assert_eq!(msg.timestamp, None);
+        }
+        other => panic!("Expected result message, got {:?}", other),
+    }
+}
@@ -1,11 +1,15 @@
-use claude::{Query, ClaudeAgentOptions};
-use claude::transport::SubprocessTransport;
+use claude::{Query, ClaudeAgentOptions, ClaudeSDKError, Message, CanUseToolCallback, HeartbeatConfig, HeartbeatEvent, HookCallback, HookErrorPolicy, HookEvent, HookJSONOutput, HookManager, HookMatcherConfig, MessageVecExt, ParseMode, PermissionResult, QueryExtras};
+use claude::mcp::{McpTool, SdkMcpServer, ToolAnnotations, ToolResult};
+use claude::testing::MockTransport;
+use claude::transport::{SubprocessTransport, Transport};
+use std::collections::HashMap;
+use std::sync::Arc;
 
 #[tokio::test]
 async fn test_query_creation_streaming_mode() {
     let opts = ClaudeAgentOptions::default();
     let transport = SubprocessTransport::new("test".to_string(), opts);
-    let query = Query::new(transport, true);
+    let query = Query::new(Box::new(transport), true);
 
     // Verify it's created in streaming mode
     drop(query);
@@ -15,7 +19,7 @@ async fn test_query_creation_streaming_mode() {
 async fn test_query_creation_non_streaming_mode() {
     let opts = ClaudeAgentOptions::default();
     let transport = SubprocessTransport::new("test".to_string(), opts);
-    let query = Query::new(transport, false);
+    let query = Query::new(Box::new(transport), false);
 
     // Verify it's created in non-streaming mode
     drop(query);
@@ -25,7 +29,7 @@ async fn test_query_creation_non_streaming_mode() {
 async fn test_query_initialize_non_streaming_returns_null() {
     let opts = ClaudeAgentOptions::default();
     let transport = SubprocessTransport::new("test".to_string(), opts);
-    let query = Query::new(transport, false);
+    let query = Query::new(Box::new(transport), false);
 
     let result = query.initialize().await;
     assert!(result.is_ok());
@@ -41,7 +45,560 @@ async fn test_query_with_different_options() {
         ..Default::default()
     };
     let transport = SubprocessTransport::new("test prompt".to_string(), opts);
-    let query = Query::new(transport, true);
+    let query = Query::new(Box::new(transport), true);
 
     drop(query);
 }
+
+#[tokio::test]
+async fn test_query_answers_can_use_tool_with_mock_transport() {
+    let mut transport = MockTransport::new().with_can_use_tool(
+        "perm_1",
+        "Bash",
+        serde_json::json!({"command": "rm -rf /"}),
+    );
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    let callback: CanUseToolCallback = Arc::new(|tool_name, _input, _ctx| {
+        Box::pin(async move {
+            if tool_name == "Bash" {
+                Ok(PermissionResult::deny("Bash is disabled"))
+            } else {
+                Ok(PermissionResult::allow())
+            }
+        })
+    });
+
+    let mut query = Query::with_can_use_tool(Box::new(transport), true, callback);
+    query.start().await.unwrap();
+
+    let response = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        handle.wait_for_control_response("perm_1"),
+    )
+    .await
+    .expect("timed out waiting for permission response")
+    .expect("permission response was not written");
+    assert_eq!(response["response"]["response"]["allow"], false);
+    assert_eq!(response["response"]["response"]["reason"], "Bash is disabled");
+}
+
+#[tokio::test]
+async fn test_query_passes_session_id_to_can_use_tool() {
+    let mut transport = MockTransport::new()
+        .with_message(serde_json::json!({"type": "system", "subtype": "init", "session_id": "session-42"}))
+        .with_can_use_tool("perm_1", "Bash", serde_json::json!({"command": "ls"}));
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    let callback: CanUseToolCallback = Arc::new(|_tool_name, _input, ctx| {
+        Box::pin(async move { Ok(PermissionResult::deny(ctx.session_id.map(String::from).unwrap_or_default())) })
+    });
+
+    let mut query = Query::with_can_use_tool(Box::new(transport), true, callback);
+    query.start().await.unwrap();
+
+    let response = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        handle.wait_for_control_response("perm_1"),
+    )
+    .await
+    .expect("timed out waiting for permission response")
+    .expect("permission response was not written");
+    assert_eq!(response["response"]["response"]["reason"], "session-42");
+}
+
+#[tokio::test]
+async fn test_query_passes_tool_annotations_to_can_use_tool() {
+    let tool = McpTool::new(
+        "drop_table".to_string(),
+        "Drop a table".to_string(),
+        serde_json::json!({"type": "object"}),
+        Arc::new(|_args| Box::pin(async { Ok(ToolResult::text("dropped".to_string())) })),
+    )
+    .with_annotations(ToolAnnotations::destructive());
+    let servers = HashMap::from([(
+        "db_tools".to_string(),
+        SdkMcpServer::new("db_tools".to_string(), "1.0.0".to_string(), vec![tool]),
+    )]);
+
+    let mut transport = MockTransport::new()
+        .with_can_use_tool("perm_1", "mcp__db_tools__drop_table", serde_json::json!({}))
+        .with_can_use_tool("perm_2", "Bash", serde_json::json!({"command": "ls"}));
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    let callback: CanUseToolCallback = Arc::new(|_tool_name, _input, ctx| {
+        Box::pin(async move {
+            match ctx.tool_annotations {
+                Some(annotations) if annotations.destructive_hint == Some(true) => {
+                    Ok(PermissionResult::deny("destructive"))
+                }
+                Some(_) => Ok(PermissionResult::allow()),
+                None => Ok(PermissionResult::deny("not an SDK tool")),
+            }
+        })
+    });
+
+    let mut query = Query::with_options(Box::new(transport), true, Some(callback), Some(servers));
+    query.start().await.unwrap();
+
+    let first = handle.wait_for_control_response("perm_1").await.unwrap();
+    assert_eq!(first["response"]["response"]["reason"], "destructive");
+    let second = handle.wait_for_control_response("perm_2").await.unwrap();
+    assert_eq!(second["response"]["response"]["reason"], "not an SDK tool");
+}
+
+#[tokio::test]
+async fn test_query_dispatches_hook_callback_with_mock_transport() {
+    let callback: HookCallback = Arc::new(|input, _tool_use_id, _ctx| {
+        Box::pin(async move {
+            let tool_name = input.get("tool_name").and_then(|v| v.as_str()).unwrap_or_default();
+            Ok(HookJSONOutput {
+                decision: Some("block".to_string()),
+                system_message: Some(format!("{} blocked by hook", tool_name)),
+                ..Default::default()
+            })
+        })
+    });
+    let mut hook_manager = HookManager::new();
+    let callback_id = hook_manager.register_callback(callback);
+
+    let mut transport = MockTransport::new()
+        .with_hook_callback("hook_1", callback_id, serde_json::json!({"tool_name": "Bash"}))
+        .with_hook_callback("hook_2", "missing", serde_json::json!({}));
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    let mut query = Query::with_hooks(Box::new(transport), true, hook_manager);
+    query.start().await.unwrap();
+
+    let wait = |request_id: &'static str| {
+        let handle = handle.clone();
+        async move {
+            tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                handle.wait_for_control_response(request_id),
+            )
+            .await
+            .expect("timed out waiting for hook response")
+            .expect("hook response was not written")
+        }
+    };
+
+    let response = wait("hook_1").await;
+    assert_eq!(response["response"]["subtype"], "success");
+    assert_eq!(response["response"]["response"]["decision"], "block");
+    assert_eq!(response["response"]["response"]["systemMessage"], "Bash blocked by hook");
+    assert_eq!(response["response"]["response"]["reason"], "Bash blocked by hook");
+
+    let response = wait("hook_2").await;
+    assert_eq!(response["response"]["subtype"], "error");
+    assert!(response["response"]["error"].as_str().unwrap().contains("missing"));
+}
+
+#[tokio::test]
+async fn test_query_pre_tool_use_block_denies_tool_and_reports_it() {
+    let callback: HookCallback = Arc::new(|_input, _tool_use_id, _ctx| {
+        Box::pin(async { Ok(HookJSONOutput::block("Destructive command")) })
+    });
+    let mut hook_manager = HookManager::new();
+    let callback_id = hook_manager.register_callback(callback);
+
+    let mut transport = MockTransport::new().with_hook_callback(
+        "hook_1",
+        callback_id.clone(),
+        serde_json::json!({
+            "hook_event_name": "PreToolUse",
+            "tool_name": "Bash",
+            "tool_input": {"command": "rm -rf /"}
+        }),
+    );
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    let mut query = Query::with_hooks(Box::new(transport), true, hook_manager);
+    let mut rx = query.receive_messages();
+    query.start().await.unwrap();
+
+    let response = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        handle.wait_for_control_response("hook_1"),
+    )
+    .await
+    .expect("timed out waiting for hook response")
+    .expect("hook response was not written");
+    let output = &response["response"]["response"];
+    assert_eq!(output["decision"], "block");
+    assert_eq!(output["hookSpecificOutput"]["hookEventName"], "PreToolUse");
+    assert_eq!(output["hookSpecificOutput"]["permissionDecision"], "deny");
+    assert_eq!(output["hookSpecificOutput"]["permissionDecisionReason"], "Destructive command");
+
+    match rx.recv().await {
+        Some(Ok(Message::System(system))) => {
+            assert_eq!(system.subtype, "hook_blocked");
+            assert_eq!(system.data["callback_id"], callback_id.as_str());
+            assert_eq!(system.data["tool_name"], "Bash");
+            assert_eq!(system.data["reason"], "Destructive command");
+        }
+        other => panic!("expected a hook_blocked notice, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_query_hung_hook_times_out_under_error_policy() {
+    let hanging = || -> HookCallback { Arc::new(|_, _, _| Box::pin(std::future::pending())) };
+    let mut hook_manager = HookManager::new();
+    let open_id = hook_manager.register_callback(hanging());
+    let closed_id = hook_manager.register_callback(hanging());
+    let timeout = std::time::Duration::from_millis(20);
+    hook_manager.add_matcher(
+        HookEvent::PreToolUse,
+        HookMatcherConfig::new("*".to_string(), vec![open_id.clone()]).with_timeout(timeout),
+    );
+    hook_manager.add_matcher(
+        HookEvent::PreToolUse,
+        HookMatcherConfig::new("*".to_string(), vec![closed_id.clone()])
+            .with_timeout(timeout)
+            .with_error_policy(HookErrorPolicy::FailClosed),
+    );
+
+    let input = serde_json::json!({"hook_event_name": "PreToolUse", "tool_name": "Bash"});
+    let mut transport = MockTransport::new()
+        .with_hook_callback("hook_open", open_id, input.clone())
+        .with_hook_callback("hook_closed", closed_id, input);
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    let mut query = Query::with_hooks(Box::new(transport), true, hook_manager);
+    query.start().await.unwrap();
+
+    let wait = |request_id: &'static str| {
+        let handle = handle.clone();
+        async move {
+            tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                handle.wait_for_control_response(request_id),
+            )
+            .await
+            .expect("timed out waiting for hook response")
+            .expect("hook response was not written")
+        }
+    };
+
+    let response = wait("hook_open").await;
+    assert_eq!(response["response"]["subtype"], "success");
+    assert!(response["response"]["response"].get("decision").is_none());
+
+    let response = wait("hook_closed").await;
+    assert_eq!(response["response"]["response"]["decision"], "block");
+    assert_eq!(response["response"]["response"]["hookSpecificOutput"]["permissionDecision"], "deny");
+}
+
+#[tokio::test]
+async fn test_simple_query_with_hooks_uses_streaming_mode() {
+    let transport = MockTransport::new()
+        .with_hook_callback(
+            "hook_1",
+            "hook_0",
+            serde_json::json!({"hook_event_name": "PreToolUse", "tool_name": "Bash"}),
+        )
+        .with_assistant_text("I could not run that")
+        .with_result("session-1");
+    let handle = transport.handle();
+
+    let extras = QueryExtras::new().hook(
+        HookEvent::PreToolUse,
+        "Bash",
+        Arc::new(|_, _, _| Box::pin(async { Ok(HookJSONOutput::block("No shell")) })),
+    );
+    let messages = claude::simple_query_with_transport_and_extras("Run ls", Box::new(transport), extras)
+        .await
+        .unwrap();
+
+    assert_eq!(messages.text_content(), "I could not run that");
+    handle.assert_user_message("Run ls");
+    let response = handle.wait_for_control_response("hook_1").await.unwrap();
+    assert_eq!(response["response"]["response"]["decision"], "block");
+}
+
+#[tokio::test]
+async fn test_query_routes_mcp_message_to_sdk_server() {
+    let tool = McpTool::new(
+        "add".to_string(),
+        "Add two numbers".to_string(),
+        serde_json::json!({"type": "object"}),
+        Arc::new(|args| {
+            Box::pin(async move {
+                let a = args.get("a").and_then(|v| v.as_i64()).unwrap_or_default();
+                let b = args.get("b").and_then(|v| v.as_i64()).unwrap_or_default();
+                Ok(ToolResult::text((a + b).to_string()))
+            })
+        }),
+    );
+    let mut servers = HashMap::new();
+    servers.insert(
+        "calc".to_string(),
+        SdkMcpServer::new("calc".to_string(), "1.0.0".to_string(), vec![tool]),
+    );
+
+    let mut transport = MockTransport::new().with_control_request(
+        "mcp_1",
+        serde_json::json!({
+            "subtype": "mcp_message",
+            "server_name": "calc",
+            "message": {
+                "jsonrpc": "2.0",
+                "id": 7,
+                "method": "tools/call",
+                "params": {"name": "add", "arguments": {"a": 2, "b": 3}}
+            }
+        }),
+    );
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    let mut query = Query::with_mcp_servers(Box::new(transport), true, servers);
+    query.start().await.unwrap();
+
+    let response = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        handle.wait_for_control_response("mcp_1"),
+    )
+    .await
+    .expect("timed out waiting for mcp response")
+    .expect("mcp response was not written");
+    let mcp_response = &response["response"]["response"]["mcp_response"];
+    assert_eq!(mcp_response["id"], 7);
+    assert_eq!(mcp_response["result"]["content"][0]["text"], "5");
+}
+
+#[tokio::test]
+async fn test_query_forwards_tool_progress() {
+    let tool = McpTool::with_context(
+        "build".to_string(),
+        "Build the project".to_string(),
+        serde_json::json!({"type": "object"}),
+        Arc::new(|_args, context| {
+            Box::pin(async move {
+                context.report_progress(25.0, "Compiling");
+                context.report_progress(100.0, "Linking");
+                Ok(ToolResult::text("ok".to_string()))
+            })
+        }),
+    );
+    let servers = HashMap::from([(
+        "builder".to_string(),
+        SdkMcpServer::new("builder".to_string(), "1.0.0".to_string(), vec![tool]),
+    )]);
+
+    let mut transport = MockTransport::new().with_control_request(
+        "mcp_1",
+        serde_json::json!({
+            "subtype": "mcp_message",
+            "server_name": "builder",
+            "message": {
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": {"name": "build", "arguments": {}, "_meta": {"progressToken": 9}}
+            }
+        }),
+    );
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    let mut query = Query::with_mcp_servers(Box::new(transport), true, servers);
+    let mut rx = query.receive_messages();
+    query.start().await.unwrap();
+    handle.wait_for_control_response("mcp_1").await.unwrap();
+
+    // Progress reaches the CLI before the tool result
+    let notifications: Vec<_> = handle
+        .control_requests()
+        .into_iter()
+        .map(|request| request["request"]["message"].clone())
+        .collect();
+    assert_eq!(notifications.len(), 2);
+    assert_eq!(notifications[0]["method"], "notifications/progress");
+    assert_eq!(
+        notifications[0]["params"],
+        serde_json::json!({"progressToken": 9, "progress": 25.0, "total": 100.0, "message": "Compiling"})
+    );
+
+    for (progress, text) in [(25.0, "Compiling"), (100.0, "Linking")] {
+        match rx.recv().await {
+            Some(Ok(Message::System(system))) => {
+                assert_eq!(system.subtype, "tool_progress");
+                assert_eq!(system.data["server_name"], "builder");
+                assert_eq!(system.data["tool_name"], "build");
+                assert_eq!(system.data["progress"], progress);
+                assert_eq!(system.data["message"], text);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_interrupt_cancels_running_tool() {
+    let started = Arc::new(tokio::sync::Notify::new());
+    let tool = McpTool::new(
+        "slow".to_string(),
+        "Never finishes".to_string(),
+        serde_json::json!({"type": "object"}),
+        Arc::new({
+            let started = Arc::clone(&started);
+            move |_args| {
+                let started = Arc::clone(&started);
+                Box::pin(async move {
+                    started.notify_one();
+                    std::future::pending().await
+                })
+            }
+        }),
+    );
+    let servers = HashMap::from([(
+        "tools".to_string(),
+        SdkMcpServer::new("tools".to_string(), "1.0.0".to_string(), vec![tool]),
+    )]);
+
+    let mut transport = MockTransport::new().with_control_request(
+        "mcp_1",
+        serde_json::json!({
+            "subtype": "mcp_message",
+            "server_name": "tools",
+            "message": {"jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {"name": "slow", "arguments": {}}}
+        }),
+    );
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    let mut query = Query::with_mcp_servers(Box::new(transport), true, servers);
+    query.start().await.unwrap();
+    started.notified().await;
+    query.interrupt().await.unwrap();
+
+    let response = handle.wait_for_control_response("mcp_1").await.unwrap();
+    let result = &response["response"]["response"]["mcp_response"]["result"];
+    assert_eq!(result["is_error"], true);
+    assert_eq!(result["content"][0]["text"], "Tool slow was cancelled");
+}
+
+#[tokio::test]
+async fn test_adding_tool_notifies_cli() {
+    let server = SdkMcpServer::new("plugins".to_string(), "1.0.0".to_string(), vec![]);
+    let servers = HashMap::from([("plugins".to_string(), server.clone())]);
+
+    let mut transport = MockTransport::new();
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    let mut query = Query::with_mcp_servers(Box::new(transport), true, servers);
+    query.start().await.unwrap();
+    server.add_tool(McpTool::new(
+        "late".to_string(),
+        "Loaded after connect".to_string(),
+        serde_json::json!({"type": "object"}),
+        Arc::new(|_args| Box::pin(async { Ok(ToolResult::text("here".to_string())) })),
+    ));
+
+    let request = handle
+        .wait_for(|handle| handle.control_requests().into_iter().next())
+        .await
+        .unwrap();
+    assert_eq!(request["request"]["subtype"], "mcp_message");
+    assert_eq!(request["request"]["server_name"], "plugins");
+    assert_eq!(
+        request["request"]["message"],
+        serde_json::json!({"jsonrpc": "2.0", "method": "notifications/tools/list_changed"})
+    );
+}
+
+#[tokio::test]
+async fn test_query_keeps_reading_after_buffer_overflow() {
+    let mut transport = MockTransport::new()
+        .with_error(ClaudeSDKError::buffer_overflow(64))
+        .with_assistant_text("still here");
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    let mut query = Query::new(Box::new(transport), false);
+    query.start().await.unwrap();
+    handle.finish();
+
+    let mut rx = query.receive_messages();
+    assert!(matches!(rx.recv().await, Some(Err(ClaudeSDKError::BufferOverflow { limit: 64 }))));
+    assert!(matches!(rx.recv().await, Some(Ok(Message::Assistant(_)))));
+    assert!(rx.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_lenient_query_passes_unknown_messages_through() {
+    let mut transport = MockTransport::new()
+        .with_message(serde_json::json!({"type": "rate_limit_event", "retry_after": 3}))
+        .with_assistant_text("still here");
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    let mut query = Query::new(Box::new(transport), false).with_parse_mode(ParseMode::Lenient);
+    query.start().await.unwrap();
+    handle.finish();
+
+    let mut rx = query.receive_messages();
+    match rx.recv().await {
+        Some(Ok(Message::Unknown(data))) => assert_eq!(data["retry_after"], 3),
+        other => panic!("expected unknown message, got {:?}", other),
+    }
+    assert!(matches!(rx.recv().await, Some(Ok(Message::Assistant(_)))));
+    assert!(rx.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_heartbeat_reports_unresponsive_cli() {
+    let mut transport = MockTransport::new().auto_respond(false);
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    let mut query = Query::new(Box::new(transport), true);
+    query.start().await.unwrap();
+    let mut health = query.start_heartbeat(HeartbeatConfig {
+        interval: std::time::Duration::from_millis(10),
+        timeout: std::time::Duration::from_millis(20),
+    });
+
+    match health.recv().await {
+        Some(HeartbeatEvent::Degraded { error }) => assert!(error.contains("timed out")),
+        other => panic!("expected degraded event, got {:?}", other),
+    }
+
+    handle.finish();
+    assert_eq!(health.recv().await, Some(HeartbeatEvent::Disconnected));
+    assert!(!query.is_connected());
+}
+
+#[tokio::test]
+async fn test_writes_proceed_while_reader_is_blocked() {
+    let mut transport = MockTransport::new()
+        .with_assistant_text("one")
+        .with_assistant_text("two")
+        .with_assistant_text("three");
+    let handle = transport.handle();
+    transport.connect().await.unwrap();
+
+    // Nobody consumes messages, so the reader parks on the full channel
+    let mut query = Query::new(Box::new(transport), true).with_channel_capacity(1);
+    query.start().await.unwrap();
+    tokio::task::yield_now().await;
+
+    let message = serde_json::json!({
+        "type": "user",
+        "message": {"role": "user", "content": "are you there?"}
+    });
+    tokio::time::timeout(std::time::Duration::from_secs(5), query.send_message(message))
+        .await
+        .expect("write blocked behind the reader")
+        .unwrap();
+    handle.assert_user_message("are you there?");
+}
@@ -1,5 +1,4 @@
 use claude::types::*;
-use serde_json;
 use std::collections::HashMap;
 
 #[test]
@@ -24,6 +23,18 @@ fn test_permission_mode_deserialization() {
     assert_eq!(mode, PermissionMode::AcceptEdits);
 }
 
+#[test]
+fn test_permission_mode_as_str_matches_serde() {
+    for mode in [
+        PermissionMode::Default,
+        PermissionMode::AcceptEdits,
+        PermissionMode::Plan,
+        PermissionMode::BypassPermissions,
+    ] {
+        assert_eq!(serde_json::to_value(&mode).unwrap(), mode.as_str());
+    }
+}
+
 #[test]
 fn test_setting_source_serialization() {
     let source = SettingSource::User;
@@ -135,6 +146,8 @@ fn test_user_message_text() {
     let msg = UserMessage {
         content: UserMessageContent::Text("Hello".to_string()),
         parent_tool_use_id: None,
+        uuid: None,
+        timestamp: None,
     };
 
     let json = serde_json::to_string(&msg).unwrap();
@@ -150,6 +163,8 @@ fn test_user_message_blocks() {
     let msg = UserMessage {
         content: UserMessageContent::Blocks(blocks),
         parent_tool_use_id: Some("parent123".to_string()),
+        uuid: None,
+        timestamp: None,
     };
 
     let json = serde_json::to_string(&msg).unwrap();
@@ -166,6 +181,8 @@ fn test_assistant_message() {
         content,
         model: "claude-3-sonnet".to_string(),
         parent_tool_use_id: None,
+        uuid: None,
+        timestamp: None,
     };
 
     let json = serde_json::to_string(&msg).unwrap();
@@ -180,10 +197,13 @@ fn test_result_message() {
         duration_api_ms: 800,
         is_error: false,
         num_turns: 3,
-        session_id: "session123".to_string(),
+        session_id: "session123".into(),
         total_cost_usd: Some(0.05),
         usage: None,
         result: Some("Success".to_string()),
+        model_usage: None,
+        uuid: None,
+        timestamp: None,
     };
 
     let json = serde_json::to_string(&msg).unwrap();
@@ -226,6 +246,54 @@ fn test_hook_event_serialization() {
     assert_eq!(json, "\"PreToolUse\"");
 }
 
+#[test]
+fn test_hook_event_names() {
+    assert_eq!(HookEvent::SubagentStop.as_str(), "SubagentStop");
+    assert_eq!(String::from(HookEvent::PreCompact), "PreCompact");
+}
+
+#[test]
+fn test_hook_input_parses_each_event() {
+    let parse = |value: serde_json::Value| {
+        let input: HashMap<String, serde_json::Value> = serde_json::from_value(value).unwrap();
+        HookInput::parse(&input).unwrap()
+    };
+
+    let input = parse(serde_json::json!({
+        "hook_event_name": "PostToolUse",
+        "session_id": "session-1",
+        "transcript_path": "/tmp/t.jsonl",
+        "cwd": "/work",
+        "tool_name": "Read",
+        "tool_input": {"file_path": "README.md"},
+        "tool_response": {"content": "# Title"}
+    }));
+    assert_eq!(input.event(), HookEvent::PostToolUse);
+    assert_eq!(input.tool_name(), Some("Read"));
+    assert_eq!(input.base().cwd, "/work");
+    match input {
+        HookInput::PostToolUse(post) => assert_eq!(post.tool_response["content"], "# Title"),
+        other => panic!("unexpected input: {:?}", other),
+    }
+
+    let input = parse(serde_json::json!({"hook_event_name": "UserPromptSubmit", "prompt": "Hi"}));
+    assert!(matches!(input, HookInput::UserPromptSubmit(ref submit) if submit.prompt == "Hi"));
+    assert_eq!(input.tool_name(), None);
+
+    let input = parse(serde_json::json!({"hook_event_name": "SubagentStop", "stop_hook_active": true}));
+    assert!(matches!(input, HookInput::SubagentStop(ref stop) if stop.stop_hook_active));
+
+    let input = parse(serde_json::json!({"hook_event_name": "Stop"}));
+    assert!(matches!(input, HookInput::Stop(ref stop) if !stop.stop_hook_active));
+
+    let input = parse(serde_json::json!({"hook_event_name": "PreCompact", "trigger": "manual", "custom_instructions": "keep APIs"}));
+    assert!(matches!(input, HookInput::PreCompact(ref compact) if compact.trigger == "manual"));
+
+    let unknown: HashMap<String, serde_json::Value> =
+        serde_json::from_value(serde_json::json!({"hook_event_name": "Unknown"})).unwrap();
+    assert!(HookInput::parse(&unknown).is_err());
+}
+
 #[test]
 fn test_control_request_interrupt() {
     let request = ControlRequest::Interrupt {};
@@ -250,6 +318,33 @@ fn test_control_request_can_use_tool() {
     assert!(json.contains("\"tool_name\":\"Bash\""));
 }
 
+#[test]
+fn test_control_request_rewind_files() {
+    let request = ControlRequest::RewindFiles {
+        user_message_id: "msg-1".to_string(),
+    };
+
+    let json = serde_json::to_value(&request).unwrap();
+    assert_eq!(json, serde_json::json!({"subtype": "rewind_files", "user_message_id": "msg-1"}));
+
+    let request = ControlRequest::Compact { instructions: None };
+    assert_eq!(serde_json::to_value(&request).unwrap(), serde_json::json!({"subtype": "compact"}));
+}
+
+#[test]
+fn test_mcp_server_status_deserialization() {
+    let status: McpServerStatus = serde_json::from_value(serde_json::json!({
+        "name": "github",
+        "status": "connected",
+        "serverInfo": {"name": "github-mcp", "version": "1.0.0"}
+    }))
+    .unwrap();
+
+    assert_eq!(status.name, "github");
+    assert_eq!(status.status, "connected");
+    assert_eq!(status.extra["serverInfo"]["version"], "1.0.0");
+}
+
 #[test]
 fn test_control_response_success() {
     let response = ControlResponseType::Success {
@@ -289,9 +384,624 @@ fn test_hook_json_output() {
         decision: Some("block".to_string()),
         system_message: Some("Blocked by hook".to_string()),
         hook_specific_output: None,
+        updated_input: None,
     };
 
     let json = serde_json::to_string(&output).unwrap();
     assert!(json.contains("\"decision\":\"block\""));
     assert!(json.contains("\"system_message\":\"Blocked by hook\""));
 }
+
+#[test]
+fn test_server_info_deserialization() {
+    let json = serde_json::json!({
+        "commands": [
+            {"name": "compact", "description": "Clear history but keep a summary", "argumentHint": "<instructions>"},
+            {"name": "review", "description": "Review a pull request"}
+        ],
+        "models": [
+            {"value": "default", "displayName": "Default", "description": "Recommended model"}
+        ],
+        "output_style": "default",
+        "available_output_styles": ["default", "Explanatory"],
+        "account": {"subscriptionType": "pro"}
+    });
+
+    let info: ServerInfo = serde_json::from_value(json).unwrap();
+    assert_eq!(info.commands[0].argument_hint.as_deref(), Some("<instructions>"));
+    assert!(info.has_command("/review"));
+    assert!(!info.has_command("deploy"));
+    assert!(info.has_model("default"));
+    assert_eq!(info.models[0].display_name, "Default");
+    assert_eq!(info.output_style.as_deref(), Some("default"));
+    assert_eq!(info.extra["account"]["subscriptionType"], "pro");
+}
+
+#[test]
+fn test_tool_input_parse() {
+    let input = |value: serde_json::Value| -> HashMap<String, serde_json::Value> { serde_json::from_value(value).unwrap() };
+
+    assert_eq!(
+        ToolInput::parse("Bash", &input(serde_json::json!({"command": "ls -la", "timeout": 5000}))),
+        ToolInput::Bash {
+            command: "ls -la".to_string(),
+            timeout: Some(5000)
+        }
+    );
+    assert_eq!(
+        ToolInput::parse(
+            "Edit",
+            &input(serde_json::json!({"file_path": "src/lib.rs", "old_string": "a", "new_string": "b"}))
+        ),
+        ToolInput::Edit {
+            file_path: "src/lib.rs".to_string(),
+            old_string: "a".to_string(),
+            new_string: "b".to_string()
+        }
+    );
+    let read = ToolInput::parse("Read", &input(serde_json::json!({"file_path": "README.md", "limit": 10})));
+    assert_eq!(read.file_path(), Some("README.md"));
+    assert_eq!(
+        ToolInput::parse("WebFetch", &input(serde_json::json!({"url": "https://example.com", "prompt": "summarize"}))),
+        ToolInput::WebFetch {
+            url: "https://example.com".to_string()
+        }
+    );
+
+    // Unknown tools and malformed input fall back to `Other`
+    let other = ToolInput::parse("Bash", &input(serde_json::json!({"cmd": "ls"})));
+    assert!(matches!(other, ToolInput::Other { ref tool_name, .. } if tool_name == "Bash"));
+    assert_eq!(ToolInput::parse("Glob", &HashMap::new()).tool_name(), "Glob");
+}
+
+#[test]
+fn test_tool_input_parse_file_and_search_tools() {
+    let input = |value: serde_json::Value| -> HashMap<String, serde_json::Value> { serde_json::from_value(value).unwrap() };
+
+    let write = ToolInput::parse("Write", &input(serde_json::json!({"file_path": "out.txt", "content": "hi"})));
+    assert_eq!(write.file_path(), Some("out.txt"));
+    assert_eq!(
+        ToolInput::parse("Glob", &input(serde_json::json!({"pattern": "**/*.rs"}))),
+        ToolInput::Glob {
+            pattern: "**/*.rs".to_string(),
+            path: None
+        }
+    );
+    assert_eq!(
+        ToolInput::parse("Grep", &input(serde_json::json!({"pattern": "TODO", "path": "src", "glob": "*.rs"}))),
+        ToolInput::Grep {
+            pattern: "TODO".to_string(),
+            path: Some("src".to_string()),
+            glob: Some("*.rs".to_string())
+        }
+    );
+    assert_eq!(
+        ToolInput::parse(
+            "Task",
+            &input(serde_json::json!({"description": "Review", "prompt": "Review the diff", "subagent_type": "reviewer"}))
+        ),
+        ToolInput::Task {
+            description: "Review".to_string(),
+            prompt: "Review the diff".to_string(),
+            subagent_type: "reviewer".to_string()
+        }
+    );
+    assert_eq!(
+        ToolInput::parse(
+            "TodoWrite",
+            &input(serde_json::json!({"todos": [{"content": "Write tests", "status": "in_progress", "activeForm": "Writing tests"}]}))
+        ),
+        ToolInput::TodoWrite {
+            todos: vec![TodoItem {
+                content: "Write tests".to_string(),
+                status: "in_progress".to_string(),
+                active_form: Some("Writing tests".to_string())
+            }]
+        }
+    );
+    assert!(matches!(
+        ToolInput::parse("TodoWrite", &input(serde_json::json!({"todos": "none"}))),
+        ToolInput::Other { .. }
+    ));
+}
+
+#[test]
+fn test_tool_use_view_parse() {
+    let block = ContentBlock::ToolUse {
+        id: "toolu_1".to_string(),
+        name: "Bash".to_string(),
+        input: serde_json::from_value(serde_json::json!({"command": "cargo test"})).unwrap(),
+    };
+
+    let view = ToolUseView::parse(&block).unwrap();
+    assert_eq!(view.id, "toolu_1");
+    assert_eq!(view.tool_name(), "Bash");
+    assert!(matches!(view.input, ToolInput::Bash { ref command, .. } if command == "cargo test"));
+    assert!(ToolUseView::parse(&ContentBlock::Text { text: "hi".to_string() }).is_none());
+}
+
+#[test]
+fn test_pre_tool_use_typed_input() {
+    let input: HashMap<String, serde_json::Value> = serde_json::from_value(serde_json::json!({
+        "hook_event_name": "PreToolUse",
+        "session_id": "s",
+        "transcript_path": "/tmp/t",
+        "cwd": "/tmp",
+        "tool_name": "Read",
+        "tool_input": {"file_path": "/etc/hosts"}
+    }))
+    .unwrap();
+
+    match HookInput::parse(&input).unwrap() {
+        HookInput::PreToolUse(pre) => assert_eq!(
+            pre.typed_input(),
+            ToolInput::Read {
+                file_path: "/etc/hosts".to_string()
+            }
+        ),
+        other => panic!("unexpected hook input: {:?}", other),
+    }
+}
+
+fn write_mcp_config(file_name: &str, contents: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("claude-rs-{}-{}", std::process::id(), file_name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_mcp_server_config_load_file() {
+    let path = write_mcp_config(
+        "claude_desktop_config.json",
+        r#"{
+            "mcpServers": {
+                "filesystem": {"command": "npx", "args": ["-y", "@modelcontextprotocol/server-filesystem", "/tmp"]},
+                "docs": {"type": "http", "url": "https://example.com/mcp", "headers": {"Authorization": "Bearer t"}}
+            },
+            "globalShortcut": "Ctrl+Space"
+        }"#,
+    );
+
+    let servers = McpServerConfig::load_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(servers.len(), 2);
+    assert!(matches!(
+        servers["filesystem"],
+        McpServerConfig::Stdio { ref command, args: Some(ref args), env: None } if command == "npx" && args.len() == 3
+    ));
+    assert!(matches!(
+        servers["docs"],
+        McpServerConfig::Http { ref url, headers: Some(_) } if url == "https://example.com/mcp"
+    ));
+}
+
+#[test]
+fn test_mcp_server_config_load_file_errors() {
+    let error_for = |file_name: &str, contents: &str| {
+        let path = write_mcp_config(file_name, contents);
+        let error = McpServerConfig::load_file(&path).unwrap_err().to_string();
+        std::fs::remove_file(&path).unwrap();
+        error.split_once(": ").unwrap().1.to_string()
+    };
+
+    assert_eq!(error_for("missing.json", "{}"), "missing \"mcpServers\" object");
+    assert_eq!(
+        error_for("no-command.json", r#"{"mcpServers": {"git": {"args": ["serve"]}}}"#),
+        "missing field `command`"
+    );
+    assert_eq!(
+        error_for("bad-type.json", r#"{"mcpServers": {"git": {"type": "ws", "url": "ws://x"}}}"#),
+        "unknown type \"ws\", expected \"stdio\", \"sse\", \"http\" or \"streamable-http\""
+    );
+    assert!(error_for("syntax.json", "{\"mcpServers\": ").contains("line 1"));
+
+    let path = write_mcp_config("named.json", r#"{"mcpServers": {"git": 5}}"#);
+    let error = McpServerConfig::load_file(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    assert!(error.to_string().ends_with("named.json (server 'git'): expected an object"));
+}
+
+#[test]
+fn test_streamable_http_server_config() {
+    let config = McpServerConfig::streamable_http("https://example.com/mcp").with_header("Authorization", "Bearer t");
+    assert_eq!(
+        serde_json::to_value(&config).unwrap(),
+        serde_json::json!({
+            "type": "http",
+            "url": "https://example.com/mcp",
+            "headers": {"Authorization": "Bearer t"}
+        })
+    );
+
+    for spelling in ["streamable-http", "streamableHttp"] {
+        let parsed: McpServerConfig =
+            serde_json::from_value(serde_json::json!({"type": spelling, "url": "https://example.com/mcp"})).unwrap();
+        assert!(matches!(parsed, McpServerConfig::StreamableHttp { headers: None, .. }));
+    }
+
+    // Headers only apply to network servers
+    let stdio = McpServerConfig::Stdio {
+        command: "mcp-git".to_string(),
+        args: None,
+        env: None,
+    }
+    .with_header("Authorization", "Bearer t");
+    assert!(matches!(stdio, McpServerConfig::Stdio { .. }));
+}
+
+fn stream_event(event: serde_json::Value) -> StreamEvent {
+    StreamEvent {
+        uuid: "e1".to_string(),
+        session_id: "s1".into(),
+        event: serde_json::from_value(event).unwrap(),
+        parent_tool_use_id: None,
+    }
+}
+
+#[test]
+fn test_stream_event_kinds() {
+    let start = stream_event(serde_json::json!({
+        "type": "content_block_start",
+        "index": 0,
+        "content_block": {"type": "text", "text": ""}
+    }));
+    assert!(matches!(
+        start.kind(),
+        StreamEventKind::ContentBlockStart { index: 0, content_block: ContentBlock::Text { .. } }
+    ));
+
+    let text = stream_event(serde_json::json!({
+        "type": "content_block_delta",
+        "index": 1,
+        "delta": {"type": "text_delta", "text": "Hel"}
+    }));
+    match text.kind() {
+        StreamEventKind::ContentBlockDelta { index, delta } => {
+            assert_eq!(index, 1);
+            assert_eq!(delta, ContentDelta::TextDelta { text: "Hel".to_string() });
+        }
+        other => panic!("Expected content block delta, got {:?}", other),
+    }
+
+    let thinking = stream_event(serde_json::json!({
+        "type": "content_block_delta",
+        "index": 0,
+        "delta": {"type": "thinking_delta", "thinking": "Hmm"}
+    }));
+    assert!(matches!(
+        thinking.kind(),
+        StreamEventKind::ContentBlockDelta { delta: ContentDelta::ThinkingDelta { .. }, .. }
+    ));
+
+    let stop = stream_event(serde_json::json!({
+        "type": "message_delta",
+        "delta": {"stop_reason": "end_turn", "stop_sequence": null},
+        "usage": {"output_tokens": 12}
+    }));
+    match stop.kind() {
+        StreamEventKind::MessageDelta { delta, usage } => {
+            assert_eq!(delta.stop_reason.as_deref(), Some("end_turn"));
+            assert_eq!(usage.unwrap()["output_tokens"], 12);
+        }
+        other => panic!("Expected message delta, got {:?}", other),
+    }
+
+    assert!(matches!(
+        stream_event(serde_json::json!({"type": "message_stop"})).kind(),
+        StreamEventKind::MessageStop
+    ));
+    assert!(matches!(
+        stream_event(serde_json::json!({"type": "message_start", "message": {"id": "m1"}})).kind(),
+        StreamEventKind::MessageStart { .. }
+    ));
+}
+
+#[test]
+fn test_unrecognized_stream_events_keep_raw_data() {
+    let ping = stream_event(serde_json::json!({"type": "ping"}));
+    assert!(matches!(ping.kind(), StreamEventKind::Unknown));
+
+    let new_delta = stream_event(serde_json::json!({
+        "type": "content_block_delta",
+        "index": 0,
+        "delta": {"type": "citations_delta", "citation": {}}
+    }));
+    assert!(matches!(
+        new_delta.kind(),
+        StreamEventKind::ContentBlockDelta { delta: ContentDelta::Unknown, .. }
+    ));
+
+    // Malformed known events fall back to Unknown too
+    let malformed = stream_event(serde_json::json!({"type": "content_block_stop"}));
+    assert!(matches!(malformed.kind(), StreamEventKind::Unknown));
+    assert_eq!(malformed.event["type"], "content_block_stop");
+}
+
+fn system_message(data: serde_json::Value) -> SystemMessage {
+    SystemMessage {
+        subtype: data["subtype"].as_str().unwrap().to_string(),
+        data: serde_json::from_value(data).unwrap(),
+    }
+}
+
+#[test]
+fn test_system_init_kind() {
+    let msg = system_message(serde_json::json!({
+        "type": "system",
+        "subtype": "init",
+        "session_id": "s1",
+        "model": "claude-sonnet-4-5",
+        "cwd": "/work",
+        "tools": ["Bash", "Read"],
+        "mcp_servers": [{"name": "tools", "status": "connected"}],
+        "permissionMode": "default",
+        "slash_commands": ["compact"],
+        "apiKeySource": "none"
+    }));
+
+    match msg.kind() {
+        SystemMessageKind::Init(info) => {
+            assert_eq!(info.session_id, "s1");
+            assert_eq!(info.model, "claude-sonnet-4-5");
+            assert_eq!(info.cwd.as_deref(), Some(std::path::Path::new("/work")));
+            assert_eq!(info.tools, vec!["Bash", "Read"]);
+            assert_eq!(info.mcp_servers[0].name, "tools");
+            assert_eq!(info.mcp_servers[0].status, "connected");
+            assert_eq!(info.permission_mode.as_deref(), Some("default"));
+            assert_eq!(info.slash_commands, vec!["compact"]);
+        }
+        other => panic!("Expected init, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_system_message_kinds() {
+    let compact = system_message(serde_json::json!({
+        "type": "system",
+        "subtype": "compact_boundary",
+        "compact_metadata": {"trigger": "auto", "pre_tokens": 120000}
+    }));
+    assert_eq!(
+        compact.kind(),
+        SystemMessageKind::CompactBoundary {
+            compact_metadata: CompactMetadata {
+                trigger: "auto".to_string(),
+                pre_tokens: Some(120000),
+            }
+        }
+    );
+
+    let status = system_message(serde_json::json!({"type": "system", "subtype": "status", "status": "compacting"}));
+    assert_eq!(
+        status.kind(),
+        SystemMessageKind::Status {
+            status: Some("compacting".to_string())
+        }
+    );
+
+    let hook = system_message(serde_json::json!({"type": "system", "subtype": "hook_response"}));
+    assert_eq!(hook.kind(), SystemMessageKind::Unknown);
+
+    // An init message without a session ID can't be typed
+    let broken = system_message(serde_json::json!({"type": "system", "subtype": "init"}));
+    assert_eq!(broken.kind(), SystemMessageKind::Unknown);
+}
+
+fn result_with_subtype(subtype: &str) -> ResultMessage {
+    ResultMessage {
+        subtype: subtype.to_string(),
+        duration_ms: 1,
+        duration_api_ms: 1,
+        is_error: subtype != "success",
+        num_turns: 1,
+        session_id: "s1".into(),
+        total_cost_usd: None,
+        usage: None,
+        result: None,
+        model_usage: None,
+        uuid: None,
+        timestamp: None,
+    }
+}
+
+#[test]
+fn test_result_subtype_classification() {
+    let success = result_with_subtype("success");
+    assert_eq!(success.kind(), ResultSubtype::Success);
+    assert!(success.is_success());
+    assert!(!success.kind().is_error());
+
+    let max_turns = result_with_subtype("error_max_turns");
+    assert!(max_turns.is_max_turns());
+    assert!(!max_turns.is_execution_error());
+    assert!(max_turns.kind().is_error());
+
+    let failed = result_with_subtype("error_during_execution");
+    assert!(failed.is_execution_error());
+    assert!(!failed.is_success());
+
+    let budget = result_with_subtype("error_max_budget_usd");
+    assert_eq!(budget.kind(), ResultSubtype::ErrorMaxBudgetUsd);
+
+    let future = result_with_subtype("error_something_new");
+    assert_eq!(future.kind(), ResultSubtype::Other("error_something_new".to_string()));
+    assert!(future.kind().is_error());
+    assert_eq!(future.kind().to_string(), "error_something_new");
+    assert_eq!(ResultSubtype::ErrorMaxTurns.as_str(), "error_max_turns");
+}
+
+#[test]
+fn test_agent_options_from_config() {
+    let options: ClaudeAgentOptions = serde_json::from_value(serde_json::json!({
+        "model": "claude-sonnet-4-5",
+        "allowed_tools": ["Read", "Grep"],
+        "permission_mode": "acceptEdits",
+        "max_turns": 5,
+        "cwd": "/work",
+        "system_prompt": {"preset": "claude_code", "append": "Be brief"},
+        "mcp_servers": {"files": {"type": "stdio", "command": "mcp-files"}},
+        "agents": {"reviewer": {"description": "Reviews code", "prompt": "Review it"}},
+        "setting_sources": ["project"],
+        "shutdown_timeout": 2.5,
+        "parse_mode": "lenient"
+    }))
+    .unwrap();
+
+    assert_eq!(options.model.as_deref(), Some("claude-sonnet-4-5"));
+    assert_eq!(options.allowed_tools, vec!["Read", "Grep"]);
+    assert_eq!(options.permission_mode, Some(PermissionMode::AcceptEdits));
+    assert_eq!(options.max_turns, Some(5));
+    assert_eq!(options.cwd, Some(std::path::PathBuf::from("/work")));
+    match options.system_prompt {
+        Some(SystemPromptConfig::Preset(ref preset)) => {
+            assert_eq!(preset.r#type, "preset");
+            assert_eq!(preset.append.as_deref(), Some("Be brief"));
+        }
+        ref other => panic!("unexpected system prompt: {:?}", other),
+    }
+    assert!(matches!(options.mcp_servers["files"], McpServerConfig::Stdio { ref command, .. } if command == "mcp-files"));
+    assert_eq!(options.agents.as_ref().unwrap()["reviewer"].prompt, "Review it");
+    assert_eq!(options.setting_sources, Some(vec![SettingSource::Project]));
+    assert_eq!(options.shutdown_timeout, Some(std::time::Duration::from_millis(2500)));
+    assert_eq!(options.parse_mode, ParseMode::Lenient);
+    // Unset fields keep their defaults
+    assert!(!options.include_partial_messages);
+    assert!(options.resume.is_none());
+}
+
+#[test]
+fn test_agent_options_roundtrip() {
+    let mut options = ClaudeAgentOptions {
+        model: Some("claude-opus-4-1".to_string()),
+        system_prompt: Some(SystemPromptConfig::Text("You are terse".to_string())),
+        shutdown_timeout: Some(std::time::Duration::from_secs(3)),
+        include_partial_messages: true,
+        required_version: Some(claude::CliVersion::new(2, 0, 0)),
+        ..Default::default()
+    };
+    options.mcp_servers.insert("remote".to_string(), McpServerConfig::Http {
+        url: "https://example.com/mcp".to_string(),
+        headers: None,
+    });
+    options
+        .mcp_servers
+        .insert("local".to_string(), McpServerConfig::Sdk(claude::mcp::SdkMcpServer::new("local".to_string(), "1.0.0".to_string(), vec![])));
+
+    let value = serde_json::to_value(&options).unwrap();
+    assert_eq!(value["shutdown_timeout"], 3.0);
+    assert_eq!(value["required_version"], "2.0.0");
+    assert!(value.get("resume").is_none());
+    // In-process servers can't be written to a file
+    assert!(value["mcp_servers"].get("local").is_none());
+
+    let loaded: ClaudeAgentOptions = serde_json::from_value(value).unwrap();
+    assert_eq!(loaded.model, options.model);
+    assert_eq!(loaded.shutdown_timeout, options.shutdown_timeout);
+    assert!(loaded.include_partial_messages);
+    assert_eq!(loaded.required_version, options.required_version);
+    assert!(matches!(loaded.mcp_servers["remote"], McpServerConfig::Http { .. }));
+    assert_eq!(loaded.mcp_servers.len(), 1);
+    assert!(serde_json::from_value::<ClaudeAgentOptions>(serde_json::json!({"shutdown_timeout": -1})).is_err());
+    assert!(serde_json::from_value::<ClaudeAgentOptions>(serde_json::json!({"required_version": "two"})).is_err());
+}
+
+#[test]
+fn test_session_id_validation() {
+    let id: SessionId = "550e8400-e29b-41d4-a716-446655440000".parse().unwrap();
+    assert_eq!(id, "550e8400-e29b-41d4-a716-446655440000");
+    assert_eq!(id.to_string(), id.as_str());
+    assert_eq!(String::from(id.clone()), id.into_string());
+
+    assert!(SessionId::new("").is_err());
+    assert!(SessionId::new("a".repeat(SessionId::MAX_LEN + 1)).is_err());
+    let err = SessionId::new("two words").unwrap_err();
+    assert_eq!(err.to_string(), "invalid session ID \"two words\": contains whitespace or control characters");
+}
+
+#[test]
+fn test_session_id_serde_is_transparent() {
+    let result: ResultMessage = serde_json::from_value(serde_json::json!({
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 1,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "session-1"
+    }))
+    .unwrap();
+    assert_eq!(result.session_id, SessionId::from("session-1"));
+    assert_eq!(serde_json::to_value(&result.session_id).unwrap(), "session-1");
+
+    let options = ClaudeAgentOptions {
+        resume: Some("session-1".into()),
+        ..Default::default()
+    };
+    assert_eq!(serde_json::to_value(&options).unwrap()["resume"], "session-1");
+}
+
+#[test]
+fn test_outgoing_user_envelope_wire_format() {
+    let text = UserEnvelope::text("Hi").to_wire();
+    assert_eq!(
+        text,
+        serde_json::json!({
+            "type": "user",
+            "message": {"role": "user", "content": "Hi"},
+            "parent_tool_use_id": null,
+            "session_id": "default"
+        })
+    );
+
+    let blocks = OutgoingMessage::from(
+        UserEnvelope::blocks(vec![ContentBlock::Text { text: "Look".to_string() }])
+            .session_id("session-1")
+            .parent_tool_use_id("task-1"),
+    )
+    .to_wire();
+    assert_eq!(blocks["message"]["content"][0]["type"], "text");
+    assert_eq!(blocks["session_id"], "session-1");
+    assert_eq!(blocks["parent_tool_use_id"], "task-1");
+    // The CLI echoes the same shape back, so it parses as a user message
+    assert!(matches!(claude::parse_message(&blocks).unwrap(), Message::User(_)));
+
+    let interrupt = OutgoingMessage::from(SDKControlRequest {
+        r#type: "control_request".to_string(),
+        request_id: "req_1".to_string(),
+        request: ControlRequest::Interrupt {},
+    })
+    .to_wire();
+    assert_eq!(interrupt["request"]["subtype"], "interrupt");
+}
+
+#[test]
+fn test_token_counts_on_messages() {
+    let message = AssistantMessage {
+        content: vec![
+            ContentBlock::Text { text: "Listing files".to_string() },
+            ContentBlock::ToolUse {
+                id: "t1".to_string(),
+                name: "Bash".to_string(),
+                input: HashMap::from([("command".to_string(), serde_json::json!("ls"))]),
+            },
+        ],
+        model: "claude-sonnet-4-5".to_string(),
+        parent_tool_use_id: None,
+        uuid: None,
+        timestamp: None,
+    };
+    assert_eq!(message.approx_output_tokens(), 4 + 1 + 4);
+
+    let result: ResultMessage = serde_json::from_value(serde_json::json!({
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 1,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "s1",
+        "usage": {"input_tokens": 12, "output_tokens": 30}
+    }))
+    .unwrap();
+    let usage = result.token_usage().unwrap();
+    assert_eq!((usage.input_tokens, usage.output_tokens), (12, 30));
+}